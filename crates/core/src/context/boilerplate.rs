@@ -2,7 +2,9 @@ use core::marker::PhantomData;
 
 use alloc::vec::Vec;
 use serde_json::Value;
-use valence_coprocessor_types::{DataBackend, DomainData, Hash, Hasher};
+use valence_coprocessor_types::{
+    ControllerMetadata, ControllerSchema, DataBackend, DomainData, Hash, Hasher, WitnessEncoding,
+};
 
 use crate::{ExecutionContext, Registry, Vm};
 
@@ -17,6 +19,11 @@ where
     /// Controller function name to the entrypoint.
     pub const CONTROLLER_ENTRYPOINT: &str = "entrypoint";
 
+    /// Controller function name to describe the entrypoint arguments.
+    ///
+    /// The export is optional; it returns a [`ControllerSchema`].
+    pub const CONTROLLER_DESCRIBE: &str = "describe";
+
     /// Initializes a new execution context.
     #[allow(dead_code)]
     pub(crate) fn init(controller: Hash, historical: Hash, data: D) -> Self {
@@ -43,6 +50,18 @@ where
         &self.controller
     }
 
+    /// Returns the deployment metadata of the controller being executed.
+    ///
+    /// Returns `None` if the controller isn't registered.
+    pub fn get_controller_metadata(&self) -> anyhow::Result<Option<ControllerMetadata>> {
+        self.registry.get_controller_metadata(&self.controller)
+    }
+
+    /// Returns the witness data encoding selected when the controller was deployed.
+    pub fn get_witness_encoding(&self) -> anyhow::Result<WitnessEncoding> {
+        self.registry.get_codec(&self.controller)
+    }
+
     /// Returns a zkVM circuit.
     pub fn get_zkvm(&self) -> anyhow::Result<Option<Vec<u8>>> {
         self.registry.get_zkvm(&self.controller)
@@ -107,4 +126,19 @@ where
     {
         vm.execute(self, self.controller(), Self::CONTROLLER_ENTRYPOINT, args)
     }
+
+    /// Returns the entrypoint arguments schema of the controller, via its `describe` export.
+    pub fn describe<VM>(&self, vm: &VM) -> anyhow::Result<ControllerSchema>
+    where
+        VM: Vm<H, D>,
+    {
+        let schema = vm.execute(
+            self,
+            self.controller(),
+            Self::CONTROLLER_DESCRIBE,
+            Value::Null,
+        )?;
+
+        Ok(serde_json::from_value(schema)?)
+    }
 }
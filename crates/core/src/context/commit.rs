@@ -0,0 +1,93 @@
+use valence_coprocessor_types::{DataBackend, Hash, Hasher};
+
+use crate::{ExecutionContext, Permission};
+
+impl<H, D> ExecutionContext<H, D>
+where
+    H: Hasher,
+    D: DataBackend,
+{
+    /// Prefix for the labeled commitments of controllers.
+    pub const PREFIX_COMMIT: &[u8] = b"context-commit";
+
+    fn commit_key(&self, label: &str) -> Hash {
+        H::digest([self.controller().as_slice(), label.as_bytes()])
+    }
+
+    /// Computes the commitment of a labeled data.
+    ///
+    /// The label is prefixed with its little-endian `u64` length, so a label and data pair can't
+    /// collide with another split of the same bytes.
+    pub fn commitment(label: &str, data: &[u8]) -> Hash {
+        let len = (label.len() as u64).to_le_bytes();
+
+        H::digest([len.as_slice(), label.as_bytes(), data])
+    }
+
+    /// Stores a labeled commitment of the data, returning it.
+    ///
+    /// Commitments are scoped per controller, and persisted on the data backend until the same
+    /// controller commits again with the same label. They outlive the execution, so a later run
+    /// can read them via [`ExecutionContext::get_commit`].
+    pub fn commit(&self, label: &str, data: &[u8]) -> anyhow::Result<Hash> {
+        self.ensure(&Permission::CircuitStorageWrite(*self.controller()))?;
+
+        let commitment = Self::commitment(label, data);
+
+        self.data
+            .set(Self::PREFIX_COMMIT, &self.commit_key(label), &commitment)?;
+
+        Ok(commitment)
+    }
+
+    /// Returns the last commitment stored by the controller under the label.
+    pub fn get_commit(&self, label: &str) -> anyhow::Result<Option<Hash>> {
+        self.data
+            .get(Self::PREFIX_COMMIT, &self.commit_key(label))?
+            .map(Hash::try_from)
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("failed to read commitment"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Blake3Context, Blake3Hasher, Blake3Historical, MemoryBackend};
+
+    use super::*;
+
+    #[test]
+    fn commitments_persist_across_runs_per_controller() {
+        let data = MemoryBackend::default();
+        let historical = Blake3Historical::load(data).unwrap();
+
+        let controller = Blake3Hasher::hash(b"controller");
+        let other = Blake3Hasher::hash(b"other");
+
+        let first = historical.context(controller);
+        let commitment = first.commit("step-1", b"state").unwrap();
+
+        assert_eq!(
+            commitment,
+            Blake3Context::<MemoryBackend>::commitment("step-1", b"state")
+        );
+
+        let second = historical.context(controller);
+
+        assert_eq!(second.get_commit("step-1").unwrap(), Some(commitment));
+        assert_eq!(second.get_commit("step-2").unwrap(), None);
+        assert_eq!(
+            historical.context(other).get_commit("step-1").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn commitments_separate_the_label_from_the_data() {
+        let commitment = Blake3Context::<MemoryBackend>::commitment;
+
+        assert_ne!(commitment("ab", b"c"), commitment("a", b"bc"));
+        assert_ne!(commitment("", b"abc"), commitment("abc", b""));
+        assert_eq!(commitment("ab", b"c"), commitment("ab", b"c"));
+    }
+}
@@ -1,8 +1,12 @@
+use alloc::{string::String, vec::Vec};
+
 use valence_coprocessor_types::{
-    CompoundOpening, DataBackend, Hash, Hasher, HistoricalUpdate, ValidatedDomainBlock,
+    CompoundOpening, DataBackend, Hash, Hasher, HistoricalUpdate, Opening, ValidatedDomainBlock,
 };
 
-use crate::{ExecutionContext, Historical};
+use valence_coprocessor_merkle::Smt;
+
+use crate::{ExecutionContext, Historical, Permission};
 
 impl<H, D> ExecutionContext<H, D>
 where
@@ -12,11 +16,138 @@ where
     /// Controller function name to validate blocks.
     pub const CONTROLLER_VALIDATE_BLOCK: &str = "validate_block";
 
+    /// Prefix for the SMT roots of the controllers, per context.
+    pub const PREFIX_SMT_ROOT: &[u8] = b"context-smt-root";
+
+    /// Prefix for the SMT namespaces of the controllers, per context.
+    pub const PREFIX_SMT_NAMESPACE: &[u8] = b"context-smt-namespace";
+
+    /// Maximum number of attempts to swap the SMT root of a context, while other inserts race.
+    pub const SMT_ROOT_SWAP_ATTEMPTS: usize = 8;
+
+    fn smt_root_key(&self, context: &str) -> Hash {
+        H::digest([self.controller().as_slice(), context.as_bytes()])
+    }
+
+    /// Returns the namespace of the SMT of the provided context, scoped per controller.
+    pub fn smt_namespace(&self, context: &str) -> Hash {
+        H::digest([
+            Self::PREFIX_SMT_NAMESPACE,
+            self.controller().as_slice(),
+            context.as_bytes(),
+        ])
+    }
+
     /// Returns the last included block for the provided domain.
+    ///
+    /// Any registered domain can be queried, not only the one of the controller. The block is the
+    /// live tip of the domain, not pinned to the historical root of the execution.
     pub fn get_latest_block(&self, domain: &str) -> anyhow::Result<Option<ValidatedDomainBlock>> {
         Historical::<H, D>::get_latest_block(&self.data, domain)
     }
 
+    /// Returns the names of the registered domains, sorted.
+    ///
+    /// As [`ExecutionContext::get_latest_block`], this reads the live registry.
+    pub fn list_domains(&self) -> anyhow::Result<Vec<String>> {
+        self.registry.list_domains()
+    }
+
+    /// Returns `true` if the key is a member of the SMT of the provided context, under the
+    /// historical root of the execution context.
+    ///
+    /// The tree key is computed as `H::key(context, key)`, and no opening is computed.
+    pub fn smt_contains(&self, context: &str, key: &[u8]) -> anyhow::Result<bool> {
+        let key = H::key(context, key);
+        let smt: Smt<D, H> = Smt::from(self.data.clone()).with_namespace(context);
+        let opening = smt.get_keyed_opening(self.historical, &key)?;
+
+        Ok(opening.key == Some(key))
+    }
+
+    /// Returns the root of the SMT of the provided context, maintained by the controller via
+    /// [`ExecutionContext::smt_insert_and_open`].
+    ///
+    /// Defaults to the empty root if the controller never inserted into the context.
+    pub fn get_smt_context_root(&self, context: &str) -> anyhow::Result<Hash> {
+        self.data
+            .get(Self::PREFIX_SMT_ROOT, &self.smt_root_key(context))?
+            .map(Hash::try_from)
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("failed to read smt root"))
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Inserts the value into the SMT of the provided context, returning the new root and the
+    /// opening of the value to it.
+    ///
+    /// The tree key is computed as `H::key(context, key)`. The tree and its root are scoped per
+    /// controller, and the root is persisted on the data backend, so consecutive inserts of the
+    /// same controller chain from the previous root. The root is swapped atomically; concurrent
+    /// inserts are retried up to [`ExecutionContext::SMT_ROOT_SWAP_ATTEMPTS`] times.
+    pub fn smt_insert_and_open(
+        &self,
+        context: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> anyhow::Result<(Hash, Opening)> {
+        let root_key = self.smt_root_key(context);
+
+        for _ in 0..Self::SMT_ROOT_SWAP_ATTEMPTS {
+            let current = self.data.get(Self::PREFIX_SMT_ROOT, &root_key)?;
+            let root = current
+                .clone()
+                .map(Hash::try_from)
+                .transpose()
+                .map_err(|_| anyhow::anyhow!("failed to read smt root"))?
+                .unwrap_or_default();
+
+            let (root, opening) =
+                self.smt_insert_and_open_from(self.data.clone(), root, context, key, value)?;
+
+            if self.data.compare_and_swap(
+                Self::PREFIX_SMT_ROOT,
+                &root_key,
+                current.as_deref(),
+                &root,
+            )? {
+                return Ok((root, opening));
+            }
+        }
+
+        anyhow::bail!("the smt root of `{context}` is being updated concurrently")
+    }
+
+    /// Inserts the value into the SMT of the provided context on top of `root`, returning the new
+    /// root and the opening of the value to it.
+    ///
+    /// Unlike [`ExecutionContext::smt_insert_and_open`], the root isn't persisted, and the tree
+    /// nodes are written to `data`. A staging backend keeps the insert off the persisted state, as
+    /// the host does while a controller computes its witnesses.
+    pub fn smt_insert_and_open_from<B>(
+        &self,
+        data: B,
+        root: Hash,
+        context: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> anyhow::Result<(Hash, Opening)>
+    where
+        B: DataBackend,
+    {
+        self.ensure(&Permission::CircuitStorageWrite(*self.controller()))?;
+
+        let key = H::key(context, key);
+        let smt: Smt<B, H> = Smt::from(data).with_namespace(self.smt_namespace(context));
+
+        let root = smt.insert(root, &key, value)?;
+        let opening = smt
+            .get_opening(root, &key)?
+            .ok_or_else(|| anyhow::anyhow!("the inserted value has no opening"))?;
+
+        Ok((root, opening))
+    }
+
     /// Returns a Merkle proof that opens a block number to the historical root.
     pub fn get_block_proof(
         &self,
@@ -36,6 +167,17 @@ where
         Historical::<H, D>::get_historical_update_with_data(&self.data, root)
     }
 
+    /// Returns the clock of the execution, in Unix seconds.
+    ///
+    /// This isn't the wall clock: it is the time at which the historical root of the execution was
+    /// recorded, so every execution anchored to the same root observes the same value. Fails if the
+    /// root has no recorded update, as the empty tree.
+    pub fn now(&self) -> anyhow::Result<u64> {
+        self.get_historical_update(&self.historical)?
+            .map(|u| u.timestamp_ms() / 1000)
+            .ok_or_else(|| anyhow::anyhow!("the historical root has no recorded update"))
+    }
+
     /// Returns the chained historical update from the previous historical root.
     pub fn get_historical_update_from_previous(
         &self,
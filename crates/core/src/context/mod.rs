@@ -6,6 +6,7 @@ use crate::{Blake3Hasher, DataBackend, Hash, Hasher, Registry};
 
 mod auth;
 mod boilerplate;
+mod commit;
 mod domain;
 mod storage;
 mod zk;
@@ -58,6 +58,25 @@ where
         self.set_storage(&fs)
     }
 
+    /// Removes a controller storage file.
+    ///
+    /// Returns `true` if the file existed.
+    pub fn remove_storage_file(&self, path: &str) -> anyhow::Result<bool> {
+        self.ensure(&Permission::CircuitStorageWrite(*self.controller()))?;
+
+        let mut fs = self.get_storage()?;
+
+        if fs.open(path)?.new {
+            return Ok(false);
+        }
+
+        fs.rm(path)?;
+
+        self.set_storage(&fs)?;
+
+        Ok(true)
+    }
+
     /// Returns the controller raw storage.
     pub fn get_raw_storage(&self) -> anyhow::Result<Option<Vec<u8>>> {
         self.data
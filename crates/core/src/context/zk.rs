@@ -36,15 +36,20 @@ where
     }
 
     /// Compute the ZK proof of the provided circuit.
+    ///
+    /// The witness carries the data encoding selected when the controller was deployed.
     pub fn get_coprocessor_witness(
         &self,
         witnesses: Vec<Witness>,
     ) -> anyhow::Result<WitnessCoprocessor> {
+        let encoding = self.get_witness_encoding()?;
+
         WitnessCoprocessor::try_from_witnesses::<H, D>(
             self.data.clone(),
             self.historical,
             witnesses,
         )
+        .map(|w| w.with_encoding(encoding))
     }
 
     /// Returns the circuit verifying key.
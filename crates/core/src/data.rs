@@ -1,76 +1,134 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use hashbrown::HashMap;
 use valence_coprocessor_merkle::Smt;
 
 use crate::{Blake3Hasher, DataBackend, Hash, Hasher as _};
 
+/// The entries of a memory backend, grouped by prefix so they can be scanned.
+type Entries = HashMap<Hash, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// The staged entries of a [`StagingBackend`], grouped by prefix; `None` marks a removed key.
+type Staged = HashMap<Hash, BTreeMap<Vec<u8>, Option<Vec<u8>>>>;
+
 /// A memory data backend.
 #[derive(Debug, Clone, Default)]
 pub struct MemoryBackend {
-    data: Arc<Mutex<HashMap<Hash, Vec<u8>>>>,
-    bulk: Arc<Mutex<HashMap<Hash, Vec<u8>>>>,
+    data: Arc<Mutex<Entries>>,
+    bulk: Arc<Mutex<Entries>>,
+}
+
+impl MemoryBackend {
+    fn prefix(prefix: &[u8]) -> Hash {
+        Blake3Hasher::digest([b"data", prefix])
+    }
+
+    fn lock<T>(entries: &Mutex<T>) -> anyhow::Result<MutexGuard<'_, T>> {
+        entries
+            .lock()
+            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))
+    }
 }
 
 impl DataBackend for MemoryBackend {
     fn get(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let data = self
-            .data
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let data = Self::lock(&self.data)?;
 
-        Ok(data.get(&key).cloned())
+        Ok(data
+            .get(&Self::prefix(prefix))
+            .and_then(|d| d.get(key))
+            .cloned())
     }
 
     fn has(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<bool> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let data = self
-            .data
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let data = Self::lock(&self.data)?;
 
-        Ok(data.get(&key).is_some())
+        Ok(data
+            .get(&Self::prefix(prefix))
+            .is_some_and(|d| d.contains_key(key)))
     }
 
     fn remove(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let mut data = self
-            .data
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let mut data = Self::lock(&self.data)?;
+        let prefix = Self::prefix(prefix);
+
+        let d = match data.get_mut(&prefix) {
+            Some(d) => d,
+            None => return Ok(None),
+        };
 
-        Ok(data.remove(&key))
+        let removed = d.remove(key);
+
+        if d.is_empty() {
+            data.remove(&prefix);
+        }
+
+        Ok(removed)
     }
 
     fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let mut d = self
-            .data
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let mut d = Self::lock(&self.data)?;
+
+        Ok(d.entry(Self::prefix(prefix))
+            .or_default()
+            .insert(key.to_vec(), data.to_vec()))
+    }
+
+    fn scan_prefix_limited(
+        &self,
+        prefix: &[u8],
+        max: usize,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let data = Self::lock(&self.data)?;
 
-        Ok(d.insert(key, data.to_vec()))
+        Ok(data
+            .get(&Self::prefix(prefix))
+            .map(|d| {
+                d.iter()
+                    .take(max)
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn compare_and_swap(
+        &self,
+        prefix: &[u8],
+        key: &[u8],
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> anyhow::Result<bool> {
+        let mut d = Self::lock(&self.data)?;
+        let entries = d.entry(Self::prefix(prefix)).or_default();
+
+        if entries.get(key).map(Vec::as_slice) != expected {
+            return Ok(false);
+        }
+
+        entries.insert(key.to_vec(), data.to_vec());
+
+        Ok(true)
     }
 
     fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let data = self
-            .bulk
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let data = Self::lock(&self.bulk)?;
 
-        Ok(data.get(&key).cloned())
+        Ok(data
+            .get(&Self::prefix(prefix))
+            .and_then(|d| d.get(key))
+            .cloned())
     }
 
     fn set_bulk(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<()> {
-        let key = Blake3Hasher::digest([b"data", prefix, key]);
-        let mut d = self
-            .bulk
-            .lock()
-            .map_err(|e| anyhow::anyhow!("failed to lock data backend: {e}"))?;
+        let mut d = Self::lock(&self.bulk)?;
 
-        d.insert(key, data.to_vec());
+        d.entry(Self::prefix(prefix))
+            .or_default()
+            .insert(key.to_vec(), data.to_vec());
 
         Ok(())
     }
@@ -78,3 +136,223 @@ impl DataBackend for MemoryBackend {
 
 /// An in-memory SMT implementation.
 pub type MemorySmt = Smt<MemoryBackend, Blake3Hasher>;
+
+/// A data backend that keeps its writes in memory, on top of another backend.
+///
+/// Reads fall through to the underlying backend for the keys that weren't written or removed, so
+/// a computation can run against the persisted state without mutating it.
+#[derive(Debug, Clone)]
+pub struct StagingBackend<D> {
+    inner: D,
+    data: Arc<Mutex<Staged>>,
+    bulk: Arc<Mutex<Staged>>,
+}
+
+impl<D: DataBackend> From<D> for StagingBackend<D> {
+    fn from(inner: D) -> Self {
+        Self {
+            inner,
+            data: Default::default(),
+            bulk: Default::default(),
+        }
+    }
+}
+
+impl<D: DataBackend> StagingBackend<D> {
+    /// Returns the underlying backend.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    fn read(
+        staged: &Staged,
+        prefix: &[u8],
+        key: &[u8],
+        inner: impl FnOnce() -> anyhow::Result<Option<Vec<u8>>>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        match staged
+            .get(&MemoryBackend::prefix(prefix))
+            .and_then(|d| d.get(key))
+        {
+            Some(d) => Ok(d.clone()),
+            None => inner(),
+        }
+    }
+
+    fn write(
+        &self,
+        prefix: &[u8],
+        key: &[u8],
+        data: Option<&[u8]>,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut staged = MemoryBackend::lock(&self.data)?;
+        let previous = Self::read(&staged, prefix, key, || self.inner.get(prefix, key))?;
+
+        staged
+            .entry(MemoryBackend::prefix(prefix))
+            .or_default()
+            .insert(key.to_vec(), data.map(<[u8]>::to_vec));
+
+        Ok(previous)
+    }
+}
+
+impl<D: DataBackend> DataBackend for StagingBackend<D> {
+    fn get(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let staged = MemoryBackend::lock(&self.data)?;
+
+        Self::read(&staged, prefix, key, || self.inner.get(prefix, key))
+    }
+
+    fn has(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<bool> {
+        self.get(prefix, key).map(|d| d.is_some())
+    }
+
+    fn remove(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.write(prefix, key, None)
+    }
+
+    fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.write(prefix, key, Some(data))
+    }
+
+    fn scan_prefix_limited(
+        &self,
+        prefix: &[u8],
+        max: usize,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let staged = MemoryBackend::lock(&self.data)?;
+        let mut entries: BTreeMap<_, _> = self.inner.scan_prefix(prefix)?.into_iter().collect();
+
+        for (key, data) in staged
+            .get(&MemoryBackend::prefix(prefix))
+            .into_iter()
+            .flatten()
+        {
+            match data {
+                Some(d) => entries.insert(key.clone(), d.clone()),
+                None => entries.remove(key),
+            };
+        }
+
+        Ok(entries.into_iter().take(max).collect())
+    }
+
+    fn compare_and_swap(
+        &self,
+        prefix: &[u8],
+        key: &[u8],
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> anyhow::Result<bool> {
+        let mut staged = MemoryBackend::lock(&self.data)?;
+        let current = Self::read(&staged, prefix, key, || self.inner.get(prefix, key))?;
+
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+
+        staged
+            .entry(MemoryBackend::prefix(prefix))
+            .or_default()
+            .insert(key.to_vec(), Some(data.to_vec()));
+
+        Ok(true)
+    }
+
+    fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let staged = MemoryBackend::lock(&self.bulk)?;
+
+        Self::read(&staged, prefix, key, || self.inner.get_bulk(prefix, key))
+    }
+
+    fn set_bulk(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        MemoryBackend::lock(&self.bulk)?
+            .entry(MemoryBackend::prefix(prefix))
+            .or_default()
+            .insert(key.to_vec(), Some(data.to_vec()));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_returns_the_keys_of_the_prefix() -> anyhow::Result<()> {
+        let backend = MemoryBackend::default();
+
+        backend.set(b"poems", b"frost", b"The Road Not Taken")?;
+        backend.set(b"poems", b"sandburg", b"Fog")?;
+        backend.set(b"novels", b"melville", b"Moby-Dick")?;
+        backend.set_bulk(b"poems", b"whitman", b"Leaves of Grass")?;
+
+        assert_eq!(
+            backend.scan_prefix(b"poems")?,
+            vec![
+                (b"frost".to_vec(), b"The Road Not Taken".to_vec()),
+                (b"sandburg".to_vec(), b"Fog".to_vec()),
+            ]
+        );
+
+        assert_eq!(backend.scan_prefix_limited(b"poems", 1)?.len(), 1);
+
+        backend.remove(b"novels", b"melville")?;
+
+        assert!(backend.scan_prefix(b"novels")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compare_and_swap_checks_the_current_data() -> anyhow::Result<()> {
+        let backend = MemoryBackend::default();
+
+        assert!(!backend.compare_and_swap(b"poems", b"frost", Some(b"Fire"), b"Ice")?);
+        assert!(backend.compare_and_swap(b"poems", b"frost", None, b"Fire")?);
+        assert!(!backend.compare_and_swap(b"poems", b"frost", None, b"Ice")?);
+        assert!(backend.compare_and_swap(b"poems", b"frost", Some(b"Fire"), b"Ice")?);
+        assert_eq!(backend.get(b"poems", b"frost")?, Some(b"Ice".to_vec()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn staged_writes_dont_reach_the_inner_backend() -> anyhow::Result<()> {
+        let backend = MemoryBackend::default();
+
+        backend.set(b"poems", b"frost", b"The Road Not Taken")?;
+        backend.set(b"poems", b"sandburg", b"Fog")?;
+
+        let staging = StagingBackend::from(backend.clone());
+
+        staging.set(b"poems", b"frost", b"Fire and Ice")?;
+        staging.set(b"poems", b"whitman", b"Leaves of Grass")?;
+        staging.remove(b"poems", b"sandburg")?;
+
+        assert_eq!(
+            staging.get(b"poems", b"frost")?,
+            Some(b"Fire and Ice".to_vec())
+        );
+        assert!(!staging.has(b"poems", b"sandburg")?);
+        assert_eq!(
+            staging.scan_prefix(b"poems")?,
+            vec![
+                (b"frost".to_vec(), b"Fire and Ice".to_vec()),
+                (b"whitman".to_vec(), b"Leaves of Grass".to_vec()),
+            ]
+        );
+
+        assert_eq!(
+            backend.scan_prefix(b"poems")?,
+            vec![
+                (b"frost".to_vec(), b"The Road Not Taken".to_vec()),
+                (b"sandburg".to_vec(), b"Fog".to_vec()),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use alloc::vec::Vec;
+use msgpacker::{Packable, Unpackable};
 use serde_json::Value;
 
-use crate::StateProof;
+use crate::{DomainData, Hash, StateProof};
 
 /// A domain definition for circuit verification.
 pub trait DomainCircuit {
@@ -26,3 +28,63 @@ pub trait DomainController {
         Ok(serde_json::to_value(proof)?)
     }
 }
+
+/// A chain domain, bundling the controller and circuit sides of its state proofs.
+///
+/// Implementors get [`DomainController`] and [`DomainCircuit`] via blanket implementations. The
+/// controller arguments are `{"key": .., "height": ..}`, forwarded to
+/// [`ChainDomain::fetch_proof`]; the proof is packed into the [`StateProof`], at the fetched root.
+pub trait ChainDomain {
+    /// A constant identifier.
+    const ID: &str;
+
+    /// The domain-specific proof.
+    type Proof: Packable + Unpackable<Error = msgpacker::Error>;
+
+    /// The output of a verified proof.
+    type Output;
+
+    /// Fetches a proof of the key at the provided height, along with the root it opens to.
+    fn fetch_proof(&self, key: &Value, height: u64) -> anyhow::Result<(Self::Proof, Hash)>;
+
+    /// Verifies the proof against a trusted root.
+    fn verify_against_root(proof: &Self::Proof, root: &Hash) -> anyhow::Result<Self::Output>;
+}
+
+impl<T: ChainDomain> DomainController for T {
+    const ID: &str = T::ID;
+
+    fn state_proof(&self, args: Value) -> anyhow::Result<StateProof> {
+        let key = args
+            .get("key")
+            .ok_or_else(|| anyhow::anyhow!("missing `key`"))?;
+
+        let height = args
+            .get("height")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow::anyhow!("missing `height`"))?;
+
+        let (proof, root) = self.fetch_proof(key, height)?;
+
+        StateProof::build(T::ID, root, Vec::new())
+            .with_number(height)
+            .with_proof(proof.pack_to_vec())
+    }
+}
+
+impl<T: ChainDomain> DomainCircuit for T {
+    type Output = T::Output;
+
+    fn verify(proof: &StateProof) -> anyhow::Result<Self::Output> {
+        anyhow::ensure!(
+            proof.domain == DomainData::identifier_from_parts(T::ID),
+            "the state proof isn't of the `{}` domain",
+            T::ID
+        );
+
+        let (_, opening) = T::Proof::unpack(&proof.proof)
+            .map_err(|e| anyhow::anyhow!("invalid `{}` state proof: {e}", T::ID))?;
+
+        T::verify_against_root(&opening, &proof.state_root)
+    }
+}
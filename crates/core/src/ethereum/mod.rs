@@ -0,0 +1,330 @@
+use alloc::{string::String, vec::Vec};
+use msgpacker::MsgPacker;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use valence_coprocessor_types::Hash;
+
+use crate::ChainDomain;
+
+mod receipt;
+mod rlp;
+mod trie;
+
+#[cfg(test)]
+mod tests;
+
+pub use receipt::*;
+pub use trie::EMPTY_TRIE_ROOT;
+
+use rlp::Rlp;
+
+/// The Ethereum domain, proving storage slots against the state root of a block.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Ethereum {
+    /// JSON-RPC endpoint used to fetch the proofs.
+    pub rpc: String,
+}
+
+impl Ethereum {
+    /// Creates the domain with the provided JSON-RPC endpoint.
+    pub fn new<R>(rpc: R) -> Self
+    where
+        R: Into<String>,
+    {
+        Self { rpc: rpc.into() }
+    }
+
+    /// Calls a JSON-RPC method of the endpoint, returning its result.
+    #[cfg(feature = "std")]
+    pub fn call(&self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let response = crate::utils::http(&json!({
+            "url": self.rpc,
+            "method": "post",
+            "headers": {
+                "Accept": "application/json",
+            },
+            "json": {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            },
+        }))?;
+
+        let body = &response["body"];
+
+        if let Some(e) = body.get("error") {
+            anyhow::bail!("`{method}` failed: {e}");
+        }
+
+        body.get("result")
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("`{method}` returned no result"))
+    }
+
+    /// Calls a JSON-RPC method of the endpoint, returning its result.
+    #[cfg(not(feature = "std"))]
+    pub fn call(&self, method: &str, _params: Value) -> anyhow::Result<Value> {
+        anyhow::bail!("calling `{method}` requires the `std` feature")
+    }
+}
+
+impl ChainDomain for Ethereum {
+    const ID: &str = "ethereum";
+
+    type Proof = EthereumStateProof;
+    type Output = EthereumStorageOutput;
+
+    /// Fetches the proof of the `slot` of the `address` of the key, at the state root of the block.
+    fn fetch_proof(&self, key: &Value, height: u64) -> anyhow::Result<(Self::Proof, Hash)> {
+        let address = const_hex::encode_prefixed(hex_field(key, "address")?);
+        let slot = pad32(&hex_field(key, "slot")?)?;
+        let block = alloc::format!("{height:#x}");
+
+        let header = self.call("eth_getBlockByNumber", json!([block, false]))?;
+        let state_root = pad32(&hex_field(&header, "stateRoot")?)?;
+
+        let response = self.call(
+            "eth_getProof",
+            json!([address, [const_hex::encode_prefixed(slot)], block]),
+        )?;
+        let proof = EthereumStateProof::from_eth_get_proof(&response, &slot)?;
+
+        Ok((proof, state_root))
+    }
+
+    fn verify_against_root(proof: &Self::Proof, root: &Hash) -> anyhow::Result<Self::Output> {
+        proof.verify(root)
+    }
+}
+
+/// An Ethereum account opening to a state root, as returned by `eth_getProof`.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumAccountProof {
+    /// Address of the account.
+    pub address: Vec<u8>,
+
+    /// Big-endian balance of the account, without leading zeroes.
+    pub balance: Vec<u8>,
+
+    /// Nonce of the account.
+    pub nonce: u64,
+
+    /// Hash of the account code.
+    pub code_hash: Hash,
+
+    /// Root of the account storage trie.
+    pub storage_hash: Hash,
+
+    /// RLP encoded trie nodes, from the state root to the account leaf.
+    pub proof: Vec<Vec<u8>>,
+}
+
+impl EthereumAccountProof {
+    /// Verifies the account against the trusted state root.
+    pub fn verify(&self, state_root: &Hash) -> anyhow::Result<()> {
+        let account = trie::verify_trie(state_root, &trie::keccak256(&self.address), &self.proof)?
+            .ok_or_else(|| anyhow::anyhow!("the account isn't present in the state"))?;
+
+        let fields = Rlp::decode(&account)?.items()?;
+
+        anyhow::ensure!(fields.len() == 4, "invalid account fields");
+        anyhow::ensure!(
+            fields[0].bytes()? == quantity(&self.nonce.to_be_bytes())
+                && fields[1].bytes()? == self.balance
+                && fields[2].bytes()? == self.storage_hash
+                && fields[3].bytes()? == self.code_hash,
+            "the account doesn't match the opened trie value"
+        );
+
+        Ok(())
+    }
+
+    /// Parses the account opening of a `eth_getProof` response.
+    ///
+    /// Accepts either the JSON-RPC envelope, or its `result` object.
+    pub fn from_eth_get_proof(response: &Value) -> anyhow::Result<Self> {
+        let response = response.get("result").unwrap_or(response);
+
+        Ok(Self {
+            address: hex_field(response, "address")?,
+            balance: quantity(&hex_field(response, "balance")?),
+            nonce: u64_field(response, "nonce")?,
+            code_hash: pad32(&hex_field(response, "codeHash")?)?,
+            storage_hash: pad32(&hex_field(response, "storageHash")?)?,
+            proof: nodes(response, "accountProof")?,
+        })
+    }
+}
+
+/// An Ethereum storage slot opening to the storage root of its account.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumStorageProof {
+    /// Storage slot, left-padded to 32 bytes.
+    pub key: Hash,
+
+    /// Big-endian value of the slot, without leading zeroes.
+    pub value: Vec<u8>,
+
+    /// RLP encoded trie nodes, from the storage root to the slot leaf.
+    pub proof: Vec<Vec<u8>>,
+}
+
+/// An Ethereum storage slot opening to a state root.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumStateProof {
+    /// Opening of the account to the state root.
+    pub account: EthereumAccountProof,
+
+    /// Opening of the slot to the account storage root.
+    pub storage: EthereumStorageProof,
+}
+
+/// A storage slot proven against a state root.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumStorageOutput {
+    /// State root the account was opened to.
+    pub state_root: Hash,
+
+    /// Address of the account.
+    pub address: Vec<u8>,
+
+    /// Root of the account storage trie.
+    pub storage_hash: Hash,
+
+    /// Storage slot, left-padded to 32 bytes.
+    pub key: Hash,
+
+    /// Big-endian value of the slot, without leading zeroes; empty for unset slots.
+    pub value: Vec<u8>,
+}
+
+impl EthereumStateProof {
+    /// Verifies the account against the trusted state root, then the slot against the storage
+    /// root of the account.
+    ///
+    /// An unset slot verifies with an empty value, if the opening proves its absence.
+    pub fn verify(&self, state_root: &Hash) -> anyhow::Result<EthereumStorageOutput> {
+        self.account.verify(state_root)?;
+
+        let value = trie::verify_trie(
+            &self.account.storage_hash,
+            &trie::keccak256(&self.storage.key),
+            &self.storage.proof,
+        )?;
+
+        let value = match &value {
+            Some(v) => Rlp::decode(v)?.bytes()?,
+            None => &[],
+        };
+
+        anyhow::ensure!(
+            value == self.storage.value,
+            "the slot value doesn't match the opened trie value"
+        );
+
+        Ok(EthereumStorageOutput {
+            state_root: *state_root,
+            address: self.account.address.clone(),
+            storage_hash: self.account.storage_hash,
+            key: self.storage.key,
+            value: self.storage.value.clone(),
+        })
+    }
+
+    /// Parses a `eth_getProof` response, selecting the storage proof of the provided slot.
+    ///
+    /// Accepts either the JSON-RPC envelope, or its `result` object.
+    pub fn from_eth_get_proof(response: &Value, slot: &[u8]) -> anyhow::Result<Self> {
+        let response = response.get("result").unwrap_or(response);
+        let slot = pad32(slot)?;
+        let account = EthereumAccountProof::from_eth_get_proof(response)?;
+
+        let storage = response
+            .get("storageProof")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow::anyhow!("missing `storageProof`"))?
+            .iter()
+            .map(|p| -> anyhow::Result<EthereumStorageProof> {
+                Ok(EthereumStorageProof {
+                    key: pad32(&hex_field(p, "key")?)?,
+                    value: quantity(&hex_field(p, "value")?),
+                    proof: nodes(p, "proof")?,
+                })
+            })
+            .find(|p| p.as_ref().map(|p| p.key == slot).unwrap_or(true))
+            .ok_or_else(|| {
+                anyhow::anyhow!("no storage proof for slot `{}`", const_hex::encode(slot))
+            })??;
+
+        Ok(Self { account, storage })
+    }
+}
+
+fn decode_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+
+    let bytes = if s.len() % 2 == 1 {
+        const_hex::decode(alloc::format!("0{s}"))
+    } else {
+        const_hex::decode(s)
+    };
+
+    bytes.map_err(|e| anyhow::anyhow!("invalid hex `{s}`: {e}"))
+}
+
+fn hex_field(value: &Value, field: &str) -> anyhow::Result<Vec<u8>> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("missing `{field}`"))
+        .and_then(decode_hex)
+}
+
+fn u64_field(value: &Value, field: &str) -> anyhow::Result<u64> {
+    let bytes = quantity(&hex_field(value, field)?);
+
+    anyhow::ensure!(bytes.len() <= 8, "`{field}` overflows a u64");
+
+    Ok(bytes.iter().fold(0, |n, b| (n << 8) | *b as u64))
+}
+
+fn nodes(value: &Value, field: &str) -> anyhow::Result<Vec<Vec<u8>>> {
+    value
+        .get(field)
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow::anyhow!("missing `{field}`"))?
+        .iter()
+        .map(|n| {
+            n.as_str()
+                .ok_or_else(|| anyhow::anyhow!("invalid `{field}` node"))
+                .and_then(decode_hex)
+        })
+        .collect()
+}
+
+fn quantity(bytes: &[u8]) -> Vec<u8> {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+
+    bytes[start..].to_vec()
+}
+
+fn pad32(bytes: &[u8]) -> anyhow::Result<Hash> {
+    let bytes = quantity(bytes);
+
+    anyhow::ensure!(bytes.len() <= 32, "value exceeds 32 bytes");
+
+    let mut hash = Hash::default();
+
+    hash[32 - bytes.len()..].copy_from_slice(&bytes);
+
+    Ok(hash)
+}
@@ -0,0 +1,130 @@
+use alloc::vec::Vec;
+use msgpacker::MsgPacker;
+use serde::{Deserialize, Serialize};
+use valence_coprocessor_types::Hash;
+
+use super::{
+    rlp::{self, Rlp},
+    trie::{keccak256, verify_trie},
+};
+
+/// An Ethereum receipt opening to the receipts root of its block, selecting one of its logs.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumReceiptProof {
+    /// Index of the receipt in the block.
+    pub receipt_index: u64,
+
+    /// Consensus encoding of the receipt, prefixed by its type for typed receipts.
+    pub receipt_rlp: Vec<u8>,
+
+    /// RLP encoded trie nodes, from the receipts root to the receipt leaf.
+    pub opening: Vec<Vec<u8>>,
+
+    /// Index of the selected log in the receipt.
+    pub log_index: u64,
+
+    /// Whether the address and topics of the log must also be set in the receipt bloom.
+    pub logs_bloom_check: bool,
+}
+
+/// A log proven to be emitted in a block.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct EthereumLogOutput {
+    /// Receipts root of the block.
+    pub receipts_root: Hash,
+
+    /// Index of the receipt in the block.
+    pub receipt_index: u64,
+
+    /// Index of the log in the receipt.
+    pub log_index: u64,
+
+    /// Address of the emitting contract.
+    pub address: Vec<u8>,
+
+    /// Topics of the log; the first is the event signature for non-anonymous events.
+    pub topics: Vec<Hash>,
+
+    /// ABI encoded data of the log.
+    pub data: Vec<u8>,
+}
+
+impl EthereumReceiptProof {
+    /// Verifies the receipt against the trusted receipts root of its block, and extracts the
+    /// selected log.
+    pub fn verify(&self, receipts_root: &Hash) -> anyhow::Result<EthereumLogOutput> {
+        let key = rlp::encode_u64(self.receipt_index);
+        let value = verify_trie(receipts_root, &key, &self.opening)?
+            .ok_or_else(|| anyhow::anyhow!("no receipt at index `{}`", self.receipt_index))?;
+
+        anyhow::ensure!(
+            value == self.receipt_rlp,
+            "the receipt doesn't match the opened trie value"
+        );
+
+        // typed receipts are prefixed by their type, below the RLP list prefixes
+        let receipt = match self.receipt_rlp.first() {
+            Some(t) if *t <= 0x7f => &self.receipt_rlp[1..],
+            _ => &self.receipt_rlp[..],
+        };
+
+        let fields = Rlp::decode(receipt)?.items()?;
+
+        anyhow::ensure!(fields.len() == 4, "invalid receipt fields");
+
+        let bloom = fields[2].bytes()?;
+        let logs = fields[3].items()?;
+        let log = logs
+            .get(self.log_index as usize)
+            .ok_or_else(|| anyhow::anyhow!("no log at index `{}`", self.log_index))?
+            .items()?;
+
+        anyhow::ensure!(log.len() == 3, "invalid log fields");
+
+        let address = log[0].bytes()?.to_vec();
+        let topics = log[1]
+            .items()?
+            .iter()
+            .map(|t| {
+                t.bytes()?
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("invalid log topic"))
+            })
+            .collect::<anyhow::Result<Vec<Hash>>>()?;
+
+        anyhow::ensure!(address.len() == 20, "invalid log address");
+
+        if self.logs_bloom_check {
+            anyhow::ensure!(bloom.len() == 256, "invalid receipt logs bloom");
+
+            let absent = core::iter::once(address.as_slice())
+                .chain(topics.iter().map(|t| t.as_slice()))
+                .find(|i| !bloom_contains(bloom, i));
+
+            anyhow::ensure!(absent.is_none(), "the log isn't set in the receipt bloom");
+        }
+
+        Ok(EthereumLogOutput {
+            receipts_root: *receipts_root,
+            receipt_index: self.receipt_index,
+            log_index: self.log_index,
+            address,
+            topics,
+            data: log[2].bytes()?.to_vec(),
+        })
+    }
+}
+
+fn bloom_contains(bloom: &[u8], item: &[u8]) -> bool {
+    let hash = keccak256(item);
+
+    hash[..6].chunks(2).all(|c| {
+        let bit = (((c[0] as usize) << 8) | c[1] as usize) & 2047;
+
+        bloom[255 - bit / 8] & (1 << (bit % 8)) != 0
+    })
+}
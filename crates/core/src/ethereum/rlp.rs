@@ -0,0 +1,130 @@
+use alloc::vec::Vec;
+
+/// A decoded RLP item, borrowing from its encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rlp<'a> {
+    /// A byte string.
+    Bytes(&'a [u8]),
+
+    /// The concatenated encoding of the items of a list.
+    List(&'a [u8]),
+}
+
+impl<'a> Rlp<'a> {
+    /// Decodes a single item that spans the whole input.
+    pub fn decode(bytes: &'a [u8]) -> anyhow::Result<Self> {
+        let (item, rest) = Self::decode_prefix(bytes)?;
+
+        anyhow::ensure!(rest.is_empty(), "trailing bytes after RLP item");
+
+        Ok(item)
+    }
+
+    /// Decodes the first item of the input, returning it with the remaining bytes.
+    pub fn decode_prefix(bytes: &'a [u8]) -> anyhow::Result<(Self, &'a [u8])> {
+        let (prefix, bytes) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of RLP input"))?;
+
+        let (list, offset, len) = match *prefix {
+            // a single byte below 0x80 is its own encoding
+            0x00..=0x7f => return Ok((Self::Bytes(core::slice::from_ref(prefix)), bytes)),
+            p @ 0x80..=0xb7 => (false, 0, (p - 0x80) as usize),
+            p @ 0xb8..=0xbf => (false, (p - 0xb7) as usize, length(bytes, p - 0xb7)?),
+            p @ 0xc0..=0xf7 => (true, 0, (p - 0xc0) as usize),
+            p @ 0xf8..=0xff => (true, (p - 0xf7) as usize, length(bytes, p - 0xf7)?),
+        };
+
+        let end = offset
+            .checked_add(len)
+            .filter(|e| *e <= bytes.len())
+            .ok_or_else(|| anyhow::anyhow!("RLP item exceeds its input"))?;
+
+        let payload = &bytes[offset..end];
+        let item = if list {
+            Self::List(payload)
+        } else {
+            Self::Bytes(payload)
+        };
+
+        Ok((item, &bytes[end..]))
+    }
+
+    /// Returns the byte string, failing for lists.
+    pub fn bytes(&self) -> anyhow::Result<&'a [u8]> {
+        match self {
+            Self::Bytes(b) => Ok(b),
+            Self::List(_) => anyhow::bail!("expected an RLP string, found a list"),
+        }
+    }
+
+    /// Returns the items of the list, failing for byte strings.
+    pub fn items(&self) -> anyhow::Result<Vec<Rlp<'a>>> {
+        let mut payload = match self {
+            Self::List(l) => *l,
+            Self::Bytes(_) => anyhow::bail!("expected an RLP list, found a string"),
+        };
+
+        let mut items = Vec::new();
+
+        while !payload.is_empty() {
+            let (item, rest) = Self::decode_prefix(payload)?;
+
+            items.push(item);
+            payload = rest;
+        }
+
+        Ok(items)
+    }
+}
+
+fn length(bytes: &[u8], len: u8) -> anyhow::Result<usize> {
+    let len = len as usize;
+
+    anyhow::ensure!(
+        len <= bytes.len() && len <= size_of::<usize>(),
+        "invalid RLP length prefix"
+    );
+    anyhow::ensure!(bytes[0] != 0, "RLP length with leading zeroes");
+
+    Ok(bytes[..len].iter().fold(0, |n, b| (n << 8) | *b as usize))
+}
+
+/// Encodes a byte string.
+pub(crate) fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [b] if *b < 0x80 => alloc::vec![*b],
+        _ => encode_payload(0x80, bytes),
+    }
+}
+
+/// Encodes a list from the encodings of its items.
+#[cfg(test)]
+pub(crate) fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    encode_payload(0xc0, &items.concat())
+}
+
+/// Encodes an unsigned integer as a big-endian string, without leading zeroes.
+pub(crate) fn encode_u64(n: u64) -> Vec<u8> {
+    let bytes = n.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+
+    encode_bytes(&bytes[start..])
+}
+
+fn encode_payload(offset: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 9);
+
+    if payload.len() < 56 {
+        out.push(offset + payload.len() as u8);
+    } else {
+        let len = (payload.len() as u64).to_be_bytes();
+        let start = len.iter().position(|b| *b != 0).unwrap_or(len.len());
+
+        out.push(offset + 55 + (len.len() - start) as u8);
+        out.extend_from_slice(&len[start..]);
+    }
+
+    out.extend_from_slice(payload);
+    out
+}
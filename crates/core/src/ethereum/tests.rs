@@ -0,0 +1,603 @@
+use alloc::vec;
+use msgpacker::{Packable as _, Unpackable as _};
+
+use super::*;
+use crate::{DomainCircuit as _, DomainData, StateProof};
+
+/// A `eth_getProof` response captured from mainnet, for the account of the Uniswap V3 positions
+/// NFT, without storage slots.
+const FIXTURE: &str = include_str!("eth_get_proof.json");
+
+fn fixture() -> Value {
+    serde_json::from_str(FIXTURE).unwrap()
+}
+
+/// The state root of the block the fixture was captured at, committed to by its root node.
+fn fixture_state_root() -> Hash {
+    trie::keccak256(
+        &EthereumAccountProof::from_eth_get_proof(&fixture())
+            .unwrap()
+            .proof[0],
+    )
+}
+
+#[test]
+fn eth_get_proof_parses_the_account() {
+    let response = fixture();
+    let result = &response["result"];
+
+    let account = EthereumAccountProof::from_eth_get_proof(&response).unwrap();
+
+    assert_eq!(
+        account.address,
+        hex::decode("c36442b4a4522e871399cd717abdd847ab11fe88").unwrap()
+    );
+    assert!(account.balance.is_empty());
+    assert_eq!(account.nonce, 1);
+    assert_eq!(
+        hex::encode(account.storage_hash),
+        result["storageHash"].as_str().unwrap()[2..]
+    );
+    assert_eq!(
+        hex::encode(account.code_hash),
+        result["codeHash"].as_str().unwrap()[2..]
+    );
+
+    let nodes: Vec<_> = result["accountProof"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n.as_str().unwrap().to_string())
+        .collect();
+    let parsed: Vec<_> = account
+        .proof
+        .iter()
+        .map(const_hex::encode_prefixed)
+        .collect();
+
+    assert_eq!(nodes, parsed);
+    assert_eq!(
+        EthereumAccountProof::from_eth_get_proof(result).unwrap(),
+        account
+    );
+}
+
+#[test]
+fn eth_get_proof_capture_verifies_against_its_state_root() {
+    let state_root = fixture_state_root();
+    let account = EthereumAccountProof::from_eth_get_proof(&fixture()).unwrap();
+
+    account.verify(&state_root).unwrap();
+
+    assert!(account.verify(&[0; 32]).is_err());
+
+    let mut forged = account.clone();
+
+    forged.storage_hash[0] ^= 1;
+
+    assert!(forged.verify(&state_root).is_err());
+
+    let mut forged = account.clone();
+
+    forged.balance = vec![1];
+
+    assert!(forged.verify(&state_root).is_err());
+
+    // every node must chain to its parent by hash
+    for i in 1..account.proof.len() {
+        let mut forged = account.clone();
+        let last = forged.proof[i].len() - 1;
+
+        forged.proof[i][last] ^= 1;
+
+        assert!(forged.verify(&state_root).is_err());
+    }
+}
+
+#[test]
+fn eth_get_proof_round_trips() {
+    let (_, response) = verifiable_eth_get_proof();
+    let proof = EthereumStateProof::from_eth_get_proof(&response, &[2]).unwrap();
+
+    let packed = proof.pack_to_vec();
+    let (_, unpacked) = EthereumStateProof::unpack(&packed).unwrap();
+
+    assert_eq!(proof, unpacked);
+
+    let json = serde_json::to_value(&proof).unwrap();
+
+    assert_eq!(proof, serde_json::from_value(json).unwrap());
+
+    let mut padded = [0; 32];
+
+    padded[31] = 2;
+
+    let unpadded = EthereumStateProof::from_eth_get_proof(&response["result"], &padded).unwrap();
+
+    assert_eq!(unpadded, proof);
+}
+
+#[test]
+fn eth_get_proof_rejects_missing_slot() {
+    let err = EthereumStateProof::from_eth_get_proof(&fixture(), &[7]).unwrap_err();
+
+    assert!(err.to_string().contains("no storage proof"));
+}
+
+fn transfer_log() -> Vec<u8> {
+    let topics = [
+        trie::keccak256(b"Transfer(address,address,uint256)"),
+        pad32(&[0x22; 20]).unwrap(),
+        pad32(&[0x33; 20]).unwrap(),
+    ];
+
+    rlp::encode_list(&[
+        rlp::encode_bytes(&[0x11; 20]),
+        rlp::encode_list(&topics.map(|t| rlp::encode_bytes(&t))),
+        rlp::encode_bytes(&pad32(&[0x05, 0xf5, 0xe1, 0x00]).unwrap()),
+    ])
+}
+
+fn bloom_of(items: &[&[u8]]) -> Vec<u8> {
+    let mut bloom = vec![0u8; 256];
+
+    for i in items {
+        for c in trie::keccak256(i)[..6].chunks(2) {
+            let bit = (((c[0] as usize) << 8) | c[1] as usize) & 2047;
+
+            bloom[255 - bit / 8] |= 1 << (bit % 8);
+        }
+    }
+
+    bloom
+}
+
+/// A legacy receipt with an empty bloom, and a typed receipt with a correct bloom, both emitting
+/// the same `Transfer`.
+fn receipts_trie() -> (Hash, Vec<EthereumReceiptProof>) {
+    let topic = trie::keccak256(b"Transfer(address,address,uint256)");
+    let bloom = bloom_of(&[
+        &[0x11; 20],
+        &topic,
+        &pad32(&[0x22; 20]).unwrap(),
+        &pad32(&[0x33; 20]).unwrap(),
+    ]);
+
+    let legacy = rlp::encode_list(&[
+        rlp::encode_u64(1),
+        rlp::encode_u64(21_000),
+        rlp::encode_bytes(&[0; 256]),
+        rlp::encode_list(&[transfer_log()]),
+    ]);
+
+    let mut typed = vec![0x02];
+
+    typed.extend(rlp::encode_list(&[
+        rlp::encode_u64(1),
+        rlp::encode_u64(72_000),
+        rlp::encode_bytes(&bloom),
+        rlp::encode_list(&[transfer_log()]),
+    ]));
+
+    // keys `0x80` and `0x01` diverge on the first nibble, leaving an odd leaf path each
+    let leaf0 = rlp::encode_list(&[rlp::encode_bytes(&[0x30]), rlp::encode_bytes(&legacy)]);
+    let leaf1 = rlp::encode_list(&[rlp::encode_bytes(&[0x31]), rlp::encode_bytes(&typed)]);
+
+    let mut children = vec![rlp::encode_bytes(&[]); 17];
+
+    children[0] = rlp::encode_bytes(&trie::keccak256(&leaf1));
+    children[8] = rlp::encode_bytes(&trie::keccak256(&leaf0));
+
+    let branch = rlp::encode_list(&children);
+    let root = trie::keccak256(&branch);
+
+    let proofs = [(legacy, leaf0), (typed, leaf1)]
+        .into_iter()
+        .enumerate()
+        .map(|(i, (receipt_rlp, leaf))| EthereumReceiptProof {
+            receipt_index: i as u64,
+            receipt_rlp,
+            opening: vec![branch.clone(), leaf],
+            log_index: 0,
+            logs_bloom_check: false,
+        })
+        .collect();
+
+    (root, proofs)
+}
+
+#[test]
+fn receipt_proof_extracts_the_log() {
+    let (root, proofs) = receipts_trie();
+    let proof = EthereumReceiptProof {
+        logs_bloom_check: true,
+        ..proofs[1].clone()
+    };
+
+    let log = proof.verify(&root).unwrap();
+
+    assert_eq!(log.receipts_root, root);
+    assert_eq!(log.receipt_index, 1);
+    assert_eq!(log.address, [0x11; 20]);
+    assert_eq!(
+        log.topics[0],
+        trie::keccak256(b"Transfer(address,address,uint256)")
+    );
+    assert_eq!(log.topics[2], pad32(&[0x33; 20]).unwrap());
+    assert_eq!(log.data, pad32(&[0x05, 0xf5, 0xe1, 0x00]).unwrap());
+
+    let packed = proof.pack_to_vec();
+    let (_, unpacked) = EthereumReceiptProof::unpack(&packed).unwrap();
+
+    assert_eq!(proof, unpacked);
+}
+
+#[test]
+fn receipt_proof_rejects_invalid_openings() {
+    let (root, proofs) = receipts_trie();
+    let proof = proofs[0].clone();
+
+    assert!(proof.verify(&root).is_ok());
+    assert!(proof.verify(&[0; 32]).is_err());
+
+    let unset = EthereumReceiptProof {
+        logs_bloom_check: true,
+        ..proof.clone()
+    };
+
+    assert!(unset.verify(&root).is_err());
+
+    let missing = EthereumReceiptProof {
+        log_index: 1,
+        ..proof.clone()
+    };
+
+    assert!(missing.verify(&root).is_err());
+
+    let swapped = EthereumReceiptProof {
+        receipt_index: 1,
+        ..proof.clone()
+    };
+
+    assert!(swapped.verify(&root).is_err());
+
+    let forged = EthereumReceiptProof {
+        receipt_rlp: proofs[1].receipt_rlp.clone(),
+        ..proof
+    };
+
+    assert!(forged.verify(&root).is_err());
+}
+
+/// Receipts of single-receipt blocks with the receipts root their clients computed, and a mainnet
+/// receipt of block 1,000,000 emitting a log.
+const RECEIPTS: &str = include_str!("receipts.json");
+
+/// Consensus encoding of a receipt from its `eth_getTransactionReceipt` fields.
+fn consensus_receipt(receipt: &Value) -> Vec<u8> {
+    // pre-Byzantium receipts commit to the post-transaction state root instead of a status
+    let outcome = match receipt.get("root") {
+        Some(_) => rlp::encode_bytes(&hex_field(receipt, "root").unwrap()),
+        None => rlp::encode_u64(u64_field(receipt, "status").unwrap()),
+    };
+
+    let logs: Vec<_> = receipt["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| {
+            let topics: Vec<_> = l["topics"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|t| rlp::encode_bytes(&decode_hex(t.as_str().unwrap()).unwrap()))
+                .collect();
+
+            rlp::encode_list(&[
+                rlp::encode_bytes(&hex_field(l, "address").unwrap()),
+                rlp::encode_list(&topics),
+                rlp::encode_bytes(&hex_field(l, "data").unwrap()),
+            ])
+        })
+        .collect();
+
+    let fields = rlp::encode_list(&[
+        outcome,
+        rlp::encode_u64(u64_field(receipt, "cumulativeGasUsed").unwrap()),
+        rlp::encode_bytes(&hex_field(receipt, "logsBloom").unwrap()),
+        rlp::encode_list(&logs),
+    ]);
+
+    match u64_field(receipt, "type").unwrap() {
+        0 => fields,
+        t => [vec![t as u8], fields].concat(),
+    }
+}
+
+/// The leaf of a trie holding a single receipt, under the key `0x80` of index `0`.
+fn single_receipt_leaf(receipt: &[u8]) -> Vec<u8> {
+    rlp::encode_list(&[rlp::encode_bytes(&[0x20, 0x80]), rlp::encode_bytes(receipt)])
+}
+
+#[test]
+fn receipt_trie_matches_client_receipts_roots() {
+    let fixture: Value = serde_json::from_str(RECEIPTS).unwrap();
+
+    for block in fixture["roots"].as_array().unwrap() {
+        let root = pad32(&hex_field(block, "receiptsRoot").unwrap()).unwrap();
+        let receipt = consensus_receipt(&block["receipt"]);
+        let opening = vec![single_receipt_leaf(&receipt)];
+
+        let value = trie::verify_trie(&root, &rlp::encode_u64(0), &opening).unwrap();
+
+        assert_eq!(value, Some(receipt.clone()), "block {}", block["block"]);
+
+        // the receipt opens, but emits no log to select
+        let err = EthereumReceiptProof {
+            receipt_index: 0,
+            receipt_rlp: receipt,
+            opening,
+            log_index: 0,
+            logs_bloom_check: false,
+        }
+        .verify(&root)
+        .unwrap_err();
+
+        assert!(err.to_string().contains("no log at index"));
+    }
+}
+
+#[test]
+fn receipt_proof_extracts_a_mainnet_log() {
+    let fixture: Value = serde_json::from_str(RECEIPTS).unwrap();
+    let receipt = &fixture["mainnet"];
+    let receipt_rlp = consensus_receipt(receipt);
+    let leaf = single_receipt_leaf(&receipt_rlp);
+    let root = trie::keccak256(&leaf);
+
+    // the bloom was computed by the client, so it must hold the log address and topic
+    let log = EthereumReceiptProof {
+        receipt_index: 0,
+        receipt_rlp,
+        opening: vec![leaf],
+        log_index: 0,
+        logs_bloom_check: true,
+    }
+    .verify(&root)
+    .unwrap();
+
+    let expected = &receipt["logs"][0];
+
+    assert_eq!(log.address, hex_field(expected, "address").unwrap());
+    assert_eq!(
+        hex::encode(log.topics[0]),
+        "e1fffcc4923d04b559f4d29a8bfc6cda04eb5b0d3c460751c2402c5c5cc9109c"
+    );
+    assert_eq!(log.data, hex_field(expected, "data").unwrap());
+}
+
+#[test]
+fn empty_trie_root_is_the_hash_of_an_empty_string() {
+    assert_eq!(trie::keccak256(&rlp::encode_bytes(&[])), EMPTY_TRIE_ROOT);
+}
+
+/// A `eth_getProof` response of slots `2` and `3` against single-leaf tries, with its state root.
+fn verifiable_eth_get_proof() -> (Hash, Value) {
+    let address = [0x44; 20];
+    let code_hash = trie::keccak256(b"code");
+    let slot = pad32(&[2]).unwrap();
+
+    let leaf = |key: &[u8], value: &[u8]| {
+        let mut path = vec![0x20];
+
+        path.extend(trie::keccak256(key));
+
+        rlp::encode_list(&[rlp::encode_bytes(&path), rlp::encode_bytes(value)])
+    };
+
+    let storage = leaf(&slot, &rlp::encode_bytes(&[0x05, 0xf5, 0xe1, 0x00]));
+    let storage_hash = trie::keccak256(&storage);
+
+    let account = rlp::encode_list(&[
+        rlp::encode_u64(1),
+        rlp::encode_bytes(&[]),
+        rlp::encode_bytes(&storage_hash),
+        rlp::encode_bytes(&code_hash),
+    ]);
+    let account = leaf(&address, &account);
+    let state_root = trie::keccak256(&account);
+
+    let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": {
+            "address": const_hex::encode_prefixed(address),
+            "accountProof": [const_hex::encode_prefixed(&account)],
+            "balance": "0x0",
+            "codeHash": const_hex::encode_prefixed(code_hash),
+            "nonce": "0x1",
+            "storageHash": const_hex::encode_prefixed(storage_hash),
+            "storageProof": [
+                {
+                    "key": "0x2",
+                    "value": "0x5f5e100",
+                    "proof": [const_hex::encode_prefixed(&storage)],
+                },
+                {
+                    "key": "0x3",
+                    "value": "0x0",
+                    "proof": [const_hex::encode_prefixed(&storage)],
+                },
+            ],
+        },
+    });
+
+    (state_root, response)
+}
+
+#[test]
+fn eth_get_proof_verifies_account_then_slot() {
+    let (state_root, response) = verifiable_eth_get_proof();
+
+    let proof = EthereumStateProof::from_eth_get_proof(&response, &[2]).unwrap();
+    let output = proof.verify(&state_root).unwrap();
+
+    assert_eq!(output.state_root, state_root);
+    assert_eq!(output.address, [0x44; 20]);
+    assert_eq!(output.storage_hash, proof.account.storage_hash);
+    assert_eq!(output.key, pad32(&[2]).unwrap());
+    assert_eq!(output.value, hex::decode("05f5e100").unwrap());
+
+    let unset = EthereumStateProof::from_eth_get_proof(&response, &[3]).unwrap();
+
+    assert!(unset.verify(&state_root).unwrap().value.is_empty());
+    assert!(proof.verify(&[0; 32]).is_err());
+
+    let mut forged = proof.clone();
+
+    forged.storage.value = vec![1];
+
+    assert!(forged.verify(&state_root).is_err());
+
+    let mut forged = proof;
+
+    forged.account.nonce = 2;
+
+    assert!(forged.verify(&state_root).is_err());
+}
+
+#[test]
+fn ethereum_domain_verifies_state_proofs() {
+    let (state_root, response) = verifiable_eth_get_proof();
+    let proof = EthereumStateProof::from_eth_get_proof(&response, &[2]).unwrap();
+
+    let state = StateProof::build(<Ethereum as ChainDomain>::ID, state_root, vec![])
+        .with_number(10)
+        .with_proof(proof.pack_to_vec())
+        .unwrap();
+    let output = Ethereum::verify(&state).unwrap();
+
+    assert_eq!(output, proof.verify(&state_root).unwrap());
+
+    let mut other = state;
+
+    other.domain = DomainData::identifier_from_parts("solana");
+
+    assert!(Ethereum::verify(&other).is_err());
+}
+
+/// Serves the JSON-RPC responses, one per connection, returning the RPC url and the requests.
+#[cfg(feature = "std")]
+fn mock_rpc(responses: Vec<Value>) -> (alloc::string::String, std::thread::JoinHandle<Vec<Value>>) {
+    use std::io::{BufRead as _, BufReader, Read as _, Write as _};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = alloc::format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::with_capacity(responses.len());
+
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut len = 0;
+
+            loop {
+                let mut line = alloc::string::String::new();
+
+                reader.read_line(&mut line).unwrap();
+
+                let line = line.trim_end().to_lowercase();
+
+                if line.is_empty() {
+                    break;
+                }
+
+                if let Some(l) = line.strip_prefix("content-length:") {
+                    len = l.trim().parse().unwrap();
+                }
+            }
+
+            let mut body = vec![0; len];
+
+            reader.read_exact(&mut body).unwrap();
+            requests.push(serde_json::from_slice(&body).unwrap());
+
+            let body = response.to_string();
+            let mut stream = reader.into_inner();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+
+        requests
+    });
+
+    (url, handle)
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn fetch_proof_opens_the_slot_at_the_block_state_root() {
+    let (state_root, response) = verifiable_eth_get_proof();
+    let header = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": {
+            "number": "0xa",
+            "stateRoot": const_hex::encode_prefixed(state_root),
+        },
+    });
+    let (url, handle) = mock_rpc(vec![header, response]);
+
+    let key = serde_json::json!({
+        "address": const_hex::encode_prefixed([0x44; 20]),
+        "slot": "0x2",
+    });
+    let (proof, root) = Ethereum::new(url).fetch_proof(&key, 10).unwrap();
+
+    assert_eq!(root, state_root);
+    assert_eq!(
+        proof.verify(&root).unwrap().value,
+        hex::decode("05f5e100").unwrap()
+    );
+
+    let requests = handle.join().unwrap();
+
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0]["method"], "eth_getBlockByNumber");
+    assert_eq!(requests[0]["params"], serde_json::json!(["0xa", false]));
+    assert_eq!(requests[1]["method"], "eth_getProof");
+    assert_eq!(
+        requests[1]["params"],
+        serde_json::json!([
+            const_hex::encode_prefixed([0x44; 20]),
+            [const_hex::encode_prefixed(pad32(&[2]).unwrap())],
+            "0xa",
+        ])
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn fetch_proof_reports_rpc_errors() {
+    let error = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "error": {"code": -32000, "message": "header not found"},
+    });
+    let (url, handle) = mock_rpc(vec![error]);
+
+    let key = serde_json::json!({
+        "address": const_hex::encode_prefixed([0x44; 20]),
+        "slot": "0x2",
+    });
+    let err = Ethereum::new(url).fetch_proof(&key, 10).unwrap_err();
+
+    assert!(err.to_string().contains("eth_getBlockByNumber"));
+    assert_eq!(handle.join().unwrap().len(), 1);
+}
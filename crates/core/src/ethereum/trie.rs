@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+use sha3::{Digest as _, Keccak256};
+use valence_coprocessor_types::Hash;
+
+use super::rlp::Rlp;
+
+pub(crate) fn keccak256(bytes: &[u8]) -> Hash {
+    Keccak256::digest(bytes).into()
+}
+
+/// Root of an empty trie, `keccak256(rlp(""))`.
+pub const EMPTY_TRIE_ROOT: Hash = [
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+];
+
+/// Verifies a Merkle-Patricia trie opening of the key, returning its value.
+///
+/// Returns `None` if the opening proves the key is absent from the trie.
+pub(crate) fn verify_trie(
+    root: &Hash,
+    key: &[u8],
+    opening: &[Vec<u8>],
+) -> anyhow::Result<Option<Vec<u8>>> {
+    if root == &EMPTY_TRIE_ROOT && opening.is_empty() {
+        return Ok(None);
+    }
+
+    let path: Vec<u8> = key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect();
+    let mut path = path.as_slice();
+    let mut nodes = opening.iter();
+    let mut node = next_node(&mut nodes, root)?;
+
+    let value = loop {
+        let items = node.items()?;
+
+        let child = match items.len() {
+            17 => match path.split_first() {
+                Some((n, rest)) => {
+                    path = rest;
+                    items[*n as usize]
+                }
+                None => break items[16].bytes()?,
+            },
+
+            2 => {
+                let (leaf, nibbles) = hex_prefix(items[0].bytes()?)?;
+
+                match path.strip_prefix(nibbles.as_slice()) {
+                    Some(rest) if leaf && rest.is_empty() => break items[1].bytes()?,
+                    Some(rest) if !leaf => path = rest,
+                    _ => break &[],
+                }
+
+                items[1]
+            }
+
+            _ => anyhow::bail!("invalid trie node"),
+        };
+
+        node = match child {
+            Rlp::List(_) => child,
+            Rlp::Bytes([]) => break &[],
+            Rlp::Bytes(h) => {
+                let hash = h
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("invalid trie node reference"))?;
+
+                next_node(&mut nodes, &hash)?
+            }
+        };
+    };
+
+    anyhow::ensure!(
+        nodes.as_slice().is_empty(),
+        "unused trie nodes in the opening"
+    );
+
+    Ok((!value.is_empty()).then(|| value.to_vec()))
+}
+
+fn next_node<'a>(
+    nodes: &mut core::slice::Iter<'a, Vec<u8>>,
+    hash: &Hash,
+) -> anyhow::Result<Rlp<'a>> {
+    let node = nodes
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("the trie opening is incomplete"))?;
+
+    anyhow::ensure!(
+        &keccak256(node) == hash,
+        "a trie node doesn't match its reference"
+    );
+
+    Rlp::decode(node)
+}
+
+fn hex_prefix(encoded: &[u8]) -> anyhow::Result<(bool, Vec<u8>)> {
+    let (flags, rest) = encoded
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("empty hex-prefix path"))?;
+
+    let leaf = flags & 0x20 != 0;
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+
+    anyhow::ensure!(flags >> 4 <= 3, "invalid hex-prefix flags");
+
+    if flags & 0x10 != 0 {
+        nibbles.push(flags & 0x0f);
+    }
+
+    nibbles.extend(rest.iter().flat_map(|b| [b >> 4, b & 0x0f]));
+
+    Ok((leaf, nibbles))
+}
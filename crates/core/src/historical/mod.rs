@@ -3,14 +3,16 @@ use core::marker::PhantomData;
 #[cfg(feature = "std")]
 use std::sync::{Arc, Mutex, RwLock};
 
+use alloc::vec::Vec;
 use msgpacker::Unpackable as _;
-use valence_coprocessor_merkle::{CompoundOpeningBuilder, Smt};
+use valence_coprocessor_merkle::{Smt, SmtDiff};
 use valence_coprocessor_types::{
-    CompoundOpening, DomainData, Hash, HistoricalNonMembership, HistoricalTransitionProof,
-    HistoricalUpdate, Preimage, ValidatedDomainBlock,
+    CompoundEntry, CompoundOpening, DomainData, Hash, HasherKind, HistoricalNonMembership,
+    HistoricalTransitionProof, HistoricalUpdate, KeyedOpening, OpeningNonMembership, Preimage,
+    ValidatedDomainBlock,
 };
 
-use crate::{Blake3Hasher, DataBackend, Hasher};
+use crate::{utils, Blake3Hasher, DataBackend, Hasher, Keccak256Hasher, Registry, Sha256Hasher};
 
 #[cfg(feature = "std")]
 mod use_std;
@@ -22,6 +24,13 @@ mod tests;
 pub type Blake3Historical<D> = Historical<Blake3Hasher, D>;
 
 /// A historical SMT coordinator.
+///
+/// The historical tree maps domain identifiers to the roots of their domain trees, and is hashed
+/// with `H`. A domain tree maps block numbers to state roots, and is hashed with the
+/// [HasherKind] selected when the domain was registered; [HasherKind::Native] resolves to `H`.
+///
+/// The domain roots are bound to their hasher in the historical tree (see [HasherKind::bind]),
+/// so the openings to the historical root commit to the hasher of the domain tree.
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct Historical<H, D>
@@ -59,6 +68,34 @@ where
     /// Prefix for the history tree indexed by current root.
     pub const PREFIX_HISTORY_CUR: &[u8] = b"historical-history-cur";
 
+    /// Prefix for the historical root computed when a block was added, indexed by domain block.
+    pub const PREFIX_BLOCK_ROOT: &[u8] = b"historical-block-root";
+
+    /// Prefix for the domain tree roots, indexed by their root bound to the domain hasher.
+    pub const PREFIX_DOMAIN_ROOT: &[u8] = b"historical-domain-root";
+
+    /// Returns the key of a domain block on the [`Historical::PREFIX_BLOCK_ROOT`] index.
+    pub fn block_root_key(domain_id: &Hash, number: u64) -> Vec<u8> {
+        [domain_id.as_slice(), &utils::encode_u64_be(number)].concat()
+    }
+
+    /// Returns the historical root computed when the block was added.
+    ///
+    /// Returns `None` if the block isn't indexed.
+    pub fn get_smt_root_at_block_with_data(
+        data: &D,
+        domain_id: &Hash,
+        number: u64,
+    ) -> anyhow::Result<Option<Hash>> {
+        data.get(
+            Self::PREFIX_BLOCK_ROOT,
+            &Self::block_root_key(domain_id, number),
+        )?
+        .map(Hash::try_from)
+        .transpose()
+        .map_err(|_| anyhow::anyhow!("failed to read historical root of the block"))
+    }
+
     /// Returns the underlying data reference.
     pub fn data(&self) -> &D {
         &self.data
@@ -82,20 +119,97 @@ where
         Smt::from(self.data.clone())
     }
 
+    /// Returns the hasher of the domain tree.
+    pub fn get_domain_hasher_with_data(data: &D, domain_id: &Hash) -> anyhow::Result<HasherKind> {
+        Registry::from(data.clone()).get_domain_hasher(domain_id)
+    }
+
+    /// Returns the root of the domain tree bound to `hasher` as the `node` of the historical tree.
+    pub fn get_domain_root_with_data(
+        data: &D,
+        hasher: HasherKind,
+        node: &Hash,
+    ) -> anyhow::Result<Hash> {
+        if hasher == HasherKind::Native || node == &Hash::default() {
+            return Ok(*node);
+        }
+
+        data.get(Self::PREFIX_DOMAIN_ROOT, node)?
+            .map(Hash::try_from)
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("failed to read the domain root"))?
+            .ok_or_else(|| anyhow::anyhow!("the domain root of the historical node is unknown"))
+    }
+
     /// Get the block proof for the provided domain and block number.
+    ///
+    /// The opening includes the hasher binding of the domain root, if any; see
+    /// [CompoundOpening::domain_hasher].
     pub fn get_block_proof_with_historical(
         data: D,
         root: Hash,
         domain_id: Hash,
         number: u64,
     ) -> anyhow::Result<CompoundOpening> {
-        let smt: Smt<D, H> = Smt::from(data).with_namespace(domain_id);
         let key = HistoricalUpdate::block_number_to_key(number);
+        let smt: Smt<D, H> = Smt::from(data.clone()).with_namespace(Self::PREFIX_HISTORICAL);
+        let historical = smt.get_keyed_opening(root, &domain_id)?;
+
+        let hasher = Self::get_domain_hasher_with_data(&data, &domain_id)?;
+        let domain = Self::get_domain_root_with_data(&data, hasher, &historical.node)?;
+        let domain = match hasher {
+            HasherKind::Native => Self::domain_opening::<H>(data, domain, &domain_id, &key)?,
+            HasherKind::Blake3 => {
+                Self::domain_opening::<Blake3Hasher>(data, domain, &domain_id, &key)?
+            }
+            HasherKind::Keccak256 => {
+                Self::domain_opening::<Keccak256Hasher>(data, domain, &domain_id, &key)?
+            }
+            HasherKind::Sha256 => {
+                Self::domain_opening::<Sha256Hasher>(data, domain, &domain_id, &key)?
+            }
+        };
 
-        CompoundOpeningBuilder::new(root)
-            .with_tree(Self::PREFIX_HISTORICAL, domain_id)
-            .with_tree(domain_id, key)
-            .opening(smt)
+        let trees = [CompoundEntry {
+            key,
+            opening: domain.opening,
+        }]
+        .into_iter()
+        .chain(hasher.to_binding())
+        .chain([CompoundEntry {
+            key: domain_id,
+            opening: historical.opening,
+        }])
+        .collect();
+
+        Ok(CompoundOpening { trees })
+    }
+
+    fn domain_opening<K: Hasher>(
+        data: D,
+        domain: Hash,
+        domain_id: &Hash,
+        key: &Hash,
+    ) -> anyhow::Result<KeyedOpening> {
+        Smt::<D, K>::from(data)
+            .with_namespace(domain_id)
+            .get_keyed_opening(domain, key)
+    }
+
+    fn domain_non_membership<K: Hasher>(
+        data: D,
+        domain: Hash,
+        domain_id: &Hash,
+        key: &Hash,
+    ) -> anyhow::Result<OpeningNonMembership> {
+        let smt: Smt<D, K> = Smt::from(data).with_namespace(domain_id);
+        let mut proof = smt.get_non_membership_opening(domain, key)?;
+
+        // the stored data is the arbitrary payload, and the leaf is the state root.
+        let preimage = smt.get_keyed_opening(domain, key)?.node;
+        proof.preimage = Preimage::Node(preimage);
+
+        Ok(proof)
     }
 
     /// Get the block proof for the provided domain and block number.
@@ -145,6 +259,22 @@ where
         Ok(data.map(|d| d.1))
     }
 
+    /// Computes the domains that changed from the `old` historical root to the `new` one.
+    ///
+    /// The keys of the changes are domain identifiers; a domain is updated when blocks were added
+    /// to its tree. See [`Smt::diff`] for the pagination.
+    pub fn diff_roots(
+        &self,
+        old: Hash,
+        new: Hash,
+        after: Option<&Hash>,
+        limit: usize,
+    ) -> anyhow::Result<SmtDiff> {
+        self.smt()
+            .with_namespace(Self::PREFIX_HISTORICAL)
+            .diff(old, new, after, limit)
+    }
+
     /// Computes a proof of non-membership of the provided block.
     pub fn get_historical_non_membership_proof_with_data(
         data: D,
@@ -152,31 +282,45 @@ where
         domain_id: &Hash,
         number: u64,
     ) -> anyhow::Result<HistoricalNonMembership> {
-        let smt: Smt<D, H> = Smt::from(data).with_namespace(Self::PREFIX_HISTORICAL);
+        let hasher = Self::get_domain_hasher_with_data(&data, domain_id)?;
+        let smt: Smt<D, H> = Smt::from(data.clone()).with_namespace(Self::PREFIX_HISTORICAL);
         let mut historical = smt.get_non_membership_opening(root, domain_id)?;
 
         let domain = match &historical.preimage {
             Preimage::Zero => None,
             Preimage::Node(_) => anyhow::bail!("unexpected pre-computed node value"),
             Preimage::Data(_) => {
-                let domain = smt.get_keyed_opening(root, domain_id)?.node;
+                let node = smt.get_keyed_opening(root, domain_id)?.node;
 
                 // The pre-image of the compound tree is not the hash of data
-                historical.preimage = Preimage::Node(domain);
+                historical.preimage = Preimage::Node(node);
 
-                let smt = smt.with_namespace(domain_id);
                 let key = HistoricalUpdate::block_number_to_key(number);
-                let mut proof = smt.get_non_membership_opening(domain, &key)?;
-
-                // the stored data is the arbitrary payload, and the leaf is the state root.
-                let preimage = smt.get_keyed_opening(domain, &key)?.node;
-                proof.preimage = Preimage::Node(preimage);
+                let domain = Self::get_domain_root_with_data(&data, hasher, &node)?;
+                let proof = match hasher {
+                    HasherKind::Native => {
+                        Self::domain_non_membership::<H>(data, domain, domain_id, &key)?
+                    }
+                    HasherKind::Blake3 => {
+                        Self::domain_non_membership::<Blake3Hasher>(data, domain, domain_id, &key)?
+                    }
+                    HasherKind::Keccak256 => Self::domain_non_membership::<Keccak256Hasher>(
+                        data, domain, domain_id, &key,
+                    )?,
+                    HasherKind::Sha256 => {
+                        Self::domain_non_membership::<Sha256Hasher>(data, domain, domain_id, &key)?
+                    }
+                };
 
                 Some(proof)
             }
         };
 
-        Ok(HistoricalNonMembership { domain, historical })
+        Ok(HistoricalNonMembership {
+            domain,
+            historical,
+            hasher,
+        })
     }
 
     /// Computes a historical tree transition proof for the provided root.
@@ -221,9 +365,24 @@ where
         proof.root::<H>(state_root)
     }
 
+    /// Extracts the historical root from the provided opening of a domain tree hashed with
+    /// `hasher`.
+    pub fn compute_root_with(
+        hasher: HasherKind,
+        proof: &CompoundOpening,
+        state_root: &Hash,
+    ) -> Hash {
+        match hasher {
+            HasherKind::Native => proof.root_with::<H, H>(state_root),
+            HasherKind::Blake3 => proof.root_with::<H, Blake3Hasher>(state_root),
+            HasherKind::Keccak256 => proof.root_with::<H, Keccak256Hasher>(state_root),
+            HasherKind::Sha256 => proof.root_with::<H, Sha256Hasher>(state_root),
+        }
+    }
+
     /// Extracts the block number from the compound opening.
     pub fn get_block_number(proof: &CompoundOpening) -> Option<u64> {
-        (proof.trees.len() == 2).then(|| {
+        proof.domain_hasher().ok().map(|_| {
             let number = &proof.trees[0].key[..8];
             let number = number.try_into().unwrap();
 
@@ -233,6 +392,10 @@ where
 
     /// Extracts the domain id from the compound opening.
     pub fn get_domain_id(proof: &CompoundOpening) -> Option<Hash> {
-        (proof.trees.len() == 2).then_some(proof.trees[1].key)
+        proof
+            .domain_hasher()
+            .ok()
+            .and_then(|_| proof.trees.last())
+            .map(|t| t.key)
     }
 }
@@ -2,7 +2,10 @@ use proptest::prelude::*;
 use rand::{rngs::StdRng, SeedableRng as _};
 use valence_coprocessor_types::Hash;
 
-use crate::MemoryBackend;
+use crate::{
+    utils, DomainOpening, MemoryBackend, SmtChange, StagingBackend, StateProof, Witness,
+    WitnessCoprocessor,
+};
 
 use super::*;
 
@@ -21,6 +24,41 @@ fn historical_tree_compound_domain_works() {
     validate_block_creation(&historical, "solana", 238910);
 }
 
+#[test]
+fn diff_roots_lists_the_changed_domains() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    validate_block_creation(&historical, "ethereum", 10);
+    validate_block_creation(&historical, "solana", 20);
+
+    let old = historical.current();
+
+    validate_block_creation(&historical, "ethereum", 11);
+    validate_block_creation(&historical, "neutron", 30);
+
+    let new = historical.current();
+    let ethereum = DomainData::identifier_from_parts("ethereum");
+    let neutron = DomainData::identifier_from_parts("neutron");
+
+    let mut expected = vec![
+        (ethereum, SmtChange::Updated),
+        (neutron, SmtChange::Inserted),
+    ];
+
+    expected.sort();
+
+    let diff = historical.diff_roots(old, new, None, 10).unwrap();
+
+    assert_eq!(diff.changes, expected);
+    assert_eq!(diff.next, None);
+
+    let page = historical.diff_roots(old, new, None, 1).unwrap();
+
+    assert_eq!(page.changes, expected[..1]);
+    assert_eq!(page.next, Some(expected[0].0));
+}
+
 proptest! {
     #[test]
     fn historical_tree_property_check(seed: u64, count: u8, domains: u8) {
@@ -57,7 +95,7 @@ fn validate_block_creation<D: DataBackend>(
     domain: &str,
     number: u64,
 ) {
-    let payload = number.to_le_bytes().to_vec();
+    let payload = utils::encode_u64_le(number).to_vec();
     let root = Blake3Hasher::hash(&payload);
     let block = ValidatedDomainBlock {
         domain: DomainData::identifier_from_parts(domain),
@@ -76,7 +114,9 @@ fn validate_block_creation<D: DataBackend>(
             .get_historical_non_membership_proof(&block.domain, block.number)
             .unwrap();
 
-        assert!(historical.verify_non_membership(&proof, &block.domain, block.number, &block.root));
+        assert!(historical
+            .verify_non_membership(&proof, &block.domain, block.number, &block.root)
+            .unwrap());
     }
 
     let (previous, smt) = historical.add_validated_block(domain, &block).unwrap();
@@ -103,3 +143,433 @@ fn validate_block_creation<D: DataBackend>(
     assert_eq!(previous, update.previous);
     assert_eq!(block, update.block);
 }
+
+#[test]
+fn context_smt_contains_checks_membership_under_root() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data.clone()).unwrap();
+
+    let context = "poem";
+    let key = Blake3Hasher::key(context, b"member");
+
+    let smt: Smt<MemoryBackend, Blake3Hasher> = Smt::from(data).with_namespace(context);
+    let root = smt.insert(Hash::default(), &key, b"data").unwrap();
+
+    let ctx = historical
+        .context_without_controller()
+        .with_historical(root);
+
+    assert!(ctx.smt_contains(context, b"member").unwrap());
+    assert!(!ctx.smt_contains(context, b"absent").unwrap());
+    assert!(!ctx.smt_contains("song", b"member").unwrap());
+
+    let ctx = ctx.with_historical(Hash::default());
+
+    assert!(!ctx.smt_contains(context, b"member").unwrap());
+}
+
+#[test]
+fn smt_root_is_indexed_by_block() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    let domain = "ethereum";
+    let id = DomainData::identifier_from_parts(domain);
+
+    for number in [10, 11, 12] {
+        let block = ValidatedDomainBlock {
+            domain: id,
+            number,
+            root: Blake3Hasher::hash(&number.to_le_bytes()),
+            payload: vec![],
+        };
+
+        let (_, smt) = historical.add_validated_block(domain, &block).unwrap();
+
+        assert_eq!(
+            historical.get_smt_root_at_block(domain, number).unwrap(),
+            Some(smt)
+        );
+    }
+
+    let root = historical
+        .get_smt_root_at_block(domain, 11)
+        .unwrap()
+        .unwrap();
+
+    assert_ne!(root, historical.current());
+    assert!(historical
+        .get_smt_root_at_block(domain, 13)
+        .unwrap()
+        .is_none());
+    assert!(historical
+        .get_smt_root_at_block("solana", 11)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn context_clock_is_pinned_to_the_historical_root() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    let domain = "ethereum";
+    let before = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let block = ValidatedDomainBlock {
+        domain: DomainData::identifier_from_parts(domain),
+        number: 10,
+        root: Blake3Hasher::hash(b"block"),
+        payload: vec![],
+    };
+
+    let (empty, smt) = historical.add_validated_block(domain, &block).unwrap();
+    let update = historical.get_historical_update(&smt).unwrap().unwrap();
+
+    let ctx = historical.context_without_controller().with_historical(smt);
+    let now = ctx.now().unwrap();
+
+    assert_eq!(now, update.timestamp_ms() / 1000);
+    assert!(now >= before && now <= before + 60);
+
+    // the clock doesn't move for a given root
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    assert_eq!(ctx.now().unwrap(), now);
+    assert!(ctx.with_historical(empty).now().is_err());
+}
+
+#[test]
+fn smt_insert_and_open_verifies_against_the_new_root() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    let controller = Blake3Hasher::hash(b"controller");
+    let context = "poem";
+
+    let ctx = historical.context(controller);
+
+    assert_eq!(ctx.get_smt_context_root(context).unwrap(), Hash::default());
+
+    let (first, opening) = ctx.smt_insert_and_open(context, b"line", b"roses").unwrap();
+    let key = Blake3Hasher::key(context, b"line");
+
+    assert!(Smt::<MemoryBackend, Blake3Hasher>::verify(
+        &opening, &first, &key, b"roses"
+    ));
+
+    // a later run chains from the persisted root
+    let ctx = historical.context(controller);
+    let (second, opening) = ctx
+        .smt_insert_and_open(context, b"verse", b"violets")
+        .unwrap();
+    let verse = Blake3Hasher::key(context, b"verse");
+
+    assert_ne!(first, second);
+    assert_eq!(ctx.get_smt_context_root(context).unwrap(), second);
+    assert!(Smt::<MemoryBackend, Blake3Hasher>::verify(
+        &opening, &second, &verse, b"violets"
+    ));
+
+    // roots are scoped per controller
+    assert_eq!(
+        historical
+            .context(Blake3Hasher::hash(b"other"))
+            .get_smt_context_root(context)
+            .unwrap(),
+        Hash::default()
+    );
+}
+
+#[test]
+fn smt_insert_and_open_scopes_the_tree_per_controller() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data.clone()).unwrap();
+
+    let context = "poem";
+    let key = Blake3Hasher::key(context, b"line");
+
+    let ctx = historical.context(Blake3Hasher::hash(b"controller"));
+    let other = historical.context(Blake3Hasher::hash(b"other"));
+
+    let (root, _) = ctx.smt_insert_and_open(context, b"line", b"roses").unwrap();
+    let (other_root, _) = other
+        .smt_insert_and_open(context, b"line", b"tulips")
+        .unwrap();
+
+    // the same key of another controller doesn't overwrite the leaf data
+    let smt = Smt::<MemoryBackend, Blake3Hasher>::from(data.clone())
+        .with_namespace(ctx.smt_namespace(context));
+    let (_, value) = smt.get_opening_with_value(root, &key).unwrap().unwrap();
+
+    assert_eq!(value, b"roses");
+
+    let smt =
+        Smt::<MemoryBackend, Blake3Hasher>::from(data).with_namespace(other.smt_namespace(context));
+    let (_, value) = smt
+        .get_opening_with_value(other_root, &key)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(value, b"tulips");
+}
+
+#[test]
+fn smt_insert_and_open_from_leaves_the_backend_untouched() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data.clone()).unwrap();
+
+    let context = "poem";
+    let ctx = historical.context(Blake3Hasher::hash(b"controller"));
+    let (root, _) = ctx.smt_insert_and_open(context, b"line", b"roses").unwrap();
+
+    let staging = StagingBackend::from(data.clone());
+    let (staged, opening) = ctx
+        .smt_insert_and_open_from(staging, root, context, b"line", b"tulips")
+        .unwrap();
+    let key = Blake3Hasher::key(context, b"line");
+
+    assert!(Smt::<MemoryBackend, Blake3Hasher>::verify(
+        &opening, &staged, &key, b"tulips"
+    ));
+    assert_eq!(ctx.get_smt_context_root(context).unwrap(), root);
+
+    let smt =
+        Smt::<MemoryBackend, Blake3Hasher>::from(data).with_namespace(ctx.smt_namespace(context));
+    let (_, value) = smt.get_opening_with_value(root, &key).unwrap().unwrap();
+
+    assert_eq!(value, b"roses");
+}
+
+/// A hasher distinct from blake3, emulating the native hasher of a zkVM.
+#[derive(Debug, Clone)]
+struct NativeHasher;
+
+impl Hasher for NativeHasher {
+    fn key(context: &str, data: &[u8]) -> Hash {
+        Blake3Hasher::key(context, data)
+    }
+
+    fn hash(data: &[u8]) -> Hash {
+        Blake3Hasher::digest([b"native".as_slice(), data])
+    }
+
+    fn hash_raw(data: &[u8]) -> Hash {
+        Blake3Hasher::hash_raw(data)
+    }
+
+    fn merge(a: &Hash, b: &Hash) -> Hash {
+        Blake3Hasher::digest([b"native".as_slice(), a, b])
+    }
+
+    fn digest<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> Hash {
+        Blake3Hasher::digest([b"native".as_slice()].into_iter().chain(data))
+    }
+}
+
+#[test]
+fn domain_tree_is_hashed_with_the_domain_hasher() {
+    let data = MemoryBackend::default();
+    let historical = Historical::<NativeHasher, _>::load(data.clone()).unwrap();
+
+    let domain = "ethereum";
+    let id = DomainData::identifier_from_parts(domain);
+
+    data.set(
+        Registry::<MemoryBackend>::PREFIX_HASHER,
+        &id,
+        &[HasherKind::Blake3.to_byte()],
+    )
+    .unwrap();
+
+    for number in [10, 11] {
+        let block = ValidatedDomainBlock {
+            domain: id,
+            number,
+            root: Blake3Hasher::hash(&number.to_le_bytes()),
+            payload: vec![],
+        };
+
+        let proof = historical
+            .get_historical_non_membership_proof(&id, number)
+            .unwrap();
+
+        assert!(historical
+            .verify_non_membership(&proof, &id, number, &block.root)
+            .unwrap());
+
+        let (_, smt) = historical.add_validated_block(domain, &block).unwrap();
+
+        let proof = historical.get_block_proof(id, number).unwrap();
+        let computed = Historical::<NativeHasher, ()>::compute_root_with(
+            HasherKind::Blake3,
+            &proof,
+            &block.root,
+        );
+
+        assert_eq!(computed, smt);
+        assert_ne!(
+            Historical::<NativeHasher, ()>::compute_root(&proof, &block.root),
+            smt
+        );
+
+        let update = historical
+            .get_latest_historical_transition_proof()
+            .unwrap()
+            .verify_with::<NativeHasher, Blake3Hasher>()
+            .unwrap();
+
+        assert_eq!(update.root, smt);
+    }
+}
+
+#[test]
+fn domain_tree_is_hashed_with_keccak256() {
+    let data = MemoryBackend::default();
+    let historical = Historical::<NativeHasher, _>::load(data.clone()).unwrap();
+
+    let domain = "ethereum";
+    let id = DomainData::identifier_from_parts(domain);
+
+    data.set(
+        Registry::<MemoryBackend>::PREFIX_HASHER,
+        &id,
+        &[HasherKind::Keccak256.to_byte()],
+    )
+    .unwrap();
+
+    let block = ValidatedDomainBlock {
+        domain: id,
+        number: 10,
+        root: Keccak256Hasher::hash(b"state"),
+        payload: vec![],
+    };
+
+    let (_, smt) = historical.add_validated_block(domain, &block).unwrap();
+    let proof = historical.get_block_proof(id, 10).unwrap();
+
+    for kind in HasherKind::ALL {
+        let computed = Historical::<NativeHasher, ()>::compute_root_with(kind, &proof, &block.root);
+
+        assert_eq!(computed == smt, kind == HasherKind::Keccak256);
+    }
+
+    let proof = historical
+        .get_historical_non_membership_proof(&id, 11)
+        .unwrap();
+
+    let root = Keccak256Hasher::hash(b"other state");
+
+    assert!(historical
+        .verify_non_membership(&proof, &id, 11, &root)
+        .unwrap());
+
+    let update = historical
+        .get_latest_historical_transition_proof()
+        .unwrap()
+        .verify_with::<NativeHasher, Keccak256Hasher>()
+        .unwrap();
+
+    assert_eq!(update.root, smt);
+}
+
+#[test]
+fn domain_root_is_bound_to_its_hasher() {
+    let data = MemoryBackend::default();
+    let historical = Historical::<NativeHasher, _>::load(data.clone()).unwrap();
+
+    let domain = "ethereum";
+    let id = DomainData::identifier_from_parts(domain);
+
+    data.set(
+        Registry::<MemoryBackend>::PREFIX_HASHER,
+        &id,
+        &[HasherKind::Sha256.to_byte()],
+    )
+    .unwrap();
+
+    let block = ValidatedDomainBlock {
+        domain: id,
+        number: 10,
+        root: Sha256Hasher::hash(b"state"),
+        payload: vec![],
+    };
+
+    let (_, smt) = historical.add_validated_block(domain, &block).unwrap();
+    let proof = historical.get_block_proof(id, 10).unwrap();
+
+    assert_eq!(proof.domain_hasher().unwrap(), HasherKind::Sha256);
+    assert_eq!(
+        Historical::<NativeHasher, ()>::get_domain_id(&proof),
+        Some(id)
+    );
+    assert_eq!(
+        Historical::<NativeHasher, ()>::get_block_number(&proof),
+        Some(10)
+    );
+
+    // a witness that claims another hasher for the domain is rejected
+    let state_proof = StateProof {
+        domain: id,
+        number: 10,
+        state_root: block.root,
+        payload: vec![],
+        proof: vec![],
+    };
+    let witness = |hasher| WitnessCoprocessor {
+        version: WitnessCoprocessor::FORMAT_VERSION,
+        root: smt,
+        proofs: vec![DomainOpening {
+            proof: state_proof.clone(),
+            opening: proof.clone(),
+            hasher,
+        }],
+        witnesses: vec![Witness::StateProof(state_proof.clone())],
+        encoding: Default::default(),
+    };
+
+    assert!(witness(HasherKind::Sha256)
+        .validate::<NativeHasher>()
+        .is_ok());
+    assert!(witness(HasherKind::Keccak256)
+        .validate::<NativeHasher>()
+        .is_err());
+
+    // the binding can't be stripped to open the domain tree with another hasher
+    let mut unbound = proof.clone();
+
+    unbound.trees.remove(1);
+
+    assert_ne!(
+        Historical::<NativeHasher, ()>::compute_root_with(
+            HasherKind::Native,
+            &unbound,
+            &block.root
+        ),
+        smt
+    );
+
+    // the transition proof carries the hasher of the domain tree
+    let transition = historical.get_latest_historical_transition_proof().unwrap();
+
+    assert_eq!(transition.previous.hasher, HasherKind::Sha256);
+    assert_eq!(transition.verify_bound::<NativeHasher>().unwrap().root, smt);
+
+    let mut proof = historical
+        .get_historical_non_membership_proof(&id, 11)
+        .unwrap();
+    let root = Sha256Hasher::hash(b"other state");
+
+    assert!(historical
+        .verify_non_membership(&proof, &id, 11, &root)
+        .unwrap());
+
+    proof.hasher = HasherKind::Native;
+
+    assert!(!historical
+        .verify_non_membership(&proof, &id, 11, &root)
+        .unwrap());
+}
@@ -6,11 +6,14 @@ use serde_json::Value;
 use uuid::Uuid;
 use valence_coprocessor_merkle::Smt;
 use valence_coprocessor_types::{
-    BlockAdded, CompoundOpening, DataBackend, DomainData, Hash, Hasher, HistoricalTransitionProof,
-    HistoricalUpdate, ValidatedBlock, ValidatedDomainBlock,
+    BlockAdded, CompoundOpening, DataBackend, DomainData, Hash, Hasher, HasherKind,
+    HistoricalTransitionProof, HistoricalUpdate, ValidatedBlock, ValidatedDomainBlock,
 };
 
-use crate::{ExecutionContext, Historical, HistoricalNonMembership, Vm};
+use crate::{
+    Blake3Hasher, ExecutionContext, Historical, HistoricalNonMembership, Keccak256Hasher, Registry,
+    Sha256Hasher, Vm,
+};
 
 impl<H, D> Historical<H, D>
 where
@@ -71,6 +74,11 @@ where
         domain: &str,
         block: &ValidatedDomainBlock,
     ) -> anyhow::Result<(Hash, Hash)> {
+        // backfills the index for the domains registered before it was introduced
+        let id = DomainData::identifier_from_parts(domain);
+
+        Registry::from(self.data.clone()).index_domain(&id, domain)?;
+
         let prev_smt;
         let latest;
         let smt = {
@@ -88,18 +96,35 @@ where
             let tree = tree.with_namespace(Self::PREFIX_HISTORICAL);
             let opening = tree.get_keyed_opening(smt, &block.domain)?;
 
+            let hasher = Self::get_domain_hasher_with_data(&self.data, &block.domain)?;
+
             // Use the node value if matches; otherwise, create a new sub-tree.
-            let leaf = if opening.key == Some(block.domain) {
-                opening.node
+            let domain = if opening.key == Some(block.domain) {
+                Self::get_domain_root_with_data(&self.data, hasher, &opening.node)?
             } else {
                 Hash::default()
             };
 
-            let tree = tree.with_namespace(block.domain);
-            let key = HistoricalUpdate::block_number_to_key(block.number);
-            let leaf = tree.insert_with_leaf(leaf, &key, block.root, &block.payload)?;
+            let data = self.data.clone();
+            let domain = match hasher {
+                HasherKind::Native => Self::insert_domain_block::<H>(data, domain, block)?,
+                HasherKind::Blake3 => {
+                    Self::insert_domain_block::<Blake3Hasher>(data, domain, block)?
+                }
+                HasherKind::Keccak256 => {
+                    Self::insert_domain_block::<Keccak256Hasher>(data, domain, block)?
+                }
+                HasherKind::Sha256 => {
+                    Self::insert_domain_block::<Sha256Hasher>(data, domain, block)?
+                }
+            };
+
+            let leaf = hasher.bind::<H>(&domain);
+
+            if leaf != domain {
+                self.data.set(Self::PREFIX_DOMAIN_ROOT, &leaf, &domain)?;
+            }
 
-            let tree = tree.with_namespace(Self::PREFIX_HISTORICAL);
             let smt = tree.insert_compound(smt, &block.domain, leaf)?;
 
             // update chained history (must be infallible)
@@ -120,6 +145,12 @@ where
 
                 self.data.set(Self::PREFIX_HISTORY_CUR, &smt, &chained)?;
 
+                self.data.set(
+                    Self::PREFIX_BLOCK_ROOT,
+                    &Self::block_root_key(&block.domain, block.number),
+                    &smt,
+                )?;
+
                 // update computed; override control vars & database
 
                 match self.current.write() {
@@ -221,6 +252,15 @@ where
         Self::get_block_proof_with_historical(self.data.clone(), root, domain_id, number)
     }
 
+    /// Returns the historical root computed when the block of the domain was added.
+    ///
+    /// Returns `None` if the block isn't indexed.
+    pub fn get_smt_root_at_block(&self, domain: &str, number: u64) -> anyhow::Result<Option<Hash>> {
+        let id = DomainData::identifier_from_parts(domain);
+
+        Self::get_smt_root_at_block_with_data(&self.data, &id, number)
+    }
+
     /// Returns `true` if the provided block exists for the domain.
     pub fn block_exists(&self, domain_id: &Hash, number: u64) -> anyhow::Result<bool> {
         let key = HistoricalUpdate::block_number_to_key(number);
@@ -245,16 +285,34 @@ where
     }
 
     /// Verifies the non-membership proof of the block.
+    ///
+    /// The proof must be bound to the hasher of the domain tree.
     pub fn verify_non_membership(
         &self,
         proof: &HistoricalNonMembership,
         domain_id: &Hash,
         number: u64,
         state_root: &Hash,
-    ) -> bool {
+    ) -> anyhow::Result<bool> {
         let root = self.current();
+        let hasher = Self::get_domain_hasher_with_data(&self.data, domain_id)?;
+
+        if proof.hasher != hasher {
+            return Ok(false);
+        }
 
-        proof.verify::<H>(&root, domain_id, number, state_root)
+        Ok(match hasher {
+            HasherKind::Native => proof.verify_with::<H, H>(&root, domain_id, number, state_root),
+            HasherKind::Blake3 => {
+                proof.verify_with::<H, Blake3Hasher>(&root, domain_id, number, state_root)
+            }
+            HasherKind::Keccak256 => {
+                proof.verify_with::<H, Keccak256Hasher>(&root, domain_id, number, state_root)
+            }
+            HasherKind::Sha256 => {
+                proof.verify_with::<H, Sha256Hasher>(&root, domain_id, number, state_root)
+            }
+        })
     }
 
     /// Computes a historical tree transition proof for the provided root.
@@ -265,4 +323,17 @@ where
 
         Self::get_historical_transition_proof_with_data(self.data.clone(), &root)
     }
+
+    /// Inserts the block into its domain tree, returning the new root of the domain tree.
+    fn insert_domain_block<K: Hasher>(
+        data: D,
+        domain: Hash,
+        block: &ValidatedDomainBlock,
+    ) -> anyhow::Result<Hash> {
+        let key = HistoricalUpdate::block_number_to_key(block.number);
+
+        Smt::<D, K>::from(data)
+            .with_namespace(block.domain)
+            .insert_with_leaf(domain, &key, block.root, &block.payload)
+    }
 }
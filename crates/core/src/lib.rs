@@ -6,15 +6,17 @@ extern crate alloc;
 
 mod context;
 mod domain;
+mod ethereum;
 mod historical;
+mod log;
 mod registry;
+mod solana;
 mod vm;
 mod zkvm;
 
 #[cfg(feature = "std")]
 mod data;
 
-#[cfg(feature = "std")]
 pub mod utils;
 
 #[cfg(feature = "mocks")]
@@ -22,8 +24,11 @@ pub mod mocks;
 
 pub use context::*;
 pub use domain::*;
+pub use ethereum::*;
 pub use historical::*;
+pub use log::*;
 pub use registry::*;
+pub use solana::*;
 pub use vm::*;
 pub use zkvm::*;
 
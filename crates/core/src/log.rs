@@ -0,0 +1,129 @@
+use alloc::{format, string::String};
+use core::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// The severity of a controller log line.
+///
+/// Log lines are carried as strings. Lines of any level other than [`LogLevel::Info`] are tagged
+/// with a `[level] ` prefix, so untagged lines, as the ones of older controllers, are info-level.
+/// Info lines starting with `[` are tagged as well, so their text is never read as a tag.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Very verbose tracing.
+    Trace,
+
+    /// Debugging information.
+    Debug,
+
+    /// General information.
+    #[default]
+    Info,
+
+    /// A potential issue.
+    Warn,
+
+    /// A failure.
+    Error,
+}
+
+impl LogLevel {
+    /// All the levels, from the least to the most severe.
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    /// Returns the lowercase name of the level.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+
+    /// Tags a log line with the level.
+    pub fn tag(&self, line: &str) -> String {
+        match self {
+            LogLevel::Info if !line.starts_with('[') => line.into(),
+            l => format!("[{}] {line}", l.as_str()),
+        }
+    }
+
+    /// Splits a log line into its level and message.
+    pub fn parse(line: &str) -> (LogLevel, &str) {
+        Self::ALL
+            .into_iter()
+            .find_map(|l| {
+                line.strip_prefix('[')
+                    .and_then(|r| r.strip_prefix(l.as_str()))
+                    .and_then(|r| r.strip_prefix("] "))
+                    .map(|m| (l, m))
+            })
+            .unwrap_or((LogLevel::Info, line))
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|l| l.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow::anyhow!("unknown log level `{s}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_levels_round_trip_through_tags() {
+        for level in LogLevel::ALL {
+            let line = level.tag("proof failed");
+
+            assert_eq!(LogLevel::parse(&line), (level, "proof failed"));
+            assert_eq!(level.as_str().parse::<LogLevel>().unwrap(), level);
+        }
+
+        assert_eq!(LogLevel::Info.tag("foo"), "foo");
+        assert_eq!(LogLevel::Warn.tag("foo"), "[warn] foo");
+        assert_eq!(LogLevel::parse("foo"), (LogLevel::Info, "foo"));
+        assert_eq!(
+            LogLevel::parse("[unknown] foo"),
+            (LogLevel::Info, "[unknown] foo")
+        );
+        assert!("verbose".parse::<LogLevel>().is_err());
+    }
+
+    #[test]
+    fn info_lines_are_never_read_as_tags() {
+        for line in [
+            "[error] echoed upstream text",
+            "[info] foo",
+            "[unknown] foo",
+        ] {
+            let tagged = LogLevel::Info.tag(line);
+
+            assert_eq!(tagged, format!("[info] {line}"));
+            assert_eq!(LogLevel::parse(&tagged), (LogLevel::Info, line));
+        }
+    }
+}
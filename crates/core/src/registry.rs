@@ -1,5 +1,8 @@
-use alloc::vec::Vec;
-use valence_coprocessor_types::{ControllerData, DomainData};
+use alloc::{string::String, vec::Vec};
+use msgpacker::{Packable as _, Unpackable as _};
+use valence_coprocessor_types::{
+    ControllerData, ControllerMetadata, DomainData, HasherKind, WitnessEncoding,
+};
 
 use crate::{DataBackend, ExecutionContext, Hash, Hasher, Permission, Vm, ZkVm};
 
@@ -23,6 +26,18 @@ impl<D: DataBackend> Registry<D> {
     /// Data backend prefix for zkVM data.
     pub const PREFIX_CIRCUIT: &[u8] = b"registry-circuit";
 
+    /// Data backend prefix for the witness encoding.
+    pub const PREFIX_CODEC: &[u8] = b"registry-codec";
+
+    /// Data backend prefix for the controller deployment metadata.
+    pub const PREFIX_METADATA: &[u8] = b"registry-metadata";
+
+    /// Data backend prefix for the hasher of the domain trees.
+    pub const PREFIX_HASHER: &[u8] = b"registry-hasher";
+
+    /// Data backend prefix for the index of the registered domain names, keyed by domain id.
+    pub const PREFIX_DOMAINS: &[u8] = b"registry-domains";
+
     /// Register a new controller, returning its identifier.
     pub fn register_controller<M, H, Z>(
         &self,
@@ -42,14 +57,19 @@ impl<D: DataBackend> Registry<D> {
         ctx.allow(&Permission::CircuitControllerWrite(id))?;
         ctx.allow(&Permission::CircuitStorageWrite(id))?;
 
+        let metadata = controller.metadata();
         let ControllerData {
             controller,
             circuit,
+            codec,
             ..
         } = controller;
 
         self.data.set(Self::PREFIX_CONTROLLER, &id, &controller)?;
         self.data.set(Self::PREFIX_CIRCUIT, &id, &circuit)?;
+        self.data.set(Self::PREFIX_CODEC, &id, &[codec.to_byte()])?;
+        self.data
+            .set(Self::PREFIX_METADATA, &id, &metadata.pack_to_vec())?;
 
         vm.updated(&id);
         zkvm.updated(&id);
@@ -58,6 +78,9 @@ impl<D: DataBackend> Registry<D> {
     }
 
     /// Register a new domain, returning its identifier.
+    ///
+    /// The hasher of a domain is fixed once chosen; re-registering the domain with a different
+    /// hasher fails.
     pub fn register_domain<M, H, Z>(
         &self,
         vm: &M,
@@ -77,20 +100,70 @@ impl<D: DataBackend> Registry<D> {
         ctx.allow(&Permission::CircuitStorageWrite(id))?;
 
         let DomainData {
+            name,
             controller,
             circuit,
-            ..
+            hasher,
         } = domain;
 
+        match self.data.get(Self::PREFIX_HASHER, &id)? {
+            Some(h) => {
+                let current = Self::parse_hasher(&h)?;
+
+                anyhow::ensure!(
+                    current == hasher,
+                    "the domain `{name}` is hashed with `{current}`; its hasher cannot be changed"
+                );
+            }
+            None => {
+                self.data
+                    .set(Self::PREFIX_HASHER, &id, &[hasher.to_byte()])?;
+            }
+        }
+
         self.data.set(Self::PREFIX_CONTROLLER, &id, &controller)?;
         self.data.set(Self::PREFIX_CIRCUIT, &id, &circuit)?;
 
+        self.index_domain(&id, &name)?;
+
         vm.updated(&id);
         zkvm.updated(&id);
 
         Ok(id)
     }
 
+    /// Adds the domain name to the index of the registered domains.
+    ///
+    /// Each domain is indexed under its own key, so concurrent registrations don't overwrite each
+    /// other. Indexing an already listed domain is a no-op.
+    pub fn index_domain(&self, id: &Hash, name: &str) -> anyhow::Result<()> {
+        if !self.data.has(Self::PREFIX_DOMAINS, id)? {
+            self.data.set(Self::PREFIX_DOMAINS, id, name.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the names of the registered domains, sorted.
+    ///
+    /// Domains registered before the index was introduced are listed once they add a block, or
+    /// are registered again.
+    pub fn list_domains(&self) -> anyhow::Result<Vec<String>> {
+        let mut domains = self
+            .data
+            .scan_prefix(Self::PREFIX_DOMAINS)?
+            .into_iter()
+            .map(|(_, name)| {
+                String::from_utf8(name)
+                    .map_err(|e| anyhow::anyhow!("invalid registered domain name: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        domains.sort_unstable();
+
+        Ok(domains)
+    }
+
     /// Returns the associated controller, if present.
     pub fn get_controller(&self, id: &Hash) -> anyhow::Result<Option<Vec<u8>>> {
         self.data.get(Self::PREFIX_CONTROLLER, id)
@@ -100,6 +173,45 @@ impl<D: DataBackend> Registry<D> {
     pub fn get_zkvm(&self, id: &Hash) -> anyhow::Result<Option<Vec<u8>>> {
         self.data.get(Self::PREFIX_CIRCUIT, id)
     }
+
+    /// Returns the deployment metadata of the controller, if present.
+    pub fn get_controller_metadata(&self, id: &Hash) -> anyhow::Result<Option<ControllerMetadata>> {
+        self.data
+            .get(Self::PREFIX_METADATA, id)?
+            .map(|m| ControllerMetadata::unpack(&m))
+            .transpose()
+            .map(|m| m.map(|m| m.1))
+            .map_err(|e| anyhow::anyhow!("error unpacking controller metadata: {e}"))
+    }
+
+    /// Returns the witness encoding selected when the controller was deployed.
+    ///
+    /// Controllers deployed without an explicit encoding default to [WitnessEncoding::Raw].
+    pub fn get_codec(&self, id: &Hash) -> anyhow::Result<WitnessEncoding> {
+        match self.data.get(Self::PREFIX_CODEC, id)?.as_deref() {
+            Some([b]) => WitnessEncoding::try_from_byte(*b),
+            Some(_) => anyhow::bail!("invalid witness encoding data"),
+            None => Ok(WitnessEncoding::default()),
+        }
+    }
+
+    /// Returns the hasher selected when the domain was registered.
+    ///
+    /// Domains registered without an explicit hasher default to [HasherKind::Native].
+    pub fn get_domain_hasher(&self, id: &Hash) -> anyhow::Result<HasherKind> {
+        self.data
+            .get(Self::PREFIX_HASHER, id)?
+            .map(|h| Self::parse_hasher(&h))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    fn parse_hasher(data: &[u8]) -> anyhow::Result<HasherKind> {
+        match data {
+            [b] => HasherKind::try_from_byte(*b),
+            _ => anyhow::bail!("invalid domain hasher data"),
+        }
+    }
 }
 
 impl<D: DataBackend> From<D> for Registry<D> {
@@ -107,3 +219,105 @@ impl<D: DataBackend> From<D> for Registry<D> {
         Self { data }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use valence_coprocessor_types::{
+        MsgpackCodec, RawCodec, ValidatedDomainBlock, Witness, WitnessCodec as _,
+    };
+
+    use super::*;
+    use crate::{
+        mocks::{MockVm, MockZkVm},
+        Blake3Historical, MemoryBackend,
+    };
+
+    #[test]
+    fn registered_domains_are_listed() {
+        let data = MemoryBackend::default();
+        let registry = Registry::from(data.clone());
+        let historical = Blake3Historical::load(data).unwrap();
+        let ctx = historical.context_without_controller();
+
+        assert!(registry.list_domains().unwrap().is_empty());
+
+        for name in ["solana", "ethereum", "solana"] {
+            let domain = DomainData {
+                name: name.into(),
+                ..Default::default()
+            };
+
+            registry
+                .register_domain(&MockVm, &MockZkVm::default(), &ctx, domain)
+                .unwrap();
+        }
+
+        assert_eq!(registry.list_domains().unwrap(), ["ethereum", "solana"]);
+        assert_eq!(ctx.list_domains().unwrap(), ["ethereum", "solana"]);
+    }
+
+    #[test]
+    fn domains_adding_blocks_are_listed() {
+        let data = MemoryBackend::default();
+        let registry = Registry::from(data.clone());
+        let historical = Blake3Historical::load(data).unwrap();
+
+        // a domain registered before the index, adding its first block since
+        for (name, number) in [("neutron", 1), ("neutron", 2), ("cosmos", 1)] {
+            let block = ValidatedDomainBlock {
+                domain: DomainData::identifier_from_parts(name),
+                number,
+                root: [number as u8; 32],
+                payload: vec![],
+            };
+
+            historical.add_validated_block(name, &block).unwrap();
+        }
+
+        assert_eq!(registry.list_domains().unwrap(), ["cosmos", "neutron"]);
+    }
+
+    #[test]
+    fn witnesses_carry_the_deployed_codec() {
+        let data = MemoryBackend::default();
+        let registry = Registry::from(data.clone());
+        let historical = Blake3Historical::load(data).unwrap();
+
+        let controller = ControllerData {
+            controller: vec![1],
+            circuit: vec![2],
+            ..Default::default()
+        }
+        .with_codec(WitnessEncoding::Msgpack);
+
+        let id = registry
+            .register_controller(
+                &MockVm,
+                &MockZkVm::default(),
+                &historical.context_without_controller(),
+                controller,
+            )
+            .unwrap();
+
+        assert_eq!(registry.get_codec(&id).unwrap(), WitnessEncoding::Msgpack);
+        assert_eq!(
+            registry.get_codec(&Hash::default()).unwrap(),
+            WitnessEncoding::Raw
+        );
+
+        let ctx = historical.context(id);
+        let data = MsgpackCodec::to_witness(&42u64).unwrap();
+        let witness = ctx.get_coprocessor_witness(vec![data]).unwrap();
+
+        assert_eq!(witness.encoding, WitnessEncoding::Msgpack);
+
+        let witnesses = witness.validate::<crate::Blake3Hasher>().unwrap();
+
+        assert_eq!(witnesses.decode::<MsgpackCodec, u64>(0).unwrap(), 42);
+        assert!(witnesses.decode::<RawCodec, Vec<u8>>(0).is_err());
+        assert!(witnesses.decode::<MsgpackCodec, u64>(1).is_err());
+
+        assert!(matches!(witnesses.witnesses[0], Witness::Data(_)));
+    }
+}
@@ -0,0 +1,260 @@
+use alloc::vec::Vec;
+use msgpacker::MsgPacker;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+use valence_coprocessor_types::Hash;
+
+use crate::ChainDomain;
+
+#[cfg(test)]
+mod tests;
+
+/// Fanout of the Merkle tree of the accounts delta hash.
+pub const SOLANA_MERKLE_FANOUT: usize = 16;
+
+/// The Solana domain.
+///
+/// A state proof opens an account to the bank hash of a slot. The bank hash commits to the
+/// accounts modified on the slot via the accounts delta hash, a Merkle tree of the account hashes
+/// sorted by pubkey.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Solana;
+
+/// A Solana account, as committed to the accounts delta hash.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct SolanaAccount {
+    /// Public key of the account.
+    pub pubkey: Hash,
+
+    /// Program that owns the account.
+    pub owner: Hash,
+
+    /// Balance of the account, in lamports.
+    pub lamports: u64,
+
+    /// Epoch at which the account will next owe rent.
+    pub rent_epoch: u64,
+
+    /// Whether the account holds a program.
+    pub executable: bool,
+
+    /// Data of the account.
+    pub data: Vec<u8>,
+}
+
+impl SolanaAccount {
+    /// Computes the hash of the account, as committed to the accounts delta hash.
+    ///
+    /// Accounts without lamports are deleted, and hash to zero.
+    pub fn hash(&self) -> Hash {
+        if self.lamports == 0 {
+            return Hash::default();
+        }
+
+        blake3::Hasher::new()
+            .update(&self.lamports.to_le_bytes())
+            .update(&self.rent_epoch.to_le_bytes())
+            .update(&self.data)
+            .update(&[self.executable as u8])
+            .update(&self.owner)
+            .update(&self.pubkey)
+            .finalize()
+            .into()
+    }
+}
+
+/// A node of the opening of an account to the accounts delta hash.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct SolanaMerkleNode {
+    /// Hashes of the group preceding the opened node.
+    pub left: Vec<Hash>,
+
+    /// Hashes of the group following the opened node.
+    pub right: Vec<Hash>,
+}
+
+/// A Solana account opening to the bank hash of a slot.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct SolanaStateProof {
+    /// The opened account.
+    pub account: SolanaAccount,
+
+    /// Merkle nodes, from the account hash to the accounts delta hash.
+    pub path: Vec<SolanaMerkleNode>,
+
+    /// Bank hash of the parent slot.
+    pub parent_bank_hash: Hash,
+
+    /// Number of signatures processed on the slot.
+    pub signature_count: u64,
+
+    /// Last blockhash of the slot.
+    pub last_blockhash: Hash,
+}
+
+impl SolanaStateProof {
+    /// Opens the account of the provided pubkey to the accounts delta hash of the accounts.
+    ///
+    /// Fails if the pubkey isn't in the accounts, or if the accounts contain duplicated pubkeys.
+    pub fn open(
+        accounts: &[SolanaAccount],
+        pubkey: &Hash,
+        parent_bank_hash: Hash,
+        signature_count: u64,
+        last_blockhash: Hash,
+    ) -> anyhow::Result<Self> {
+        let mut accounts: Vec<_> = accounts.iter().collect();
+
+        accounts.sort_unstable_by_key(|a| a.pubkey);
+
+        anyhow::ensure!(
+            accounts.windows(2).all(|w| w[0].pubkey != w[1].pubkey),
+            "duplicated account pubkey"
+        );
+
+        let mut index = accounts
+            .binary_search_by_key(pubkey, |a| a.pubkey)
+            .map_err(|_| {
+                anyhow::anyhow!("no account for pubkey `{}`", const_hex::encode(pubkey))
+            })?;
+
+        let account = accounts[index].clone();
+        let mut hashes: Vec<_> = accounts.iter().map(|a| a.hash()).collect();
+        let mut path = Vec::new();
+
+        loop {
+            let group = index / SOLANA_MERKLE_FANOUT * SOLANA_MERKLE_FANOUT;
+            let end = hashes.len().min(group + SOLANA_MERKLE_FANOUT);
+
+            path.push(SolanaMerkleNode {
+                left: hashes[group..index].to_vec(),
+                right: hashes[index + 1..end].to_vec(),
+            });
+
+            hashes = hashes
+                .chunks(SOLANA_MERKLE_FANOUT)
+                .map(|c| merkle_group(c, &[], &[]))
+                .collect();
+
+            index /= SOLANA_MERKLE_FANOUT;
+
+            if hashes.len() == 1 {
+                break;
+            }
+        }
+
+        Ok(Self {
+            account,
+            path,
+            parent_bank_hash,
+            signature_count,
+            last_blockhash,
+        })
+    }
+
+    /// Computes the accounts delta hash from the account and its path.
+    pub fn accounts_delta_hash(&self) -> anyhow::Result<Hash> {
+        anyhow::ensure!(!self.path.is_empty(), "the account path can't be empty");
+
+        self.path.iter().try_fold(self.account.hash(), |node, n| {
+            anyhow::ensure!(
+                n.left.len() + n.right.len() < SOLANA_MERKLE_FANOUT,
+                "a Merkle node exceeds the fanout"
+            );
+
+            Ok(merkle_group(&n.left, &[node], &n.right))
+        })
+    }
+
+    /// Computes the bank hash of the slot from the account and its path.
+    pub fn bank_hash(&self) -> anyhow::Result<Hash> {
+        let delta = self.accounts_delta_hash()?;
+
+        Ok(Sha256::new()
+            .chain_update(self.parent_bank_hash)
+            .chain_update(delta)
+            .chain_update(self.signature_count.to_le_bytes())
+            .chain_update(self.last_blockhash)
+            .finalize()
+            .into())
+    }
+}
+
+/// The output of a verified Solana state proof.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct SolanaCircuitOutput {
+    /// Bank hash of the slot.
+    pub bank_hash: Hash,
+
+    /// Public key of the account.
+    pub pubkey: Hash,
+
+    /// Program that owns the account.
+    pub owner: Hash,
+
+    /// Balance of the account, in lamports.
+    pub lamports: u64,
+
+    /// Data of the account.
+    pub data: Vec<u8>,
+}
+
+impl ChainDomain for Solana {
+    const ID: &str = "solana";
+
+    type Proof = SolanaStateProof;
+    type Output = SolanaCircuitOutput;
+
+    /// Returns the opening carried by the key, at the bank hash it computes.
+    ///
+    /// The RPC doesn't expose account openings, so the key is a [`SolanaStateProof`] collected by a
+    /// validator plugin.
+    fn fetch_proof(&self, key: &Value, _height: u64) -> anyhow::Result<(Self::Proof, Hash)> {
+        let proof: SolanaStateProof = serde_json::from_value(key.clone())?;
+        let bank_hash = proof.bank_hash()?;
+
+        Ok((proof, bank_hash))
+    }
+
+    /// Verifies the account opening against the trusted bank hash.
+    fn verify_against_root(proof: &Self::Proof, root: &Hash) -> anyhow::Result<Self::Output> {
+        anyhow::ensure!(
+            &proof.bank_hash()? == root,
+            "the account doesn't open to the bank hash"
+        );
+
+        let SolanaAccount {
+            pubkey,
+            owner,
+            lamports,
+            data,
+            ..
+        } = proof.account.clone();
+
+        Ok(SolanaCircuitOutput {
+            bank_hash: *root,
+            pubkey,
+            owner,
+            lamports,
+            data,
+        })
+    }
+}
+
+fn merkle_group(left: &[Hash], node: &[Hash], right: &[Hash]) -> Hash {
+    left.iter()
+        .chain(node)
+        .chain(right)
+        .fold(Sha256::new(), |h, n| h.chain_update(n))
+        .finalize()
+        .into()
+}
@@ -0,0 +1,118 @@
+use msgpacker::{Packable as _, Unpackable as _};
+use serde_json::json;
+
+use super::*;
+use crate::{DomainCircuit as _, DomainController as _, DomainData};
+
+fn accounts(n: u8) -> Vec<SolanaAccount> {
+    (0..n)
+        .map(|i| SolanaAccount {
+            pubkey: [i.wrapping_mul(37); 32],
+            owner: [0xaa; 32],
+            lamports: i as u64 * 1_000,
+            rent_epoch: u64::MAX,
+            executable: i % 5 == 0,
+            data: alloc::vec![i; i as usize],
+        })
+        .collect()
+}
+
+/// Accounts delta hash, computed level by level as the validator does.
+fn delta_hash(accounts: &[SolanaAccount]) -> Hash {
+    let mut accounts = accounts.to_vec();
+
+    accounts.sort_unstable_by_key(|a| a.pubkey);
+
+    let mut hashes: Vec<_> = accounts.iter().map(SolanaAccount::hash).collect();
+
+    loop {
+        hashes = hashes
+            .chunks(SOLANA_MERKLE_FANOUT)
+            .map(|c| {
+                let mut h = Sha256::new();
+
+                c.iter().for_each(|n| h.update(n));
+
+                h.finalize().into()
+            })
+            .collect();
+
+        if hashes.len() == 1 {
+            return hashes[0];
+        }
+    }
+}
+
+#[test]
+fn accounts_open_to_the_delta_hash() {
+    for n in [1, 2, 16, 17, 255] {
+        let accounts = accounts(n);
+        let expected = delta_hash(&accounts);
+
+        for a in &accounts {
+            let proof = SolanaStateProof::open(&accounts, &a.pubkey, [1; 32], 3, [2; 32]).unwrap();
+
+            assert_eq!(proof.accounts_delta_hash().unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn state_proof_round_trips() {
+    let accounts = accounts(40);
+    let pubkey = accounts[33].pubkey;
+    let proof = SolanaStateProof::open(&accounts, &pubkey, [1; 32], 3, [2; 32]).unwrap();
+
+    let packed = proof.pack_to_vec();
+    let (_, unpacked) = SolanaStateProof::unpack(&packed).unwrap();
+
+    assert_eq!(proof, unpacked);
+
+    let json = serde_json::to_value(&proof).unwrap();
+
+    assert_eq!(proof, serde_json::from_value(json).unwrap());
+
+    let state = Solana
+        .state_proof(json!({"key": proof, "height": 42}))
+        .unwrap();
+    let output = Solana::verify(&state).unwrap();
+
+    assert_eq!(state.number, 42);
+    assert_eq!(output.bank_hash, proof.bank_hash().unwrap());
+    assert_eq!(output.pubkey, pubkey);
+    assert_eq!(output.owner, accounts[33].owner);
+    assert_eq!(output.lamports, accounts[33].lamports);
+    assert_eq!(output.data, accounts[33].data);
+}
+
+#[test]
+fn verify_rejects_untrusted_bank_hash() {
+    let accounts = accounts(20);
+    let proof =
+        SolanaStateProof::open(&accounts, &accounts[7].pubkey, [1; 32], 3, [2; 32]).unwrap();
+    let state = Solana
+        .state_proof(json!({"key": proof, "height": 42}))
+        .unwrap();
+
+    let mut untrusted = state.clone();
+
+    untrusted.state_root = [9; 32];
+
+    assert!(Solana::verify(&untrusted).is_err());
+
+    let mut tampered = proof.clone();
+
+    tampered.account.lamports += 1;
+
+    let mut forged = state.clone();
+
+    forged.proof = tampered.pack_to_vec();
+
+    assert!(Solana::verify(&forged).is_err());
+
+    let mut other = state;
+
+    other.domain = DomainData::identifier_from_parts("ethereum");
+
+    assert!(Solana::verify(&other).is_err());
+}
@@ -1,12 +1,178 @@
 //! A collection of utils.
+//!
+//! Values crossing the controller and circuit boundary are little-endian unless stated otherwise:
+//! block numbers passed to the host and the test domain payloads use the LE helpers, while the
+//! historical tree keys and EVM-compatible domains use the BE helpers.
 
-use std::time;
+use core::fmt;
 
+#[cfg(feature = "std")]
+use std::{io::Read as _, time};
+
+#[cfg(feature = "std")]
 use reqwest::blocking::Client;
+#[cfg(feature = "std")]
 use serde_json::Value;
 
+/// Encodes a `u64` as big-endian bytes.
+pub const fn encode_u64_be(value: u64) -> [u8; 8] {
+    value.to_be_bytes()
+}
+
+/// Decodes a `u64` from exactly 8 big-endian bytes.
+pub fn decode_u64_be(bytes: &[u8]) -> anyhow::Result<u64> {
+    <[u8; 8]>::try_from(bytes)
+        .map(u64::from_be_bytes)
+        .map_err(|_| anyhow::anyhow!("expected 8 bytes, got {}", bytes.len()))
+}
+
+/// Encodes a `u64` as little-endian bytes.
+pub const fn encode_u64_le(value: u64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+/// Decodes a `u64` from exactly 8 little-endian bytes.
+pub fn decode_u64_le(bytes: &[u8]) -> anyhow::Result<u64> {
+    <[u8; 8]>::try_from(bytes)
+        .map(u64::from_le_bytes)
+        .map_err(|_| anyhow::anyhow!("expected 8 bytes, got {}", bytes.len()))
+}
+
+/// Default maximum size, in bytes, of a HTTP response body.
+pub const HTTP_MAX_RESPONSE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Error returned when a HTTP response body exceeds the configured limit.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    /// The maximum allowed size, in bytes.
+    pub limit: usize,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "response body exceeds the limit of {} bytes", self.limit)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ResponseTooLarge {}
+
+/// Default timeout, in milliseconds, of a HTTP request that doesn't set `timeout_ms`.
+pub const HTTP_DEFAULT_TIMEOUT_MS: u64 = 5_000;
+
+/// Default maximum timeout, in milliseconds, a HTTP request can set via `timeout_ms`.
+pub const HTTP_MAX_TIMEOUT_MS: u64 = 30_000;
+
+/// Limits enforced by the host on the HTTP requests performed on behalf of controllers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpLimits {
+    /// Maximum size, in bytes, of a response body.
+    pub max_response: usize,
+
+    /// Maximum timeout, in milliseconds; larger `timeout_ms` arguments are reduced to it.
+    pub max_timeout_ms: u64,
+}
+
+impl Default for HttpLimits {
+    fn default() -> Self {
+        Self {
+            max_response: HTTP_MAX_RESPONSE_SIZE,
+            max_timeout_ms: HTTP_MAX_TIMEOUT_MS,
+        }
+    }
+}
+
+impl HttpLimits {
+    /// Returns the timeout, in milliseconds, of a request with the provided arguments.
+    ///
+    /// The `timeout_ms` argument is reduced to [`HttpLimits::max_timeout_ms`], and defaults to
+    /// [`HTTP_DEFAULT_TIMEOUT_MS`], within the same bound, if unset.
+    #[cfg(feature = "std")]
+    pub fn timeout_ms(&self, args: &Value) -> anyhow::Result<u64> {
+        let timeout_ms = match args.get("timeout_ms") {
+            Some(t) => t
+                .as_u64()
+                .filter(|t| *t > 0)
+                .ok_or_else(|| anyhow::anyhow!("invalid timeout_ms argument"))?,
+            None => HTTP_DEFAULT_TIMEOUT_MS,
+        };
+
+        Ok(timeout_ms.min(self.max_timeout_ms.max(1)))
+    }
+}
+
+/// Default status codes retried by a HTTP request with a `retry` policy.
+pub const HTTP_RETRY_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+/// Maximum delay, in milliseconds, honored from a `Retry-After` header.
+pub const HTTP_MAX_RETRY_AFTER_MS: u64 = 60_000;
+
+/// Maximum number of attempts of a HTTP request with a `retry` policy.
+pub const HTTP_MAX_RETRY_ATTEMPTS: u32 = 5;
+
+/// Maximum delay, in milliseconds, between two attempts of a HTTP request.
+pub const HTTP_MAX_RETRY_BACKOFF_MS: u64 = 10_000;
+
+/// Error returned when a HTTP request doesn't complete within its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeout {
+    /// The exceeded timeout, in milliseconds.
+    pub timeout_ms: u64,
+}
+
+impl fmt::Display for RequestTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http request timed out after {} ms", self.timeout_ms)
+    }
+}
+
+impl core::error::Error for RequestTimeout {}
+
+/// Perform a HTTP request with the default [`HttpLimits`].
+///
+/// See [`http_with_limits`].
+#[cfg(feature = "std")]
+pub fn http(args: &Value) -> anyhow::Result<Value> {
+    http_with_limits(args, &HttpLimits::default())
+}
+
+/// Perform a HTTP request, limiting the response body to `max_response` bytes.
+///
+/// See [`http_with_limits`].
+#[cfg(feature = "std")]
+pub fn http_with_limit(args: &Value, max_response: usize) -> anyhow::Result<Value> {
+    http_with_limits(
+        args,
+        &HttpLimits {
+            max_response,
+            ..Default::default()
+        },
+    )
+}
+
 /// Perform a HTTP request.
 ///
+/// The body is read incrementally, and the request fails with [`ResponseTooLarge`] as soon as it
+/// exceeds [`HttpLimits::max_response`] bytes.
+///
+/// The whole request, including the body, must complete within the optional `timeout_ms`
+/// argument, or [`HTTP_DEFAULT_TIMEOUT_MS`] if unset; otherwise it fails with [`RequestTimeout`].
+/// The timeout is reduced to [`HttpLimits::max_timeout_ms`], so a controller can't hold a host
+/// thread for longer.
+///
+/// Bodies with a `Content-Encoding` are returned as bytes, regardless of the `Accept` header. So
+/// are all bodies if the `raw_response` argument is `true`, for callers decoding the body
+/// themselves.
+///
+/// The optional `retry` argument, `{"max_attempts", "backoff_ms", "statuses"}`, retries the
+/// request on connection errors and on the provided statuses, or [`HTTP_RETRY_STATUSES`] if unset.
+/// The delay doubles from `backoff_ms` after each attempt, unless the response sets a
+/// `Retry-After` delay in seconds, honored up to [`HTTP_MAX_RETRY_AFTER_MS`]. The attempts are
+/// capped to [`HTTP_MAX_RETRY_ATTEMPTS`] and the delays to [`HTTP_MAX_RETRY_BACKOFF_MS`], and the
+/// timeout bounds all the attempts together with their delays: no attempt is made past it.
+///
 /// # Example
 ///
 /// ```rust,ignore
@@ -25,7 +191,9 @@ use serde_json::Value;
 ///
 /// assert_eq!(ret["body"]["data"].as_str().unwrap(), "foo");
 /// ```
-pub fn http(args: &Value) -> anyhow::Result<Value> {
+#[cfg(feature = "std")]
+pub fn http_with_limits(args: &Value, limits: &HttpLimits) -> anyhow::Result<Value> {
+    let max_response = limits.max_response;
     let url = args
         .get("url")
         .and_then(Value::as_str)
@@ -46,7 +214,9 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
         _ => anyhow::bail!("unknown method `{}`", method),
     };
 
-    client = client.timeout(time::Duration::from_secs(5));
+    let timeout_ms = limits.timeout_ms(args)?;
+
+    client = client.timeout(time::Duration::from_millis(timeout_ms));
 
     if let Some(a) = args.get("basic_auth") {
         let username = match a.get("username").and_then(Value::as_str) {
@@ -132,7 +302,60 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
         _ => anyhow::bail!("invalid query"),
     }
 
-    let ret = client.send()?;
+    let timeout = |e: anyhow::Error| match is_timeout(&e) {
+        true => RequestTimeout { timeout_ms }.into(),
+        false => e,
+    };
+
+    let retry = match args.get("retry") {
+        Some(r) => HttpRetry::from_value(r)?,
+        None => HttpRetry::default(),
+    };
+
+    let deadline = time::Instant::now() + time::Duration::from_millis(timeout_ms);
+    let mut attempt = 1;
+    let mut ret = loop {
+        // every attempt gets the time left to the deadline, so the retries can't extend it
+        let request = client
+            .try_clone()
+            .ok_or_else(|| anyhow::anyhow!("the request can't be retried"))?
+            .timeout(deadline.saturating_duration_since(time::Instant::now()));
+
+        let retrying = attempt < retry.max_attempts;
+        let (failed, delay) = match request.send() {
+            Ok(r) if retrying && retry.statuses.contains(&r.status().as_u16()) => {
+                let delay = retry_after(&r).unwrap_or_else(|| retry.backoff(attempt));
+
+                (Ok(r), delay)
+            }
+            Ok(r) => break r,
+            Err(e) if retrying && e.is_connect() => (Err(e), retry.backoff(attempt)),
+            Err(e) => return Err(timeout(e.into())),
+        };
+
+        if time::Instant::now() + delay >= deadline {
+            match failed {
+                Ok(r) => break r,
+                Err(e) => return Err(timeout(e.into())),
+            }
+        }
+
+        tracing::debug!("retrying http request to `{url}` in {delay:?}; attempt {attempt}");
+
+        std::thread::sleep(delay);
+        attempt += 1;
+    };
+
+    if ret
+        .content_length()
+        .is_some_and(|l| l > max_response as u64)
+    {
+        return Err(ResponseTooLarge {
+            limit: max_response,
+        }
+        .into());
+    }
+
     let status = ret.status().as_u16();
     let headers: serde_json::Map<String, Value> = ret
         .headers()
@@ -141,14 +364,20 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
         .map(|(k, v)| (k, Value::String(v)))
         .collect();
 
-    let body: Value = match wants {
-        "json" => ret.json().unwrap_or_default(),
-        "text" => ret.text().map(Value::String).unwrap_or_default(),
-        _ => match ret.bytes() {
-            Ok(b) => serde_json::to_value(b.to_vec()).unwrap_or_default(),
-            _ => Value::default(),
-        },
-    };
+    // encoded bodies are returned as bytes, to be decoded by the caller
+    let encoded = headers
+        .get("content-encoding")
+        .and_then(Value::as_str)
+        .is_some_and(|e| !e.eq_ignore_ascii_case("identity"));
+
+    let raw = args.get("raw_response").and_then(Value::as_bool) == Some(true);
+
+    if encoded || raw {
+        wants = "data";
+    }
+
+    let body = read_limited(&mut ret, max_response).map_err(timeout)?;
+    let body = decode_body(status, body, wants);
 
     Ok(serde_json::json!({
         "status": status,
@@ -156,3 +385,282 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
         "body": body,
     }))
 }
+
+/// The retry policy of a HTTP request.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpRetry {
+    max_attempts: u32,
+    backoff_ms: u64,
+    statuses: Vec<u16>,
+}
+
+#[cfg(feature = "std")]
+impl Default for HttpRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 0,
+            statuses: HTTP_RETRY_STATUSES.to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl HttpRetry {
+    fn from_value(value: &Value) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("invalid retry argument");
+        let mut retry = Self::default();
+
+        if let Some(m) = value.get("max_attempts") {
+            retry.max_attempts = m
+                .as_u64()
+                .filter(|m| *m > 0)
+                .ok_or_else(invalid)?
+                .min(HTTP_MAX_RETRY_ATTEMPTS as u64) as u32;
+        }
+
+        if let Some(b) = value.get("backoff_ms") {
+            retry.backoff_ms = b
+                .as_u64()
+                .ok_or_else(invalid)?
+                .min(HTTP_MAX_RETRY_BACKOFF_MS);
+        }
+
+        if let Some(s) = value.get("statuses") {
+            retry.statuses = serde_json::from_value(s.clone()).map_err(|_| invalid())?;
+        }
+
+        Ok(retry)
+    }
+
+    /// Delay after the provided failed attempt, starting from 1.
+    ///
+    /// Capped to [`HTTP_MAX_RETRY_BACKOFF_MS`].
+    fn backoff(&self, attempt: u32) -> time::Duration {
+        let factor = 1u64 << (attempt - 1).min(16);
+        let ms = self
+            .backoff_ms
+            .saturating_mul(factor)
+            .min(HTTP_MAX_RETRY_BACKOFF_MS);
+
+        time::Duration::from_millis(ms)
+    }
+}
+
+/// Returns the `Retry-After` delay of the response, if set in seconds.
+#[cfg(feature = "std")]
+fn retry_after(response: &reqwest::blocking::Response) -> Option<time::Duration> {
+    let secs: u64 = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    let ms = secs.saturating_mul(1000).min(HTTP_MAX_RETRY_AFTER_MS);
+
+    Some(time::Duration::from_millis(ms))
+}
+
+/// Returns `true` if the response of [`http`] has no content.
+///
+/// This is the case of a `204 No Content` status, or of an empty body.
+#[cfg(feature = "std")]
+pub fn is_empty_body(response: &Value) -> bool {
+    let status = response.get("status").and_then(Value::as_u64);
+    let body = response.get("body");
+
+    status == Some(204)
+        || match body {
+            None | Some(Value::Null) => true,
+            Some(Value::String(s)) => s.is_empty(),
+            Some(Value::Array(a)) => a.is_empty(),
+            _ => false,
+        }
+}
+
+/// Decodes a response body into the requested representation.
+///
+/// Empty bodies decode to an empty string for text, and `null` otherwise, instead of failing to
+/// parse.
+#[cfg(feature = "std")]
+fn decode_body(status: u16, body: Vec<u8>, wants: &str) -> Value {
+    if status == 204 || body.is_empty() {
+        return match wants {
+            "text" => Value::String(String::new()),
+            _ => Value::Null,
+        };
+    }
+
+    match wants {
+        "json" => serde_json::from_slice(&body).unwrap_or_default(),
+        "text" => String::from_utf8(body)
+            .map(Value::String)
+            .unwrap_or_default(),
+        _ => serde_json::to_value(body).unwrap_or_default(),
+    }
+}
+
+/// Reads at most `limit` bytes, failing with [`ResponseTooLarge`] without buffering the excess.
+#[cfg(feature = "std")]
+fn read_limited<R: std::io::Read>(reader: R, limit: usize) -> anyhow::Result<Vec<u8>> {
+    let mut body = Vec::new();
+
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| anyhow::Error::new(e).context("error reading the response body"))?;
+
+    if body.len() > limit {
+        return Err(ResponseTooLarge { limit }.into());
+    }
+
+    Ok(body)
+}
+
+/// Returns `true` if the error is a timeout of the HTTP client, either sending the request or
+/// reading the body.
+#[cfg(feature = "std")]
+fn is_timeout(e: &anyhow::Error) -> bool {
+    e.chain().any(|e| {
+        let e = match e.downcast_ref::<std::io::Error>() {
+            Some(io) => io
+                .get_ref()
+                .map(|e| e as &(dyn std::error::Error + 'static)),
+            None => Some(e),
+        };
+
+        e.and_then(|e| e.downcast_ref::<reqwest::Error>())
+            .is_some_and(reqwest::Error::is_timeout)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn u64_endianness_round_trip() {
+        for n in [0, 1, 0xff, 0x0102030405060708, u64::MAX] {
+            assert_eq!(decode_u64_be(&encode_u64_be(n)).unwrap(), n);
+            assert_eq!(decode_u64_le(&encode_u64_le(n)).unwrap(), n);
+        }
+
+        assert_eq!(encode_u64_be(0x0102030405060708), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(encode_u64_le(0x0102030405060708), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_ne!(
+            decode_u64_le(&encode_u64_be(0x0102030405060708)).unwrap(),
+            0x0102030405060708
+        );
+    }
+
+    #[test]
+    fn request_timeouts_are_reduced_to_the_host_maximum() {
+        let limits = HttpLimits {
+            max_timeout_ms: 1_000,
+            ..Default::default()
+        };
+
+        let timeout = |args| limits.timeout_ms(&args);
+
+        assert_eq!(timeout(serde_json::json!({})).unwrap(), 1_000);
+        assert_eq!(timeout(serde_json::json!({"timeout_ms": 50})).unwrap(), 50);
+        assert_eq!(
+            timeout(serde_json::json!({"timeout_ms": u64::MAX})).unwrap(),
+            1_000
+        );
+        assert!(timeout(serde_json::json!({"timeout_ms": 0})).is_err());
+        assert!(timeout(serde_json::json!({"timeout_ms": "1"})).is_err());
+
+        let defaults = HttpLimits::default();
+
+        assert_eq!(
+            defaults.timeout_ms(&serde_json::json!({})).unwrap(),
+            HTTP_DEFAULT_TIMEOUT_MS
+        );
+        assert_eq!(
+            defaults
+                .timeout_ms(&serde_json::json!({"timeout_ms": 3_600_000}))
+                .unwrap(),
+            HTTP_MAX_TIMEOUT_MS
+        );
+    }
+
+    #[test]
+    fn retry_policies_are_clamped_to_the_host_bounds() {
+        let retry = HttpRetry::from_value(&serde_json::json!({
+            "max_attempts": u64::MAX,
+            "backoff_ms": u64::MAX,
+        }))
+        .unwrap();
+
+        assert_eq!(retry.max_attempts, HTTP_MAX_RETRY_ATTEMPTS);
+        assert_eq!(retry.backoff_ms, HTTP_MAX_RETRY_BACKOFF_MS);
+
+        let max = time::Duration::from_millis(HTTP_MAX_RETRY_BACKOFF_MS);
+
+        assert_eq!(retry.backoff(1), max);
+        assert_eq!(retry.backoff(u32::MAX), max);
+
+        let retry = HttpRetry::from_value(&serde_json::json!({
+            "max_attempts": 3,
+            "backoff_ms": 100,
+        }))
+        .unwrap();
+
+        assert_eq!(retry.max_attempts, 3);
+        assert_eq!(retry.backoff(1), time::Duration::from_millis(100));
+        assert_eq!(retry.backoff(3), time::Duration::from_millis(400));
+        assert_eq!(retry.backoff(30), max);
+
+        assert!(HttpRetry::from_value(&serde_json::json!({"max_attempts": 0})).is_err());
+    }
+
+    #[test]
+    fn u64_decode_rejects_invalid_length() {
+        assert!(decode_u64_be(&[0; 7]).is_err());
+        assert!(decode_u64_le(&[0; 9]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn empty_bodies_are_decoded_explicitly() {
+        for status in [200, 204] {
+            assert_eq!(decode_body(status, vec![], "json"), Value::Null);
+            assert_eq!(decode_body(status, vec![], "data"), Value::Null);
+            assert_eq!(
+                decode_body(status, vec![], "text"),
+                Value::String(String::new())
+            );
+        }
+
+        assert_eq!(
+            decode_body(200, b"[1]".to_vec(), "json"),
+            serde_json::json!([1])
+        );
+
+        let response = serde_json::json!({"status": 204, "headers": {}, "body": null});
+        assert!(is_empty_body(&response));
+
+        let response = serde_json::json!({"status": 200, "headers": {}, "body": "foo"});
+        assert!(!is_empty_body(&response));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_limited_rejects_oversized_body() {
+        let body = vec![0xaau8; 64];
+
+        assert_eq!(read_limited(&body[..], 64).unwrap(), body);
+
+        let err = read_limited(&body[..], 63).unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ResponseTooLarge>(),
+            Some(&ResponseTooLarge { limit: 63 })
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use alloc::{collections::BTreeMap, string::String};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{DataBackend, ExecutionContext, Hash, Hasher};
@@ -29,3 +31,31 @@ where
     /// A notification that the controller has been updated.
     fn updated(&self, controller: &Hash);
 }
+
+/// A structured envelope of named outputs returned by a controller.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedOutputs {
+    /// The outputs indexed by name.
+    pub outputs: BTreeMap<String, Value>,
+}
+
+impl NamedOutputs {
+    /// Returns the output with the provided name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.outputs.get(name)
+    }
+
+    /// Parses the envelope from a controller return value.
+    pub fn try_from_value(value: Value) -> anyhow::Result<Self> {
+        serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("the value is not a named outputs envelope: {e}"))
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, Value)> for NamedOutputs {
+    fn from_iter<T: IntoIterator<Item = (S, Value)>>(iter: T) -> Self {
+        Self {
+            outputs: iter.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+        }
+    }
+}
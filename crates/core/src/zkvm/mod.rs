@@ -1,10 +1,15 @@
 use alloc::vec::Vec;
 use msgpacker::MsgPacker;
 use serde::{Deserialize, Serialize};
-use valence_coprocessor_types::{CompoundOpening, StateProof, ValidatedWitnesses, Witness};
+use valence_coprocessor_types::{
+    CompoundOpening, HasherKind, StateProof, ValidatedWitnesses, Witness, WitnessEncoding,
+};
 
 use crate::{DataBackend, ExecutionContext, Hash, Hasher, Historical, Proof};
 
+#[cfg(feature = "bincode")]
+use valence_coprocessor_types::{BincodeCodec, WitnessCodec as _};
+
 #[cfg(test)]
 mod tests;
 
@@ -50,11 +55,17 @@ pub struct DomainOpening {
 
     /// Opening proof to the coprocessor root.
     pub opening: CompoundOpening,
+
+    /// Hasher of the domain tree.
+    pub hasher: HasherKind,
 }
 
 /// A circuit witness data obtained via Valence API.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker)]
 pub struct WitnessCoprocessor {
+    /// Serialization format version.
+    pub version: u16,
+
     /// Co-processor historical commitments root.
     pub root: Hash,
 
@@ -63,9 +74,53 @@ pub struct WitnessCoprocessor {
 
     /// Witness data for the circuit.
     pub witnesses: Vec<Witness>,
+
+    /// Encoding of the witness data selected when the controller was deployed.
+    pub encoding: WitnessEncoding,
 }
 
 impl WitnessCoprocessor {
+    /// Serialization format version of the co-processor witness.
+    pub const FORMAT_VERSION: u16 = 4;
+
+    /// Ensures the witness was produced with a compatible serialization format.
+    pub fn ensure_version(&self) -> anyhow::Result<()> {
+        Self::ensure_format_version(self.version)
+    }
+
+    /// Reads the serialization format version prefix of a bincode encoded witness.
+    ///
+    /// The version is the leading field, so it is read without decoding the rest of the witness,
+    /// whose layout may differ between versions.
+    pub fn version_of(bytes: &[u8]) -> anyhow::Result<u16> {
+        let prefix = bytes
+            .get(..2)
+            .ok_or_else(|| anyhow::anyhow!("the witness has no format version prefix"))?;
+
+        Ok(u16::from_le_bytes([prefix[0], prefix[1]]))
+    }
+
+    /// Decodes a bincode encoded witness, checking its format version before decoding it.
+    #[cfg(feature = "bincode")]
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        Self::ensure_format_version(Self::version_of(bytes)?)?;
+
+        BincodeCodec::decode(bytes)
+    }
+
+    fn ensure_format_version(version: u16) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            version == Self::FORMAT_VERSION,
+            "incompatible witness format version `{}`; {} v{} expects version `{}`",
+            version,
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            Self::FORMAT_VERSION
+        );
+
+        Ok(())
+    }
+
     /// Attemtps to create an instance from a set of witnesses.
     ///
     /// Will compute the domain opening for every state proof.
@@ -88,24 +143,49 @@ impl WitnessCoprocessor {
                     proof.domain,
                     proof.number,
                 )?;
+                let hasher = Historical::<H, D>::get_domain_hasher_with_data(&data, &proof.domain)?;
 
-                Ok(DomainOpening { proof, opening })
+                Ok(DomainOpening {
+                    proof,
+                    opening,
+                    hasher,
+                })
             })
             .collect::<anyhow::Result<_>>()?;
 
         Ok(Self {
+            version: Self::FORMAT_VERSION,
             root,
             proofs,
             witnesses,
+            encoding: WitnessEncoding::default(),
         })
     }
 
+    /// Sets the encoding of the witness data.
+    pub fn with_encoding(mut self, encoding: WitnessEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
     /// Validates the co-processor witness, yielding verified state proofs & data for the circuit.
     pub fn validate<H: Hasher>(mut self) -> anyhow::Result<ValidatedWitnesses> {
+        self.ensure_version()?;
+
         let mut witnesses = self.witnesses.iter_mut();
 
         for p in self.proofs {
-            let root = Historical::<H, ()>::compute_root(&p.opening, &p.proof.state_root);
+            // the hasher of the witness is checked against the one bound to the root
+            let hasher = p.opening.domain_hasher()?;
+
+            anyhow::ensure!(
+                hasher == p.hasher,
+                "the opening is bound to the `{hasher}` hasher, not `{}`",
+                p.hasher
+            );
+
+            let root =
+                Historical::<H, ()>::compute_root_with(hasher, &p.opening, &p.proof.state_root);
             let domain = Historical::<H, ()>::get_domain_id(&p.opening)
                 .ok_or_else(|| anyhow::anyhow!("failed to compute domain id"))?;
 
@@ -130,6 +210,7 @@ impl WitnessCoprocessor {
         Ok(ValidatedWitnesses {
             root: self.root,
             witnesses: self.witnesses,
+            encoding: self.encoding,
         })
     }
 }
@@ -1,6 +1,6 @@
 use valence_coprocessor_types::{Blake3Hasher, DomainData, ValidatedDomainBlock};
 
-use crate::{Blake3Historical, MemoryBackend};
+use crate::{utils, Blake3Historical, MemoryBackend};
 
 use super::*;
 
@@ -44,15 +44,79 @@ fn crosschain_coprocessor_witness_validates() {
     assert_eq!(witnesses.root, root);
 }
 
+#[test]
+fn coprocessor_witness_rejects_incompatible_version() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    let data = historical.data().clone();
+    let root = historical.current();
+
+    let mut witnesses =
+        WitnessCoprocessor::try_from_witnesses::<Blake3Hasher, MemoryBackend>(data, root, vec![])
+            .unwrap();
+
+    assert!(witnesses.ensure_version().is_ok());
+
+    witnesses.version += 1;
+
+    let err = witnesses.validate::<Blake3Hasher>().unwrap_err();
+
+    assert!(err.to_string().contains(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn witness_version_is_checked_before_decoding() {
+    let data = MemoryBackend::default();
+    let historical = Blake3Historical::load(data).unwrap();
+
+    let data = historical.data().clone();
+    let root = historical.current();
+
+    let witnesses =
+        WitnessCoprocessor::try_from_witnesses::<Blake3Hasher, MemoryBackend>(data, root, vec![])
+            .unwrap();
+
+    let bytes = bincode::serialize(&witnesses).unwrap();
+
+    assert_eq!(
+        WitnessCoprocessor::version_of(&bytes).unwrap(),
+        WitnessCoprocessor::FORMAT_VERSION
+    );
+    assert_eq!(WitnessCoprocessor::decode(&bytes).unwrap(), witnesses);
+
+    // the unversioned layout preceding the format version, led by the root
+    #[derive(Serialize)]
+    struct UnversionedWitness {
+        root: Hash,
+        proofs: Vec<DomainOpening>,
+        witnesses: Vec<Witness>,
+    }
+
+    let old = bincode::serialize(&UnversionedWitness {
+        root: [1; 32],
+        proofs: vec![],
+        witnesses: vec![Witness::Data(vec![1, 2, 3])],
+    })
+    .unwrap();
+
+    let err = WitnessCoprocessor::decode(&old).unwrap_err().to_string();
+
+    assert!(err.contains("incompatible witness format version `257`"));
+    assert!(err.contains(env!("CARGO_PKG_VERSION")));
+
+    assert!(WitnessCoprocessor::decode(&[2]).is_err());
+}
+
 fn create_state_root(number: u64) -> Hash {
-    let payload = number.to_le_bytes().to_vec();
+    let payload = utils::encode_u64_le(number).to_vec();
 
     Blake3Hasher::hash(&payload)
 }
 
 fn create_block(historical: &Blake3Historical<MemoryBackend>, domain: &str, number: u64) {
     let id = DomainData::identifier_from_parts(domain);
-    let payload = number.to_le_bytes().to_vec();
+    let payload = utils::encode_u64_le(number).to_vec();
     let root = create_state_root(number);
     let block = ValidatedDomainBlock {
         domain: id,
@@ -64,6 +128,29 @@ fn create_block(historical: &Blake3Historical<MemoryBackend>, domain: &str, numb
     historical.add_validated_block(domain, &block).unwrap();
 }
 
+#[test]
+fn state_proof_builder_validates_the_domain() {
+    let state_root = create_state_root(7);
+    let proof = StateProof::build("ethereum", state_root, vec![1])
+        .with_number(7)
+        .with_proof(vec![2])
+        .unwrap();
+
+    assert_eq!(
+        proof,
+        StateProof {
+            domain: DomainData::identifier_from_parts("ethereum"),
+            number: 7,
+            state_root,
+            payload: vec![1],
+            proof: vec![2],
+        }
+    );
+    assert!(StateProof::build("", state_root, vec![])
+        .with_proof(vec![])
+        .is_err());
+}
+
 fn create_state_proof(arg: (&str, u64)) -> Witness {
     let (domain, number) = arg;
 
@@ -0,0 +1,189 @@
+use std::{fmt, io::Read as _, str::FromStr};
+
+/// A compression codec for the stored values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// Values are stored as-is.
+    #[default]
+    None,
+
+    /// Values are compressed with zstd.
+    Zstd,
+
+    /// Values are compressed with lz4.
+    Lz4,
+}
+
+impl Codec {
+    /// Magic bytes prefixing compressed values, followed by the codec byte.
+    ///
+    /// Values without the magic are read as uncompressed, so entries written before compression
+    /// was enabled remain readable.
+    pub const MAGIC: &[u8] = b"\xffvcz";
+
+    /// Compression level used for zstd.
+    pub const ZSTD_LEVEL: i32 = 3;
+
+    /// Maximum size, in bytes, of a decompressed value.
+    ///
+    /// Bounds the memory a corrupt or crafted value can allocate on read; it fits the largest
+    /// stored value, a controller storage image.
+    pub const MAX_DECODED_SIZE: usize = 512 * 1024 * 1024;
+
+    /// Returns the name of the codec.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Codec::None => "none",
+            Codec::Zstd => "zstd",
+            Codec::Lz4 => "lz4",
+        }
+    }
+
+    /// Returns the format byte of the codec.
+    pub const fn to_byte(&self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Zstd => 1,
+            Codec::Lz4 => 2,
+        }
+    }
+
+    /// Parses the codec from its format byte.
+    pub fn try_from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Zstd),
+            2 => Ok(Codec::Lz4),
+            b => anyhow::bail!("unknown compression codec `{b}`"),
+        }
+    }
+
+    /// Encodes the value, prefixing it with the format header if compressed.
+    ///
+    /// An uncompressed value that starts with the magic bytes is tagged with the header of
+    /// [`Codec::None`], so it isn't mistaken for a compressed one on read.
+    pub fn encode(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let compressed = match self {
+            Codec::None if !data.starts_with(Self::MAGIC) => return Ok(data.to_vec()),
+            Codec::None => data.to_vec(),
+            Codec::Zstd => zstd::encode_all(data, Self::ZSTD_LEVEL)?,
+            Codec::Lz4 => lz4_flex::compress_prepend_size(data),
+        };
+
+        Ok([Self::MAGIC, &[self.to_byte()], &compressed].concat())
+    }
+
+    /// Decodes a value written with any codec.
+    ///
+    /// Fails if the value decompresses to more than [`Codec::MAX_DECODED_SIZE`] bytes.
+    pub fn decode(data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        Self::decode_bounded(data, Self::MAX_DECODED_SIZE)
+    }
+
+    /// Decodes a value written with any codec, failing if it decompresses to more than `max`
+    /// bytes.
+    pub fn decode_bounded(data: Vec<u8>, max: usize) -> anyhow::Result<Vec<u8>> {
+        let Some(payload) = data.strip_prefix(Self::MAGIC) else {
+            return Ok(data);
+        };
+
+        let (codec, payload) = payload
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("missing compression codec byte"))?;
+
+        let lz4_err = |e| anyhow::anyhow!("failed to decompress lz4 value: {e}");
+        let too_large = || anyhow::anyhow!("the decompressed value exceeds {max} bytes");
+
+        match Self::try_from_byte(*codec)? {
+            Codec::None => Ok(payload.to_vec()),
+            Codec::Zstd => {
+                let mut data = Vec::new();
+
+                // one byte past the bound tells an oversized value apart from one that fits
+                zstd::Decoder::new(payload)?
+                    .take(max as u64 + 1)
+                    .read_to_end(&mut data)?;
+
+                anyhow::ensure!(data.len() <= max, too_large());
+
+                Ok(data)
+            }
+            Codec::Lz4 => {
+                let (size, payload) =
+                    lz4_flex::block::uncompressed_size(payload).map_err(lz4_err)?;
+
+                anyhow::ensure!(size <= max, too_large());
+
+                lz4_flex::block::decompress(payload, size).map_err(lz4_err)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Codec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Codec::None, Codec::Zstd, Codec::Lz4]
+            .into_iter()
+            .find(|c| c.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("unknown compression codec `{s}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_values_shrink_and_round_trip() {
+        let data = b"proof".repeat(4096);
+
+        for codec in [Codec::Zstd, Codec::Lz4] {
+            let encoded = codec.encode(&data).unwrap();
+
+            assert!(encoded.len() < data.len() / 10);
+            assert_eq!(Codec::decode(encoded).unwrap(), data);
+        }
+
+        let encoded = Codec::None.encode(&data).unwrap();
+
+        assert_eq!(encoded, data);
+        assert_eq!(Codec::decode(encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn oversized_values_are_rejected_on_decode() {
+        let data = vec![0; 4096];
+
+        for codec in [Codec::Zstd, Codec::Lz4] {
+            let encoded = codec.encode(&data).unwrap();
+
+            assert_eq!(Codec::decode_bounded(encoded.clone(), 4096).unwrap(), data);
+            assert!(Codec::decode_bounded(encoded, 4095).is_err());
+            assert_eq!(codec.to_string().parse::<Codec>().unwrap(), codec);
+        }
+
+        assert!("brotli".parse::<Codec>().is_err());
+    }
+
+    #[test]
+    fn uncompressed_values_with_the_magic_are_tagged() {
+        let data = [Codec::MAGIC, &[Codec::Zstd.to_byte()], b"raw"].concat();
+        let encoded = Codec::None.encode(&data).unwrap();
+
+        assert_ne!(encoded, data);
+        assert_eq!(Codec::decode(encoded).unwrap(), data);
+
+        let data = Codec::MAGIC.to_vec();
+        let encoded = Codec::None.encode(&data).unwrap();
+
+        assert_eq!(Codec::decode(encoded).unwrap(), data);
+    }
+}
@@ -1,39 +1,95 @@
+mod compression;
+
+pub use compression::*;
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, OnceLock},
+};
+
 use r2d2::Pool;
-use redis::{Client, Commands as _, IntoConnectionInfo};
-use valence_coprocessor::{Blake3Hasher, DataBackend, Hash, Hasher as _};
+use redis::{Client, Commands as _, ConnectionLike, InfoDict, IntoConnectionInfo};
+use valence_coprocessor::{Blake3Hasher, DataBackend, Hash, Hasher as _, HASH_LEN};
 
 #[derive(Debug, Clone)]
 pub struct RedisBackend {
     pool: Pool<Client>,
     namespace: String,
+    compression: Codec,
+    atomic_set: Arc<OnceLock<bool>>,
 }
 
 impl RedisBackend {
+    /// Minimum redis version that supports the `GET` option of `SET`.
+    pub const ATOMIC_SET_VERSION: (u64, u64) = (6, 2);
+
     /// Opens a new connection into redis.
     pub fn open<T: IntoConnectionInfo>(params: T) -> anyhow::Result<Self> {
         let client = Client::open(params)?;
 
         // test the connection
-        client.get_connection()?;
+        let atomic_set = Self::supports_atomic_set(&mut client.get_connection()?);
+        let atomic_set = Arc::new(OnceLock::from(atomic_set));
 
         let pool = Pool::builder().build(client)?;
         let namespace = Default::default();
+        let compression = Default::default();
 
-        Ok(Self { pool, namespace })
+        Ok(Self {
+            pool,
+            namespace,
+            compression,
+            atomic_set,
+        })
     }
 
     /// Uses a pre-defined redis client.
+    ///
+    /// The server version is probed on the first write.
     pub fn with_redis(mut self, client: Client) -> anyhow::Result<Self> {
+        self.atomic_set = Default::default();
         self.pool = Pool::builder().build(client)?;
         Ok(self)
     }
 
+    /// Returns `true` if the server supports `SET ... GET` and `GETDEL`, probing it once.
+    fn atomic_set<C: ConnectionLike>(&self, conn: &mut C) -> bool {
+        *self
+            .atomic_set
+            .get_or_init(|| Self::supports_atomic_set(conn))
+    }
+
+    /// Returns `true` if the server can replace a value and return the prior one atomically.
+    fn supports_atomic_set<C: ConnectionLike>(conn: &mut C) -> bool {
+        let info: Option<InfoDict> = redis::cmd("INFO").arg("server").query(conn).ok();
+        let version = info.and_then(|i| i.get::<String>("redis_version"));
+        let version = version.as_deref().and_then(|v| {
+            let mut v = v.split('.').map(|n| n.parse::<u64>().ok());
+
+            Some((v.next()??, v.next()??))
+        });
+
+        match version {
+            Some(v) => v >= Self::ATOMIC_SET_VERSION,
+            None => false,
+        }
+    }
+
     /// Associate this dataset with a namespace.
     pub fn with_namespace(mut self, namespace: String) -> Self {
         self.namespace = namespace;
         self
     }
 
+    /// Compresses the written values with the provided codec.
+    ///
+    /// Values are decompressed transparently on read, regardless of the codec they were written
+    /// with.
+    pub fn with_compression(mut self, compression: Codec) -> Self {
+        self.compression = compression;
+        self
+    }
+
     /// Computes the prefix key.
     pub fn prefix(&self, bytes: &[u8]) -> Hash {
         Blake3Hasher::digest([self.namespace.as_bytes(), bytes])
@@ -45,13 +101,31 @@ impl RedisBackend {
 
         [&prefix, key].concat()
     }
+
+    /// Computes the `SCAN` pattern that matches the internal keys of the prefix.
+    pub fn pattern(&self, prefix: &[u8]) -> Vec<u8> {
+        let mut pattern = Vec::with_capacity(2 * HASH_LEN + 1);
+
+        // the hashed prefix is binary, and may contain glob special characters
+        for b in self.prefix(prefix) {
+            if matches!(b, b'*' | b'?' | b'[' | b']' | b'\\') {
+                pattern.push(b'\\');
+            }
+
+            pattern.push(b);
+        }
+
+        pattern.push(b'*');
+        pattern
+    }
 }
 
 impl DataBackend for RedisBackend {
     fn get(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
         let key = self.key(prefix, key);
+        let data: Option<Vec<u8>> = self.pool.get()?.get(&key)?;
 
-        Ok(self.pool.get()?.get(&key)?)
+        data.map(Codec::decode).transpose()
     }
 
     fn has(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<bool> {
@@ -60,18 +134,146 @@ impl DataBackend for RedisBackend {
 
     fn remove(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
         let key = self.key(prefix, key);
+        let mut conn = self.pool.get()?;
+
+        let data: Option<Vec<u8>> = if self.atomic_set(&mut *conn) {
+            conn.get_del(&key)?
+        } else {
+            // older servers lack `GETDEL`; the transaction reads and removes the key atomically
+            let (old,): (Option<Vec<u8>>,) = redis::pipe()
+                .atomic()
+                .get(&key)
+                .del(&key)
+                .ignore()
+                .query(&mut *conn)?;
 
-        Ok(self.pool.get()?.get_del(&key)?)
+            old
+        };
+
+        data.map(Codec::decode).transpose()
     }
 
     fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
         let key = self.key(prefix, key);
         let mut conn = self.pool.get()?;
 
-        let old = conn.get_del(&key)?;
-        let _: () = conn.set(key, data)?;
+        let data = self.compression.encode(data)?;
+        let old: Option<Vec<u8>> = if self.atomic_set(&mut *conn) {
+            redis::cmd("SET")
+                .arg(&key)
+                .arg(data)
+                .arg("GET")
+                .query(&mut *conn)?
+        } else {
+            // older servers can't return the prior value on `SET`; the transaction reads and
+            // replaces the key atomically
+            let (old,): (Option<Vec<u8>>,) = redis::pipe()
+                .atomic()
+                .get(&key)
+                .set(&key, data)
+                .ignore()
+                .query(&mut *conn)?;
+
+            old
+        };
+
+        old.map(Codec::decode).transpose()
+    }
+
+    fn set_many(&self, prefix: &[u8], entries: &[(Vec<u8>, Vec<u8>)]) -> anyhow::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let entries = entries
+            .iter()
+            .map(|(key, data)| Ok((self.key(prefix, key), self.compression.encode(data)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let _: () = self.pool.get()?.mset(&entries)?;
+
+        Ok(())
+    }
+
+    fn remove_many(&self, prefix: &[u8], keys: &[Vec<u8>]) -> anyhow::Result<()> {
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<_> = keys.iter().map(|key| self.key(prefix, key)).collect();
+        let _: () = self.pool.get()?.del(&keys)?;
+
+        Ok(())
+    }
+
+    fn scan_prefix_limited(
+        &self,
+        prefix: &[u8],
+        max: usize,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut conn = self.pool.get()?;
+
+        let mut keys = BTreeSet::new();
+
+        // a key may be returned more than once by a scan
+        for key in conn.scan_match::<_, Vec<u8>>(self.pattern(prefix))? {
+            if keys.len() >= max {
+                break;
+            }
+
+            keys.insert(key);
+        }
+
+        let mut entries = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            // the key may have been removed since it was scanned
+            let data: Option<Vec<u8>> = conn.get(&key)?;
+
+            if let Some(d) = data {
+                entries.push((key[HASH_LEN..].to_vec(), Codec::decode(d)?));
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn compare_and_swap(
+        &self,
+        prefix: &[u8],
+        key: &[u8],
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> anyhow::Result<bool> {
+        let key = self.key(prefix, key);
+        let data = self.compression.encode(data)?;
+        let mut conn = self.pool.get()?;
+
+        // the stored value may be compressed, so it is compared after decoding; the watch aborts
+        // the transaction if the key changes in between
+        let _: () = redis::cmd("WATCH").arg(&key).query(&mut *conn)?;
+
+        let current = (|| {
+            let current: Option<Vec<u8>> = conn.get(&key)?;
+
+            current.map(Codec::decode).transpose()
+        })();
+
+        // the watch would otherwise outlive the call on the pooled connection, so it is cleared
+        // on every path that doesn't reach the transaction
+        if !matches!(&current, Ok(c) if c.as_deref() == expected) {
+            let unwatch: redis::RedisResult<()> = redis::cmd("UNWATCH").query(&mut *conn);
+
+            return current.and(unwatch.map_err(Into::into)).map(|_| false);
+        }
+
+        let swapped: Option<()> = redis::pipe()
+            .atomic()
+            .set(&key, data)
+            .ignore()
+            .query(&mut *conn)?;
 
-        Ok(old)
+        Ok(swapped.is_some())
     }
 
     // TODO split the storage
@@ -115,3 +317,84 @@ fn test_redis_connection() {
     assert!(client.remove(prf, key).unwrap().is_none());
     assert!(client.get(prf, key).unwrap().is_none());
 }
+
+#[test]
+#[ignore = "depends on running docker"]
+fn test_redis_set_is_atomic() {
+    // depends on `docker run --rm -p 56379:6379 redis`
+
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    let client = RedisBackend::open("redis://127.0.0.1:56379/").unwrap();
+    let prf = b"prefix-atomic";
+    let key = b"key";
+
+    assert!(client.atomic_set(&mut *client.pool.get().unwrap()));
+
+    client.remove(prf, key).unwrap();
+    client.set(prf, key, &0u64.to_le_bytes()).unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            let done = done.clone();
+
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    assert!(client.get(prf, key).unwrap().is_some());
+                }
+            })
+        })
+        .collect();
+
+    let writers: Vec<_> = (0..4u64)
+        .map(|w| {
+            let client = client.clone();
+
+            thread::spawn(move || {
+                for i in 0..500u64 {
+                    let old = client.set(prf, key, &(w * 1000 + i).to_le_bytes()).unwrap();
+
+                    assert!(old.is_some());
+                }
+            })
+        })
+        .collect();
+
+    writers.into_iter().for_each(|w| w.join().unwrap());
+    done.store(true, Ordering::Relaxed);
+    readers.into_iter().for_each(|r| r.join().unwrap());
+
+    client.remove(prf, key).unwrap();
+}
+
+#[test]
+#[ignore = "depends on running docker"]
+fn test_redis_transactions_without_set_get() {
+    // depends on `docker run --rm -p 56379:6379 redis`
+
+    let client = RedisBackend::open("redis://127.0.0.1:56379/").unwrap();
+    let client = RedisBackend {
+        atomic_set: Arc::new(OnceLock::from(false)),
+        ..client
+    };
+
+    let prf = b"prefix-transactions";
+    let key = b"key";
+
+    client.remove(prf, key).unwrap();
+
+    assert!(client.set(prf, key, b"val").unwrap().is_none());
+    assert_eq!(client.set(prf, key, b"vxl").unwrap(), Some(b"val".to_vec()));
+    assert_eq!(client.remove(prf, key).unwrap(), Some(b"vxl".to_vec()));
+    assert!(client.remove(prf, key).unwrap().is_none());
+    assert!(!client.has(prf, key).unwrap());
+}
@@ -0,0 +1,380 @@
+use std::{
+    fs,
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde_json::{json, Value};
+use valence_coprocessor::Base64;
+
+use crate::{json_body, App, ProjectStructure};
+
+/// A build of the definitions deployed to the co-processor.
+///
+/// The build receives the resolved project, and returns the response of the co-processor to the
+/// registration of the built definitions.
+pub trait Builder {
+    /// Builds the domain controller of the project, and registers it under `name`.
+    fn deploy_domain(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        name: &str,
+    ) -> anyhow::Result<Value>;
+
+    /// Builds the controller of the project with its circuit, and registers them.
+    ///
+    /// `circuit_dir` is the directory of the circuit package, relative to the workspace root.
+    fn deploy_controller(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        circuit: &str,
+        circuit_dir: &str,
+    ) -> anyhow::Result<Value>;
+}
+
+/// A reproducible build, running the image of the app in its container runtime.
+///
+/// The container registers the definitions itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DockerBuilder;
+
+impl Builder for DockerBuilder {
+    fn deploy_domain(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        name: &str,
+    ) -> anyhow::Result<Value> {
+        let output = app.run_docker(
+            "domain",
+            &project.wsroot,
+            &project.package,
+            &project.pkrelative,
+            &[name],
+        )?;
+
+        anyhow::ensure!(output.status.success(), "failed to deploy domain");
+
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    fn deploy_controller(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        circuit: &str,
+        circuit_dir: &str,
+    ) -> anyhow::Result<Value> {
+        let output = app.run_docker(
+            "controller",
+            &project.wsroot,
+            &project.package,
+            &project.pkrelative,
+            &[circuit, format!("/mnt{circuit_dir}").as_str()],
+        )?;
+
+        anyhow::ensure!(output.status.success(), "failed to deploy circuit");
+
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+/// A build with the toolchains of the host, `cargo` with the `wasm32-unknown-unknown` target and
+/// `cargo prove`.
+///
+/// The wrappers are extracted from the `docker/rust-wrappers.tar.gz` templates of the image, found
+/// in the valence co-processor sources of [`App::local_sources`], into `target/valence` of the
+/// workspace. The valence crates are taken from the same sources instead of the git tag of the
+/// image, so the build runs without network access if the other dependencies are cached. The
+/// built definitions are registered via the HTTP endpoints of the app.
+///
+/// The build isn't reproducible, so its IDs won't match the ones of a Docker build.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LocalBuilder;
+
+impl LocalBuilder {
+    /// Target of the circuit ELF, as built by `cargo prove`.
+    pub const CIRCUIT_ELF: &str =
+        "target/elf-compilation/riscv32im-succinct-zkvm-elf/release/program-circuit";
+
+    /// Templates of the wrappers, relative to the valence co-processor sources.
+    pub const TEMPLATES: &str = "docker/rust-wrappers.tar.gz";
+
+    /// Git source of the valence crates in the manifests of the templates.
+    const GIT_SOURCE: &str = r#"git = "https://github.com/timewave-computer/valence-coprocessor.git", tag = "!VERSION!""#;
+
+    /// Valence crates depended on by the templates, with their directory in the sources.
+    const PACKAGES: &[(&str, &str)] = &[
+        ("valence-coprocessor", "crates/core"),
+        ("valence-coprocessor-sp1", "crates/runtime/sp1"),
+        ("valence-coprocessor-wasm", "crates/runtime/wasm"),
+    ];
+
+    /// Returns the valence co-processor sources this binary was built from.
+    ///
+    /// The sources are only complete if the binary was installed from a checkout of the
+    /// repository, as with `cargo install --path` or `cargo install --git`.
+    pub fn default_sources() -> PathBuf {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+        manifest
+            .ancestors()
+            .nth(3)
+            .unwrap_or(manifest)
+            .to_path_buf()
+    }
+
+    fn warn() {
+        eprintln!("WARNING: building with the local toolchains; the build is NOT reproducible.");
+        eprintln!(
+            "WARNING: the deployed IDs won't match a Docker build. Use it for development only."
+        );
+    }
+
+    /// Extracts the `kind` wrapper of `package`, located at `path`, and returns its directory.
+    ///
+    /// Performs the same substitutions as the entrypoint of the image.
+    fn wrapper(
+        app: &App,
+        project: &ProjectStructure,
+        kind: &str,
+        package: &str,
+        path: &str,
+    ) -> anyhow::Result<PathBuf> {
+        let sources = &app.local_sources;
+        let templates = sources.join(Self::TEMPLATES);
+
+        anyhow::ensure!(
+            templates.is_file(),
+            "the wrapper templates `{}` were not found; set the valence co-processor sources with `--valence-src`",
+            templates.display()
+        );
+
+        let root = Path::new(&project.wsroot).join("target").join("valence");
+
+        fs::create_dir_all(&root)?;
+
+        run(Command::new("tar")
+            .arg("-xzf")
+            .arg(&templates)
+            .arg("-C")
+            .arg(&root))?;
+
+        let dir = root.join("rust-wrappers").join(kind);
+        let lib = package.replace('-', "_");
+        let vars = [
+            ("!PROJECT!", package),
+            ("!VERSION!", app.tag.as_str()),
+            ("!PATH!", path),
+            ("!LIB!", lib.as_str()),
+        ];
+
+        for file in ["Cargo.toml", "src/lib.rs", "src/main.rs"] {
+            let file = dir.join(file);
+
+            if !file.is_file() {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&file)?;
+            let contents = Self::PACKAGES.iter().fold(contents, |c, (name, relative)| {
+                let source = sources.join(relative);
+
+                c.replace(
+                    &format!("{name} = {{ {}", Self::GIT_SOURCE),
+                    &format!(r#"{name} = {{ path = "{}""#, source.display()),
+                )
+            });
+
+            anyhow::ensure!(
+                !contents.contains(Self::GIT_SOURCE),
+                "the wrapper `{kind}` depends on an unknown valence crate"
+            );
+
+            let contents = vars.iter().fold(contents, |c, (k, v)| c.replace(k, v));
+
+            fs::write(file, contents)?;
+        }
+
+        Ok(dir)
+    }
+
+    fn build_wasm(project: &ProjectStructure, dir: &Path) -> anyhow::Result<Vec<u8>> {
+        let manifest = dir.join("Cargo.toml");
+
+        run(Command::new("cargo").args([
+            "build",
+            "--target",
+            "wasm32-unknown-unknown",
+            "--release",
+            "--manifest-path",
+            manifest.display().to_string().as_str(),
+        ]))?;
+
+        let wasm = dir
+            .join("target")
+            .join("wasm32-unknown-unknown")
+            .join("release")
+            .join(format!(
+                "{}_controller.wasm",
+                project.package.replace('-', "_")
+            ));
+
+        Ok(fs::read(wasm)?)
+    }
+
+    fn register(app: &App, path: &str, body: Value) -> anyhow::Result<Value> {
+        let uri = format!("{}/api/registry/{path}", app.base_url());
+
+        let response = app.http().post(uri).json(&body).send()?;
+
+        Ok(json_body(response)?)
+    }
+}
+
+impl Builder for LocalBuilder {
+    fn deploy_domain(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        name: &str,
+    ) -> anyhow::Result<Value> {
+        Self::warn();
+
+        let path = format!("{}{}", project.wsroot, project.pkrelative);
+        let dir = Self::wrapper(app, project, "domain-controller", &project.package, &path)?;
+        let controller = Self::build_wasm(project, &dir)?;
+
+        Self::register(
+            app,
+            "domain",
+            json!({
+                "controller": Base64::encode(controller),
+                "name": name,
+            }),
+        )
+    }
+
+    fn deploy_controller(
+        &self,
+        app: &App,
+        project: &ProjectStructure,
+        circuit: &str,
+        circuit_dir: &str,
+    ) -> anyhow::Result<Value> {
+        Self::warn();
+
+        let path = format!("{}{}", project.wsroot, project.pkrelative);
+        let dir = Self::wrapper(app, project, "controller", &project.package, &path)?;
+        let controller = Self::build_wasm(project, &dir)?;
+
+        let circuit_path = format!("{}{circuit_dir}", project.wsroot);
+        let dir = Self::wrapper(app, project, "circuit", circuit, &circuit_path)?;
+
+        run(Command::new("cargo")
+            .current_dir(&dir)
+            .args(["prove", "build"]))?;
+
+        let circuit = fs::read(dir.join(Self::CIRCUIT_ELF))?;
+
+        Self::register(
+            app,
+            "controller",
+            json!({
+                "controller": Base64::encode(controller),
+                "circuit": Base64::encode(circuit),
+            }),
+        )
+    }
+}
+
+/// Runs a build command, forwarding all of its output to stderr.
+fn run(command: &mut Command) -> anyhow::Result<()> {
+    let output = command.stderr(Stdio::inherit()).output()?;
+
+    io::stderr().write_all(&output.stdout)?;
+
+    anyhow::ensure!(
+        output.status.success(),
+        "`{}` failed with {}",
+        command.get_program().to_string_lossy(),
+        output.status
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_wrappers_point_to_the_host_packages() {
+        let wsroot = tempfile::tempdir().unwrap();
+        let project = ProjectStructure {
+            metadata: Value::Null,
+            wsroot: wsroot.path().display().to_string(),
+            pkrelative: "/crates/my-controller".into(),
+            package: "my-controller".into(),
+        };
+        let app = App::default().with_tag("v1.2.3");
+        let sources = LocalBuilder::default_sources();
+
+        let dir = LocalBuilder::wrapper(
+            &app,
+            &project,
+            "circuit",
+            "my-circuit",
+            "/ws/crates/my-circuit",
+        )
+        .unwrap();
+
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        let main = fs::read_to_string(dir.join("src/main.rs")).unwrap();
+        let core = format!(
+            r#"valence-coprocessor = {{ path = "{}""#,
+            sources.join("crates/core").display()
+        );
+
+        assert_eq!(
+            dir,
+            wsroot.path().join("target/valence/rust-wrappers/circuit")
+        );
+        assert!(manifest.contains(r#"my-circuit.path = "/ws/crates/my-circuit""#));
+        assert!(manifest.contains(&core));
+        assert!(!manifest.contains("git =") && !manifest.contains("v1.2.3"));
+        assert!(main.contains("my_circuit::circuit(w.witnesses)"));
+        assert!(!manifest.contains('!') && !main.contains("!::"));
+
+        let dir = LocalBuilder::wrapper(
+            &app,
+            &project,
+            "controller",
+            &project.package,
+            &format!("{}{}", project.wsroot, project.pkrelative),
+        )
+        .unwrap();
+        let manifest = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+        let path = format!(
+            r#"my-controller.path = "{}/crates/my-controller""#,
+            wsroot.path().display()
+        );
+        let wasm = format!(
+            r#"valence-coprocessor-wasm = {{ path = "{}""#,
+            sources.join("crates/runtime/wasm").display()
+        );
+
+        assert!(manifest.contains(&path));
+        assert!(manifest.contains(&wasm));
+
+        let app = app.with_local_sources(wsroot.path());
+        let err = LocalBuilder::wrapper(&app, &project, "controller", "my-controller", "/ws")
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("--valence-src"));
+    }
+}
@@ -1,13 +1,14 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::Value;
 
-use crate::App;
+use crate::{App, ProofSystem};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    /// Socket address of the co-processor.
+    /// Socket address of the co-processor, or `unix:<PATH>` for a Unix domain socket.
     #[arg(short, long, value_name = "SOCKET", default_value = App::DEFAULT_SOCKET)]
     pub socket: String,
 
@@ -19,10 +20,97 @@ pub struct Cli {
     #[arg(short, long, value_name = "DOCKER_HOST", default_value_t = App::DEFAULT_DOCKER_HOST)]
     pub docker_host: bool,
 
+    /// Container runtime used to build the definitions, such as `docker` or `podman`.
+    #[arg(
+        long,
+        env = "VALENCE_CONTAINER_RUNTIME",
+        value_name = "RUNTIME",
+        default_value = App::DEFAULT_CONTAINER_RUNTIME
+    )]
+    pub container_runtime: String,
+
+    /// Optional request timeout in seconds; propagated as deadline to prove requests.
+    #[arg(long, value_name = "TIMEOUT")]
+    pub timeout: Option<u64>,
+
+    /// Optional token granting access to the admin endpoints of the co-processor.
+    #[arg(long, env = "VALENCE_ADMIN_TOKEN", value_name = "TOKEN")]
+    pub admin_token: Option<String>,
+
+    /// Format of the command output on stdout; diagnostics are always written to stderr.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Json)]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub cmd: Commands,
 }
 
+/// A shell supported by the completion scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    /// Bash.
+    Bash,
+    /// Zsh.
+    Zsh,
+    /// Fish.
+    Fish,
+    /// PowerShell.
+    #[value(name = "powershell")]
+    PowerShell,
+}
+
+impl Cli {
+    /// Name of the binary completed by the scripts.
+    pub const BIN_NAME: &str = "cargo-valence";
+
+    /// Writes the completion script of the shell.
+    #[cfg(feature = "completions")]
+    pub fn completions<W: Write>(shell: CompletionShell, out: &mut W) -> anyhow::Result<()> {
+        use clap::CommandFactory as _;
+        use clap_complete::Shell;
+
+        let shell = match shell {
+            CompletionShell::Bash => Shell::Bash,
+            CompletionShell::Zsh => Shell::Zsh,
+            CompletionShell::Fish => Shell::Fish,
+            CompletionShell::PowerShell => Shell::PowerShell,
+        };
+
+        clap_complete::generate(shell, &mut Self::command(), Self::BIN_NAME, out);
+
+        Ok(())
+    }
+
+    /// Writes the completion script of the shell.
+    #[cfg(not(feature = "completions"))]
+    pub fn completions<W: Write>(_shell: CompletionShell, _out: &mut W) -> anyhow::Result<()> {
+        anyhow::bail!("built without shell completions; enable the `completions` feature")
+    }
+}
+
+/// Format of the command output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A single compact JSON object per command; failures emit `{"error": ..}`.
+    #[default]
+    Json,
+    /// Indented JSON, for humans.
+    Pretty,
+}
+
+impl OutputFormat {
+    /// Renders a command response.
+    pub fn render(&self, response: &Value) -> String {
+        let rendered = match self {
+            OutputFormat::Json => serde_json::to_string(response),
+            OutputFormat::Pretty => serde_json::to_string_pretty(response),
+        };
+
+        // a `Value` always serializes
+        rendered.unwrap_or_default()
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Deploys definitions to the co-processor
@@ -47,6 +135,18 @@ pub enum Commands {
             default_value = "/var/share/proof.bin"
         )]
         path: PathBuf,
+
+        /// Seed used to derive a reproducible proof path, instead of the provided path
+        #[arg(short, long, value_name = "SEED")]
+        seed: Option<String>,
+
+        /// Hex co-processor root to compute the proof against, instead of the latest one
+        #[arg(long, value_name = "ROOT", conflicts_with = "seed")]
+        root: Option<String>,
+
+        /// Retries with the latest root if the provided root is no longer available
+        #[arg(long, value_name = "RETRIES", default_value_t = 0, requires = "root")]
+        root_retries: usize,
     },
 
     /// Reads a file from the storage, returning its base64 data
@@ -65,6 +165,128 @@ pub enum Commands {
         path: PathBuf,
     },
 
+    /// Cancels a queued proof that wasn't picked up by a prover yet
+    Cancel {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+    },
+
+    /// Replays a stored proof, reporting whether its public inputs are reproduced
+    Replay {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+    },
+
+    /// Downloads a file from the storage, streaming its bytes to the output
+    Download {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Path to the file on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+
+        /// Offset of the first byte to be downloaded
+        #[arg(long, value_name = "OFFSET")]
+        offset: Option<u64>,
+
+        /// Path of the output file
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: PathBuf,
+    },
+
+    /// Returns the co-processor root computed when a block was added to a domain
+    SmtRoot {
+        /// Name of the domain
+        #[arg(value_name = "DOMAIN")]
+        domain: String,
+
+        /// Block number
+        #[arg(value_name = "NUMBER")]
+        number: u64,
+    },
+
+    /// Returns the co-processor root most recently published on a chain
+    PublishedRoot {
+        /// Name of the chain
+        #[arg(value_name = "CHAIN")]
+        chain: String,
+    },
+
+    /// Lists the domains that changed between two co-processor roots
+    DiffRoots {
+        /// Hex co-processor root to compare from
+        #[arg(value_name = "OLD")]
+        old: String,
+
+        /// Hex co-processor root to compare to
+        #[arg(value_name = "NEW")]
+        new: String,
+
+        /// Hex domain id after which the changes are listed
+        #[arg(long, value_name = "AFTER")]
+        after: Option<String>,
+
+        /// Maximum number of changes to list; returns a single page with its `next` cursor
+        #[arg(long, value_name = "LIMIT")]
+        limit: Option<usize>,
+    },
+
+    /// Returns the last witness set computed for a circuit
+    LastWitnesses {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+    },
+
+    /// Returns the entrypoint arguments schema of a circuit controller
+    Describe {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+    },
+
+    /// Streams the service log, as newline-delimited JSON (requires the admin token)
+    TailLog {
+        /// Only lines in scope of this circuit
+        #[arg(short, long, value_name = "CIRCUIT")]
+        circuit: Option<String>,
+
+        /// Only lines in scope of this request id
+        #[arg(short, long, value_name = "REQUEST_ID")]
+        request_id: Option<String>,
+
+        /// Minimum level of the lines
+        #[arg(short, long, value_name = "LEVEL")]
+        level: Option<String>,
+    },
+
     /// Returns the VK of a circuit
     Vk {
         /// ID of the deployed circuit
@@ -72,6 +294,13 @@ pub enum Commands {
         circuit: String,
     },
 
+    /// Returns the VK fingerprints of the provided circuits
+    VkHashes {
+        /// IDs of the deployed circuits
+        #[arg(value_name = "CIRCUITS", required = true)]
+        circuits: Vec<String>,
+    },
+
     /// Returns the public inputs of the proof stored on the provided path of the virtual
     /// filesystem.
     ProofInputs {
@@ -88,6 +317,156 @@ pub enum Commands {
         )]
         path: PathBuf,
     },
+
+    /// Estimates the gas to verify a proof of the circuit on an EVM verifier.
+    Gas {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Proof system of the EVM verifier
+        #[arg(long, value_enum, default_value = "groth16")]
+        system: ProofSystem,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+    },
+
+    /// Submits a proof of the circuit to an on-chain relayer.
+    Relay {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Endpoint of the relayer
+        #[arg(long, env = "VALENCE_RELAYER", value_name = "URL")]
+        relayer: String,
+
+        /// Hex function selector prepended to the calldata
+        #[arg(long, value_name = "SELECTOR")]
+        selector: Option<String>,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+    },
+
+    /// Calls the entrypoint of a circuit controller.
+    Entrypoint {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Optional JSON argument to be passed to the controller.
+        #[arg(short, long, value_name = "JSON")]
+        json: Option<String>,
+
+        /// Optional name of the output to be returned, for controllers with named outputs.
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<String>,
+    },
+
+    /// Returns the proving capacity of the co-processor.
+    Capacity,
+
+    /// Returns the co-processor version and supported capabilities.
+    Info,
+
+    /// Writes the completion script of the shell to stdout.
+    #[command(hide = true)]
+    Completions {
+        /// Shell of the completion script
+        #[arg(value_enum, value_name = "SHELL")]
+        shell: CompletionShell,
+    },
+
+    /// Exports a deployed circuit into a bundle file.
+    Export {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Nonce used when the circuit was deployed
+        #[arg(short, long, value_name = "NONCE", default_value_t = 0)]
+        nonce: u64,
+
+        /// Path of the bundle file
+        #[arg(short, long, value_name = "PATH", default_value = "bundle.json")]
+        path: PathBuf,
+    },
+
+    /// Deploys a circuit from a bundle file.
+    Import {
+        /// Path of the bundle file
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Exports a stored proof into a package file that can be verified offline.
+    ExportPackage {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+
+        /// Path of the package file
+        #[arg(short, long, value_name = "OUT", default_value = "package.json")]
+        out: PathBuf,
+    },
+
+    /// Verifies a stored proof against the verifying key of its circuit.
+    #[cfg(feature = "sp1")]
+    Verify {
+        /// ID of the deployed circuit
+        #[arg(value_name = "CIRCUIT")]
+        circuit: String,
+
+        /// Path to the proof on the virtual filesystem
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            default_value = "/var/share/proof.bin"
+        )]
+        path: PathBuf,
+
+        /// Verifies a proof of the mock prover, instead of a Groth16 proof.
+        #[arg(long)]
+        mock: bool,
+    },
+
+    /// Verifies a package file, without network access.
+    #[cfg(feature = "sp1")]
+    VerifyPackage {
+        /// Path of the package file
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+
+        /// Hex encoded hash of the verifying key of the circuit, as returned on export.
+        ///
+        /// Without it, the proof is only checked against the packaged verifying key.
+        #[arg(long, value_name = "HASH")]
+        vk_hash: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -101,6 +480,22 @@ pub enum CmdDeploy {
         /// Path of the controller (defaults to current dir).
         #[arg(short, long, value_name = "PATH")]
         controller: Option<PathBuf>,
+
+        /// Optional JSON genesis block, added to the domain once deployed.
+        #[arg(short, long, value_name = "JSON")]
+        genesis: Option<String>,
+
+        /// Compresses the genesis block payload, if supported by the co-processor.
+        #[arg(long, requires = "genesis")]
+        compress: bool,
+
+        /// Builds with the host toolchains instead of Docker; the build isn't reproducible.
+        #[arg(long)]
+        local: bool,
+
+        /// Valence co-processor sources of the local build; defaults to the ones of this binary.
+        #[arg(long, value_name = "PATH", requires = "local")]
+        valence_src: Option<PathBuf>,
     },
 
     /// Deploys a circuit to the co-processor.
@@ -112,5 +507,67 @@ pub enum CmdDeploy {
         /// Workspace member name of the circuit.
         #[arg(short, long, value_name = "CIRCUIT")]
         circuit: String,
+
+        /// Optional JSON sample arguments, executed to report the cycle count of the circuit.
+        #[arg(short, long, value_name = "JSON")]
+        sample: Option<String>,
+
+        /// Builds with the host toolchains instead of Docker; the build isn't reproducible.
+        #[arg(long)]
+        local: bool,
+
+        /// Valence co-processor sources of the local build; defaults to the ones of this binary.
+        #[arg(long, value_name = "PATH", requires = "local")]
+        valence_src: Option<PathBuf>,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn output_format_defaults_to_compact_json() {
+        let cli = Cli::try_parse_from(["cargo-valence", "info"]).unwrap();
+        let response = json!({"controller": "c0ffee", "nested": {"a": 1}});
+
+        assert_eq!(cli.format, OutputFormat::Json);
+        assert_eq!(
+            cli.format.render(&response),
+            r#"{"controller":"c0ffee","nested":{"a":1}}"#
+        );
+
+        let cli = Cli::try_parse_from(["cargo-valence", "--format", "pretty", "info"]).unwrap();
+        let pretty = cli.format.render(&response);
+
+        assert!(pretty.lines().count() > 1);
+        assert_eq!(serde_json::from_str::<Value>(&pretty).unwrap(), response);
+        assert!(Cli::try_parse_from(["cargo-valence", "--format", "yaml", "info"]).is_err());
+    }
+
+    #[cfg(feature = "completions")]
+    #[test]
+    fn completions_are_generated_for_each_shell() {
+        for shell in CompletionShell::value_variants() {
+            let mut script = Vec::new();
+
+            Cli::completions(*shell, &mut script).unwrap();
+
+            let script = String::from_utf8(script).unwrap();
+
+            assert!(script.contains(Cli::BIN_NAME), "{shell:?}");
+            assert!(script.contains("verify-package"), "{shell:?}");
+        }
+
+        let cli = Cli::try_parse_from(["cargo-valence", "completions", "powershell"]).unwrap();
+
+        assert!(matches!(
+            cli.cmd,
+            Commands::Completions {
+                shell: CompletionShell::PowerShell
+            }
+        ));
+    }
+}
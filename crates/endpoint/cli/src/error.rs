@@ -0,0 +1,255 @@
+use std::{fmt, io};
+
+use reqwest::{blocking::Response, StatusCode};
+
+/// An error of the co-processor client.
+///
+/// Converts into [`anyhow::Error`], so callers that don't need to distinguish the failures can
+/// keep using `?`.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request failed, either on the transport or with an error status.
+    Http(reqwest::Error),
+
+    /// The co-processor returned a response that couldn't be decoded.
+    MalformedResponse(String),
+
+    /// The proof was computed incorrectly.
+    ProofFailed {
+        /// Log lines of the controller.
+        log: Vec<String>,
+    },
+
+    /// The proof was cancelled before a prover picked it up.
+    ProofCancelled,
+
+    /// The requested resource wasn't found.
+    NotFound(String),
+
+    /// The request requires the admin token, or the signature of the owner of the resource.
+    Forbidden(String),
+
+    /// The operation didn't complete in time.
+    Timeout(String),
+
+    /// A chunked upload failed; it can be resumed with its session id.
+    UploadInterrupted {
+        /// The id of the upload session.
+        session: String,
+        /// The error of the last attempt.
+        source: Box<ClientError>,
+    },
+
+    /// A failure outside of the co-processor, as reading a local file or building a definition.
+    Other(anyhow::Error),
+}
+
+impl ClientError {
+    /// Returns `true` if the operation may succeed when retried.
+    ///
+    /// A failed or cancelled proof, a malformed response, a rejected request or a missing resource
+    /// won't change on a retry.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(e) => !e.status().is_some_and(|s| s.is_client_error()),
+            Self::Timeout(_) => true,
+            Self::UploadInterrupted { source, .. } => source.is_transient(),
+            _ => false,
+        }
+    }
+
+    /// Maps the error status of a co-processor response into its error.
+    ///
+    /// `404` is [`ClientError::NotFound`], `401` and `403` are [`ClientError::Forbidden`], `408`
+    /// and `504` are [`ClientError::Timeout`]; other error statuses are [`ClientError::Http`]. The
+    /// body of the response, if any, is kept as the error message.
+    pub fn check(response: Response) -> Result<Response, Self> {
+        let status = response.status();
+        let message = |response: Response| {
+            let url = response.url().to_string();
+
+            match response.text().unwrap_or_default().trim() {
+                "" => format!("{status} at `{url}`"),
+                body => format!("{status} at `{url}`: {body}"),
+            }
+        };
+
+        match status {
+            StatusCode::NOT_FOUND => Err(Self::NotFound(message(response))),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Err(Self::Forbidden(message(response)))
+            }
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => {
+                Err(Self::Timeout(message(response)))
+            }
+            _ => response.error_for_status().map_err(Self::Http),
+        }
+    }
+}
+
+/// Checks the status of a co-processor response, via [`ClientError::check`].
+pub(crate) trait CheckStatus: Sized {
+    fn check_status(self) -> Result<Self, ClientError>;
+}
+
+impl CheckStatus for Response {
+    fn check_status(self) -> Result<Self, ClientError> {
+        ClientError::check(self)
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "http request failed: {e}"),
+            Self::MalformedResponse(e) => write!(f, "malformed response: {e}"),
+            Self::ProofFailed { log } => {
+                write!(f, "the proof was computed incorrectly")?;
+
+                if !log.is_empty() {
+                    write!(f, ": {}", log.join("\n"))?;
+                }
+
+                Ok(())
+            }
+            Self::ProofCancelled => write!(f, "the proof was cancelled"),
+            Self::NotFound(e) => write!(f, "not found: {e}"),
+            Self::Forbidden(e) => write!(f, "forbidden: {e}"),
+            Self::Timeout(e) => write!(f, "timeout: {e}"),
+            Self::UploadInterrupted { session, source } => {
+                write!(f, "upload session `{session}` interrupted: {source}")
+            }
+            Self::Other(e) => write!(f, "{e:#}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(e) => Some(e),
+            Self::UploadInterrupted { source, .. } => Some(source.as_ref()),
+            Self::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        match e {
+            e if e.is_decode() => Self::MalformedResponse(e.to_string()),
+            e if e.is_timeout() => Self::Timeout(e.to_string()),
+            e => Self::Http(e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ClientError {
+    fn from(e: anyhow::Error) -> Self {
+        // keeps the client errors of the nested calls
+        e.downcast().unwrap_or_else(Self::Other)
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Other(e.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock::MockServer, App, ProofHandle};
+
+    use super::*;
+
+    #[test]
+    fn error_statuses_map_into_client_errors() {
+        let server = MockServer::serve([
+            (404, "unknown circuit"),
+            (403, ""),
+            (504, ""),
+            (400, ""),
+            (500, ""),
+            (200, "not json"),
+        ]);
+        let app = server.app();
+
+        let err = app.vk("circuit").unwrap_err();
+
+        assert!(
+            matches!(&err, ClientError::NotFound(e) if e.contains("unknown circuit")),
+            "{err}"
+        );
+        assert!(!err.is_transient());
+        assert!(matches!(
+            app.get_last_witnesses("circuit"),
+            Err(ClientError::Forbidden(_))
+        ));
+
+        let err = app.vk("circuit").unwrap_err();
+
+        assert!(matches!(err, ClientError::Timeout(_)));
+        assert!(err.is_transient());
+
+        let err = app.vk("circuit").unwrap_err();
+
+        assert!(matches!(err, ClientError::Http(_)));
+        assert!(!err.is_transient());
+
+        let err = app.vk("circuit").unwrap_err();
+
+        assert!(matches!(err, ClientError::Http(_)));
+        assert!(err.is_transient());
+
+        assert!(matches!(
+            app.vk("circuit"),
+            Err(ClientError::MalformedResponse(_))
+        ));
+
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+
+        assert_eq!(paths[0], "/api/registry/controller/circuit/vk");
+    }
+
+    #[test]
+    fn proof_handles_report_typed_errors() {
+        let server = MockServer::serve([(404, ""), (403, ""), (200, r#"{"cancelled":true}"#)]);
+        let app = server.app().with_admin_token(Some("secret".into()));
+        let handle = ProofHandle::new(app, "circuit", "/var/proofs/p.bin");
+
+        assert!(matches!(handle.try_poll(), Err(ClientError::NotFound(_))));
+        assert!(matches!(handle.cancel(), Err(ClientError::Forbidden(_))));
+        assert!(handle.cancel().unwrap());
+
+        let requests = server.requests();
+
+        assert_eq!(
+            requests[1].path,
+            "/api/registry/controller/circuit/prove/cancel"
+        );
+        assert_eq!(requests[2].json()["path"], "/var/proofs/p.bin");
+        assert_eq!(requests[2].header(App::ADMIN_HEADER), Some("secret"));
+    }
+
+    #[test]
+    fn anyhow_errors_keep_the_client_error() {
+        let err = anyhow::Error::from(ClientError::ProofCancelled);
+
+        assert!(matches!(
+            ClientError::from(err),
+            ClientError::ProofCancelled
+        ));
+        assert!(matches!(
+            ClientError::from(anyhow::anyhow!("local failure")),
+            ClientError::Other(_)
+        ));
+    }
+}
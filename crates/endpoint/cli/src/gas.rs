@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::{App, ClientError};
+
+/// A proof system supported by the EVM verifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProofSystem {
+    /// Groth16 over BN254.
+    Groth16,
+    /// PLONK over BN254.
+    Plonk,
+}
+
+impl ProofSystem {
+    /// Returns the size in bytes of an EVM encoded proof, including the verifier selector.
+    pub const fn proof_len(&self) -> usize {
+        match self {
+            ProofSystem::Groth16 => 4 + 8 * 32,
+            ProofSystem::Plonk => 4 + 27 * 32,
+        }
+    }
+
+    /// Returns the fixed cost of the verifier precompiles and logic.
+    ///
+    /// Groth16 performs a 4-pair pairing check (EIP-197) and two scalar multiplications for the
+    /// public inputs (EIP-196). PLONK performs a 2-pair pairing check and the multi-scalar
+    /// multiplication of the commitments, which dominates its cost.
+    pub const fn verifier_gas(&self) -> u64 {
+        match self {
+            ProofSystem::Groth16 => 45_000 + 4 * 34_000 + 2 * (6_000 + 150) + 20_000,
+            ProofSystem::Plonk => 45_000 + 2 * 34_000 + 28 * (6_000 + 150) + 40_000,
+        }
+    }
+}
+
+/// Gas cost of a non-zero calldata byte (EIP-2028).
+const CALLDATA_NONZERO_GAS: u64 = 16;
+
+/// Gas cost of the SHA-256 precompile: a base cost and a per-word cost.
+const SHA256_GAS: (u64, u64) = (60, 12);
+
+impl App {
+    /// Estimates the gas to verify a proof of the circuit on an EVM verifier.
+    ///
+    /// The proof stored on `path` is used to measure the size of the public values. The estimate
+    /// assumes non-zero calldata bytes, the SHA-256 digest of the public values as committed by
+    /// the verifier, and the reference costs of the BN254 precompiles; it excludes the base
+    /// transaction cost and any application logic wrapping the verifier.
+    pub fn estimate_verification_gas<C, P>(
+        &self,
+        circuit: C,
+        path: P,
+        system: ProofSystem,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let (_, inputs) = self.load_proof(circuit, path)?.decode()?;
+
+        let proof_len = system.proof_len();
+        let words = inputs.len().div_ceil(32) as u64;
+
+        let calldata = (proof_len + inputs.len()) as u64 * CALLDATA_NONZERO_GAS;
+        let digest = SHA256_GAS.0 + SHA256_GAS.1 * words;
+        let verifier = system.verifier_gas();
+
+        Ok(json!({
+            "system": format!("{system:?}").to_lowercase(),
+            "proof_bytes": proof_len,
+            "public_values_bytes": inputs.len(),
+            "calldata_gas": calldata,
+            "verifier_gas": verifier + digest,
+            "total_gas": calldata + verifier + digest,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_coprocessor::Proof;
+
+    use crate::mock::{self, MockServer};
+
+    use super::*;
+
+    #[test]
+    fn verification_gas_scales_with_the_public_values() {
+        let proof = Proof::new([1; 260], [2; 40]);
+        let server = MockServer::serve([
+            (200, mock::stored_proof(&proof)),
+            (200, mock::stored_proof(&proof)),
+            (200, json!({"data": null}).to_string()),
+        ]);
+        let app = server.app();
+
+        let groth16 = app
+            .estimate_verification_gas("circuit", "/var/share/proofs/p.bin", ProofSystem::Groth16)
+            .unwrap();
+
+        // 300 bytes of calldata, and the SHA-256 digest of two words
+        assert_eq!(groth16["system"], "groth16");
+        assert_eq!(groth16["public_values_bytes"], 40);
+        assert_eq!(groth16["calldata_gas"], 300 * 16);
+        assert_eq!(groth16["verifier_gas"], 213_300 + 60 + 2 * 12);
+        assert_eq!(groth16["total_gas"], 4_800 + 213_384);
+
+        let plonk = app
+            .estimate_verification_gas("circuit", "/var/share/proofs/p.bin", ProofSystem::Plonk)
+            .unwrap();
+
+        assert_eq!(plonk["proof_bytes"], ProofSystem::Plonk.proof_len());
+        assert!(plonk["total_gas"].as_u64() > groth16["total_gas"].as_u64());
+
+        assert!(matches!(
+            app.estimate_verification_gas("circuit", "/var/share/proofs/p.bin", ProofSystem::Plonk),
+            Err(ClientError::NotFound(_))
+        ));
+
+        let request = &server.requests()[0];
+
+        assert_eq!(request.path, "/api/registry/controller/circuit/storage/fs");
+        assert_eq!(request.json()["path"], "/var/share/proofs/p.bin");
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use valence_coprocessor::{Base64, LogLevel, Proof};
+
+use crate::{App, ClientError};
+
+/// A handle to a proof submitted to the co-processor queue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofHandle {
+    app: App,
+    circuit: String,
+    path: PathBuf,
+}
+
+impl ProofHandle {
+    /// Default interval between polls while awaiting a proof.
+    pub const POLL_INTERVAL: Duration = Duration::from_millis(App::DEFAULT_POLL_FREQUENCY_MS);
+
+    /// Creates a handle for a proof of `circuit` stored at `path` of the virtual filesystem.
+    pub fn new<C, P>(app: App, circuit: C, path: P) -> Self
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        Self {
+            app,
+            circuit: circuit.as_ref().into(),
+            path: path.as_ref().into(),
+        }
+    }
+
+    /// ID of the proven circuit.
+    pub fn circuit(&self) -> &str {
+        &self.circuit
+    }
+
+    /// Path of the proof on the virtual filesystem.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the stored proof result, if available.
+    ///
+    /// The result carries the `commitment` to the public inputs of its proof, checked against the
+    /// one stored by the co-processor, if any.
+    ///
+    /// Fails with [`ClientError::ProofFailed`] if the result reports the proof as computed
+    /// incorrectly, [`ClientError::ProofCancelled`] if the proof was cancelled, or
+    /// [`ClientError::Timeout`] if its deadline expired before a worker picked it up.
+    pub fn try_poll(&self) -> Result<Option<Value>, ClientError> {
+        let data = match self.app.storage_file(&self.circuit, &self.path)? {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let data = Base64::decode(data)
+            .map_err(|e| ClientError::MalformedResponse(format!("invalid proof encoding: {e}")))?;
+        let data: Value = serde_json::from_slice(&data)
+            .map_err(|e| ClientError::MalformedResponse(format!("invalid proof result: {e}")))?;
+
+        if data.get("cancelled").and_then(Value::as_bool) == Some(true) {
+            return Err(ClientError::ProofCancelled);
+        }
+
+        if data.get("expired").and_then(Value::as_bool) == Some(true) {
+            return Err(ClientError::Timeout(format!(
+                "the deadline of the proof `{}` expired before it was computed",
+                self.path.display()
+            )));
+        }
+
+        if data.get("success").and_then(Value::as_bool) == Some(false) {
+            return Err(ClientError::ProofFailed {
+                log: Self::log_lines(&data),
+            });
+        }
+
+        let mut data = data;
+
+        if let Some(proof) = data.get("proof").and_then(Value::as_str) {
+            let proof = Proof::try_from_base64(proof)
+                .map_err(|e| ClientError::MalformedResponse(format!("invalid proof: {e}")))?;
+            let commitment = hex::encode(proof.input_commitment()?);
+
+            match data.get("commitment") {
+                Some(c) if c != commitment.as_str() => {
+                    return Err(ClientError::MalformedResponse(format!(
+                        "the stored commitment `{c}` doesn't match the proof inputs `{commitment}`"
+                    )))
+                }
+                _ => data["commitment"] = commitment.into(),
+            }
+        }
+
+        Ok(Some(data))
+    }
+
+    /// Returns the stored proof, if available.
+    ///
+    /// Doesn't block, so many handles can be polled cooperatively.
+    pub fn poll(&self) -> Result<Option<Proof>, ClientError> {
+        Ok(self.poll_with_log()?.map(|(p, _)| p))
+    }
+
+    /// Returns the stored proof, if available, along with the log lines of the controller.
+    ///
+    /// If the proof failed, the log is carried by [`ClientError::ProofFailed`].
+    pub fn poll_with_log(&self) -> Result<Option<(Proof, Vec<String>)>, ClientError> {
+        let data = match self.try_poll()? {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let proof = data.get("proof").and_then(Value::as_str).ok_or_else(|| {
+            ClientError::MalformedResponse("no proof in the stored result".into())
+        })?;
+
+        let proof = Proof::try_from_base64(proof)
+            .map_err(|e| ClientError::MalformedResponse(format!("invalid proof: {e}")))?;
+
+        Ok(Some((proof, Self::log_lines(&data))))
+    }
+
+    /// Returns the log lines of a stored proof result.
+    pub fn log_lines(result: &Value) -> Vec<String> {
+        result
+            .get("log")
+            .and_then(Value::as_array)
+            .map(|l| {
+                l.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the log lines of a stored proof result, split into their level and message.
+    pub fn log_entries(result: &Value) -> Vec<(LogLevel, String)> {
+        Self::log_lines(result)
+            .iter()
+            .map(|l| {
+                let (level, message) = LogLevel::parse(l);
+
+                (level, message.to_string())
+            })
+            .collect()
+    }
+
+    /// Cancels the proof if it wasn't picked up by a prover yet.
+    ///
+    /// Returns `false` if the proof is already being computed, or completed.
+    pub fn cancel(&self) -> Result<bool, ClientError> {
+        self.app.cancel_proof(&self.circuit, &self.path)
+    }
+
+    /// Blocks until the proof result is available.
+    ///
+    /// Polls with the retries and frequency configured on the app. Fails if the retries are
+    /// exhausted, or the timeout of the app elapses, before the proof is stored.
+    pub fn await_proof(&self) -> Result<Value, ClientError> {
+        self.await_proof_with_params(None, None)
+    }
+
+    /// Blocks until the proof result is available, polling at most `retries` times after the first
+    /// poll, every `frequency_ms` milliseconds.
+    ///
+    /// An explicit argument takes precedence over the value configured on the app via
+    /// [`App::with_proof_retries`] and [`App::with_poll_frequency_ms`]; a `None` falls back to it.
+    pub fn await_proof_with_params(
+        &self,
+        retries: Option<u64>,
+        frequency_ms: Option<u64>,
+    ) -> Result<Value, ClientError> {
+        let retries = retries.or(self.app.proof_retries);
+        let frequency = Duration::from_millis(frequency_ms.unwrap_or(self.app.poll_frequency_ms));
+        let timeout = self.app.timeout.map(Duration::from_secs);
+        let start = Instant::now();
+        let mut attempt = 0;
+
+        loop {
+            if let Some(p) = self.try_poll()? {
+                return Ok(p);
+            }
+
+            if timeout.is_some_and(|t| start.elapsed() >= t) {
+                return Err(ClientError::Timeout(format!(
+                    "proof at `{}` not available in time",
+                    self.path.display()
+                )));
+            }
+
+            if retries.is_some_and(|r| attempt >= r) {
+                return Err(ClientError::Timeout(format!(
+                    "proof at `{}` not available after {} polls",
+                    self.path.display(),
+                    attempt + 1
+                )));
+            }
+
+            attempt += 1;
+
+            thread::sleep(frequency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::mock::{self, MockServer};
+
+    use super::*;
+
+    #[test]
+    fn polls_follow_the_app_configuration_unless_overridden() {
+        let pending = || (200, json!({"data": null}).to_string());
+        let proof = Proof::new([1], [2]);
+        let stored = mock::stored_proof(&proof);
+        let server = MockServer::serve([
+            pending(),
+            pending(),
+            pending(),
+            pending(),
+            pending(),
+            (200, stored),
+        ]);
+        let app = server.app().with_proof_retries(2).with_poll_frequency_ms(1);
+        let handle = ProofHandle::new(app, "circuit", "/var/share/proofs/p.bin");
+
+        // the first poll, then the two configured retries
+        let err = handle.await_proof().unwrap_err();
+
+        assert!(err.to_string().contains("after 3 polls"), "{err}");
+
+        // an explicit argument takes precedence
+        assert!(handle.await_proof_with_params(Some(0), None).is_err());
+
+        let result = handle.await_proof_with_params(Some(5), Some(1)).unwrap();
+
+        assert_eq!(result["proof"], proof.to_base64());
+        assert_eq!(server.requests().len(), 6);
+    }
+
+    #[test]
+    fn polled_proofs_carry_the_controller_log() {
+        let proof = Proof::new([1], [2]);
+        let server = MockServer::serve([
+            (
+                200,
+                mock::storage_body(&json!({
+                    "success": true,
+                    "proof": proof.to_base64(),
+                    "log": ["fetched the balances", "[warn] slow endpoint"],
+                })),
+            ),
+            (
+                200,
+                mock::storage_body(&json!({
+                    "success": false,
+                    "log": ["[error] invalid witness"],
+                })),
+            ),
+        ]);
+        let handle = ProofHandle::new(server.app(), "circuit", "/var/share/proofs/p.bin");
+
+        let (polled, log) = handle.poll_with_log().unwrap().unwrap();
+
+        assert_eq!(polled, proof);
+        assert_eq!(log, ["fetched the balances", "[warn] slow endpoint"]);
+
+        match handle.poll_with_log() {
+            Err(ClientError::ProofFailed { log }) => assert_eq!(log, ["[error] invalid witness"]),
+            r => panic!("unexpected poll result: {r:?}"),
+        }
+    }
+
+    #[test]
+    fn polled_results_commit_to_the_proof_inputs() {
+        let proof = Proof::new([1], [2]);
+        let commitment = hex::encode(proof.input_commitment().unwrap());
+        let other = hex::encode(Proof::new([1], [3]).input_commitment().unwrap());
+
+        let server = MockServer::serve([
+            (
+                200,
+                mock::storage_body(&json!({"success": true, "proof": proof.to_base64()})),
+            ),
+            (
+                200,
+                mock::storage_body(&json!({
+                    "success": true,
+                    "proof": proof.to_base64(),
+                    "commitment": commitment,
+                })),
+            ),
+            (
+                200,
+                mock::storage_body(&json!({
+                    "success": true,
+                    "proof": proof.to_base64(),
+                    "commitment": other,
+                })),
+            ),
+        ]);
+        let handle = ProofHandle::new(server.app(), "circuit", "/var/share/proofs/p.bin");
+
+        assert_eq!(
+            handle.try_poll().unwrap().unwrap()["commitment"],
+            commitment
+        );
+        assert_eq!(
+            handle.try_poll().unwrap().unwrap()["commitment"],
+            commitment
+        );
+        assert!(matches!(
+            handle.try_poll(),
+            Err(ClientError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn log_entries_carry_the_controller_log_levels() {
+        let result = serde_json::json!({
+            "log": [
+                "fetching balances",
+                "[warn] slow endpoint",
+                "[error] invalid proof",
+                "[info] [error] echoed text",
+            ],
+        });
+
+        assert_eq!(
+            ProofHandle::log_entries(&result),
+            vec![
+                (LogLevel::Info, "fetching balances".to_string()),
+                (LogLevel::Warn, "slow endpoint".to_string()),
+                (LogLevel::Error, "invalid proof".to_string()),
+                (LogLevel::Info, "[error] echoed text".to_string()),
+            ]
+        );
+    }
+}
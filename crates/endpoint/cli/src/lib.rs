@@ -1,14 +1,40 @@
+mod build;
 mod cli;
+mod error;
+mod gas;
+mod handle;
+mod logs;
+#[cfg(test)]
+mod mock;
+mod package;
+mod relay;
+mod storage;
+mod upload;
 
 use std::{
     fs,
+    io::Read,
     path::{Path, PathBuf},
     process::{Command, Output, Stdio},
+    sync::{Arc, OnceLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+pub use build::*;
 pub use cli::*;
+pub use error::*;
+pub use gas::*;
+pub use handle::*;
+pub use logs::*;
+pub use package::*;
+pub use relay::*;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use valence_coprocessor::{Base64, Proof};
+use valence_coprocessor::{
+    Base64, Blake3Hasher, ControllerData, ControllerSchema, Hash, Hasher as _, NamedOutputs, Proof,
+    SmtChange, SmtDiff,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct App {
@@ -17,12 +43,106 @@ pub struct App {
     pub socket: String,
     pub tag: String,
     pub docker_host: bool,
+    /// Container runtime used to run the image; any Docker CLI compatible binary, like `podman`.
+    pub container_runtime: String,
+    /// Builds with the local toolchains instead of the image; not reproducible.
+    pub local_build: bool,
+    /// Valence co-processor sources of the local builds, with the wrapper templates and crates.
+    pub local_sources: PathBuf,
+    /// Optional request timeout in seconds.
+    pub timeout: Option<u64>,
+    /// Optional token granting access to the admin endpoints.
+    pub admin_token: Option<String>,
+    /// Compresses the domain block payloads, if supported by the co-processor.
+    pub compress_blocks: bool,
+    /// Maximum number of polls after the first one while awaiting a proof; unbounded if `None`.
+    pub proof_retries: Option<u64>,
+    /// Interval between polls while awaiting a proof, in milliseconds.
+    pub poll_frequency_ms: u64,
+    http: HttpClient,
+}
+
+/// A HTTP client shared by the clones of an app, built on first use.
+///
+/// Reusing the client keeps its connection pool alive across requests.
+#[derive(Debug, Clone, Default)]
+struct HttpClient {
+    client: Arc<OnceLock<reqwest::blocking::Client>>,
+
+    /// `true` if the client was provided via [`App::with_http_client`].
+    custom: bool,
+}
+
+impl PartialEq for HttpClient {
+    fn eq(&self, other: &Self) -> bool {
+        // a built client derives from the app configuration; a provided one is compared by identity
+        match (self.custom, other.custom) {
+            (false, false) => true,
+            (true, true) => Arc::ptr_eq(&self.client, &other.client),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HttpClient {}
+
+/// A block added to a domain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddedDomainBlock {
+    /// The added block, as returned by the co-processor.
+    pub block: Value,
+    /// Size of the payload, in bytes, before compression.
+    pub payload_size: usize,
+    /// Maximum size of the payload, in bytes, advertised by the co-processor.
+    pub payload_limit: Option<usize>,
+    /// `true` if the payload was sent compressed.
+    pub compressed: bool,
+}
+
+/// A co-processor root published on-chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedRoot {
+    /// The published co-processor root.
+    pub root: Hash,
+    /// Chain the root was published to.
+    pub chain: String,
+    /// Hash of the publication transaction.
+    pub tx: String,
+    /// Address of the contract that holds the root, if any.
+    pub contract: Option<String>,
+    /// Block number of the publication transaction, if known.
+    pub block: Option<u64>,
+    /// UNIX timestamp, in seconds, of when the co-processor recorded the publication.
+    pub published_at: u64,
+}
+
+impl PublishedRoot {
+    fn from_response(mut response: Value) -> Result<Self, ClientError> {
+        let root = response
+            .get("root")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::MalformedResponse("no root received".into()))
+            .and_then(|r| {
+                hex::decode(r).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+            })?;
+        let root = Hash::try_from(root)
+            .map_err(|_| ClientError::MalformedResponse("invalid root received".into()))?;
+
+        response["root"] = json!(root);
+
+        serde_json::from_value(response).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+    }
 }
 
-struct ProjectStructure {
+/// A package resolved from the metadata of its workspace.
+pub struct ProjectStructure {
+    /// Metadata of the workspace, as returned by `cargo metadata`.
     pub metadata: Value,
+    /// Root directory of the workspace.
     pub wsroot: String,
+    /// Directory of the package, relative to the workspace root.
     pub pkrelative: String,
+    /// Name of the package.
     pub package: String,
 }
 
@@ -33,15 +153,46 @@ impl Default for App {
             socket: Self::DEFAULT_SOCKET.into(),
             tag: Self::DEFAULT_TAG.into(),
             docker_host: Self::DEFAULT_DOCKER_HOST,
+            container_runtime: Self::DEFAULT_CONTAINER_RUNTIME.into(),
+            local_build: false,
+            local_sources: LocalBuilder::default_sources(),
+            timeout: None,
+            admin_token: None,
+            compress_blocks: false,
+            proof_retries: None,
+            poll_frequency_ms: Self::DEFAULT_POLL_FREQUENCY_MS,
+            http: HttpClient::default(),
         }
     }
 }
 
 impl App {
+    /// Version of the exported bundle format.
+    pub const BUNDLE_VERSION: u64 = 1;
+
+    /// Directory of the virtual filesystem where seeded proofs are stored.
+    pub const PROOFS_DIR: &str = "/var/share/proofs";
+
     pub const DEFAULT_DOCKER: &str = concat!("vtw11/valence:", env!("CARGO_PKG_VERSION"));
     pub const DEFAULT_SOCKET: &str = "https://service.coprocessor.valence.zone";
     pub const DEFAULT_TAG: &str = concat!("v", env!("CARGO_PKG_VERSION"));
     pub const DEFAULT_DOCKER_HOST: bool = false;
+    pub const DEFAULT_CONTAINER_RUNTIME: &str = "docker";
+
+    /// Scheme of a co-processor socket reached over a Unix domain socket.
+    pub const UNIX_SCHEME: &str = "unix:";
+
+    /// Default interval between polls while awaiting a proof, in milliseconds.
+    pub const DEFAULT_POLL_FREQUENCY_MS: u64 = 2000;
+
+    /// Maximum number of concurrent requests issued by [`App::get_vk_hashes`].
+    pub const VK_HASH_CONCURRENCY: usize = 8;
+
+    /// Creates an app that reaches the co-processor over the Unix domain socket at `path`.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> Self {
+        Self::default().with_socket(format!("{}{}", Self::UNIX_SCHEME, path.as_ref().display()))
+    }
 
     pub fn with_docker<V: AsRef<str>>(mut self, docker: V) -> Self {
         self.docker = docker.as_ref().into();
@@ -63,7 +214,118 @@ impl App {
         self
     }
 
-    fn run_docker(
+    pub fn with_container_runtime<V: AsRef<str>>(mut self, runtime: V) -> Self {
+        self.container_runtime = runtime.as_ref().into();
+        self
+    }
+
+    /// Builds the deployed definitions with the host toolchains, via [`LocalBuilder`].
+    pub fn with_local_build(mut self, local_build: bool) -> Self {
+        self.local_build = local_build;
+        self
+    }
+
+    /// Sets the valence co-processor sources of the local builds.
+    pub fn with_local_sources<P: AsRef<Path>>(mut self, sources: P) -> Self {
+        self.local_sources = sources.as_ref().to_path_buf();
+        self
+    }
+
+    /// Returns the builder of the deployed definitions.
+    pub fn builder(&self) -> &'static dyn Builder {
+        match self.local_build {
+            true => &LocalBuilder,
+            false => &DockerBuilder,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<u64>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_admin_token(mut self, admin_token: Option<String>) -> Self {
+        self.admin_token = admin_token;
+        self
+    }
+
+    pub fn with_block_compression(mut self, compress_blocks: bool) -> Self {
+        self.compress_blocks = compress_blocks;
+        self
+    }
+
+    /// Sets the maximum number of polls after the first one while awaiting a proof.
+    ///
+    /// Applies to [`ProofHandle::await_proof`]; an explicit argument of
+    /// [`ProofHandle::await_proof_with_params`] takes precedence.
+    pub fn with_proof_retries(mut self, retries: u64) -> Self {
+        self.proof_retries = Some(retries);
+        self
+    }
+
+    /// Sets the interval between polls while awaiting a proof, in milliseconds.
+    ///
+    /// Applies to [`ProofHandle::await_proof`]; an explicit argument of
+    /// [`ProofHandle::await_proof_with_params`] takes precedence.
+    pub fn with_poll_frequency_ms(mut self, frequency_ms: u64) -> Self {
+        self.poll_frequency_ms = frequency_ms;
+        self
+    }
+
+    /// Sets the HTTP client used for the requests to the co-processor.
+    ///
+    /// Allows a pre-configured client, such as one with custom connection limits, to be shared.
+    /// If the co-processor is reached over a Unix domain socket, the client must be configured to
+    /// route over it.
+    pub fn with_http_client(mut self, client: reqwest::blocking::Client) -> Self {
+        self.http = HttpClient {
+            client: Arc::new(OnceLock::from(client)),
+            custom: true,
+        };
+        self
+    }
+
+    /// Returns the HTTP client of the app.
+    pub(crate) fn http(&self) -> &reqwest::blocking::Client {
+        // as `reqwest::blocking::Client::new`, fails only if the TLS backend can't be initialized
+        self.http.client.get_or_init(|| {
+            self.http_builder()
+                .build()
+                .expect("failed to initialize the HTTP client")
+        })
+    }
+
+    /// Returns a builder of HTTP clients that reach the co-processor.
+    pub(crate) fn http_builder(&self) -> reqwest::blocking::ClientBuilder {
+        let builder = reqwest::blocking::Client::builder();
+
+        #[cfg(unix)]
+        if let Some(path) = self.unix_socket() {
+            return builder.unix_socket(path);
+        }
+
+        builder
+    }
+
+    /// Returns the path of the Unix domain socket of the co-processor, if any.
+    #[cfg(unix)]
+    pub fn unix_socket(&self) -> Option<&Path> {
+        self.socket.strip_prefix(Self::UNIX_SCHEME).map(Path::new)
+    }
+
+    /// Returns the base URL of the co-processor requests.
+    ///
+    /// Requests over a Unix domain socket are routed by the client, so the host is a placeholder.
+    pub fn base_url(&self) -> &str {
+        #[cfg(unix)]
+        if self.unix_socket().is_some() {
+            return "http://localhost";
+        }
+
+        &self.socket
+    }
+
+    pub(crate) fn run_docker(
         &self,
         cmd: &str,
         wsroot: &str,
@@ -71,10 +333,33 @@ impl App {
         pkrelative: &str,
         args: &[&str],
     ) -> anyhow::Result<Output> {
-        let mut command = Command::new("docker");
+        // the container deploys to the socket, and can't reach a path of the host
+        anyhow::ensure!(
+            !self.socket.starts_with(Self::UNIX_SCHEME),
+            "the docker builder can't reach a co-processor over a Unix domain socket"
+        );
+
+        let output = self
+            .docker_command(cmd, wsroot, package, pkrelative, args)
+            .stderr(Stdio::inherit())
+            .output()?;
+
+        Ok(output)
+    }
+
+    fn docker_command(
+        &self,
+        cmd: &str,
+        wsroot: &str,
+        package: &str,
+        pkrelative: &str,
+        args: &[&str],
+    ) -> Command {
+        let mut command = Command::new(&self.container_runtime);
 
         command.args(["run", "--rm", "-i"]);
 
+        // both docker and podman share the host network stack with `--network host`
         if self.docker_host {
             command.args(["--network", "host"]);
         }
@@ -94,50 +379,156 @@ impl App {
             command.arg(a);
         }
 
-        let output = command.stderr(Stdio::inherit()).output()?;
-
-        Ok(output)
+        command
     }
 
     /// Deploys a domain.
     ///
     /// Returns the deployed ID.
-    pub fn deploy_domain<P, N>(&self, path: Option<P>, name: N) -> anyhow::Result<Value>
+    pub fn deploy_domain<P, N>(&self, path: Option<P>, name: N) -> Result<Value, ClientError>
     where
         P: AsRef<Path>,
         N: AsRef<str>,
     {
-        let ProjectStructure {
-            wsroot,
-            pkrelative,
-            package,
-            ..
-        } = TryFrom::try_from(path)?;
+        let project = ProjectStructure::try_from(path)?;
+
+        Ok(self
+            .builder()
+            .deploy_domain(self, &project, name.as_ref())?)
+    }
+
+    /// Deploys a domain and adds its genesis block.
+    ///
+    /// Returns the deployed domain ID with the added genesis block. A domain can't be
+    /// unregistered, so if the genesis block is rejected, the error reports the deployed ID; the
+    /// domain is left without blocks until one is added via [`App::add_domain_block`].
+    pub fn deploy_domain_with_genesis<P, N, G>(
+        &self,
+        path: Option<P>,
+        name: N,
+        genesis: G,
+    ) -> Result<(String, AddedDomainBlock), ClientError>
+    where
+        P: AsRef<Path>,
+        N: AsRef<str>,
+        G: AsRef<str>,
+    {
+        let name = name.as_ref();
+        let genesis: Value = serde_json::from_str(genesis.as_ref())
+            .map_err(|e| anyhow::anyhow!("invalid genesis block: {e}"))?;
+
+        let domain = self
+            .deploy_domain(path, name)?
+            .get("domain")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::MalformedResponse("no domain id received".into()))?
+            .to_string();
+
+        let block = self.add_domain_block(name, &genesis).map_err(|e| {
+            anyhow::anyhow!(
+                "domain `{name}` deployed as `{domain}`, but its genesis block was rejected: {e}"
+            )
+        })?;
+
+        Ok((domain, block))
+    }
+
+    /// Content encoding of the compressed domain block payloads.
+    pub const BLOCK_ENCODING: &str = "zstd";
+
+    /// Adds a block to a domain, validated by the domain controller.
+    ///
+    /// The payload is checked against the limit advertised by the co-processor before it is sent,
+    /// and compressed if enabled via [`App::with_block_compression`] and supported by the
+    /// co-processor.
+    pub fn add_domain_block<D>(
+        &self,
+        domain: D,
+        args: &Value,
+    ) -> Result<AddedDomainBlock, ClientError>
+    where
+        D: AsRef<str>,
+    {
+        let domain = domain.as_ref();
+        let payload = serde_json::to_vec(args)?;
+        let payload_size = payload.len();
+
+        // servers predating the limits don't advertise them
+        let info = self.server_info().unwrap_or_default();
+        let payload_limit = info
+            .get("max_block_payload")
+            .and_then(Value::as_u64)
+            .map(|l| l as usize);
+
+        if let Some(limit) = payload_limit.filter(|l| payload_size > *l) {
+            return Err(ClientError::Other(anyhow::anyhow!(
+                "the block payload of domain `{domain}` has {payload_size} bytes, exceeding the limit of {limit} bytes of the co-processor"
+            )));
+        }
+
+        let supported = info
+            .get("block_encodings")
+            .and_then(Value::as_array)
+            .is_some_and(|e| e.iter().any(|e| e.as_str() == Some(Self::BLOCK_ENCODING)));
+
+        let encoded = match self.compress_blocks && supported {
+            true => Some(zstd::encode_all(payload.as_slice(), 0)?),
+            false => None,
+        };
 
-        let output = self.run_docker("domain", &wsroot, &package, &pkrelative, &[name.as_ref()])?;
+        // only worth sending compressed if smaller
+        let encoded = encoded.filter(|e| e.len() < payload_size);
+        let compressed = encoded.is_some();
 
-        anyhow::ensure!(output.status.success(), "failed to deploy domain");
+        let uri = format!("{}/api/registry/domain/{}", self.base_url(), domain);
+        let request = self
+            .http()
+            .post(uri)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+
+        let request = match encoded {
+            Some(e) => request
+                .header(reqwest::header::CONTENT_ENCODING, Self::BLOCK_ENCODING)
+                .body(e),
+            None => request.body(payload),
+        };
+
+        let response = request.send()?;
 
-        Ok(serde_json::from_slice(&output.stdout)?)
+        if response.status() == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+            return Err(ClientError::Other(anyhow::anyhow!(
+                "the block payload of domain `{domain}` was rejected as too large by the co-processor"
+            )));
+        }
+
+        let block = json_body(response)?;
+
+        Ok(AddedDomainBlock {
+            block,
+            payload_size,
+            payload_limit,
+            compressed,
+        })
     }
 
     /// Deploys a circuit with its controller.
     ///
     /// Returns the deployed ID.
-    pub fn deploy_circuit<P, C>(&self, controller: Option<P>, circuit: C) -> anyhow::Result<Value>
+    pub fn deploy_circuit<P, C>(
+        &self,
+        controller: Option<P>,
+        circuit: C,
+    ) -> Result<Value, ClientError>
     where
         P: AsRef<Path>,
         C: AsRef<str>,
     {
-        let ProjectStructure {
-            metadata,
-            wsroot,
-            pkrelative,
-            package,
-        } = TryFrom::try_from(controller)?;
+        let project = ProjectStructure::try_from(controller)?;
+        let wsroot = &project.wsroot;
 
         let circuit = circuit.as_ref();
-        let circuit_dir = metadata
+        let circuit_dir = project
+            .metadata
             .get("packages")
             .and_then(Value::as_array)
             .ok_or_else(|| anyhow::anyhow!("failed to get packages from metadata"))?
@@ -162,126 +553,844 @@ impl App {
             .to_string()
             .split_off(wsroot.len());
 
-        let output = self.run_docker(
-            "controller",
-            &wsroot,
-            &package,
-            &pkrelative,
-            &[circuit, format!("/mnt{}", circuit_dir.as_str()).as_str()],
-        )?;
+        Ok(self
+            .builder()
+            .deploy_controller(self, &project, circuit, &circuit_dir)?)
+    }
+
+    /// Submits a proof to the co-processor queue.
+    ///
+    /// If a timeout is set, the request carries a deadline so the co-processor abandons the job
+    /// once the client is no longer waiting for it.
+    pub fn prove<C, P, A>(
+        &self,
+        circuit: C,
+        output: P,
+        args: Option<A>,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+        A: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/prove",
+            self.base_url(),
+            circuit.as_ref(),
+        );
 
-        anyhow::ensure!(output.status.success(), "failed to deploy circuit");
+        let response = self.send_prove(uri, output.as_ref(), args)?.text()?;
 
-        Ok(serde_json::from_slice(&output.stdout)?)
+        Ok(serde_json::from_str(&response)?)
     }
 
-    /// Submits a proof to the co-processor queue.
-    pub fn prove<C, P, A>(&self, circuit: C, output: P, args: Option<A>) -> anyhow::Result<Value>
+    /// Submits a proof to the co-processor queue, computed against the provided co-processor root.
+    ///
+    /// If the root is no longer available when the request is processed (e.g. it was pruned), the
+    /// latest root is fetched and the request is retried, at most `retries` times. A `retries` of
+    /// `0` disables the retry.
+    pub fn prove_with_root_retry<C, P, A>(
+        &self,
+        circuit: C,
+        root: Hash,
+        output: P,
+        args: Option<A>,
+        retries: usize,
+    ) -> Result<Value, ClientError>
     where
         C: AsRef<str>,
         P: AsRef<Path>,
         A: AsRef<str>,
+    {
+        let mut root = root;
+        let mut attempt = 0;
+
+        loop {
+            let uri = format!(
+                "{}/api/registry/controller/{}/prove/{}",
+                self.base_url(),
+                circuit.as_ref(),
+                hex::encode(root),
+            );
+
+            let response = self.send_prove(uri, output.as_ref(), args.as_ref())?;
+
+            if response.status() != reqwest::StatusCode::GONE {
+                return json_body(response);
+            }
+
+            if attempt >= retries {
+                return Err(ClientError::NotFound(format!(
+                    "the co-processor root `{}` is no longer available",
+                    hex::encode(root)
+                )));
+            }
+
+            attempt += 1;
+            root = self.root()?;
+        }
+    }
+
+    fn send_prove<A>(
+        &self,
+        uri: String,
+        output: &Path,
+        args: Option<A>,
+    ) -> Result<reqwest::blocking::Response, ClientError>
+    where
+        A: AsRef<str>,
     {
         let args: Value = match args {
             Some(a) => serde_json::from_str(a.as_ref())?,
             None => Value::Null,
         };
-        let uri = format!(
-            "{}/api/registry/controller/{}/prove",
-            self.socket,
-            circuit.as_ref(),
-        );
 
-        let response = reqwest::blocking::Client::new()
-            .post(uri)
+        let mut request = self.http().post(uri);
+        let mut deadline = None;
+
+        if let Some(t) = self.timeout {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(anyhow::Error::from)?
+                .as_secs();
+
+            request = request.timeout(Duration::from_secs(t));
+            deadline.replace(now.saturating_add(t));
+        }
+
+        let response = request
             .json(&json!({
                 "args": args,
                 "payload": {
                     "cmd": "store",
-                    "path": output.as_ref()
-                }
+                    "path": output
+                },
+                "deadline": deadline,
             }))
-            .send()?
-            .text()?;
+            .send()?;
 
-        Ok(serde_json::from_str(&response)?)
+        Ok(response)
+    }
+
+    /// Submits a proof to the co-processor queue, returning a handle to await its result.
+    ///
+    /// The output path should be unique, as a previous proof stored on the same path would be
+    /// returned by the handle.
+    pub fn prove_handle<C, P, A>(
+        &self,
+        circuit: C,
+        output: P,
+        args: Option<A>,
+    ) -> Result<ProofHandle, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+        A: AsRef<str>,
+    {
+        self.prove(circuit.as_ref(), output.as_ref(), args)?;
+
+        Ok(ProofHandle::new(self.clone(), circuit, output))
+    }
+
+    /// Submits a proof to the co-processor queue, storing it on a path derived from the seed.
+    ///
+    /// If no seed is provided, a unique one is generated from the current time. The path can be
+    /// reconstructed later via [`App::proof_path_for`] and the returned handle.
+    pub fn prove_with_seed<C, S, A>(
+        &self,
+        circuit: C,
+        seed: Option<S>,
+        args: Option<A>,
+    ) -> Result<ProofHandle, ClientError>
+    where
+        C: AsRef<str>,
+        S: AsRef<str>,
+        A: AsRef<str>,
+    {
+        let seed = match seed {
+            Some(s) => s.as_ref().to_string(),
+            None => Self::time_seed()?,
+        };
+
+        let path = Self::proof_path_for(&seed)?;
+
+        self.prove_handle(circuit, path, args)
+    }
+
+    /// Submits a proof to the co-processor queue without awaiting it, returning a handle to poll
+    /// its result.
+    ///
+    /// If a root is provided, the proof is computed against it, and the submission fails if the
+    /// root is no longer available. The proof is stored on a unique path, so concurrent
+    /// submissions can be polled independently via [`ProofHandle::poll`].
+    pub fn submit_proof<C, A>(
+        &self,
+        circuit: C,
+        root: Option<Hash>,
+        args: Option<A>,
+    ) -> Result<ProofHandle, ClientError>
+    where
+        C: AsRef<str>,
+        A: AsRef<str>,
+    {
+        let path = Self::proof_path_for(&Self::time_seed()?)?;
+
+        match root {
+            Some(r) => self.prove_with_root_retry(circuit.as_ref(), r, &path, args, 0)?,
+            None => self.prove(circuit.as_ref(), &path, args)?,
+        };
+
+        Ok(ProofHandle::new(self.clone(), circuit, path))
+    }
+
+    fn time_seed() -> anyhow::Result<String> {
+        Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)?
+            .as_nanos()
+            .to_string())
+    }
+
+    /// Deterministically derives the storage path of a proof from the provided seed.
+    ///
+    /// The path encodes the whole digest of the seed, split into 8 character components to fit
+    /// the FAT-16 constraints of the virtual filesystem, against which it is validated.
+    pub fn proof_path_for(seed: &str) -> anyhow::Result<PathBuf> {
+        let digest = Blake3Hasher::digest([Self::PROOFS_DIR.as_bytes(), seed.as_bytes()]);
+        let name = hex::encode(digest);
+        let (dirs, file) = name.split_at(name.len() - 8);
+
+        let mut path = PathBuf::from(Self::PROOFS_DIR);
+
+        for i in (0..dirs.len()).step_by(8) {
+            path.push(&dirs[i..i + 8]);
+        }
+
+        path.push(format!("{file}.bin"));
+
+        Self::ensure_fat_path(&path)?;
+
+        Ok(path)
+    }
+
+    /// Ensures every component of the path fits the FAT-16 8.3 constraints of the virtual
+    /// filesystem.
+    pub fn ensure_fat_path(path: &Path) -> anyhow::Result<()> {
+        for c in path.components().filter_map(|c| c.as_os_str().to_str()) {
+            let (stem, ext) = c.split_once('.').unwrap_or((c, ""));
+
+            anyhow::ensure!(
+                c.is_ascii() && stem.len() <= 8 && ext.len() <= 3 && !ext.contains('.'),
+                "the path component `{c}` doesn't fit the FAT-16 8.3 constraints"
+            );
+        }
+
+        Ok(())
     }
 
-    pub fn storage<C, P>(&self, circuit: C, path: P) -> anyhow::Result<Value>
+    pub fn storage<C, P>(&self, circuit: C, path: P) -> Result<Value, ClientError>
     where
         C: AsRef<str>,
         P: AsRef<Path>,
     {
         let uri = format!(
             "{}/api/registry/controller/{}/storage/fs",
-            self.socket,
+            self.base_url(),
             circuit.as_ref()
         );
 
-        let response = reqwest::blocking::Client::new()
+        let response = self
+            .http()
             .post(uri)
             .json(&json!({
                 "path": path.as_ref()
             }))
             .send()?
+            .check_status()?
             .json::<Value>()?
             .get("data")
-            .ok_or_else(|| anyhow::anyhow!("no data received"))?
+            .ok_or_else(|| ClientError::MalformedResponse("no data received".into()))?
             .as_str()
-            .ok_or_else(|| anyhow::anyhow!("invalid data received"))?
+            .ok_or_else(|| ClientError::MalformedResponse("invalid data received".into()))?
             .to_string();
 
         Ok(json!({"data": response}))
     }
 
-    /// Returns the verifying key of a circuit.
-    pub fn vk<C>(&self, circuit: C) -> anyhow::Result<Value>
+    /// Cancels the queued proof that would be stored on the provided path.
+    ///
+    /// Returns `false` if no proof was waiting for a prover, including when it was already picked
+    /// up or completed. Fails unless the admin token is set, or the request is signed by the
+    /// submitter of the proof.
+    pub fn cancel_proof<C, P>(&self, circuit: C, path: P) -> Result<bool, ClientError>
     where
         C: AsRef<str>,
+        P: AsRef<Path>,
     {
         let uri = format!(
-            "{}/api/registry/controller/{}/vk",
-            self.socket,
+            "{}/api/registry/controller/{}/prove/cancel",
+            self.base_url(),
             circuit.as_ref()
         );
 
-        let response = reqwest::blocking::Client::new()
-            .get(uri)
-            .send()?
+        let mut request = self.http().post(uri);
+
+        if let Some(token) = &self.admin_token {
+            request = request.header(Self::ADMIN_HEADER, token);
+        }
+
+        let response = request
+            .json(&json!({
+                "path": path.as_ref()
+            }))
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            return Err(ClientError::Forbidden(format!(
+                "cancelling the proof `{}` requires the admin token, or the signature of its submitter",
+                path.as_ref().display()
+            )));
+        }
+
+        let cancelled = response
+            .check_status()?
             .json::<Value>()?
-            .get("base64")
-            .ok_or_else(|| anyhow::anyhow!("no data received"))?
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("invalid data received"))?
-            .to_string();
+            .get("cancelled")
+            .and_then(Value::as_bool)
+            .ok_or_else(|| ClientError::MalformedResponse("invalid cancel response".into()))?;
 
-        Ok(json!({"vk": response}))
+        Ok(cancelled)
     }
 
-    /// Returns the proof inputs of a proven circuit.
-    pub fn proof_inputs<C, P>(&self, circuit: C, path: P) -> anyhow::Result<Value>
+    /// Replays the proof stored on the provided path, reporting whether the public inputs of the
+    /// new proof match the stored ones.
+    ///
+    /// The co-processor recomputes the witnesses with the original arguments against the original
+    /// root, and proves them again; the call blocks until the new proof is computed, bounded by
+    /// the optional request timeout. Replaying requires the admin token, unless the request is
+    /// signed by the submitter of the proof.
+    pub fn replay_proof<C, P>(&self, circuit: C, path: P) -> Result<Value, ClientError>
     where
         C: AsRef<str>,
         P: AsRef<Path>,
     {
         let uri = format!(
-            "{}/api/registry/controller/{}/storage/fs",
-            self.socket,
+            "{}/api/registry/controller/{}/replay",
+            self.base_url(),
             circuit.as_ref()
         );
 
-        let response = reqwest::blocking::Client::new()
-            .post(uri)
+        // a replay proves again, so the client default timeout doesn't apply
+        let mut request = self.http_builder().timeout(None).build()?.post(uri);
+
+        if let Some(t) = self.timeout {
+            request = request.timeout(Duration::from_secs(t));
+        }
+
+        if let Some(token) = &self.admin_token {
+            request = request.header(Self::ADMIN_HEADER, token);
+        }
+
+        let response = request
             .json(&json!({
                 "path": path.as_ref()
             }))
-            .send()?
-            .json::<Value>()?
-            .get("data")
-            .ok_or_else(|| anyhow::anyhow!("no data received"))?
-            .as_str()
-            .ok_or_else(|| anyhow::anyhow!("invalid data received"))?
-            .to_string();
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            return Err(ClientError::Forbidden(format!(
+                "replaying the proof `{}` requires the admin token",
+                path.as_ref().display()
+            )));
+        }
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "no provenance recorded for the proof `{}`",
+                path.as_ref().display()
+            )));
+        }
+
+        if response.status() == reqwest::StatusCode::GONE {
+            return Err(ClientError::NotFound(format!(
+                "the root of the proof `{}` is no longer available",
+                path.as_ref().display()
+            )));
+        }
+
+        json_body(response)
+    }
+
+    /// Returns a reader that streams the bytes of a proof file as they are downloaded.
+    ///
+    /// The optional `offset` and `length` select a byte range of the file, so an interrupted
+    /// download can be resumed. As with [`App::get_storage_file_to`], the path must fit the FAT-16
+    /// 8.3 constraints.
+    pub fn get_proof_stream<C, P>(
+        &self,
+        circuit: C,
+        path: P,
+        offset: Option<u64>,
+        length: Option<u64>,
+    ) -> Result<impl Read, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        Self::ensure_fat_path(path.as_ref())?;
+
+        let uri = format!(
+            "{}/api/registry/controller/{}/storage/fs/bytes",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "path": path.as_ref(),
+                "offset": offset,
+                "length": length,
+            }))
+            .send()?
+            .check_status()?;
+
+        Ok(response)
+    }
+
+    /// Returns the ELF size of a circuit and, for the sample arguments, its cycle count.
+    ///
+    /// The cycle count is computed by the co-processor in execute mode, without proving, and
+    /// estimates the proving complexity of the circuit.
+    pub fn circuit_report<C>(
+        &self,
+        circuit: C,
+        sample: Option<&Value>,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/report",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "sample": sample,
+            }))
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "the circuit `{}` was not found",
+                circuit.as_ref()
+            )));
+        }
+
+        json_body(response)
+    }
+
+    /// Returns the verifying key of a circuit.
+    pub fn vk<C>(&self, circuit: C) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/vk",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .get(uri)
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("base64")
+            .ok_or_else(|| ClientError::MalformedResponse("no data received".into()))?
+            .as_str()
+            .ok_or_else(|| ClientError::MalformedResponse("invalid data received".into()))?
+            .to_string();
+
+        Ok(json!({"vk": response}))
+    }
+
+    /// Returns the VK fingerprint of each of the provided circuits.
+    ///
+    /// The requests are issued concurrently, bounded by [`App::VK_HASH_CONCURRENCY`], over a
+    /// shared client. The results are positionally aligned with the circuits, and a failure for
+    /// one circuit doesn't affect the others.
+    pub fn get_vk_hashes(&self, circuits: &[&str]) -> Vec<(String, Result<Hash, ClientError>)> {
+        let client = self.http();
+
+        circuits
+            .chunks(Self::VK_HASH_CONCURRENCY)
+            .flat_map(|chunk| {
+                thread::scope(|s| {
+                    let handles: Vec<_> = chunk
+                        .iter()
+                        .map(|c| s.spawn(|| self.get_vk_hash(client, c)))
+                        .collect();
+
+                    chunk
+                        .iter()
+                        .zip(handles)
+                        .map(|(c, h)| {
+                            let hash = h.join().unwrap_or_else(|_| {
+                                Err(ClientError::Other(anyhow::anyhow!(
+                                    "vk hash request panicked"
+                                )))
+                            });
+
+                            (c.to_string(), hash)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect()
+    }
+
+    fn get_vk_hash(
+        &self,
+        client: &reqwest::blocking::Client,
+        circuit: &str,
+    ) -> Result<Hash, ClientError> {
+        let uri = format!(
+            "{}/api/registry/controller/{}/vk/hash",
+            self.base_url(),
+            circuit
+        );
+
+        let hash = client
+            .get(uri)
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("hash")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::MalformedResponse("no hash received".into()))
+            .and_then(|h| {
+                hex::decode(h).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+            })?;
+
+        Hash::try_from(hash)
+            .map_err(|_| ClientError::MalformedResponse("invalid hash received".into()))
+    }
+
+    /// Returns the latest co-processor root.
+    pub fn root(&self) -> Result<Hash, ClientError> {
+        let uri = format!("{}/api/historical", self.base_url());
+
+        let root = self
+            .http()
+            .get(uri)
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("root")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::MalformedResponse("no root received".into()))
+            .and_then(|r| {
+                hex::decode(r).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+            })?;
+
+        Hash::try_from(root)
+            .map_err(|_| ClientError::MalformedResponse("invalid root received".into()))
+    }
+
+    /// Returns the co-processor root most recently published on the provided chain, along with
+    /// where it was published.
+    ///
+    /// The metadata allows a verifier to confirm the root on-chain independently, before checking
+    /// proofs against it.
+    pub fn get_published_root<C>(&self, chain: C) -> Result<PublishedRoot, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/historical/published/{}",
+            self.base_url(),
+            chain.as_ref()
+        );
+
+        let response = self.http().get(uri).send()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "no root published on `{}`",
+                chain.as_ref()
+            )));
+        }
+
+        PublishedRoot::from_response(response.check_status()?.json()?)
+    }
+
+    /// Records the co-processor root as published on the provided chain by the transaction `tx`.
+    ///
+    /// Requires the admin token.
+    pub fn publish_root<C, T>(
+        &self,
+        chain: C,
+        root: &Hash,
+        tx: T,
+        contract: Option<&str>,
+        block: Option<u64>,
+    ) -> Result<PublishedRoot, ClientError>
+    where
+        C: AsRef<str>,
+        T: AsRef<str>,
+    {
+        let token = self.admin_token.as_deref().ok_or_else(|| {
+            ClientError::Forbidden("publishing a root requires an admin token".into())
+        })?;
+
+        let uri = format!(
+            "{}/api/historical/published/{}",
+            self.base_url(),
+            chain.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .header(Self::ADMIN_HEADER, token)
+            .json(&json!({
+                "root": hex::encode(root),
+                "tx": tx.as_ref(),
+                "contract": contract,
+                "block": block,
+            }))
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            return Err(ClientError::Forbidden(
+                "the admin token was rejected by the service".into(),
+            ));
+        }
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "the root `{}` is unknown to the co-processor",
+                hex::encode(root)
+            )));
+        }
+
+        PublishedRoot::from_response(response.check_status()?.json()?)
+    }
+
+    /// Returns a page of the domains that changed from the `old` co-processor root to the `new`
+    /// one.
+    ///
+    /// The changes are sorted by domain id, starting after the `after` cursor. The service bounds
+    /// the page size; if more changes are available, [`SmtDiff::next`] is the cursor of the next
+    /// page.
+    pub fn diff_roots_page(
+        &self,
+        old: &Hash,
+        new: &Hash,
+        after: Option<&Hash>,
+        limit: Option<usize>,
+    ) -> Result<SmtDiff, ClientError> {
+        let uri = format!(
+            "{}/api/historical/diff/{}/{}",
+            self.base_url(),
+            hex::encode(old),
+            hex::encode(new)
+        );
+
+        let mut query = vec![];
+
+        if let Some(a) = after {
+            query.push(("after", hex::encode(a)));
+        }
+
+        if let Some(l) = limit {
+            query.push(("limit", l.to_string()));
+        }
+
+        let response = self.http().get(uri).query(&query).send()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(
+                "the roots aren't known by the co-processor".into(),
+            ));
+        }
+
+        let response = response.check_status()?.json::<Value>()?;
+        let hash = |h: &Value| -> Result<Hash, ClientError> {
+            let h = h
+                .as_str()
+                .ok_or_else(|| ClientError::MalformedResponse("invalid hash received".into()))?;
+
+            hex::decode(h)
+                .ok()
+                .and_then(|h| Hash::try_from(h).ok())
+                .ok_or_else(|| ClientError::MalformedResponse("invalid hash received".into()))
+        };
+
+        let changes = response
+            .get("changes")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ClientError::MalformedResponse("no changes received".into()))?
+            .iter()
+            .map(|c| {
+                let key = hash(&c["key"])?;
+                let change = serde_json::from_value(c["change"].clone())
+                    .map_err(|e| ClientError::MalformedResponse(e.to_string()))?;
+
+                Ok((key, change))
+            })
+            .collect::<Result<_, ClientError>>()?;
+
+        let next = match response.get("next") {
+            Some(Value::Null) | None => None,
+            Some(n) => Some(hash(n)?),
+        };
+
+        Ok(SmtDiff { changes, next })
+    }
+
+    /// Returns the domains that changed from the `old` co-processor root to the `new` one.
+    ///
+    /// Fetches all the pages of [`App::diff_roots_page`].
+    pub fn diff_roots(
+        &self,
+        old: &Hash,
+        new: &Hash,
+    ) -> Result<Vec<(Hash, SmtChange)>, ClientError> {
+        let mut changes = vec![];
+        let mut after = None;
+
+        loop {
+            let page = self.diff_roots_page(old, new, after.as_ref(), None)?;
+
+            changes.extend(page.changes);
+
+            match page.next {
+                Some(n) => after = Some(n),
+                None => return Ok(changes),
+            }
+        }
+    }
+
+    /// Returns the co-processor root computed when the block was added to the domain.
+    ///
+    /// An opening against this root is contemporaneous with the block. An error is returned if the
+    /// block isn't indexed.
+    pub fn get_smt_root_at_block<D>(&self, domain: D, number: u64) -> Result<Hash, ClientError>
+    where
+        D: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/domain/{}/block/{}/root",
+            self.base_url(),
+            domain.as_ref(),
+            number
+        );
+
+        let response = self.http().get(uri).send()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "block {number} of domain `{}` isn't indexed",
+                domain.as_ref()
+            )));
+        }
+
+        let root = response
+            .check_status()?
+            .json::<Value>()?
+            .get("root")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ClientError::MalformedResponse("no root received".into()))
+            .and_then(|r| {
+                hex::decode(r).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+            })?;
+
+        Hash::try_from(root)
+            .map_err(|_| ClientError::MalformedResponse("invalid root received".into()))
+    }
+
+    /// Returns the last witness set computed for a circuit, without recomputing it.
+    ///
+    /// The service keeps the witnesses for a short time only; an error is returned if they
+    /// expired or were never computed.
+    pub fn get_last_witnesses<C>(&self, circuit: C) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/witnesses/last",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .get(uri)
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        Ok(response)
+    }
+
+    /// Returns the entrypoint arguments schema of a circuit controller.
+    ///
+    /// An error is returned if the controller doesn't implement the `describe` export.
+    pub fn describe_controller<C>(&self, circuit: C) -> Result<ControllerSchema, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/describe",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self.http().get(uri).send()?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(ClientError::NotFound(format!(
+                "the controller of `{}` doesn't implement `describe`",
+                circuit.as_ref()
+            )));
+        }
+
+        Ok(response.check_status()?.json()?)
+    }
+
+    /// Returns the proof inputs of a proven circuit.
+    pub fn proof_inputs<C, P>(&self, circuit: C, path: P) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/storage/fs",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "path": path.as_ref()
+            }))
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("data")
+            .ok_or_else(|| ClientError::MalformedResponse("no data received".into()))?
+            .as_str()
+            .ok_or_else(|| ClientError::MalformedResponse("invalid data received".into()))?
+            .to_string();
 
         let response = Base64::decode(response)?;
         let response: Value = serde_json::from_slice(&response)?;
@@ -289,10 +1398,284 @@ impl App {
         let proof = response
             .get("proof")
             .and_then(Value::as_str)
-            .ok_or_else(|| anyhow::anyhow!("unexpected data format for proof"))?;
-        let inputs = Proof::try_from_base64(proof)?.inputs;
+            .ok_or_else(|| {
+                ClientError::MalformedResponse("unexpected data format for proof".into())
+            })?;
+        let proof = Proof::try_from_base64(proof)?;
+        let commitment = hex::encode(proof.input_commitment()?);
 
-        Ok(json!({"inputs": inputs}))
+        Ok(json!({"inputs": proof.inputs, "commitment": commitment}))
+    }
+
+    /// Calls the entrypoint of a circuit controller.
+    ///
+    /// If `output` is provided, the controller is expected to return named outputs, and only the
+    /// value with the provided name is returned.
+    pub fn entrypoint<C, A, O>(
+        &self,
+        circuit: C,
+        args: Option<A>,
+        output: Option<O>,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        A: AsRef<str>,
+        O: AsRef<str>,
+    {
+        let args: Value = match args {
+            Some(a) => serde_json::from_str(a.as_ref())?,
+            None => Value::Null,
+        };
+        let uri = format!(
+            "{}/api/registry/controller/{}/entrypoint",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let ret = self
+            .http()
+            .post(uri)
+            .json(&args)
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("ret")
+            .cloned()
+            .ok_or_else(|| ClientError::MalformedResponse("no data received".into()))?;
+
+        let output = match output {
+            Some(o) => o,
+            None => return Ok(ret),
+        };
+
+        let output = output.as_ref();
+
+        NamedOutputs::try_from_value(ret)?
+            .get(output)
+            .cloned()
+            .ok_or_else(|| {
+                ClientError::NotFound(format!("the controller returned no output `{output}`"))
+            })
+    }
+
+    /// Returns the proving capacity of the co-processor.
+    ///
+    /// Includes the queue depth, in-flight jobs, maximum concurrency, and estimated wait time.
+    pub fn prover_capacity(&self) -> Result<Value, ClientError> {
+        let uri = format!("{}/api/stats/capacity", self.base_url());
+
+        let response = self
+            .http()
+            .get(uri)
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        Ok(response)
+    }
+
+    /// Verifies a batch of proofs of the same circuit, returning a pass/fail result per proof.
+    ///
+    /// The results are positionally aligned with the provided proofs.
+    pub fn verify_batch<C>(&self, circuit: C, proofs: &[Proof]) -> Result<Vec<bool>, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/verify/batch",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let proofs: Vec<_> = proofs.iter().map(Proof::to_base64).collect();
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({ "proofs": proofs }))
+            .send()?
+            .check_status()?
+            .json::<Value>()?;
+
+        let results: Vec<bool> = serde_json::from_value(
+            response
+                .get("results")
+                .cloned()
+                .ok_or_else(|| ClientError::MalformedResponse("no results received".into()))?,
+        )?;
+
+        if results.len() != proofs.len() {
+            return Err(ClientError::MalformedResponse(
+                "the number of results doesn't match the number of proofs".into(),
+            ));
+        }
+
+        Ok(results)
+    }
+
+    /// Returns the service version, supported proof systems, encodings, and enabled endpoints.
+    pub fn server_info(&self) -> Result<Value, ClientError> {
+        let uri = format!("{}/api/info", self.base_url());
+
+        let response = self
+            .http()
+            .get(uri)
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        Ok(response)
+    }
+
+    /// Exports a deployed circuit into a bundle file.
+    ///
+    /// The nonce is the one used when the circuit was deployed, and is required so the bundle can
+    /// be re-imported under the same ID.
+    pub fn export_bundle<C, P>(&self, circuit: C, nonce: u64, out: P) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let circuit = circuit.as_ref();
+        let bytecode = self.get_base64(&format!("registry/controller/{circuit}/circuit"))?;
+        let controller = self.get_base64(&format!("registry/controller/{circuit}/runtime"))?;
+        let vk = self.get_base64(&format!("registry/controller/{circuit}/vk"))?;
+
+        let id = ControllerData::identifier_from_parts(&Base64::decode(&bytecode)?, nonce);
+
+        if hex::encode(id) != circuit {
+            return Err(ClientError::Other(anyhow::anyhow!(
+                "the provided nonce doesn't match the circuit id"
+            )));
+        }
+
+        let bundle = json!({
+            "version": Self::BUNDLE_VERSION,
+            "circuit": circuit,
+            "nonce": nonce,
+            "tag": self.tag,
+            "bytecode": bytecode,
+            "controller": controller,
+            "vk": vk,
+        });
+
+        fs::write(out.as_ref(), serde_json::to_vec_pretty(&bundle)?)?;
+
+        Ok(json!({"circuit": circuit, "path": out.as_ref()}))
+    }
+
+    /// Deploys a circuit from a bundle file.
+    ///
+    /// Fails if the deployed ID differs from the one recorded on the bundle.
+    pub fn import_bundle<P>(&self, path: P) -> Result<Value, ClientError>
+    where
+        P: AsRef<Path>,
+    {
+        let bundle: Value = serde_json::from_slice(&fs::read(path.as_ref())?)?;
+        let field = |k: &str| {
+            bundle
+                .get(k)
+                .ok_or_else(|| anyhow::anyhow!("the bundle has no `{k}` field"))
+        };
+
+        let version = field("version")?.as_u64();
+
+        if version != Some(Self::BUNDLE_VERSION) {
+            return Err(ClientError::Other(anyhow::anyhow!(
+                "unsupported bundle version `{version:?}`; expected `{}`",
+                Self::BUNDLE_VERSION
+            )));
+        }
+
+        let circuit = field("circuit")?
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("invalid bundle circuit id"))?;
+
+        let uri = format!("{}/api/registry/controller", self.base_url());
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "controller": field("controller")?,
+                "circuit": field("bytecode")?,
+                "nonce": field("nonce")?,
+            }))
+            .send()?
+            .check_status()?
+            .json::<Value>()?;
+
+        let deployed = response
+            .get("controller")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                ClientError::MalformedResponse(format!("invalid deploy response: {response}"))
+            })?;
+
+        if deployed != circuit {
+            return Err(ClientError::Other(anyhow::anyhow!(
+                "the deployed circuit `{deployed}` differs from the bundle `{circuit}`"
+            )));
+        }
+
+        Ok(json!({"circuit": deployed}))
+    }
+
+    /// Returns the base64 contents of a file from the storage, if present.
+    pub(crate) fn storage_file<C, P>(
+        &self,
+        circuit: C,
+        path: P,
+    ) -> Result<Option<String>, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let uri = format!(
+            "{}/api/registry/controller/{}/storage/fs",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "path": path.as_ref()
+            }))
+            .send()?
+            .check_status()?
+            .json::<Value>()?;
+
+        // the service might return the contents either bare or wrapped into a `data` field
+        let data = match response {
+            Value::Object(mut o) => o.remove("data").unwrap_or_default(),
+            d => d,
+        };
+
+        match data {
+            Value::String(d) => Ok(Some(d)),
+            Value::Null => Ok(None),
+            d => Err(ClientError::MalformedResponse(format!(
+                "invalid storage data: {d}"
+            ))),
+        }
+    }
+
+    fn get_base64(&self, path: &str) -> Result<String, ClientError> {
+        let uri = format!("{}/api/{path}", self.base_url());
+
+        let data = self
+            .http()
+            .get(uri)
+            .send()?
+            .check_status()?
+            .json::<Value>()?
+            .get("base64")
+            .ok_or_else(|| ClientError::MalformedResponse("no data received".into()))?
+            .as_str()
+            .ok_or_else(|| ClientError::MalformedResponse("invalid data received".into()))?
+            .to_string();
+
+        Ok(data)
     }
 }
 
@@ -353,3 +1736,413 @@ impl<P: AsRef<Path>> TryFrom<Option<P>> for ProjectStructure {
         })
     }
 }
+
+/// Parses a JSON response body, treating `204 No Content` and empty bodies as `null`.
+///
+/// The error statuses are mapped via [`ClientError::check`].
+pub(crate) fn json_body(response: reqwest::blocking::Response) -> Result<Value, ClientError> {
+    let response = response.check_status()?;
+
+    if response.status() == reqwest::StatusCode::NO_CONTENT {
+        return Ok(Value::Null);
+    }
+
+    let body = response.bytes()?;
+
+    if body.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    serde_json::from_slice(&body).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{self, MockServer};
+
+    use super::*;
+
+    #[test]
+    fn docker_command_uses_the_container_runtime() {
+        let args = |app: &App| {
+            let command = app.docker_command("circuit", "/ws", "pkg", "/pkg", &["name"]);
+            let args: Vec<_> = command
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect();
+
+            (command.get_program().to_string_lossy().into_owned(), args)
+        };
+
+        let (program, docker) = args(&App::default());
+
+        assert_eq!(program, "docker");
+        assert!(!docker.contains(&"--network".to_string()));
+
+        let podman = App::default()
+            .with_container_runtime("podman")
+            .with_docker_host(true);
+        let (program, podman) = args(&podman);
+
+        assert_eq!(program, "podman");
+        assert_eq!(podman[3..5], ["--network", "host"]);
+        assert_eq!(podman[5..], docker[3..]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_sockets_route_only_the_co_processor_requests() {
+        use std::{
+            io::{BufRead as _, BufReader, Write as _},
+            os::unix::net::UnixListener,
+            thread,
+        };
+
+        let path = std::env::temp_dir().join(format!("valence-cli-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+
+            reader.read_line(&mut request).unwrap();
+
+            // drain the headers
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            let body = r#"{"version":"unix"}"#;
+
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+
+            request
+        });
+
+        let app = App::unix(&path);
+
+        assert_eq!(app.unix_socket(), Some(path.as_path()));
+        assert_eq!(app.base_url(), "http://localhost");
+        assert_eq!(app.server_info().unwrap()["version"], "unix");
+        assert!(server.join().unwrap().starts_with("GET /api/info "));
+
+        // the socket isn't forwarded to the builder container
+        let err = app
+            .run_docker("circuit", "/ws", "pkg", "/pkg", &[])
+            .unwrap_err();
+
+        assert!(err.to_string().contains("Unix domain socket"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bundles_redeploy_under_the_same_id() {
+        let bytecode = Base64::encode(b"circuit");
+        let controller = Base64::encode(b"controller");
+        let vk = Base64::encode(b"vk");
+        let id = hex::encode(ControllerData::identifier_from_parts(b"circuit", 7));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+
+        let body = |data: &str| json!({"base64": data}).to_string();
+        let source = MockServer::serve([
+            (200, body(&bytecode)),
+            (200, body(&controller)),
+            (200, body(&vk)),
+            (200, body(&bytecode)),
+            (200, body(&controller)),
+            (200, body(&vk)),
+        ]);
+        let app = source.app();
+
+        assert_eq!(
+            app.export_bundle(&id, 7, &path).unwrap()["circuit"],
+            id.as_str()
+        );
+        assert!(app
+            .export_bundle(&id, 8, dir.path().join("other.json"))
+            .is_err());
+
+        let paths: Vec<_> = source.requests().into_iter().map(|r| r.path).collect();
+
+        assert_eq!(paths[0], format!("/api/registry/controller/{id}/circuit"));
+        assert_eq!(paths[1], format!("/api/registry/controller/{id}/runtime"));
+        assert_eq!(paths[2], format!("/api/registry/controller/{id}/vk"));
+
+        let target = MockServer::serve([
+            (200, json!({"controller": id}).to_string()),
+            (200, json!({"controller": "other"}).to_string()),
+        ]);
+        let app = target.app();
+
+        assert_eq!(app.import_bundle(&path).unwrap()["circuit"], id.as_str());
+        assert!(app.import_bundle(&path).is_err());
+
+        let deploy = &target.requests()[0];
+
+        assert_eq!(deploy.path, "/api/registry/controller");
+        assert_eq!(
+            deploy.json(),
+            json!({"controller": controller, "circuit": bytecode, "nonce": 7})
+        );
+    }
+
+    #[test]
+    fn bundles_reject_unknown_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+
+        fs::write(
+            &path,
+            json!({"version": App::BUNDLE_VERSION + 1}).to_string(),
+        )
+        .unwrap();
+
+        let err = App::default().import_bundle(&path).unwrap_err();
+
+        assert!(
+            err.to_string().contains("unsupported bundle version"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn prover_capacity_reads_the_pool_stats() {
+        let capacity = json!({
+            "queued": 3,
+            "in_flight": 1,
+            "workers": 2,
+            "max_workers": 8,
+            "estimated_wait_ms": 1800,
+        });
+        let server = MockServer::serve([(200, capacity.to_string())]);
+
+        assert_eq!(server.app().prover_capacity().unwrap(), capacity);
+
+        let request = &server.requests()[0];
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/stats/capacity");
+    }
+
+    #[test]
+    fn entrypoints_select_named_outputs() {
+        let ret = json!({"ret": {"outputs": {"rate": "1.05", "messages": [1, 2]}}});
+        let server = MockServer::serve([
+            (200, ret.to_string()),
+            (200, ret.to_string()),
+            (200, ret.to_string()),
+            (200, json!({"ret": 42}).to_string()),
+        ]);
+        let app = server.app();
+
+        let entrypoint = |output: Option<&str>| app.entrypoint("circuit", Some("{}"), output);
+
+        assert_eq!(entrypoint(None).unwrap(), ret["ret"]);
+        assert_eq!(entrypoint(Some("messages")).unwrap(), json!([1, 2]));
+        assert!(matches!(
+            entrypoint(Some("fee")),
+            Err(ClientError::NotFound(_))
+        ));
+        assert!(entrypoint(Some("rate")).is_err());
+
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+
+        assert_eq!(paths[0], "/api/registry/controller/circuit/entrypoint");
+    }
+
+    #[test]
+    fn proof_streams_check_the_path() {
+        let server = MockServer::serve([(200, "fog")]);
+        let app = server.app();
+
+        assert!(app
+            .get_proof_stream("circuit", "/var/share/proofs/long-name.bin", None, None)
+            .is_err());
+
+        let mut proof = String::new();
+
+        app.get_proof_stream("circuit", "/var/share/proofs/p.bin", Some(1), None)
+            .unwrap()
+            .read_to_string(&mut proof)
+            .unwrap();
+
+        assert_eq!(proof, "fog");
+
+        let requests = server.requests();
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].json()["offset"], 1);
+    }
+
+    #[test]
+    fn seeded_proof_paths_are_reproducible() {
+        let path = App::proof_path_for("job-1").unwrap();
+
+        assert_eq!(path, App::proof_path_for("job-1").unwrap());
+        assert_ne!(path, App::proof_path_for("job-2").unwrap());
+        assert!(path.starts_with(App::PROOFS_DIR));
+        assert!(App::ensure_fat_path(&path).is_ok());
+
+        assert!(App::ensure_fat_path(Path::new("/var/share/proofs/long-name.bin")).is_err());
+        assert!(App::ensure_fat_path(Path::new("/var/share/proofs/p.json")).is_err());
+
+        let server = MockServer::serve([(200, "{}"), (200, "{}")]);
+        let app = server.app();
+
+        let handle = app
+            .prove_with_seed("circuit", Some("job-1"), Some("{}"))
+            .unwrap();
+
+        assert_eq!(handle.path(), path);
+
+        let unseeded = app
+            .prove_with_seed("circuit", None::<&str>, Some("{}"))
+            .unwrap();
+
+        assert_ne!(unseeded.path(), path);
+
+        let request = &server.requests()[0];
+
+        assert_eq!(request.path, "/api/registry/controller/circuit/prove");
+        assert_eq!(request.json()["payload"]["path"], path.to_str().unwrap());
+    }
+
+    #[test]
+    fn batches_are_verified_positionally() {
+        let proofs = [Proof::new([1], [2]), Proof::new([3], [4])];
+        let server = MockServer::serve([
+            (200, json!({"results": [true, false]}).to_string()),
+            (200, json!({"results": [true]}).to_string()),
+        ]);
+        let app = server.app();
+
+        assert_eq!(app.verify_batch("circuit", &proofs).unwrap(), [true, false]);
+        assert!(matches!(
+            app.verify_batch("circuit", &proofs),
+            Err(ClientError::MalformedResponse(_))
+        ));
+
+        let request = &server.requests()[0];
+
+        assert_eq!(
+            request.path,
+            "/api/registry/controller/circuit/verify/batch"
+        );
+        assert_eq!(
+            request.json(),
+            json!({"proofs": [proofs[0].to_base64(), proofs[1].to_base64()]})
+        );
+    }
+
+    #[test]
+    fn vk_hashes_are_fetched_per_circuit() {
+        let circuits: Vec<_> = (0..App::VK_HASH_CONCURRENCY + 2)
+            .map(|i| format!("{i:02x}"))
+            .collect();
+        let circuits: Vec<_> = circuits.iter().map(String::as_str).collect();
+
+        // the requests are concurrent, so the responses are routed by circuit
+        let server = MockServer::serve_with(circuits.len(), |request| {
+            let circuit = request.path.split('/').nth(4).unwrap();
+            let i = u8::from_str_radix(circuit, 16).unwrap();
+
+            match i {
+                1 => (404, b"unknown circuit".to_vec()),
+                2 => (200, json!({"hash": "zz"}).to_string().into_bytes()),
+                _ => (
+                    200,
+                    json!({"hash": hex::encode([i % 2; 32])})
+                        .to_string()
+                        .into_bytes(),
+                ),
+            }
+        });
+
+        let hashes = server.app().get_vk_hashes(&circuits);
+
+        assert_eq!(hashes.len(), circuits.len());
+
+        for (i, (circuit, hash)) in hashes.into_iter().enumerate() {
+            assert_eq!(circuit, circuits[i]);
+
+            match i {
+                1 => assert!(matches!(hash, Err(ClientError::NotFound(_)))),
+                2 => assert!(matches!(hash, Err(ClientError::MalformedResponse(_)))),
+                _ => assert_eq!(hash.unwrap(), [i as u8 % 2; 32]),
+            }
+        }
+
+        assert_eq!(server.requests().len(), circuits.len());
+    }
+
+    #[test]
+    fn submitted_proofs_are_polled_on_unique_paths() {
+        let proof = Proof::new([1], [2]);
+        let root = [7; 32];
+        let server = MockServer::serve([
+            (200, "{}".to_string()),
+            (200, json!({"data": null}).to_string()),
+            (200, mock::stored_proof(&proof)),
+            (200, "{}".to_string()),
+        ]);
+        let app = server.app();
+
+        let handle = app.submit_proof("circuit", None, Some("{}")).unwrap();
+
+        assert_eq!(handle.circuit(), "circuit");
+        assert_eq!(handle.poll().unwrap(), None);
+        assert_eq!(handle.poll().unwrap(), Some(proof));
+
+        let other = app.submit_proof("circuit", Some(root), Some("{}")).unwrap();
+
+        assert_ne!(other.path(), handle.path());
+
+        // the path encodes the whole digest, rather than a prefix that could collide
+        let name: String = handle
+            .path()
+            .strip_prefix(App::PROOFS_DIR)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().replace(".bin", ""))
+            .collect();
+
+        assert_eq!(hex::decode(name).unwrap().len(), 32);
+
+        let requests = server.requests();
+
+        assert_eq!(requests[0].path, "/api/registry/controller/circuit/prove");
+        assert_eq!(
+            requests[0].json()["payload"]["path"],
+            handle.path().to_str().unwrap()
+        );
+        assert_eq!(
+            requests[3].path,
+            format!(
+                "/api/registry/controller/circuit/prove/{}",
+                hex::encode(root)
+            )
+        );
+    }
+
+    #[test]
+    fn apps_compare_provided_clients_by_identity() {
+        assert_eq!(App::default(), App::default());
+
+        let client = reqwest::blocking::Client::new();
+        let app = App::default().with_http_client(client.clone());
+
+        assert_ne!(app, App::default());
+        assert_eq!(app, app.clone());
+        assert_ne!(app, App::default().with_http_client(client));
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::{BufRead as _, BufReader};
+
+use serde_json::Value;
+
+use crate::{App, CheckStatus as _, ClientError};
+
+/// A filter of the service log lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Only lines in scope of this circuit.
+    pub circuit: Option<String>,
+
+    /// Only lines in scope of this request id.
+    pub request_id: Option<String>,
+
+    /// Only lines at least as severe as this level (`error`, `warn`, `info`, `debug`, `trace`).
+    pub level: Option<String>,
+}
+
+impl LogFilter {
+    pub fn with_circuit<C: AsRef<str>>(mut self, circuit: C) -> Self {
+        self.circuit = Some(circuit.as_ref().into());
+        self
+    }
+
+    pub fn with_request_id<R: AsRef<str>>(mut self, request_id: R) -> Self {
+        self.request_id = Some(request_id.as_ref().into());
+        self
+    }
+
+    pub fn with_level<L: AsRef<str>>(mut self, level: L) -> Self {
+        self.level = Some(level.as_ref().into());
+        self
+    }
+
+    fn to_query(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("circuit", &self.circuit),
+            ("request_id", &self.request_id),
+            ("level", &self.level),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| v.as_deref().map(|v| (k, v)))
+        .collect()
+    }
+}
+
+impl App {
+    /// Header carrying the admin token of a request.
+    pub const ADMIN_HEADER: &str = "valence-coprocessor-admin";
+
+    /// Tails the service log, returning the matching lines as they are emitted.
+    ///
+    /// Requires the admin token of the service. The stream starts with the matching recent lines,
+    /// and ends when the connection is closed.
+    pub fn tail_service_log(
+        &self,
+        filter: &LogFilter,
+    ) -> Result<impl Iterator<Item = anyhow::Result<Value>>, ClientError> {
+        let token = self
+            .admin_token
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("the service log requires an admin token"))?;
+
+        let uri = format!("{}/api/logs/tail", self.base_url());
+
+        // the stream is long-lived; no request timeout is applied
+        let response = self
+            .http_builder()
+            .timeout(None)
+            .build()?
+            .get(uri)
+            .query(&filter.to_query())
+            .header(Self::ADMIN_HEADER, token)
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            return Err(ClientError::Forbidden(
+                "the admin token was rejected by the service".into(),
+            ));
+        }
+
+        let lines = BufReader::new(response.check_status()?)
+            .lines()
+            .filter(|l| l.as_ref().map(|l| !l.trim().is_empty()).unwrap_or(true))
+            .map(|l| Ok(serde_json::from_str(&l?)?));
+
+        Ok(lines)
+    }
+}
@@ -1,13 +1,55 @@
-use cargo_valence::{App, Cli, CmdDeploy, Commands};
+use std::{fs::OpenOptions, io, process::ExitCode};
+
+use cargo_valence::{
+    AbiEncoder, App, Cli, CmdDeploy, Commands, LocalBuilder, LogFilter, OutputFormat,
+};
 use clap::Parser as _;
+use serde_json::{json, Value};
+use valence_coprocessor::{Hash, SmtChange};
+
+#[cfg(feature = "sp1")]
+use cargo_valence::verify_package;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let format = cli.format;
+
+    match run(cli) {
+        Ok(Some(response)) => {
+            println!("{}", format.render(&response));
+
+            ExitCode::SUCCESS
+        }
 
-fn main() -> anyhow::Result<()> {
+        Ok(None) => ExitCode::SUCCESS,
+
+        Err(e) => {
+            match format {
+                OutputFormat::Json => {
+                    println!("{}", format.render(&json!({"error": format!("{e:#}")})))
+                }
+                OutputFormat::Pretty => eprintln!("Error: {e:?}"),
+            }
+
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command, returning its response.
+///
+/// Streaming commands print their own output, and return `None`.
+fn run(cli: Cli) -> anyhow::Result<Option<Value>> {
     let Cli {
         cmd,
         socket,
         tag,
         docker_host,
-    } = Cli::parse();
+        container_runtime,
+        timeout,
+        admin_token,
+        format: _,
+    } = cli;
 
     eprintln!("cargo-valence is deprecated! Use valence-domain-clients instead.");
     eprintln!("https://github.com/timewave-computer/valence-domain-clients?tab=readme-ov-file#cli");
@@ -15,32 +57,303 @@ fn main() -> anyhow::Result<()> {
     let app = App::default()
         .with_tag(tag)
         .with_socket(socket)
-        .with_docker_host(docker_host);
+        .with_docker_host(docker_host)
+        .with_container_runtime(container_runtime)
+        .with_timeout(timeout)
+        .with_admin_token(admin_token);
 
     let response = match cmd {
         Commands::Deploy(d) => match d {
-            CmdDeploy::Domain { name, controller } => app.deploy_domain(controller, name)?,
+            CmdDeploy::Domain {
+                name,
+                controller,
+                genesis: None,
+                local,
+                valence_src,
+                ..
+            } => app
+                .with_local_build(local)
+                .with_local_sources(valence_src.unwrap_or_else(LocalBuilder::default_sources))
+                .deploy_domain(controller, name)?,
+
+            CmdDeploy::Domain {
+                name,
+                controller,
+                genesis: Some(genesis),
+                compress,
+                local,
+                valence_src,
+            } => {
+                let (domain, genesis) = app
+                    .with_local_build(local)
+                    .with_local_sources(valence_src.unwrap_or_else(LocalBuilder::default_sources))
+                    .with_block_compression(compress)
+                    .deploy_domain_with_genesis(controller, name, genesis)?;
+
+                json!({"domain": domain, "genesis": genesis})
+            }
 
             CmdDeploy::Circuit {
                 controller,
                 circuit,
-            } => app.deploy_circuit(controller, circuit)?,
+                sample,
+                local,
+                valence_src,
+            } => {
+                let sample: Option<Value> =
+                    sample.as_deref().map(serde_json::from_str).transpose()?;
+                let app = app
+                    .with_local_build(local)
+                    .with_local_sources(valence_src.unwrap_or_else(LocalBuilder::default_sources));
+                let mut deployed = app.deploy_circuit(controller, circuit)?;
+                let id = deployed
+                    .get("controller")
+                    .and_then(Value::as_str)
+                    .map(String::from);
+
+                // the circuit is deployed; a failed report is only a warning
+                if let Some(id) = id {
+                    match app.circuit_report(&id, sample.as_ref()) {
+                        Ok(r) => deployed["report"] = r,
+                        Err(e) => eprintln!("failed to report the circuit `{id}`: {e}"),
+                    }
+                }
+
+                deployed
+            }
         },
 
+        Commands::Prove {
+            circuit,
+            json,
+            path: _,
+            seed: Some(seed),
+            ..
+        } => {
+            let handle = app.prove_with_seed(circuit, Some(seed), json)?;
+
+            json!({"status": "received", "path": handle.path()})
+        }
+
         Commands::Prove {
             circuit,
             json,
             path,
+            seed: None,
+            root: None,
+            ..
         } => app.prove(circuit, path, json)?,
 
+        Commands::Prove {
+            circuit,
+            json,
+            path,
+            root: Some(root),
+            root_retries,
+            ..
+        } => {
+            let root = Hash::try_from(hex::decode(root.trim_start_matches("0x"))?)
+                .map_err(|_| anyhow::anyhow!("invalid root"))?;
+
+            app.prove_with_root_retry(circuit, root, path, json, root_retries)?
+        }
+
         Commands::Storage { circuit, path } => app.storage(circuit, path)?,
 
+        Commands::Cancel { circuit, path } => {
+            json!({ "cancelled": app.cancel_proof(circuit, path)? })
+        }
+
+        Commands::Replay { circuit, path } => app.replay_proof(circuit, path)?,
+
+        Commands::Download {
+            circuit,
+            path,
+            offset,
+            output,
+        } => {
+            let mut stream = app.get_proof_stream(circuit, path, offset, None)?;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(offset.is_some())
+                .write(true)
+                .truncate(offset.is_none())
+                .open(&output)?;
+
+            let written = io::copy(&mut stream, &mut file)?;
+
+            json!({"path": output, "written": written})
+        }
+
+        Commands::SmtRoot { domain, number } => {
+            let root = app.get_smt_root_at_block(domain, number)?;
+
+            json!({ "root": hex::encode(root) })
+        }
+
+        Commands::PublishedRoot { chain } => {
+            let published = app.get_published_root(chain)?;
+            let mut response = json!(published);
+
+            response["root"] = hex::encode(published.root).into();
+
+            response
+        }
+
+        Commands::DiffRoots {
+            old,
+            new,
+            after,
+            limit,
+        } => {
+            let old = parse_hash(&old)?;
+            let new = parse_hash(&new)?;
+            let after = after.as_deref().map(parse_hash).transpose()?;
+            let encode = |changes: Vec<(Hash, SmtChange)>| -> Vec<Value> {
+                changes
+                    .into_iter()
+                    .map(|(key, change)| json!({"key": hex::encode(key), "change": change}))
+                    .collect()
+            };
+
+            if after.is_none() && limit.is_none() {
+                json!({ "changes": encode(app.diff_roots(&old, &new)?) })
+            } else {
+                let page = app.diff_roots_page(&old, &new, after.as_ref(), limit)?;
+
+                json!({
+                    "changes": encode(page.changes),
+                    "next": page.next.map(hex::encode),
+                })
+            }
+        }
+
+        Commands::LastWitnesses { circuit } => app.get_last_witnesses(circuit)?,
+
+        Commands::Describe { circuit } => serde_json::to_value(app.describe_controller(circuit)?)?,
+
+        Commands::TailLog {
+            circuit,
+            request_id,
+            level,
+        } => {
+            let filter = LogFilter {
+                circuit,
+                request_id,
+                level,
+            };
+
+            for line in app.tail_service_log(&filter)? {
+                println!("{}", serde_json::to_string(&line?)?);
+            }
+
+            return Ok(None);
+        }
+
         Commands::Vk { circuit } => app.vk(circuit)?,
 
+        Commands::VkHashes { circuits } => {
+            let circuits: Vec<_> = circuits.iter().map(String::as_str).collect();
+            let hashes: serde_json::Map<_, _> = app
+                .get_vk_hashes(&circuits)
+                .into_iter()
+                .map(|(c, h)| {
+                    let h = match h {
+                        Ok(h) => json!({ "hash": hex::encode(h) }),
+                        Err(e) => json!({ "error": e.to_string() }),
+                    };
+
+                    (c, h)
+                })
+                .collect();
+
+            Value::Object(hashes)
+        }
+
         Commands::ProofInputs { circuit, path } => app.proof_inputs(circuit, path)?,
+
+        Commands::Gas {
+            circuit,
+            system,
+            path,
+        } => app.estimate_verification_gas(circuit, path, system)?,
+
+        Commands::Relay {
+            circuit,
+            relayer,
+            selector,
+            path,
+        } => {
+            let mut encoder = AbiEncoder::default();
+
+            if let Some(s) = selector {
+                let s = hex::decode(s.trim_start_matches("0x"))?;
+                let s = <[u8; 4]>::try_from(s)
+                    .map_err(|_| anyhow::anyhow!("the selector must have 4 bytes"))?;
+
+                encoder = encoder.with_selector(s);
+            }
+
+            app.relay_proof(circuit, path, relayer, &encoder)?
+        }
+
+        Commands::Entrypoint {
+            circuit,
+            json,
+            output,
+        } => app.entrypoint(circuit, json, output)?,
+
+        Commands::Capacity => app.prover_capacity()?,
+
+        Commands::Info => app.server_info()?,
+
+        Commands::Completions { shell } => {
+            Cli::completions(shell, &mut io::stdout())?;
+
+            return Ok(None);
+        }
+
+        Commands::Export {
+            circuit,
+            nonce,
+            path,
+        } => app.export_bundle(circuit, nonce, path)?,
+
+        Commands::Import { path } => app.import_bundle(path)?,
+
+        Commands::ExportPackage { circuit, path, out } => {
+            app.export_verification_package(circuit, path, out)?
+        }
+
+        #[cfg(feature = "sp1")]
+        Commands::Verify {
+            circuit,
+            path,
+            mock,
+        } => {
+            let valid = app.verify_proof(&circuit, &path, mock)?;
+
+            json!({"circuit": circuit, "path": path, "valid": valid})
+        }
+
+        #[cfg(feature = "sp1")]
+        Commands::VerifyPackage { path, vk_hash } => {
+            let package = verify_package(path, vk_hash.as_deref())?;
+
+            json!({
+                "circuit": package.circuit,
+                "commitment": package.commitment,
+                "pinned": vk_hash.is_some(),
+                "valid": true,
+                "vk_hash": package.vk_hash,
+            })
+        }
     };
 
-    println!("{}", serde_json::to_string(&response)?);
+    Ok(Some(response))
+}
 
-    Ok(())
+fn parse_hash(hash: &str) -> anyhow::Result<Hash> {
+    Hash::try_from(hex::decode(hash.trim_start_matches("0x"))?)
+        .map_err(|_| anyhow::anyhow!("invalid hash `{hash}`"))
 }
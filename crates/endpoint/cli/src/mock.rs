@@ -0,0 +1,153 @@
+use std::{
+    io::{BufRead as _, BufReader, Read as _, Write as _},
+    net::TcpListener,
+    thread::{self, JoinHandle},
+};
+
+use serde_json::{json, Value};
+use valence_coprocessor::{Base64, Proof};
+
+use crate::App;
+
+/// Returns the storage response of a file with the provided JSON contents.
+pub fn storage_body(contents: &Value) -> String {
+    json!({"data": Base64::encode(contents.to_string())}).to_string()
+}
+
+/// Returns the storage response of a successfully stored proof.
+pub fn stored_proof(proof: &Proof) -> String {
+    storage_body(&json!({"success": true, "proof": proof.to_base64()}))
+}
+
+/// A request received by the [`MockServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path of the request, with its query.
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    /// Returns the value of a header, matched case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the body, parsed as JSON.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+/// A co-processor serving canned responses, one per connection.
+pub struct MockServer {
+    url: String,
+    handle: JoinHandle<Vec<MockRequest>>,
+}
+
+impl MockServer {
+    /// Serves the responses, as `(status, body)`, to the next connections.
+    pub fn serve<I, B>(responses: I) -> Self
+    where
+        I: IntoIterator<Item = (u16, B)>,
+        B: Into<Vec<u8>>,
+    {
+        let responses: Vec<(u16, Vec<u8>)> =
+            responses.into_iter().map(|(s, b)| (s, b.into())).collect();
+        let count = responses.len();
+        let mut responses = responses.into_iter();
+
+        Self::serve_with(count, move |_| responses.next().unwrap())
+    }
+
+    /// Serves the next `count` connections, responding to each request with the handler.
+    ///
+    /// Useful when the requests are concurrent, so their order isn't known.
+    pub fn serve_with<F>(count: usize, mut handler: F) -> Self
+    where
+        F: FnMut(&MockRequest) -> (u16, Vec<u8>) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::with_capacity(count);
+
+            for _ in 0..count {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+
+                reader.read_line(&mut line).unwrap();
+
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+                let mut headers = Vec::new();
+
+                loop {
+                    let mut line = String::new();
+
+                    reader.read_line(&mut line).unwrap();
+
+                    match line.trim_end().split_once(':') {
+                        Some((k, v)) => headers.push((k.trim().to_string(), v.trim().to_string())),
+                        None => break,
+                    }
+                }
+
+                let len = headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, v)| v.parse().ok())
+                    .unwrap_or(0);
+                let mut request_body = vec![0; len];
+
+                reader.read_exact(&mut request_body).unwrap();
+
+                let request = MockRequest {
+                    method,
+                    path,
+                    headers,
+                    body: request_body,
+                };
+                let (status, body) = handler(&request);
+
+                let mut stream = reader.into_inner();
+                let head = format!(
+                    "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                    body.len()
+                );
+
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+
+                requests.push(request);
+            }
+
+            requests
+        });
+
+        Self { url, handle }
+    }
+
+    /// Returns the url of the server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns an app connected to the server.
+    pub fn app(&self) -> App {
+        App::default().with_socket(&self.url)
+    }
+
+    /// Waits for all the responses to be served, returning the received requests.
+    pub fn requests(self) -> Vec<MockRequest> {
+        self.handle.join().unwrap()
+    }
+}
@@ -0,0 +1,493 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use valence_coprocessor::{Base64, Blake3Hasher, Hash, Hasher as _, Proof};
+
+#[cfg(feature = "sp1")]
+use valence_coprocessor_sp1::{verify_groth16_batch, verify_mock};
+
+use crate::{App, ClientError, ProofHandle};
+
+/// A self-contained package to verify a proof offline.
+///
+/// The package is a JSON object with the following fields:
+///
+/// - `version`: the version of the format; currently `2`.
+/// - `circuit`: the hex encoded ID of the proven circuit.
+/// - `proof`: the base64 encoded SP1 Groth16 proof bytes.
+/// - `inputs`: the base64 encoded public inputs of the proof.
+/// - `commitment`: the hex encoded commitment to the public inputs, computed as
+///   `blake3(0x00 || "proof-inputs" || inputs)`.
+/// - `vk`: the base64 encoded bincode of the SP1 verifying key of the circuit.
+/// - `vk_hash`: the hex encoded hash of the verifying key bytes, computed as
+///   `blake3(0x00 || vk)`.
+///
+/// A package is valid if the commitment matches the inputs, the hash matches the verifying key,
+/// and the proof verifies against the verifying key and the inputs.
+///
+/// The verifying key isn't bound to the circuit ID, so a package is only self-consistent: a proof
+/// of any program verifies against its own key. To verify a proof of the circuit, pin the
+/// verifying key hash to the one of the deployed circuit, as returned on export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationPackage {
+    /// Version of the format.
+    pub version: u64,
+
+    /// Hex encoded ID of the proven circuit.
+    pub circuit: String,
+
+    /// Base64 encoded proof bytes.
+    pub proof: String,
+
+    /// Base64 encoded public inputs of the proof.
+    pub inputs: String,
+
+    /// Hex encoded commitment to the public inputs.
+    pub commitment: String,
+
+    /// Base64 encoded verifying key of the circuit.
+    pub vk: String,
+
+    /// Hex encoded hash of the verifying key bytes.
+    pub vk_hash: String,
+}
+
+impl VerificationPackage {
+    /// Current version of the format.
+    pub const VERSION: u64 = 2;
+
+    /// Creates a package for a proof of the circuit.
+    pub fn new<C, V>(circuit: C, proof: &Proof, vk: V) -> anyhow::Result<Self>
+    where
+        C: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let vk_hash = Self::vk_hash(&Base64::decode(vk.as_ref())?);
+
+        Ok(Self {
+            version: Self::VERSION,
+            circuit: circuit.as_ref().into(),
+            proof: proof.proof.clone(),
+            inputs: proof.inputs.clone(),
+            commitment: hex::encode(proof.input_commitment()?),
+            vk: vk.as_ref().into(),
+            vk_hash,
+        })
+    }
+
+    /// Computes the hex encoded hash of the verifying key bytes.
+    pub fn vk_hash(vk: &[u8]) -> String {
+        hex::encode(Blake3Hasher::hash(vk))
+    }
+
+    /// Reads a package file.
+    pub fn read<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let package: Self = serde_json::from_slice(&fs::read(path.as_ref())?)?;
+
+        anyhow::ensure!(
+            package.version == Self::VERSION,
+            "unsupported package version `{}`; expected `{}`",
+            package.version,
+            Self::VERSION
+        );
+
+        Ok(package)
+    }
+
+    /// Writes the package to a file.
+    pub fn write<P>(&self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        fs::write(path.as_ref(), serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Returns the packaged proof.
+    pub fn to_proof(&self) -> Proof {
+        Proof {
+            proof: self.proof.clone(),
+            inputs: self.inputs.clone(),
+        }
+    }
+
+    /// Verifies the package with the provided proof verifier.
+    ///
+    /// The verifier takes the decoded verifying key and the proof, and returns whether the proof
+    /// is valid. If `vk_hash` is provided, the verifying key of the package must match it.
+    pub fn verify_with<F>(&self, vk_hash: Option<&str>, verifier: F) -> anyhow::Result<()>
+    where
+        F: FnOnce(&[u8], &Proof) -> anyhow::Result<bool>,
+    {
+        let proof = self.to_proof();
+        let commitment = hex::encode(proof.input_commitment()?);
+
+        anyhow::ensure!(
+            commitment == self.commitment,
+            "the commitment `{}` doesn't match the public inputs",
+            self.commitment
+        );
+
+        let vk = Base64::decode(&self.vk)?;
+        let hash = Self::vk_hash(&vk);
+
+        anyhow::ensure!(
+            hash == self.vk_hash,
+            "the verifying key hash `{}` doesn't match the verifying key",
+            self.vk_hash
+        );
+
+        if let Some(pinned) = vk_hash {
+            anyhow::ensure!(
+                hash.eq_ignore_ascii_case(pinned.trim_start_matches("0x")),
+                "the verifying key hash `{hash}` doesn't match the pinned hash `{pinned}`"
+            );
+        }
+
+        anyhow::ensure!(verifier(&vk, &proof)?, "invalid proof");
+
+        Ok(())
+    }
+
+    /// Verifies the package as a SP1 Groth16 proof.
+    ///
+    /// If `vk_hash` is provided, the verifying key of the package must match it.
+    #[cfg(feature = "sp1")]
+    pub fn verify(&self, vk_hash: Option<&str>) -> anyhow::Result<()> {
+        self.verify_with(vk_hash, |vk, proof| {
+            Ok(verify_groth16_batch(vk, core::slice::from_ref(proof))? == [true])
+        })
+    }
+}
+
+/// The public inputs of an application circuit.
+///
+/// Application circuits commit to the co-processor root as the first 32 bytes of their public
+/// inputs, followed by the application outputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApplicationInputs {
+    /// Co-processor root the proof was computed against.
+    pub root: Hash,
+
+    /// Application outputs of the circuit.
+    pub outputs: Vec<u8>,
+}
+
+impl ApplicationInputs {
+    /// Decodes the public inputs of an application circuit.
+    pub fn decode(inputs: &[u8]) -> anyhow::Result<Self> {
+        let root = inputs.get(..32).ok_or_else(|| {
+            anyhow::anyhow!("the public inputs don't commit to a co-processor root")
+        })?;
+
+        Ok(Self {
+            root: Hash::try_from(root)?,
+            outputs: inputs[32..].to_vec(),
+        })
+    }
+}
+
+/// Verifies an application proof with the provided proof verifier, returning its application
+/// outputs.
+///
+/// The verifier takes the verifying key and the proof, and returns whether the proof is valid. The
+/// root committed by the proof must match `expected_root`.
+pub fn verify_application_proof_with<F>(
+    vk: &[u8],
+    proof: &Proof,
+    expected_root: &Hash,
+    verifier: F,
+) -> anyhow::Result<Vec<u8>>
+where
+    F: FnOnce(&[u8], &Proof) -> anyhow::Result<bool>,
+{
+    let (_, inputs) = proof.decode()?;
+    let ApplicationInputs { root, outputs } = ApplicationInputs::decode(&inputs)?;
+
+    anyhow::ensure!(
+        &root == expected_root,
+        "the proof root `{}` doesn't match the expected root `{}`",
+        hex::encode(root),
+        hex::encode(expected_root)
+    );
+
+    anyhow::ensure!(verifier(vk, proof)?, "invalid proof");
+
+    Ok(outputs)
+}
+
+/// Verifies a package file, without network access.
+///
+/// If `vk_hash` is provided, the verifying key of the package must match it. Returns the verified
+/// package.
+#[cfg(feature = "sp1")]
+pub fn verify_package<P>(path: P, vk_hash: Option<&str>) -> anyhow::Result<VerificationPackage>
+where
+    P: AsRef<Path>,
+{
+    let package = VerificationPackage::read(path)?;
+
+    package.verify(vk_hash)?;
+
+    Ok(package)
+}
+
+impl App {
+    /// Verifies the proof stored on the provided path against the verifying key of the circuit.
+    ///
+    /// The proof is verified locally as a SP1 Groth16 proof, or as a proof of the mock prover if
+    /// `mock` is set. Returns `false` if the proof is invalid.
+    #[cfg(feature = "sp1")]
+    pub fn verify_proof<C, P>(&self, circuit: C, path: P, mock: bool) -> Result<bool, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let circuit = circuit.as_ref();
+        let proof = ProofHandle::new(self.clone(), circuit, path.as_ref())
+            .poll()?
+            .ok_or_else(|| anyhow::anyhow!("no proof stored at `{}`", path.as_ref().display()))?;
+
+        let vk = self.vk(circuit)?;
+        let vk = vk.get("vk").and_then(Value::as_str).ok_or_else(|| {
+            ClientError::MalformedResponse("invalid verifying key received".into())
+        })?;
+        let vk = Base64::decode(vk)?;
+
+        let valid = match mock {
+            true => verify_mock(&vk, &proof)?,
+            false => verify_groth16_batch(&vk, core::slice::from_ref(&proof))? == [true],
+        };
+
+        Ok(valid)
+    }
+
+    /// Verifies an application proof end-to-end, returning its application outputs.
+    ///
+    /// The proof is verified locally as a SP1 Groth16 proof against the verifying key of the
+    /// circuit, and its committed root must match `expected_root`, typically the latest root
+    /// published on-chain.
+    #[cfg(feature = "sp1")]
+    pub fn verify_application_proof<C>(
+        &self,
+        circuit: C,
+        proof: &Proof,
+        expected_root: &Hash,
+    ) -> Result<Vec<u8>, ClientError>
+    where
+        C: AsRef<str>,
+    {
+        let vk = self.vk(circuit)?;
+        let vk = vk.get("vk").and_then(Value::as_str).ok_or_else(|| {
+            ClientError::MalformedResponse("invalid verifying key received".into())
+        })?;
+        let vk = Base64::decode(vk)?;
+
+        let outputs = verify_application_proof_with(&vk, proof, expected_root, |vk, proof| {
+            Ok(verify_groth16_batch(vk, core::slice::from_ref(proof))? == [true])
+        })?;
+
+        Ok(outputs)
+    }
+
+    /// Exports the proof stored on the provided path into a verification package file.
+    ///
+    /// The package bundles the proof, the verifying key, the public inputs and their commitment,
+    /// so it can be verified offline with [`VerificationPackage::verify_with`]. The returned
+    /// verifying key hash is the one to pin on verification.
+    pub fn export_verification_package<C, P, O>(
+        &self,
+        circuit: C,
+        path: P,
+        out: O,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+        O: AsRef<Path>,
+    {
+        let circuit = circuit.as_ref();
+        let proof = ProofHandle::new(self.clone(), circuit, path.as_ref())
+            .poll()?
+            .ok_or_else(|| anyhow::anyhow!("no proof stored at `{}`", path.as_ref().display()))?;
+
+        let vk = self.vk(circuit)?;
+        let vk = vk.get("vk").and_then(Value::as_str).ok_or_else(|| {
+            ClientError::MalformedResponse("invalid verifying key received".into())
+        })?;
+
+        let package = VerificationPackage::new(circuit, &proof, vk)?;
+
+        package.write(out.as_ref())?;
+
+        Ok(json!({
+            "circuit": circuit,
+            "commitment": package.commitment,
+            "path": out.as_ref(),
+            "vk_hash": package.vk_hash,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_exported_package_verifies() {
+        let out = tempfile::NamedTempFile::new().unwrap();
+        let proof = Proof::new(b"proof", b"inputs");
+        let vk = Base64::encode(b"vk");
+        let vk_hash = VerificationPackage::vk_hash(b"vk");
+
+        VerificationPackage::new("c0ffee", &proof, &vk)
+            .unwrap()
+            .write(out.path())
+            .unwrap();
+
+        let package = VerificationPackage::read(out.path()).unwrap();
+
+        assert_eq!(package.vk_hash, vk_hash);
+
+        package
+            .verify_with(Some(&vk_hash), |k, p| Ok(k == b"vk" && p == &proof))
+            .unwrap();
+
+        let mut tampered = package.clone();
+
+        tampered.inputs = Base64::encode(b"other inputs");
+
+        assert!(tampered.verify_with(None, |_, _| Ok(true)).is_err());
+        assert!(package.verify_with(None, |_, _| Ok(false)).is_err());
+
+        // a self-consistent package with another verifying key
+        let other =
+            VerificationPackage::new("c0ffee", &proof, Base64::encode(b"other vk")).unwrap();
+
+        assert!(other.verify_with(None, |_, _| Ok(true)).is_ok());
+        assert!(other.verify_with(Some(&vk_hash), |_, _| Ok(true)).is_err());
+
+        let mut tampered = package.clone();
+
+        tampered.vk = other.vk;
+
+        assert!(tampered.verify_with(None, |_, _| Ok(true)).is_err());
+    }
+
+    /// Returns the verifying key and the proof of the sample Groth16 proof of the SP1 runtime.
+    #[cfg(feature = "sp1")]
+    fn groth16_sample() -> (Vec<u8>, sp1_sdk::SP1ProofWithPublicValues) {
+        let sample = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("..")
+            .join("runtime")
+            .join("sp1")
+            .join("assets")
+            .join("sample-groth16-proof");
+
+        let vk = fs::read(sample.join("hello.vk")).unwrap();
+        let sp1 = sp1_sdk::SP1ProofWithPublicValues::load(sample.join("hello.proof")).unwrap();
+
+        (vk, sp1)
+    }
+
+    #[test]
+    #[cfg(feature = "sp1")]
+    fn a_groth16_package_verifies() {
+        let (vk, sp1) = groth16_sample();
+        let vk_hash = VerificationPackage::vk_hash(&vk);
+        let proof = Proof::new(sp1.bytes(), sp1.public_values.to_vec());
+
+        let out = tempfile::NamedTempFile::new().unwrap();
+
+        VerificationPackage::new("c0ffee", &proof, Base64::encode(&vk))
+            .unwrap()
+            .write(out.path())
+            .unwrap();
+
+        let package = verify_package(out.path(), Some(&vk_hash)).unwrap();
+
+        assert_eq!(package.to_proof(), proof);
+
+        // other public inputs, with a consistent commitment
+        let forged = Proof::new(sp1.bytes(), b"other inputs");
+        let forged = VerificationPackage::new("c0ffee", &forged, Base64::encode(&vk)).unwrap();
+
+        assert!(forged.verify(Some(&vk_hash)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "sp1")]
+    fn stored_proofs_verify_against_the_circuit_vk() {
+        use crate::mock::{self, MockServer};
+
+        let (vk, sp1) = groth16_sample();
+        let proof = Proof::new(sp1.bytes(), sp1.public_values.to_vec());
+        let forged = Proof::new(sp1.bytes(), b"other inputs");
+        let vk = json!({"base64": Base64::encode(&vk)}).to_string();
+
+        let server = MockServer::serve([
+            (200, mock::stored_proof(&proof)),
+            (200, vk.clone()),
+            (200, mock::stored_proof(&forged)),
+            (200, vk),
+            (200, json!({"data": null}).to_string()),
+        ]);
+        let app = server.app();
+        let path = "/var/share/proofs/p.bin";
+
+        assert!(app.verify_proof("circuit", path, false).unwrap());
+        assert!(!app.verify_proof("circuit", path, false).unwrap());
+        assert!(app.verify_proof("circuit", path, false).is_err());
+
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+
+        assert_eq!(paths[0], "/api/registry/controller/circuit/storage/fs");
+        assert_eq!(paths[1], "/api/registry/controller/circuit/vk");
+    }
+
+    #[test]
+    fn application_proofs_verify_against_the_expected_root() {
+        let root = [7; 32];
+        let inputs = [&root[..], b"outputs"].concat();
+        let proof = Proof::new(b"proof", &inputs);
+
+        assert_eq!(
+            ApplicationInputs::decode(&inputs).unwrap(),
+            ApplicationInputs {
+                root,
+                outputs: b"outputs".to_vec(),
+            }
+        );
+
+        let outputs = verify_application_proof_with(b"vk", &proof, &root, |k, p| {
+            Ok(k == b"vk" && p == &proof)
+        })
+        .unwrap();
+
+        assert_eq!(outputs, b"outputs");
+    }
+
+    #[test]
+    fn application_proofs_are_rejected() {
+        let root = [7; 32];
+        let proof = Proof::new(b"proof", [&root[..], b"outputs"].concat());
+
+        // a proof of another root
+        assert!(verify_application_proof_with(b"vk", &proof, &[8; 32], |_, _| Ok(true)).is_err());
+
+        // an invalid proof
+        assert!(verify_application_proof_with(b"vk", &proof, &root, |_, _| Ok(false)).is_err());
+
+        // public inputs too short to commit to a root
+        let proof = Proof::new(b"proof", &root[..31]);
+
+        assert!(ApplicationInputs::decode(&root[..31]).is_err());
+        assert!(verify_application_proof_with(b"vk", &proof, &root, |_, _| Ok(true)).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use serde_json::{json, Value};
+use valence_coprocessor::{Base64, Proof};
+
+use crate::{json_body, App, ClientError};
+
+/// Encodes a proof into the calldata submitted on-chain.
+pub trait CalldataEncoder {
+    /// Encodes the proof bytes and its public inputs.
+    fn encode(&self, proof: &[u8], inputs: &[u8]) -> Vec<u8>;
+}
+
+/// ABI encoder of `(bytes publicValues, bytes proofBytes)`, optionally prefixed by a selector.
+///
+/// This matches the argument layout of the SP1 EVM verifiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AbiEncoder {
+    /// Function selector prepended to the arguments.
+    pub selector: Option<[u8; 4]>,
+}
+
+impl AbiEncoder {
+    /// Prepends the provided function selector to the encoded arguments.
+    pub fn with_selector(mut self, selector: [u8; 4]) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    fn padded_len(len: usize) -> usize {
+        len.div_ceil(32) * 32
+    }
+
+    fn word(value: usize) -> [u8; 32] {
+        let mut word = [0u8; 32];
+
+        word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+        word
+    }
+}
+
+impl CalldataEncoder for AbiEncoder {
+    fn encode(&self, proof: &[u8], inputs: &[u8]) -> Vec<u8> {
+        let inputs_offset = 2 * 32;
+        let proof_offset = inputs_offset + 32 + Self::padded_len(inputs.len());
+
+        let mut calldata = Vec::new();
+
+        if let Some(s) = self.selector {
+            calldata.extend(s);
+        }
+
+        calldata.extend(Self::word(inputs_offset));
+        calldata.extend(Self::word(proof_offset));
+
+        for bytes in [inputs, proof] {
+            calldata.extend(Self::word(bytes.len()));
+            calldata.extend(bytes);
+            calldata.resize(
+                calldata.len() + Self::padded_len(bytes.len()) - bytes.len(),
+                0,
+            );
+        }
+
+        calldata
+    }
+}
+
+impl App {
+    /// Loads the proof stored on the provided path of the circuit virtual filesystem.
+    pub fn load_proof<C, P>(&self, circuit: C, path: P) -> Result<Proof, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let data = self
+            .storage_file(circuit, path)?
+            .ok_or_else(|| ClientError::NotFound("no proof found on the provided path".into()))?;
+
+        let data: Value = serde_json::from_slice(&Base64::decode(data)?)?;
+        let proof = data.get("proof").and_then(Value::as_str).ok_or_else(|| {
+            ClientError::MalformedResponse("unexpected data format for proof".into())
+        })?;
+
+        Proof::try_from_base64(proof).map_err(|e| ClientError::MalformedResponse(e.to_string()))
+    }
+
+    /// Submits the proof stored on `path` to a relayer, returning the relayer response.
+    ///
+    /// A `204 No Content` response is a success, and returns `null`.
+    ///
+    /// The proof is encoded as calldata with the provided encoder, and posted as
+    /// `{"circuit", "calldata"}` with the calldata hex encoded.
+    pub fn relay_proof<C, P, R, E>(
+        &self,
+        circuit: C,
+        path: P,
+        relayer: R,
+        encoder: &E,
+    ) -> Result<Value, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+        R: AsRef<str>,
+        E: CalldataEncoder,
+    {
+        let circuit = circuit.as_ref();
+        let (proof, inputs) = self.load_proof(circuit, path)?.decode()?;
+        let calldata = encoder.encode(&proof, &inputs);
+
+        // the relayer isn't the co-processor, so its client doesn't route over the app socket
+        let response = reqwest::blocking::Client::new()
+            .post(relayer.as_ref())
+            .json(&json!({
+                "circuit": circuit,
+                "calldata": format!("0x{}", hex::encode(calldata)),
+            }))
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock::{self, MockServer};
+
+    use super::*;
+
+    #[test]
+    fn abi_encoder_pads_the_dynamic_arguments() {
+        let calldata = AbiEncoder::default()
+            .with_selector([0xde, 0xad, 0xbe, 0xef])
+            .encode(&[1; 3], &[2; 40]);
+
+        let mut expected = vec![0xde, 0xad, 0xbe, 0xef];
+
+        for w in [64, 160, 40] {
+            expected.extend(AbiEncoder::word(w));
+        }
+
+        expected.extend([2; 40]);
+        expected.extend([0; 24]);
+        expected.extend(AbiEncoder::word(3));
+        expected.extend([1; 3]);
+        expected.extend([0; 29]);
+
+        assert_eq!(calldata, expected);
+        assert_eq!(
+            AbiEncoder::default().encode(&[1; 3], &[2; 40]),
+            expected[4..]
+        );
+    }
+
+    #[test]
+    fn proofs_are_relayed_as_calldata() {
+        let proof = Proof::new([1; 3], [2; 40]);
+        let coprocessor = MockServer::serve([
+            (200, mock::stored_proof(&proof)),
+            (200, mock::stored_proof(&proof)),
+            (200, json!({"data": null}).to_string()),
+        ]);
+        let relayer = MockServer::serve([(200, r#"{"tx":"0xabc"}"#), (204, "")]);
+        let app = coprocessor.app();
+        let encoder = AbiEncoder::default();
+
+        let response = app
+            .relay_proof(
+                "circuit",
+                "/var/share/proofs/p.bin",
+                relayer.url(),
+                &encoder,
+            )
+            .unwrap();
+
+        assert_eq!(response["tx"], "0xabc");
+
+        let response = app
+            .relay_proof(
+                "circuit",
+                "/var/share/proofs/p.bin",
+                relayer.url(),
+                &encoder,
+            )
+            .unwrap();
+
+        assert_eq!(response, Value::Null);
+        assert!(matches!(
+            app.relay_proof(
+                "circuit",
+                "/var/share/proofs/p.bin",
+                relayer.url(),
+                &encoder
+            ),
+            Err(ClientError::NotFound(_))
+        ));
+
+        let request = &relayer.requests()[0];
+        let calldata = encoder.encode(&[1; 3], &[2; 40]);
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(
+            request.json(),
+            json!({"circuit": "circuit", "calldata": format!("0x{}", hex::encode(calldata))})
+        );
+        assert_eq!(coprocessor.requests().len(), 3);
+    }
+}
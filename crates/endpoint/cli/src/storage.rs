@@ -0,0 +1,191 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use serde_json::{json, Value};
+use valence_coprocessor::Base64;
+
+use crate::{App, CheckStatus as _, ClientError};
+
+/// Number of base64 characters decoded at once; a multiple of a base64 quantum.
+const CHUNK_LEN: usize = 64 * 1024;
+
+impl App {
+    /// Writes a file from the storage into `out` as it is downloaded, returning its length.
+    ///
+    /// The base64 contents are decoded in chunks, so the file is never held in memory. Returns
+    /// `None` if the file doesn't exist.
+    pub fn get_storage_file_to<C, P, W>(
+        &self,
+        circuit: C,
+        path: P,
+        out: &mut W,
+    ) -> Result<Option<u64>, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+        W: Write,
+    {
+        Self::ensure_fat_path(path.as_ref())?;
+
+        let uri = format!(
+            "{}/api/registry/controller/{}/storage/fs",
+            self.base_url(),
+            circuit.as_ref()
+        );
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "path": path.as_ref()
+            }))
+            .send()?
+            .check_status()?;
+
+        Ok(decode_base64_json(BufReader::new(response), out)?)
+    }
+
+    /// Returns a file from the storage, if present.
+    pub fn get_storage_file<C, P>(
+        &self,
+        circuit: C,
+        path: P,
+    ) -> Result<Option<Vec<u8>>, ClientError>
+    where
+        C: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        let mut data = Vec::new();
+
+        Ok(self
+            .get_storage_file_to(circuit, path, &mut data)?
+            .map(|_| data))
+    }
+}
+
+/// Decodes a JSON base64 string, or `null`, from the reader into `out`.
+///
+/// A body wrapped into a `data` field is decoded in one shot.
+fn decode_base64_json<R, W>(mut reader: R, out: &mut W) -> anyhow::Result<Option<u64>>
+where
+    R: BufRead,
+    W: Write,
+{
+    let first = loop {
+        let buf = reader.fill_buf()?;
+        let first = *buf
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("empty storage response"))?;
+
+        reader.consume(1);
+
+        if !first.is_ascii_whitespace() {
+            break first;
+        }
+    };
+
+    match first {
+        b'"' => (),
+        b'n' => {
+            let mut rest = Vec::new();
+
+            reader.read_to_end(&mut rest)?;
+
+            anyhow::ensure!(rest.trim_ascii() == b"ull", "invalid storage response");
+
+            return Ok(None);
+        }
+        b'{' => {
+            let mut body = vec![first];
+
+            reader.read_to_end(&mut body)?;
+
+            return match serde_json::from_slice::<Value>(&body)?.get("data") {
+                Some(Value::String(d)) => {
+                    let data = Base64::decode(d)?;
+
+                    out.write_all(&data)?;
+
+                    Ok(Some(data.len() as u64))
+                }
+                Some(Value::Null) | None => Ok(None),
+                Some(d) => anyhow::bail!("invalid storage data: {d}"),
+            };
+        }
+        _ => anyhow::bail!("invalid storage response"),
+    }
+
+    let mut pending = Vec::with_capacity(CHUNK_LEN);
+    let mut len = 0;
+
+    loop {
+        let buf = reader.fill_buf()?;
+
+        anyhow::ensure!(!buf.is_empty(), "unterminated storage data");
+
+        let end = buf.iter().position(|b| *b == b'"');
+        let chunk = &buf[..end.unwrap_or(buf.len())];
+
+        anyhow::ensure!(
+            !chunk.contains(&b'\\'),
+            "unexpected escape in the storage data"
+        );
+
+        pending.extend_from_slice(chunk);
+
+        let consumed = chunk.len();
+        let decodable = match end {
+            Some(_) => pending.len(),
+            None => pending.len() - pending.len() % 4,
+        };
+
+        if end.is_some() || decodable >= CHUNK_LEN {
+            let data = Base64::decode(std::str::from_utf8(&pending[..decodable])?)?;
+
+            out.write_all(&data)?;
+            len += data.len() as u64;
+            pending.drain(..decodable);
+        }
+
+        if end.is_some() {
+            reader.consume(consumed + 1);
+            break;
+        }
+
+        reader.consume(consumed);
+    }
+
+    Ok(Some(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_is_decoded_in_chunks() {
+        let data: Vec<u8> = (0..3 * CHUNK_LEN as u32).map(|i| (i * 7) as u8).collect();
+        let body = serde_json::to_vec(&Base64::encode(&data)).unwrap();
+
+        // a tiny buffer forces the quanta to be split across reads
+        let mut out = Vec::new();
+        let len = decode_base64_json(BufReader::with_capacity(7, body.as_slice()), &mut out);
+
+        assert_eq!(len.unwrap(), Some(data.len() as u64));
+        assert_eq!(out, data);
+
+        let mut out = Vec::new();
+        let body = json!({"data": Base64::encode(b"wrapped")}).to_string();
+
+        assert_eq!(
+            decode_base64_json(body.as_bytes(), &mut out).unwrap(),
+            Some(7)
+        );
+        assert_eq!(out, b"wrapped");
+
+        assert_eq!(decode_base64_json(&b" null"[..], &mut out).unwrap(), None);
+        assert!(decode_base64_json(&b"\"AAAA"[..], &mut out).is_err());
+    }
+}
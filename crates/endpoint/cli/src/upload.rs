@@ -0,0 +1,191 @@
+use serde_json::{json, Value};
+use valence_coprocessor::Base64;
+
+use crate::{json_body, App, ClientError};
+
+impl App {
+    /// Default size of a circuit chunk uploaded by [`App::deploy_controller_chunked`].
+    ///
+    /// 1 MiB, or about 1.4 MB once base64 encoded, which fits the body limits of common proxies.
+    pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+    /// Consecutive failures of a chunk upload before it is given up.
+    pub const CHUNK_RETRIES: usize = 3;
+
+    /// Deploys a controller, uploading its circuit in chunks of `chunk_size` bytes.
+    ///
+    /// A failed chunk is retried from the bytes already received by the co-processor. If the
+    /// retries are exhausted, the error reports the session id, so the upload can be completed
+    /// with [`App::resume_controller_upload`].
+    ///
+    /// Returns the allocated ID, exactly like a single request deploy.
+    pub fn deploy_controller_chunked(
+        &self,
+        controller: &[u8],
+        circuit: &[u8],
+        nonce: u64,
+        chunk_size: Option<usize>,
+    ) -> Result<Value, ClientError> {
+        let uri = format!("{}/api/registry/controller/upload", self.base_url());
+
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "controller": Base64::encode(controller),
+                "size": circuit.len(),
+                "nonce": nonce,
+            }))
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        let session = response
+            .get("session")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                ClientError::MalformedResponse(format!("invalid upload response: {response}"))
+            })?;
+
+        self.resume_controller_upload(session, circuit, chunk_size)
+    }
+
+    /// Uploads the remaining chunks of a circuit to the session, and finalizes the deploy.
+    ///
+    /// The chunks already received by the co-processor are not uploaded again. A failed chunk, or
+    /// a failed probe of the received bytes, counts as a retry; once the retries are exhausted, the
+    /// error is a [`ClientError::UploadInterrupted`] carrying the session id.
+    pub fn resume_controller_upload(
+        &self,
+        session: &str,
+        circuit: &[u8],
+        chunk_size: Option<usize>,
+    ) -> Result<Value, ClientError> {
+        let chunk_size = chunk_size.unwrap_or(Self::DEFAULT_CHUNK_SIZE).max(1);
+        let uri = format!(
+            "{}/api/registry/controller/upload/{session}",
+            self.base_url()
+        );
+
+        let interrupted = |e| ClientError::UploadInterrupted {
+            session: session.into(),
+            source: Box::new(e),
+        };
+
+        // the offset is unknown until the co-processor is probed for the received bytes
+        let mut offset: Option<usize> = None;
+        let mut failures = 0;
+
+        loop {
+            let received = match offset {
+                Some(o) if o >= circuit.len() => break,
+                Some(o) => {
+                    let end = o.saturating_add(chunk_size).min(circuit.len());
+
+                    self.upload_chunk(&uri, o, &circuit[o..end])
+                        .inspect(|_| failures = 0)
+                }
+                None => self.upload_received(&uri),
+            };
+
+            match received {
+                Ok(r) => offset = Some(r),
+                Err(e) => {
+                    failures += 1;
+
+                    if failures > Self::CHUNK_RETRIES {
+                        return Err(interrupted(e));
+                    }
+
+                    // the chunk might have been stored before the failure
+                    offset = None;
+                }
+            }
+        }
+
+        let response = self
+            .http()
+            .post(format!("{uri}/finalize"))
+            .json(&json!({}))
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)
+            .map_err(interrupted)?;
+
+        Ok(response)
+    }
+
+    fn upload_chunk(&self, uri: &str, offset: usize, chunk: &[u8]) -> Result<usize, ClientError> {
+        let response = self
+            .http()
+            .post(uri)
+            .json(&json!({
+                "offset": offset,
+                "data": Base64::encode(chunk),
+            }))
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        received(&response)
+    }
+
+    fn upload_received(&self, uri: &str) -> Result<usize, ClientError> {
+        let response = self
+            .http()
+            .get(uri)
+            .send()
+            .map_err(ClientError::from)
+            .and_then(json_body)?;
+
+        received(&response)
+    }
+}
+
+fn received(response: &Value) -> Result<usize, ClientError> {
+    response
+        .get("received")
+        .and_then(Value::as_u64)
+        .and_then(|r| usize::try_from(r).ok())
+        .ok_or_else(|| {
+            ClientError::MalformedResponse(format!("invalid upload response: {response}"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{mock::MockServer, ClientError};
+
+    #[test]
+    fn exhausted_retries_report_the_session() {
+        // a session, a probe, a failed chunk, and failed probes until the retries are exhausted
+        let mut responses = vec![
+            (200, json!({"session": "fog"}).to_string()),
+            (200, json!({"received": 0}).to_string()),
+            (500, String::new()),
+        ];
+
+        responses.extend((0..3).map(|_| (500, String::new())));
+
+        let server = MockServer::serve(responses);
+        let err = server
+            .app()
+            .deploy_controller_chunked(b"controller", b"little cat feet", 0, Some(4))
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, ClientError::UploadInterrupted { session, .. } if session == "fog"),
+            "{err}"
+        );
+        assert!(err.to_string().contains("`fog`"));
+        assert!(err.is_transient());
+
+        let requests = server.requests();
+
+        assert_eq!(requests.len(), 6);
+        assert!(requests[2].json().get("offset").is_some());
+        assert!(requests[3..].iter().all(|r| r.method == "GET"));
+    }
+}
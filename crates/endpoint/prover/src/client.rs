@@ -212,4 +212,70 @@ impl Client {
 
         Ok(vk)
     }
+
+    /// Executes the circuit with the provided witnesses, returning its cycle count.
+    ///
+    /// The `circuit` argument will be used to index the proving key, which carries the ELF. If the
+    /// proving key cannot be found, `elf` will be evaluated to return the elf binary, executed
+    /// without computing its proving key.
+    pub fn get_sp1_cycles<F, W>(&self, circuit: Hash, witnesses: &W, elf: F) -> anyhow::Result<u64>
+    where
+        F: FnOnce(&Hash) -> anyhow::Result<Vec<u8>>,
+        W: AsRef<[u8]>,
+    {
+        let mut socket = self.connect()?;
+
+        socket.send(
+            Request::Sp1Execute {
+                circuit: circuit.into(),
+                witnesses: Base64::encode(witnesses.as_ref()),
+            }
+            .pack_to_vec()
+            .into(),
+        )?;
+
+        let res = socket.read()?.into_data().to_vec();
+        let res = Response::unpack(&res)?.1;
+
+        match res {
+            Response::Cycles(c) => {
+                socket.send(Request::Close.pack_to_vec().into()).ok();
+
+                return Ok(c);
+            }
+
+            Response::ProvingKeyNotCached => (),
+
+            Response::Err(e) => anyhow::bail!("error processing request: {e}"),
+            _ => anyhow::bail!("unexpected response {res:?}"),
+        }
+
+        let elf = elf(&circuit)?;
+        let elf = Base64::encode(elf);
+
+        socket.send(
+            Request::Sp1Execute {
+                circuit: Circuit::Elf {
+                    identifier: circuit,
+                    bytes: elf,
+                },
+                witnesses: Base64::encode(witnesses.as_ref()),
+            }
+            .pack_to_vec()
+            .into(),
+        )?;
+
+        let res = socket.read()?.into_data().to_vec();
+        let res = Response::unpack(&res)?.1;
+
+        let cycles = match res {
+            Response::Cycles(c) => c,
+            Response::Err(e) => anyhow::bail!("error processing request: {e}"),
+            _ => anyhow::bail!("unexpected response {res:?}"),
+        };
+
+        socket.send(Request::Close.pack_to_vec().into()).ok();
+
+        Ok(cycles)
+    }
 }
@@ -63,6 +63,102 @@ impl Cluster {
     }
 }
 
+/// Hit and miss counters of the prover affinity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AffinityStats {
+    /// Number of proofs routed to the prover that last proved their circuit.
+    pub hits: u64,
+
+    /// Number of proofs routed to another prover.
+    pub misses: u64,
+}
+
+/// Routes the proofs of a circuit to the prover that last proved it, so its proving key is warm.
+///
+/// The warm prover is skipped if it is busier than the least loaded prover of the cluster, so a
+/// dominant circuit is still spread across the cluster.
+#[derive(Debug, Default)]
+struct Affinity {
+    provers: HashMap<Hash, String>,
+    load: HashMap<String, usize>,
+    stats: AffinityStats,
+}
+
+impl Affinity {
+    fn load(&self, address: &str) -> usize {
+        self.load.get(address).copied().unwrap_or_default()
+    }
+
+    fn select(&mut self, cluster: &mut Cluster, circuit: &Hash) -> Option<Client> {
+        let least = cluster
+            .clients
+            .iter()
+            .map(|c| self.load(c.address()))
+            .min()?;
+
+        let warm = self
+            .provers
+            .get(circuit)
+            .and_then(|a| cluster.clients.iter().find(|c| c.address() == a))
+            .filter(|c| self.load(c.address()) <= least)
+            .cloned();
+
+        if let Some(c) = warm {
+            tracing::debug!("routing circuit to warm prover `{}`...", c.address());
+
+            self.stats.hits += 1;
+
+            return Some(c);
+        }
+
+        self.stats.misses += 1;
+
+        let client = (0..cluster.clients.len())
+            .filter_map(|_| cluster.rotate())
+            .find(|c| self.load(c.address()) <= least)?;
+
+        self.provers.insert(*circuit, client.address().into());
+
+        Some(client)
+    }
+
+    /// Returns the prover of a circuit lookup, as a verifying key or a cycle count, without
+    /// counting it as routed.
+    ///
+    /// The warm prover is preferred regardless of its load, as it has the proving key of the
+    /// circuit; otherwise, the least loaded prover is returned.
+    fn lookup(&self, cluster: &Cluster, circuit: &Hash) -> Option<Client> {
+        self.provers
+            .get(circuit)
+            .and_then(|a| cluster.clients.iter().find(|c| c.address() == a))
+            .or_else(|| {
+                cluster
+                    .clients
+                    .iter()
+                    .min_by_key(|c| self.load(c.address()))
+            })
+            .cloned()
+    }
+}
+
+/// Counts a proof in flight on a prover, until dropped.
+struct Lease {
+    affinity: Option<Arc<Mutex<Affinity>>>,
+    address: String,
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Some(a) = &self.affinity {
+            let mut a = a.lock();
+
+            if let Some(l) = a.load.get_mut(&self.address) {
+                *l = l.saturating_sub(1);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, MsgPacker)]
 pub struct AllocatedProvers {
     pub public: Vec<String>,
@@ -73,16 +169,81 @@ pub struct AllocatedProvers {
 pub struct ProverScheduler {
     public: Arc<Mutex<Cluster>>,
     owned: Arc<Mutex<HashMap<Vec<u8>, Cluster>>>,
+    affinity: Option<Arc<Mutex<Affinity>>>,
+    mock: bool,
 }
 
 impl ProverScheduler {
-    fn get_client<H, D>(&self, ctx: &ExecutionContext<H, D>) -> anyhow::Result<Client>
+    /// Marks the provers of the scheduler as mock provers, so their proofs are verified as proofs
+    /// of the mock prover.
+    pub fn with_mock(mut self, mock: bool) -> Self {
+        self.mock = mock;
+        self
+    }
+
+    /// Returns `true` if the provers of the scheduler are mock provers.
+    pub fn is_mock(&self) -> bool {
+        self.mock
+    }
+
+    /// Routes consecutive proofs of a circuit to the prover that has its proving key warm.
+    pub fn with_affinity(mut self, affinity: bool) -> Self {
+        self.affinity = affinity.then(Default::default);
+        self
+    }
+
+    /// Returns the hit and miss counters of the prover affinity.
+    ///
+    /// The counters are zeroed if the affinity is disabled.
+    pub fn affinity_stats(&self) -> AffinityStats {
+        self.affinity
+            .as_ref()
+            .map(|a| a.lock().stats)
+            .unwrap_or_default()
+    }
+
+    fn select(&self, cluster: &mut Cluster, circuit: &Hash) -> Option<Client> {
+        match &self.affinity {
+            Some(a) => a.lock().select(cluster, circuit),
+            None => cluster.rotate(),
+        }
+    }
+
+    fn lookup(&self, cluster: &mut Cluster, circuit: &Hash) -> Option<Client> {
+        match &self.affinity {
+            Some(a) => a.lock().lookup(cluster, circuit),
+            None => cluster.rotate(),
+        }
+    }
+
+    fn lease(&self, client: &Client) -> Lease {
+        if let Some(a) = &self.affinity {
+            *a.lock().load.entry(client.address().into()).or_default() += 1;
+        }
+
+        Lease {
+            affinity: self.affinity.clone(),
+            address: client.address().into(),
+        }
+    }
+
+    /// Returns the prover of the context, routed by `route` within the cluster of the owner, or
+    /// the public one.
+    fn get_client<H, D, R>(&self, ctx: &ExecutionContext<H, D>, route: R) -> anyhow::Result<Client>
     where
         H: Hasher,
         D: DataBackend,
+        R: Fn(&Self, &mut Cluster, &Hash) -> Option<Client>,
     {
+        let circuit = ctx.controller();
+
         if let Some(owner) = ctx.owner() {
-            if let Some(c) = self.owned.lock().get_mut(owner).and_then(Cluster::rotate) {
+            if let Some(c) = self
+                .owned
+                .lock()
+                .get_mut(owner)
+                .and_then(|c| route(self, c, circuit))
+            {
                 tracing::debug!("returning owned prover...");
 
                 return Ok(c);
@@ -91,9 +252,7 @@ impl ProverScheduler {
 
         tracing::debug!("no owned prover available; falling back to public cluster...");
 
-        self.public
-            .lock()
-            .rotate()
+        route(self, &mut self.public.lock(), circuit)
             .ok_or_else(|| anyhow::anyhow!("no available public client"))
     }
 
@@ -140,6 +299,33 @@ impl ProverScheduler {
     }
 }
 
+impl ProverScheduler {
+    /// Executes the circuit of the context without proving, returning its cycle count.
+    ///
+    /// The count estimates the proving complexity of the circuit for the provided witnesses.
+    pub fn cycles<D>(
+        &self,
+        ctx: &ExecutionContext<Sp1Hasher, D>,
+        w: WitnessCoprocessor,
+    ) -> anyhow::Result<u64>
+    where
+        D: DataBackend,
+    {
+        w.ensure_version()?;
+
+        let circuit = *ctx.controller();
+        let w = bincode::serialize(&w)?;
+        let client = self.get_client(ctx, Self::lookup)?;
+        let _lease = self.lease(&client);
+
+        client.get_sp1_cycles(circuit, &w, |_| {
+            ctx.get_zkvm()
+                .transpose()
+                .ok_or_else(|| anyhow::anyhow!("failed to fetch ELF contents from context"))?
+        })
+    }
+}
+
 impl ZkVm for ProverScheduler {
     type Hasher = Sp1Hasher;
 
@@ -153,6 +339,8 @@ impl ZkVm for ProverScheduler {
     {
         tracing::debug!("initiating prove request...");
 
+        w.ensure_version()?;
+
         let circuit = *ctx.controller();
         let w = bincode::serialize(&w)?;
 
@@ -162,7 +350,8 @@ impl ZkVm for ProverScheduler {
         );
 
         let t = ProofType::Groth16;
-        let client = self.get_client(ctx)?;
+        let client = self.get_client(ctx, Self::select)?;
+        let _lease = self.lease(&client);
         let recursive = Vec::new();
 
         let proof = client.get_sp1_proof(circuit, t, &w, &recursive, |_| {
@@ -181,7 +370,8 @@ impl ZkVm for ProverScheduler {
         D: DataBackend,
     {
         let circuit = *ctx.controller();
-        let client = self.get_client(ctx)?;
+        let client = self.get_client(ctx, Self::lookup)?;
+        let _lease = self.lease(&client);
         let vk = client.get_sp1_verifying_key(circuit, |_| {
             ctx.get_zkvm()
                 .transpose()
@@ -193,3 +383,99 @@ impl ZkVm for ProverScheduler {
 
     fn updated(&self, _circuit: &Hash) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cluster(provers: &[&str]) -> Cluster {
+        let mut cluster = Cluster::default();
+
+        for p in provers {
+            cluster.push(p);
+        }
+
+        cluster
+    }
+
+    #[test]
+    fn affinity_routes_a_circuit_to_its_warm_prover() {
+        let scheduler = ProverScheduler::default().with_affinity(true);
+        let mut cluster = cluster(&["a", "b", "c"]);
+
+        let first = scheduler.select(&mut cluster, &[1; 32]).unwrap();
+        let other = scheduler.select(&mut cluster, &[2; 32]).unwrap();
+
+        assert_ne!(first, other);
+
+        for _ in 0..3 {
+            assert_eq!(scheduler.select(&mut cluster, &[1; 32]).unwrap(), first);
+        }
+
+        assert_eq!(
+            scheduler.affinity_stats(),
+            AffinityStats { hits: 3, misses: 2 }
+        );
+    }
+
+    #[test]
+    fn affinity_skips_a_busy_warm_prover() {
+        let scheduler = ProverScheduler::default().with_affinity(true);
+        let mut cluster = cluster(&["a", "b"]);
+
+        let warm = scheduler.select(&mut cluster, &[1; 32]).unwrap();
+        let lease = scheduler.lease(&warm);
+        let busy = scheduler.select(&mut cluster, &[1; 32]).unwrap();
+
+        assert_ne!(busy, warm);
+        assert_eq!(scheduler.affinity_stats().misses, 2);
+
+        // the other prover is warm now, and the released one is as loaded as it
+        drop(lease);
+
+        assert_eq!(scheduler.select(&mut cluster, &[1; 32]).unwrap(), busy);
+        assert_eq!(scheduler.affinity_stats().hits, 1);
+    }
+
+    #[test]
+    fn affinity_lookups_have_no_side_effects() {
+        let scheduler = ProverScheduler::default().with_affinity(true);
+        let mut cluster = cluster(&["a", "b"]);
+
+        let warm = scheduler.select(&mut cluster, &[1; 32]).unwrap();
+        let stats = scheduler.affinity_stats();
+        let _lease = scheduler.lease(&warm);
+
+        // the warm prover is returned even if busy, without routing the circuit
+        for _ in 0..3 {
+            assert_eq!(scheduler.lookup(&mut cluster, &[1; 32]).unwrap(), warm);
+        }
+
+        let cold = scheduler.lookup(&mut cluster, &[2; 32]).unwrap();
+
+        assert_ne!(cold, warm);
+        assert_eq!(scheduler.lookup(&mut cluster, &[2; 32]).unwrap(), cold);
+        assert_eq!(scheduler.affinity_stats(), stats);
+
+        let affinity = scheduler.affinity.as_ref().unwrap().lock();
+
+        assert!(!affinity.provers.contains_key(&[2; 32]));
+    }
+
+    #[test]
+    fn leases_release_their_load_when_dropped() {
+        let scheduler = ProverScheduler::default().with_affinity(true);
+        let client = Client::new("a");
+        let load = || scheduler.affinity.as_ref().unwrap().lock().load("a");
+
+        let first = scheduler.lease(&client);
+        let second = scheduler.lease(&client);
+
+        assert_eq!(load(), 2);
+
+        drop(first);
+        drop(second);
+
+        assert_eq!(load(), 0);
+    }
+}
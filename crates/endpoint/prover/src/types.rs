@@ -55,6 +55,14 @@ pub enum Request {
 
     /// Close the connection
     Close,
+
+    /// Execute the SP1 circuit without proving, counting its cycles.
+    Sp1Execute {
+        /// Executed circuit
+        circuit: Circuit,
+        /// Circuit witnesses (base64)
+        witnesses: String,
+    },
 }
 
 /// Possible states resulting of a proof request.
@@ -76,6 +84,9 @@ pub enum Response {
 
     /// An error has occurred.
     Err(String),
+
+    /// The number of cycles of an execution.
+    Cycles(u64),
 }
 
 #[derive(Debug)]
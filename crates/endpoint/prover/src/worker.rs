@@ -18,7 +18,7 @@ use crate::{
 pub struct Worker {
     cache: KeysCache,
     sp1mock: CpuProver,
-    sp1cpu: CpuProver,
+    sp1cpu: Arc<CpuProver>,
     sp1gpu: Option<Arc<Mutex<CudaProver>>>,
     rx: Receiver<Task>,
     tx: Sender<()>,
@@ -174,6 +174,50 @@ impl Worker {
                 None => Response::ProvingKeyNotCached,
             },
 
+            Request::Sp1Execute { circuit, witnesses } => {
+                // the execution needs only the ELF, so no proving key is computed for it
+                let elf = match circuit {
+                    Circuit::Identifier(c) => match self
+                        .cache
+                        .get(&c)
+                        .await
+                        .and_then(|pk| bincode::deserialize::<SP1ProvingKey>(&pk).ok())
+                    {
+                        Some(pk) => pk.elf,
+                        None => return Response::ProvingKeyNotCached,
+                    },
+
+                    Circuit::Elf { bytes, .. } => match Base64::decode(bytes) {
+                        Ok(e) => e,
+                        Err(e) => return Response::Err(format!("error decoding the elf: {e}")),
+                    },
+                };
+
+                let witnesses = match Base64::decode(&witnesses) {
+                    Ok(w) => w,
+                    Err(e) => return Response::Err(format!("error decoding the witnesses: {e}")),
+                };
+
+                let sp1cpu = self.sp1cpu.clone();
+                let cycles = tokio::task::spawn_blocking(move || {
+                    let mut stdin = SP1Stdin::new();
+
+                    stdin.write_slice(&witnesses);
+
+                    sp1cpu
+                        .execute(&elf, &stdin)
+                        .run()
+                        .map(|(_, report)| report.total_instruction_count())
+                })
+                .await;
+
+                match cycles {
+                    Ok(Ok(c)) => Response::Cycles(c),
+                    Ok(Err(e)) => Response::Err(format!("circuit execution failed: {e}")),
+                    Err(e) => Response::Err(format!("circuit execution aborted: {e}")),
+                }
+            }
+
             Request::Close => Response::Ack,
         }
     }
@@ -187,7 +231,7 @@ impl Worker {
         tracing::debug!("spawning a new worker thread...");
 
         let sp1mock = ProverClient::builder().mock().build();
-        let sp1cpu = ProverClient::builder().cpu().build();
+        let sp1cpu = Arc::new(ProverClient::builder().cpu().build());
 
         let worker = Self {
             cache,
@@ -241,3 +285,69 @@ impl Worker {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &[u8] =
+        include_bytes!("../../../runtime/sp1/assets/sample-groth16-proof/hello.elf");
+
+    fn worker() -> Worker {
+        let (_, rx) = flume::unbounded();
+        let (tx, _) = flume::unbounded();
+
+        Worker {
+            cache: KeysCache::new(KeysCache::MIN_CAP),
+            sp1mock: ProverClient::builder().mock().build(),
+            sp1cpu: Arc::new(ProverClient::builder().cpu().build()),
+            sp1gpu: None,
+            rx,
+            tx,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn execution_computes_no_proving_key() {
+        let worker = worker();
+        let identifier = [1; 32];
+
+        let res = worker
+            .execute(Request::Sp1Execute {
+                circuit: identifier.into(),
+                witnesses: Base64::encode(b""),
+            })
+            .await;
+
+        assert!(matches!(res, Response::ProvingKeyNotCached));
+
+        let res = worker
+            .execute(Request::Sp1Execute {
+                circuit: Circuit::Elf {
+                    identifier,
+                    bytes: Base64::encode(HELLO),
+                },
+                witnesses: Base64::encode(b""),
+            })
+            .await;
+
+        // the sample reads a witness, so its execution fails without one
+        assert!(
+            matches!(&res, Response::Err(e) if e.starts_with("circuit execution failed")),
+            "{res:?}"
+        );
+        assert!(worker.cache.get(&identifier).await.is_none());
+
+        let res = worker
+            .execute(Request::Sp1Execute {
+                circuit: Circuit::Elf {
+                    identifier,
+                    bytes: "!".into(),
+                },
+                witnesses: Base64::encode(b""),
+            })
+            .await;
+
+        assert!(matches!(res, Response::Err(e) if e.starts_with("error decoding the elf")));
+    }
+}
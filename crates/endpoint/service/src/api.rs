@@ -1,15 +1,42 @@
-use std::path::PathBuf;
+use std::{
+    io::Cursor,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use flume::Sender;
-use poem::{http::StatusCode, web::Data, Error as PoemError};
-use poem_openapi::{param::Path, payload::Json, types::Base64, Object, OpenApi};
+use poem::{http::StatusCode, web::Data, Body, Error as PoemError};
+use poem_openapi::{
+    param::{Header, Path, Query},
+    payload::{Binary, Json},
+    types::Base64,
+    ApiResponse, Object, OpenApi,
+};
 use serde_json::{json, Value};
-use valence_coprocessor::{BlockAdded, Hash, HistoricalUpdate, ValidatedDomainBlock};
-use valence_coprocessor::{ControllerData, DomainData};
+use tokio::io::AsyncReadExt as _;
+use valence_coprocessor::{
+    Blake3Hasher, BlockAdded, Hash, Hasher as _, HistoricalUpdate, ValidatedDomainBlock,
+};
+use valence_coprocessor::{ControllerData, DomainData, HasherKind, Proof, WitnessEncoding};
 use valence_coprocessor_prover::scheduler::ProverScheduler;
-
-use crate::Context;
-use crate::{worker::Job, Historical, Registry, ServiceVm};
+use valence_coprocessor_sp1::{verify_groth16_batch, verify_mock_batch};
+use valence_coprocessor_wasm::host;
+
+use crate::{
+    data::BackendKind,
+    health::Readiness,
+    logs::{AdminToken, LogFilter, ServiceLog},
+    middleware,
+    proofs::{ProofProvenance, ProofStore},
+    published::PublishedRoot,
+    upload::{UploadError, Uploads},
+    witnesses::LastWitnesses,
+    Context,
+};
+use crate::{
+    worker::{self, Cancel, Job, PendingJobs, PoolStats, Reply},
+    Historical, Registry, ServiceVm,
+};
 
 pub struct Api;
 
@@ -24,12 +51,196 @@ pub struct RegisterControllerRequest {
     /// Optional nonce to affect the controller id.
     #[oai(default)]
     pub nonce: Option<u64>,
+
+    /// Optional witness data encoding (`raw`, `msgpack`, or `bincode`). Defaults to `raw`.
+    #[oai(default)]
+    pub codec: Option<String>,
+
+    /// Optional sample arguments, executed to report the cycle count of the circuit.
+    #[oai(default)]
+    pub sample: Option<Value>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerUploadRequest {
+    /// A Base64 WASM encoded controller.
+    pub controller: Base64<Vec<u8>>,
+
+    /// Size of the circuit to be uploaded in chunks, in bytes.
+    pub size: u64,
+
+    /// Optional nonce to affect the controller id.
+    #[oai(default)]
+    pub nonce: Option<u64>,
+
+    /// Optional witness data encoding (`raw`, `msgpack`, or `bincode`). Defaults to `raw`.
+    #[oai(default)]
+    pub codec: Option<String>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerUploadResponse {
+    /// Session id of the upload.
+    pub session: String,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerUploadChunkRequest {
+    /// Offset of the chunk in the circuit.
+    pub offset: u64,
+
+    /// A Base64 chunk of the circuit.
+    pub data: Base64<Vec<u8>>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerUploadStatusResponse {
+    /// Number of circuit bytes received; the offset of the next chunk.
+    pub received: u64,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerUploadFinalizeRequest {
+    /// Optional sample arguments, executed to report the cycle count of the circuit.
+    #[oai(default)]
+    pub sample: Option<Value>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerVerifyBatchRequest {
+    /// The base64 encoded proofs to be verified.
+    pub proofs: Vec<String>,
+}
+
+impl ControllerVerifyBatchRequest {
+    /// Maximum number of proofs of a batch.
+    pub const MAX_PROOFS: usize = 64;
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerVerifyBatchResponse {
+    /// The verification result of each proof, in the order of the request.
+    pub results: Vec<bool>,
+}
+
+#[derive(Object, Debug, Clone)]
+pub struct ServerInfoResponse {
+    /// The service name and version.
+    pub version: String,
+
+    /// Proof systems supported by the provers.
+    pub proof_systems: Vec<String>,
+
+    /// Supported witness data encodings.
+    pub encodings: Vec<String>,
+
+    /// Enabled API endpoints.
+    pub endpoints: Vec<String>,
+
+    /// Active data backend.
+    pub backend: String,
+
+    /// Retention policy and usage of the proof results.
+    pub proof_retention: ProofRetentionResponse,
+
+    /// Maximum number of witnesses a controller can return.
+    pub max_witnesses: usize,
+
+    /// Maximum size, in bytes, of a domain block payload, once decoded.
+    pub max_block_payload: usize,
+
+    /// Supported content encodings of a domain block payload.
+    pub block_encodings: Vec<String>,
+}
+
+#[derive(Object, Debug, Clone, Default)]
+pub struct ProofRetentionResponse {
+    /// Maximum age of a proof result, in seconds.
+    pub max_age_secs: Option<u64>,
+
+    /// Maximum number of proof results per circuit.
+    pub max_count: Option<usize>,
+
+    /// Maximum total size of the proof results, in bytes.
+    pub max_size: Option<u64>,
+
+    /// Number of tracked proof results.
+    pub proofs: usize,
+
+    /// Total size of the tracked proof results, in bytes.
+    pub size: u64,
+}
+
+impl From<&ProofStore> for ProofRetentionResponse {
+    fn from(store: &ProofStore) -> Self {
+        let policy = store.policy();
+        let (proofs, size) = store.usage();
+
+        Self {
+            max_age_secs: policy.max_age.map(|a| a.as_secs()),
+            max_count: policy.max_count,
+            max_size: policy.max_size,
+            proofs,
+            size,
+        }
+    }
+}
+
+impl ServerInfoResponse {
+    /// Creates the service info with the provided enabled endpoints and active data backend.
+    pub fn new(endpoints: Vec<String>, backend: BackendKind) -> Self {
+        Self {
+            version: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).into(),
+            proof_systems: vec!["compressed".into(), "groth16".into()],
+            encodings: WitnessEncoding::ALL
+                .iter()
+                .map(|e| e.as_str().into())
+                .collect(),
+            endpoints,
+            backend: backend.as_str().into(),
+            proof_retention: Default::default(),
+            max_witnesses: host::MAX_WITNESSES,
+            max_block_payload: middleware::MAX_BLOCK_PAYLOAD,
+            block_encodings: vec![middleware::BLOCK_ENCODING.into()],
+        }
+    }
+
+    /// Sets the maximum size, in bytes, of a domain block payload.
+    pub fn with_max_block_payload(mut self, bytes: usize) -> Self {
+        self.max_block_payload = bytes;
+        self
+    }
 }
 
 #[derive(Object, Debug)]
 pub struct RegisterControllerResponse {
     /// The allocated controller id as hex.
     pub controller: String,
+
+    /// Size of the circuit ELF, in bytes.
+    pub elf_size: usize,
+
+    /// Cycle count of the circuit for the sample arguments, if provided and executed successfully.
+    pub cycles: Option<u64>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerReportRequest {
+    /// Optional sample arguments, executed to report the cycle count of the circuit.
+    #[oai(default)]
+    pub sample: Option<Value>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerReportResponse {
+    /// Size of the circuit ELF, in bytes.
+    pub elf_size: usize,
+
+    /// Cycle count of the circuit for the sample arguments, if provided.
+    pub cycles: Option<u64>,
+
+    /// Logs of the operation.
+    pub log: Vec<String>,
 }
 
 #[derive(Object, Debug)]
@@ -42,6 +253,13 @@ pub struct RegisterDomainRequest {
 
     /// A Base64 circuit encoded prover.
     pub circuit: Base64<Vec<u8>>,
+
+    /// Optional hasher of the domain tree (`native`, `blake3`, `keccak256`, or `sha256`).
+    /// Defaults to `native`.
+    ///
+    /// The hasher of a domain is fixed once chosen.
+    #[oai(default)]
+    pub hasher: Option<String>,
 }
 
 #[derive(Object, Debug)]
@@ -56,6 +274,18 @@ pub struct ControllerStorageFileRequest {
     pub path: String,
 }
 
+#[derive(Object, Debug)]
+pub struct ControllerStorageFileRangeRequest {
+    /// Path of the controller file.
+    pub path: String,
+
+    /// Optional offset of the first byte to be returned.
+    pub offset: Option<u64>,
+
+    /// Optional maximum number of bytes to be returned.
+    pub length: Option<u64>,
+}
+
 #[derive(Object, Debug)]
 pub struct ControllerStorageFileStoreRequest {
     /// Path of the controller file.
@@ -98,6 +328,93 @@ pub struct ControllerProveRequest {
 
     /// Optional callback payload.
     pub payload: Option<Value>,
+
+    /// Optional UNIX timestamp (in seconds) after which the proof request is abandoned.
+    pub deadline: Option<u64>,
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerReplayResponse {
+    /// `true` if the replayed proof has the same public inputs as the stored one.
+    pub matches: bool,
+
+    /// Co-processor root the proofs were computed against, in hex.
+    pub root: String,
+
+    /// Commitment to the public inputs of the stored proof, in hex.
+    pub original_commitment: String,
+
+    /// Commitment to the public inputs of the replayed proof, in hex.
+    pub commitment: String,
+
+    /// Base64 public inputs of the stored proof.
+    pub original_inputs: String,
+
+    /// Base64 public inputs of the replayed proof.
+    pub inputs: String,
+
+    /// Logs of the operation.
+    pub log: Vec<String>,
+}
+
+#[derive(Object, Debug)]
+pub struct PublishRootRequest {
+    /// The published co-processor root, in hex.
+    pub root: String,
+
+    /// Hash of the publication transaction.
+    pub tx: String,
+
+    /// Address of the contract that holds the root, if any.
+    #[oai(default)]
+    pub contract: Option<String>,
+
+    /// Block number of the publication transaction, if known.
+    #[oai(default)]
+    pub block: Option<u64>,
+}
+
+#[derive(Object, Debug)]
+pub struct PublishedRootResponse {
+    /// The published co-processor root, in hex.
+    pub root: String,
+
+    /// Chain the root was published to.
+    pub chain: String,
+
+    /// Hash of the publication transaction.
+    pub tx: String,
+
+    /// Address of the contract that holds the root, if any.
+    pub contract: Option<String>,
+
+    /// Block number of the publication transaction, if known.
+    pub block: Option<u64>,
+
+    /// UNIX timestamp, in seconds, of when the publication was recorded.
+    pub published_at: u64,
+}
+
+impl From<PublishedRoot> for PublishedRootResponse {
+    fn from(p: PublishedRoot) -> Self {
+        Self {
+            root: hex::encode(p.root),
+            chain: p.chain,
+            tx: p.tx,
+            contract: p.contract,
+            block: p.block,
+            published_at: p.published_at,
+        }
+    }
+}
+
+#[derive(Object, Debug)]
+pub struct ControllerVkHashResponse {
+    /// Blake3 digest of the verifying key, in hex.
+    pub hash: String,
+
+    /// Logs of the operation.
+    pub log: Vec<String>,
 }
 
 #[derive(Object, Debug)]
@@ -136,6 +453,20 @@ pub struct ProverRequest {
     pub address: String,
 }
 
+#[derive(Object, Debug)]
+pub struct PoolCapacityResponse {
+    /// Number of jobs waiting for a worker.
+    pub queued: usize,
+    /// Number of jobs currently being proven.
+    pub in_flight: usize,
+    /// Number of spawned workers.
+    pub workers: usize,
+    /// Maximum number of concurrent workers.
+    pub max_workers: usize,
+    /// Estimated wait time of a new job, in milliseconds.
+    pub estimated_wait_ms: u64,
+}
+
 #[derive(Object, Debug)]
 pub struct DomainAddBlockResponse {
     /// Domain to which the block was added.
@@ -154,18 +485,127 @@ pub struct DomainAddBlockResponse {
     pub payload: Vec<u8>,
 }
 
+#[derive(ApiResponse)]
+pub enum LogStreamResponse {
+    /// A stream of newline-delimited JSON log lines.
+    #[oai(status = 200, content_type = "application/x-ndjson")]
+    Ok(Binary<Body>),
+}
+
 #[OpenApi]
 impl Api {
     /// Service stats.
     #[oai(path = "/stats", method = "get")]
-    pub async fn stats(&self) -> poem::Result<Json<Value>> {
+    pub async fn stats(
+        &self,
+        vm: Data<&ServiceVm>,
+        zkvm: Data<&ProverScheduler>,
+    ) -> poem::Result<Json<Value>> {
         const VERSION: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
+        let cache = vm.block_proof_cache_stats();
+        let affinity = zkvm.affinity_stats();
+        let endpoints: Vec<_> = vm
+            .endpoint_health()
+            .into_iter()
+            .map(|h| {
+                json!({
+                    "endpoint": h.endpoint,
+                    "state": h.state.to_string(),
+                    "consecutive_failures": h.consecutive_failures,
+                    "successes": h.successes,
+                    "failures": h.failures,
+                    "rejected": h.rejected,
+                })
+            })
+            .collect();
+
         Ok(Json(json!({
             "version": VERSION,
+            "block_proof_cache": {
+                "hits": cache.hits,
+                "misses": cache.misses,
+            },
+            "prover_affinity": {
+                "hits": affinity.hits,
+                "misses": affinity.misses,
+            },
+            "endpoints": endpoints,
         })))
     }
 
+    /// Stream the service log, as newline-delimited JSON.
+    ///
+    /// Requires the admin token. The lines can be filtered by circuit, request id, and minimum
+    /// level; the stream starts with the matching recent lines.
+    #[oai(path = "/logs/tail", method = "get")]
+    pub async fn tail_log(
+        &self,
+        circuit: Query<Option<String>>,
+        request_id: Query<Option<String>>,
+        level: Query<Option<String>>,
+        #[oai(name = "valence-coprocessor-admin")] admin: Header<Option<String>>,
+        token: Data<&AdminToken>,
+        log: Data<&ServiceLog>,
+    ) -> poem::Result<LogStreamResponse> {
+        if !token.verify(admin.as_deref()) {
+            return Err(PoemError::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let filter = LogFilter::parse(circuit.0, request_id.0, level.as_deref()).map_err(perr)?;
+        let rx = log.subscribe(filter);
+
+        Ok(LogStreamResponse::Ok(Binary(Body::from_bytes_stream(
+            rx.into_stream(),
+        ))))
+    }
+
+    /// Service version and supported capabilities.
+    #[oai(path = "/info", method = "get")]
+    pub async fn info(
+        &self,
+        info: Data<&ServerInfoResponse>,
+        proofs: Data<&ProofStore>,
+        vm: Data<&ServiceVm>,
+    ) -> poem::Result<Json<ServerInfoResponse>> {
+        let mut info = info.clone();
+
+        info.proof_retention = ProofRetentionResponse::from(*proofs);
+        info.max_witnesses = vm.max_witnesses();
+
+        Ok(Json(info))
+    }
+
+    /// Service readiness, asserting the controller runtime can execute a module.
+    #[oai(path = "/ready", method = "get")]
+    pub async fn ready(
+        &self,
+        readiness: Data<&Readiness>,
+        vm: Data<&ServiceVm>,
+        ctx: Data<&Context>,
+    ) -> poem::Result<Json<Value>> {
+        readiness
+            .check(*vm, *ctx)
+            .map_err(|e| PoemError::from_string(e, StatusCode::SERVICE_UNAVAILABLE))?;
+
+        Ok(Json(json!({"ready": true})))
+    }
+
+    /// Proving pool capacity.
+    #[oai(path = "/stats/capacity", method = "get")]
+    pub async fn capacity(
+        &self,
+        stats: Data<&PoolStats>,
+    ) -> poem::Result<Json<PoolCapacityResponse>> {
+        Ok(Json(PoolCapacityResponse {
+            queued: stats.queued(),
+            in_flight: stats.in_flight(),
+            workers: stats.workers(),
+            max_workers: stats.max_workers(),
+            estimated_wait_ms: stats.estimated_wait_ms(),
+        }))
+    }
+
     /// Register a new controller, returning its allocated id.
     #[oai(path = "/registry/controller", method = "post")]
     pub async fn registry_controller(
@@ -176,20 +616,134 @@ impl Api {
         ctx: Data<&Context>,
         request: Json<RegisterControllerRequest>,
     ) -> poem::Result<Json<RegisterControllerResponse>> {
+        let RegisterControllerRequest {
+            controller,
+            circuit,
+            nonce,
+            codec,
+            sample,
+        } = request.0;
+
         let controller = ControllerData {
-            controller: request.controller.to_vec(),
-            circuit: request.circuit.to_vec(),
-            nonce: request.nonce.unwrap_or(0),
+            controller: controller.0,
+            circuit: circuit.0,
+            nonce: nonce.unwrap_or(0),
+            codec: parse_codec(codec.as_deref())?,
         };
 
-        let controller = registry
-            .register_controller(*vm, *zkvm, *ctx, controller)
+        register_controller(&registry, &vm, &zkvm, &ctx, controller, sample).map(Json)
+    }
+
+    /// Starts the deploy of a controller whose circuit is uploaded in chunks, returning the
+    /// session id of the upload.
+    #[oai(path = "/registry/controller/upload", method = "post")]
+    pub async fn registry_controller_upload(
+        &self,
+        uploads: Data<&Uploads>,
+        request: Json<ControllerUploadRequest>,
+    ) -> poem::Result<Json<ControllerUploadResponse>> {
+        let ControllerUploadRequest {
+            controller,
+            size,
+            nonce,
+            codec,
+        } = request.0;
+
+        let codec = parse_codec(codec.as_deref())?;
+        let size = usize::try_from(size).map_err(|_| r400())?;
+        let session = uploads
+            .start(controller.0, nonce.unwrap_or(0), codec, size)
+            .map_err(upload_err)?;
+
+        Ok(Json(ControllerUploadResponse { session }))
+    }
+
+    /// Returns the number of circuit bytes received by an upload, so it can be resumed.
+    #[oai(path = "/registry/controller/upload/:session", method = "get")]
+    pub async fn registry_controller_upload_status(
+        &self,
+        session: Path<String>,
+        uploads: Data<&Uploads>,
+    ) -> poem::Result<Json<ControllerUploadStatusResponse>> {
+        let received = uploads.received(&session).map_err(upload_err)?;
+
+        Ok(Json(ControllerUploadStatusResponse {
+            received: received as u64,
+        }))
+    }
+
+    /// Appends a chunk of the circuit to an upload.
+    ///
+    /// Chunks must be sent in order; bytes already received are skipped, so a failed chunk can be
+    /// retried.
+    #[oai(path = "/registry/controller/upload/:session", method = "post")]
+    pub async fn registry_controller_upload_chunk(
+        &self,
+        session: Path<String>,
+        uploads: Data<&Uploads>,
+        request: Json<ControllerUploadChunkRequest>,
+    ) -> poem::Result<Json<ControllerUploadStatusResponse>> {
+        let offset = usize::try_from(request.offset).map_err(|_| r400())?;
+        let received = uploads
+            .append(&session, offset, &request.data)
+            .map_err(upload_err)?;
+
+        Ok(Json(ControllerUploadStatusResponse {
+            received: received as u64,
+        }))
+    }
+
+    /// Registers the controller of a completed upload, returning its allocated id.
+    #[oai(
+        path = "/registry/controller/upload/:session/finalize",
+        method = "post"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn registry_controller_upload_finalize(
+        &self,
+        session: Path<String>,
+        uploads: Data<&Uploads>,
+        registry: Data<&Registry>,
+        vm: Data<&ServiceVm>,
+        zkvm: Data<&ProverScheduler>,
+        ctx: Data<&Context>,
+        request: Json<ControllerUploadFinalizeRequest>,
+    ) -> poem::Result<Json<RegisterControllerResponse>> {
+        let controller = uploads.finalize(&session).map_err(upload_err)?;
+
+        register_controller(&registry, &vm, &zkvm, &ctx, controller, request.0.sample).map(Json)
+    }
+
+    /// Reports the ELF size of the controller circuit and, for the sample arguments, its cycle
+    /// count.
+    ///
+    /// The cycle count is computed in execute mode, without proving.
+    #[oai(path = "/registry/controller/:controller/report", method = "post")]
+    pub async fn controller_report(
+        &self,
+        controller: Path<String>,
+        vm: Data<&ServiceVm>,
+        zkvm: Data<&ProverScheduler>,
+        ctx: Data<&Context>,
+        request: Json<ControllerReportRequest>,
+    ) -> poem::Result<Json<ControllerReportResponse>> {
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let ctx = ctx.clone().with_controller(controller);
+
+        let elf_size = ctx.get_zkvm().map_err(perr)?.ok_or_else(r404)?.len();
+        let cycles = request
+            .0
+            .sample
+            .map(|sample| cycle_count(&ctx, *vm, *zkvm, sample))
+            .transpose()
             .map_err(perr)?;
-        let controller = RegisterControllerResponse {
-            controller: hex::encode(controller),
-        };
+        let log = ctx.get_log().map_err(perr)?;
 
-        Ok(Json(controller))
+        Ok(Json(ControllerReportResponse {
+            elf_size,
+            cycles,
+            log,
+        }))
     }
 
     /// Register a new domain, returning its allocated id.
@@ -202,10 +756,19 @@ impl Api {
         ctx: Data<&Context>,
         request: Json<RegisterDomainRequest>,
     ) -> poem::Result<Json<RegisterDomainResponse>> {
+        let hasher = request
+            .hasher
+            .as_deref()
+            .map(str::parse::<HasherKind>)
+            .transpose()
+            .map_err(|_| r400())?
+            .unwrap_or_default();
+
         let domain = DomainData {
             name: request.name.clone(),
             controller: request.controller.to_vec(),
             circuit: request.circuit.to_vec(),
+            hasher,
         };
 
         let domain = registry
@@ -259,6 +822,7 @@ impl Api {
         &self,
         controller: Path<String>,
         ctx: Data<&Context>,
+        proofs: Data<&ProofStore>,
         request: Json<ControllerStorageFileRequest>,
     ) -> poem::Result<Json<Value>> {
         let path = request.0.path;
@@ -266,6 +830,9 @@ impl Api {
         tracing::debug!("received file request for path `{path}`...");
 
         let controller = try_str_to_hash(&controller).map_err(perr)?;
+
+        proofs.touch(&controller, &path);
+
         let ctx = ctx.clone().with_controller(controller);
         let data = ctx.get_storage_file(&path).map_err(perr)?;
         let data = data.map(valence_coprocessor::Base64::encode);
@@ -273,6 +840,51 @@ impl Api {
         Ok(Json(json!(data)))
     }
 
+    /// Returns a byte range of a file from the controller storage as raw bytes.
+    ///
+    /// The storage is a single filesystem image, so the file is loaded whole off the async
+    /// runtime; only the requested range is streamed, without copying it.
+    #[oai(
+        path = "/registry/controller/:controller/storage/fs/bytes",
+        method = "post"
+    )]
+    pub async fn get_storage_file_bytes(
+        &self,
+        controller: Path<String>,
+        ctx: Data<&Context>,
+        proofs: Data<&ProofStore>,
+        request: Json<ControllerStorageFileRangeRequest>,
+    ) -> poem::Result<Binary<Body>> {
+        let ControllerStorageFileRangeRequest {
+            path,
+            offset,
+            length,
+        } = request.0;
+
+        tracing::debug!("received file bytes request for path `{path}`...");
+
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+
+        proofs.touch(&controller, &path);
+
+        let ctx = ctx.clone().with_controller(controller);
+        let data = tokio::task::spawn_blocking(move || ctx.get_storage_file(&path))
+            .await
+            .map_err(perr)?
+            .map_err(perr)?
+            .ok_or_else(r404)?;
+
+        let len = data.len() as u64;
+        let offset = offset.unwrap_or(0).min(len);
+        let length = length.unwrap_or(len).min(len - offset);
+
+        let mut data = Cursor::new(data);
+
+        data.set_position(offset);
+
+        Ok(Binary(Body::from_async_read(data.take(length))))
+    }
+
     /// Computes the witnesses for a controller proof.
     #[oai(path = "/registry/controller/:controller/witnesses", method = "post")]
     pub async fn controller_witnesses(
@@ -280,6 +892,7 @@ impl Api {
         controller: Path<String>,
         ctx: Data<&Context>,
         vm: Data<&ServiceVm>,
+        last: Data<&LastWitnesses>,
         request: Json<ControllerProveRequest>,
     ) -> poem::Result<Json<ControllerWitnessesResponse>> {
         let ControllerProveRequest { args, .. } = request.0;
@@ -288,23 +901,53 @@ impl Api {
         let ctx = ctx.clone().with_controller(controller);
         let witnesses = ctx.get_circuit_witnesses(*vm, args).map_err(perr)?;
         let witnesses = ctx.get_coprocessor_witness(witnesses).map_err(perr)?;
+
+        last.record(controller, &witnesses);
+
         let witnesses = serde_json::to_value(witnesses).unwrap_or_default();
         let log = ctx.get_log().unwrap_or_default();
 
         Ok(Json(ControllerWitnessesResponse { witnesses, log }))
     }
 
+    /// Returns the last witness set computed for a controller, if still cached.
+    #[oai(
+        path = "/registry/controller/:controller/witnesses/last",
+        method = "get"
+    )]
+    pub async fn controller_last_witnesses(
+        &self,
+        controller: Path<String>,
+        last: Data<&LastWitnesses>,
+    ) -> poem::Result<Json<Value>> {
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let witnesses = last.get(&controller).ok_or_else(r404)?;
+        let witnesses = serde_json::to_value(witnesses).unwrap_or_default();
+
+        Ok(Json(witnesses))
+    }
+
     /// Computes the controller proof.
     #[oai(path = "/registry/controller/:controller/prove", method = "post")]
+    #[allow(clippy::too_many_arguments)]
     pub async fn controller_prove(
         &self,
         controller: Path<String>,
         pool: Data<&Sender<Job>>,
+        pending: Data<&PendingJobs>,
         vm: Data<&ServiceVm>,
         ctx: Data<&Context>,
+        last: Data<&LastWitnesses>,
+        #[oai(name = "x-request-id")] request_id: Header<Option<String>>,
         request: Json<ControllerProveRequest>,
     ) -> poem::Result<Json<Value>> {
-        let ControllerProveRequest { args, payload } = request.0;
+        let ControllerProveRequest {
+            args,
+            payload,
+            deadline,
+        } = request.0;
+        let fresh = host::is_fresh(&args);
+        let witness_args = args.clone();
 
         let controller = try_str_to_hash(&controller).map_err(perr)?;
         let ctx = ctx.clone().with_controller(controller);
@@ -312,35 +955,106 @@ impl Api {
         let witness = ctx.get_coprocessor_witness(witnesses).map_err(perr)?;
         let owner = ctx.owner().map(|o| o.to_vec());
 
+        last.record(*ctx.controller(), &witness);
+
+        if worker::is_expired(deadline) {
+            tracing::debug!("prove request deadline expired before submission");
+            return Err(r408());
+        }
+
         tracing::debug!("coprocessor witness computed; submitting job...");
 
-        pool.send(Job::Prove {
-            circuit: controller,
-            witness,
-            payload,
-            owner,
-        })
-        .map_err(|e| anyhow::anyhow!("failed to submit prove job: {e}"))
-        .map_err(perr)?;
+        submit_prove(
+            *pool,
+            *pending,
+            Job::Prove {
+                circuit: controller,
+                args: witness_args,
+                witness,
+                payload,
+                owner,
+                deadline,
+                fresh,
+                request_id: request_id.0,
+            },
+        )?;
 
         Ok(Json(json!({"status": "received"})))
     }
 
+    /// Cancels the queued proofs of the controller that store on the provided path.
+    ///
+    /// Returns `cancelled: false` if no proof was waiting for a worker, including when it was
+    /// already picked up or completed. Requires the admin token, or the signature of the submitter
+    /// of every queued proof; the cancelled proofs store a terminal `cancelled` status.
+    #[oai(
+        path = "/registry/controller/:controller/prove/cancel",
+        method = "post"
+    )]
+    pub async fn controller_prove_cancel(
+        &self,
+        controller: Path<String>,
+        pending: Data<&PendingJobs>,
+        ctx: Data<&Context>,
+        #[oai(name = "valence-coprocessor-admin")] admin: Header<Option<String>>,
+        token: Data<&AdminToken>,
+        request: Json<ControllerStorageFileRequest>,
+    ) -> poem::Result<Json<Value>> {
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let admin = token.verify(admin.as_deref());
+
+        let cancelled = match pending.cancel(controller, &request.path, ctx.owner(), admin) {
+            Cancel::Cancelled => true,
+            Cancel::NotQueued => false,
+            Cancel::Forbidden => return Err(PoemError::from_status(StatusCode::FORBIDDEN)),
+        };
+
+        Ok(Json(json!({"cancelled": cancelled})))
+    }
+
     /// Computes the controller proof for the provided co-processor root.
+    ///
+    /// Returns `410 Gone` if the root is no longer available, so the client can retry with the
+    /// latest root.
     #[oai(path = "/registry/controller/:controller/prove/:root", method = "post")]
+    #[allow(clippy::too_many_arguments)]
     pub async fn controller_prove_root(
         &self,
         controller: Path<String>,
         root: Path<String>,
+        historical: Data<&Historical>,
         pool: Data<&Sender<Job>>,
+        pending: Data<&PendingJobs>,
         vm: Data<&ServiceVm>,
         ctx: Data<&Context>,
+        last: Data<&LastWitnesses>,
+        #[oai(name = "x-request-id")] request_id: Header<Option<String>>,
         request: Json<ControllerProveRequest>,
     ) -> poem::Result<Json<Value>> {
-        let ControllerProveRequest { args, payload } = request.0;
+        let ControllerProveRequest {
+            args,
+            payload,
+            deadline,
+        } = request.0;
+        let fresh = host::is_fresh(&args);
+        let witness_args = args.clone();
 
         let controller = try_str_to_hash(&controller).map_err(perr)?;
         let root = try_str_to_hash(&root).map_err(perr)?;
+        let available = root == historical.current()
+            || historical
+                .get_historical_update(&root)
+                .map_err(perr)?
+                .is_some();
+
+        if !available {
+            tracing::debug!(
+                "prove request root `{}` is not available",
+                hex::encode(root)
+            );
+            return Err(r410());
+        }
+
         let ctx = ctx
             .clone()
             .with_controller(controller)
@@ -350,18 +1064,137 @@ impl Api {
         let witness = ctx.get_coprocessor_witness(witnesses).map_err(perr)?;
         let owner = ctx.owner().map(|o| o.to_vec());
 
+        last.record(*ctx.controller(), &witness);
+
+        if worker::is_expired(deadline) {
+            tracing::debug!("prove request deadline expired before submission");
+            return Err(r408());
+        }
+
         tracing::debug!("coprocessor witness computed; submitting job...");
 
-        pool.send(Job::Prove {
+        submit_prove(
+            *pool,
+            *pending,
+            Job::Prove {
+                circuit: controller,
+                args: witness_args,
+                witness,
+                payload,
+                owner,
+                deadline,
+                fresh,
+                request_id: request_id.0,
+            },
+        )?;
+
+        Ok(Json(json!({"status": "received"})))
+    }
+
+    /// Returns the fingerprint of the controller verifying key.
+    #[oai(path = "/registry/controller/:controller/vk/hash", method = "get")]
+    pub async fn controller_vk_hash(
+        &self,
+        controller: Path<String>,
+        ctx: Data<&Context>,
+        zkvm: Data<&ProverScheduler>,
+    ) -> poem::Result<Json<ControllerVkHashResponse>> {
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let ctx = ctx.clone().with_controller(controller);
+
+        let vk = ctx.get_verifying_key(*zkvm).map_err(perr)?;
+        let hash = Blake3Hasher::hash(&vk);
+        let log = ctx.get_log().map_err(perr)?;
+
+        Ok(Json(ControllerVkHashResponse {
+            hash: hex::encode(hash),
+            log,
+        }))
+    }
+
+    /// Replays the proof stored on the provided path, comparing its public inputs.
+    ///
+    /// The witnesses are recomputed with the original arguments against the original root, and
+    /// proven again on the worker pool. Only the signer of the original request, or the admin
+    /// token, may replay a proof. Returns `404 Not Found` if the provenance of the proof wasn't
+    /// recorded, `403 Forbidden` if the request isn't authorized, and `410 Gone` if its root is no
+    /// longer available.
+    #[oai(path = "/registry/controller/:controller/replay", method = "post")]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn controller_replay(
+        &self,
+        controller: Path<String>,
+        historical: Data<&Historical>,
+        pool: Data<&Sender<Job>>,
+        vm: Data<&ServiceVm>,
+        ctx: Data<&Context>,
+        #[oai(name = "valence-coprocessor-admin")] admin: Header<Option<String>>,
+        token: Data<&AdminToken>,
+        request: Json<ControllerStorageFileRequest>,
+    ) -> poem::Result<Json<ControllerReplayResponse>> {
+        let path = request.0.path;
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let provenance = ProofProvenance::load(*historical, &controller, &path)
+            .map_err(perr)?
+            .ok_or_else(r404)?;
+
+        if !token.verify(admin.as_deref()) && !provenance.is_owned_by(ctx.owner()) {
+            return Err(PoemError::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let ProofProvenance {
+            args,
+            root,
+            inputs: original_inputs,
+            commitment: original_commitment,
+            owner,
+        } = provenance;
+
+        let available = root == historical.current()
+            || historical
+                .get_historical_update(&root)
+                .map_err(perr)?
+                .is_some();
+
+        if !available {
+            return Err(r410());
+        }
+
+        tracing::debug!(
+            "replaying proof `{path}` against root `{}`...",
+            hex::encode(root)
+        );
+
+        let ctx = ctx
+            .clone()
+            .with_controller(controller)
+            .with_historical(root);
+
+        let witnesses = ctx.get_circuit_witnesses(*vm, args).map_err(perr)?;
+        let witness = ctx.get_coprocessor_witness(witnesses).map_err(perr)?;
+        let (reply, rx) = flume::bounded(1);
+
+        pool.send(Job::Replay {
             circuit: controller,
             witness,
-            payload,
             owner,
+            reply: Reply(reply),
         })
-        .map_err(|e| anyhow::anyhow!("failed to submit prove job: {e}"))
         .map_err(perr)?;
 
-        Ok(Json(json!({"status": "received"})))
+        let proof = rx.recv_async().await.map_err(perr)?.map_err(perr)?;
+        let commitment = proof.input_commitment().map_err(perr)?;
+        let log = ctx.get_log().map_err(perr)?;
+
+        Ok(Json(ControllerReplayResponse {
+            matches: commitment == original_commitment && proof.inputs == original_inputs,
+            root: hex::encode(root),
+            original_commitment: hex::encode(original_commitment),
+            commitment: hex::encode(commitment),
+            original_inputs,
+            inputs: proof.inputs,
+            log,
+        }))
     }
 
     /// Returns the controller verifying key.
@@ -384,6 +1217,64 @@ impl Api {
         }))
     }
 
+    /// Verifies a batch of proofs of the controller circuit, fetching its verifying key once.
+    ///
+    /// Batches of more than [`ControllerVerifyBatchRequest::MAX_PROOFS`] proofs are rejected, as
+    /// are proofs that aren't valid base64 encoded proofs. The proofs are verified as proofs of the
+    /// mock prover if the scheduler runs mock provers.
+    #[oai(
+        path = "/registry/controller/:controller/verify/batch",
+        method = "post"
+    )]
+    pub async fn controller_verify_batch(
+        &self,
+        controller: Path<String>,
+        ctx: Data<&Context>,
+        zkvm: Data<&ProverScheduler>,
+        request: Json<ControllerVerifyBatchRequest>,
+    ) -> poem::Result<Json<ControllerVerifyBatchResponse>> {
+        let ControllerVerifyBatchRequest { proofs } = request.0;
+
+        if proofs.len() > ControllerVerifyBatchRequest::MAX_PROOFS {
+            return Err(PoemError::from_string(
+                format!(
+                    "at most {} proofs can be verified in a batch",
+                    ControllerVerifyBatchRequest::MAX_PROOFS
+                ),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ));
+        }
+
+        let proofs = proofs
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                Proof::try_from_base64(p).map_err(|e| {
+                    PoemError::from_string(
+                        format!("proof {i} is not a valid encoded proof: {e}"),
+                        StatusCode::BAD_REQUEST,
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let ctx = ctx.clone().with_controller(controller);
+
+        let vk = ctx.get_verifying_key(*zkvm).map_err(perr)?;
+        let mock = zkvm.is_mock();
+
+        let results = tokio::task::spawn_blocking(move || match mock {
+            true => verify_mock_batch(&vk, &proofs),
+            false => verify_groth16_batch(&vk, &proofs),
+        })
+        .await
+        .map_err(perr)?
+        .map_err(perr)?;
+
+        Ok(Json(ControllerVerifyBatchResponse { results }))
+    }
+
     /// Returns the controller circuit bytecode.
     #[oai(path = "/registry/controller/:controller/circuit", method = "get")]
     pub async fn controller_circuit(
@@ -448,6 +1339,29 @@ impl Api {
         Ok(Json(ControllerEntrypointResponse { ret, log }))
     }
 
+    /// Returns the entrypoint arguments schema of the controller.
+    ///
+    /// Returns `404` if the controller doesn't implement the `describe` export.
+    #[oai(path = "/registry/controller/:controller/describe", method = "get")]
+    pub async fn controller_describe(
+        &self,
+        controller: Path<String>,
+        ctx: Data<&Context>,
+        vm: Data<&ServiceVm>,
+    ) -> poem::Result<Json<Value>> {
+        let controller = try_str_to_hash(&controller).map_err(perr)?;
+        let ctx = ctx.clone().with_controller(controller);
+
+        let schema = ctx
+            .describe(*vm)
+            .map_err(|e| match e.is::<host::ExportNotFound>() {
+                true => PoemError::from_string(e.to_string(), StatusCode::NOT_FOUND),
+                false => perr(e),
+            })?;
+
+        Ok(Json(serde_json::to_value(schema).map_err(perr)?))
+    }
+
     /// Get the latest proven block for the domain.
     #[oai(path = "/registry/domain/:domain/latest", method = "get")]
     pub async fn domain_latest(
@@ -478,6 +1392,26 @@ impl Api {
         })))
     }
 
+    /// Get the co-processor root computed when the block was added to the domain.
+    #[oai(path = "/registry/domain/:domain/block/:number/root", method = "get")]
+    pub async fn domain_block_root(
+        &self,
+        domain: Path<String>,
+        number: Path<u64>,
+        historical: Data<&Historical>,
+    ) -> poem::Result<Json<Value>> {
+        let root = historical
+            .get_smt_root_at_block(&domain, *number)
+            .map_err(perr)?
+            .ok_or_else(r404)?;
+
+        Ok(Json(json!({
+            "domain": &*domain,
+            "number": *number,
+            "root": hex::encode(root),
+        })))
+    }
+
     /// Adds a new block to the domain.
     #[oai(path = "/registry/domain/:domain", method = "post")]
     pub async fn domain_add_block(
@@ -558,6 +1492,122 @@ impl Api {
         })))
     }
 
+    /// List the domains that changed between two historical roots.
+    ///
+    /// The changes are sorted by domain id, and paginated: if `next` is set, it is the `after`
+    /// cursor of the next page.
+    #[oai(path = "/historical/diff/:old/:new", method = "get")]
+    pub async fn historical_diff(
+        &self,
+        old: Path<String>,
+        new: Path<String>,
+        after: Query<Option<String>>,
+        limit: Query<Option<usize>>,
+        historical: Data<&Historical>,
+    ) -> poem::Result<Json<Value>> {
+        const MAX_LIMIT: usize = 1000;
+
+        let old = try_str_to_hash(&old).map_err(perr)?;
+        let new = try_str_to_hash(&new).map_err(perr)?;
+        let after = after
+            .as_deref()
+            .map(try_str_to_hash)
+            .transpose()
+            .map_err(perr)?;
+        let limit = limit.unwrap_or(MAX_LIMIT).clamp(1, MAX_LIMIT);
+
+        for root in [&old, &new] {
+            let known = root == &Hash::default()
+                || root == &historical.current()
+                || historical
+                    .get_historical_update(root)
+                    .map_err(perr)?
+                    .is_some();
+
+            if !known {
+                return Err(r404());
+            }
+        }
+
+        let diff = historical
+            .diff_roots(old, new, after.as_ref(), limit)
+            .map_err(perr)?;
+
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|(key, change)| json!({"key": hex::encode(key), "change": change}))
+            .collect();
+
+        Ok(Json(json!({
+            "changes": changes,
+            "next": diff.next.map(hex::encode),
+        })))
+    }
+
+    /// Returns the co-processor root most recently published on the provided chain.
+    ///
+    /// The publication metadata allows a verifier to confirm the root on-chain independently.
+    #[oai(path = "/historical/published/:chain", method = "get")]
+    pub async fn historical_published(
+        &self,
+        chain: Path<String>,
+        historical: Data<&Historical>,
+    ) -> poem::Result<Json<PublishedRootResponse>> {
+        let published = PublishedRoot::load(*historical, &chain)
+            .map_err(perr)?
+            .ok_or_else(r404)?;
+
+        Ok(Json(published.into()))
+    }
+
+    /// Records a co-processor root as published on the provided chain.
+    ///
+    /// Requires the admin token. The root must be known to the co-processor.
+    #[oai(path = "/historical/published/:chain", method = "post")]
+    pub async fn historical_publish(
+        &self,
+        chain: Path<String>,
+        #[oai(name = "valence-coprocessor-admin")] admin: Header<Option<String>>,
+        token: Data<&AdminToken>,
+        historical: Data<&Historical>,
+        request: Json<PublishRootRequest>,
+    ) -> poem::Result<Json<PublishedRootResponse>> {
+        if !token.verify(admin.as_deref()) {
+            return Err(PoemError::from_status(StatusCode::FORBIDDEN));
+        }
+
+        let root = try_str_to_hash(&request.root).map_err(|_| r400())?;
+        let known = root == historical.current()
+            || historical
+                .get_historical_update(&root)
+                .map_err(perr)?
+                .is_some();
+
+        if !known {
+            return Err(r404());
+        }
+
+        let published_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(perr)?
+            .as_secs();
+
+        let request = request.0;
+        let published = PublishedRoot {
+            root,
+            chain: chain.0,
+            tx: request.tx,
+            contract: request.contract,
+            block: request.block,
+            published_at,
+        };
+
+        published.save(*historical).map_err(perr)?;
+
+        Ok(Json(published.into()))
+    }
+
     /// Get the historical proof for the provided domain.
     #[oai(path = "/historical/:domain/:number", method = "get")]
     pub async fn historical_proof(
@@ -729,6 +1779,7 @@ impl Api {
         &self,
         ctx: Data<&Context>,
         vm: Data<&ServiceVm>,
+        last: Data<&LastWitnesses>,
         request: Json<ControllerProveRequest>,
     ) -> poem::Result<Json<ControllerWitnessesResponse>> {
         let ControllerProveRequest { args, .. } = request.0;
@@ -763,6 +1814,8 @@ impl Api {
             Err(e) => return Ok(return_with_log(&ctx, e)),
         };
 
+        last.record(*ctx.controller(), &witnesses);
+
         let witnesses = serde_json::to_value(witnesses).unwrap_or_default();
         let log = ctx.get_log().unwrap_or_default();
 
@@ -771,30 +1824,53 @@ impl Api {
 
     /// Computes the circuit proof.
     #[oai(path = "/circuit/prove", method = "post")]
+    #[allow(clippy::too_many_arguments)]
     pub async fn circuit_prove(
         &self,
         pool: Data<&Sender<Job>>,
+        pending: Data<&PendingJobs>,
         vm: Data<&ServiceVm>,
         ctx: Data<&Context>,
+        last: Data<&LastWitnesses>,
+        #[oai(name = "x-request-id")] request_id: Header<Option<String>>,
         request: Json<ControllerProveRequest>,
     ) -> poem::Result<Json<Value>> {
-        let ControllerProveRequest { args, payload } = request.0;
+        let ControllerProveRequest {
+            args,
+            payload,
+            deadline,
+        } = request.0;
+        let fresh = host::is_fresh(&args);
+        let witness_args = args.clone();
 
         let witnesses = ctx.get_circuit_witnesses(*vm, args).map_err(perr)?;
         let witness = ctx.get_coprocessor_witness(witnesses).map_err(perr)?;
         let owner = ctx.owner().map(|o| o.to_vec());
+
+        last.record(*ctx.controller(), &witness);
         let circuit = *ctx.controller();
 
+        if worker::is_expired(deadline) {
+            tracing::debug!("prove request deadline expired before submission");
+            return Err(r408());
+        }
+
         tracing::debug!("coprocessor witness computed; submitting job...");
 
-        pool.send(Job::Prove {
-            circuit,
-            witness,
-            payload,
-            owner,
-        })
-        .map_err(|e| anyhow::anyhow!("failed to submit prove job: {e}"))
-        .map_err(perr)?;
+        submit_prove(
+            *pool,
+            *pending,
+            Job::Prove {
+                circuit,
+                args: witness_args,
+                witness,
+                payload,
+                owner,
+                deadline,
+                fresh,
+                request_id: request_id.0,
+            },
+        )?;
 
         Ok(Json(json!({"status": "received"})))
     }
@@ -913,6 +1989,98 @@ impl Api {
     }
 }
 
+/// Sends a prove job to the pool, indexing it as pending if it stores its proof.
+fn submit_prove(pool: &Sender<Job>, pending: &PendingJobs, job: Job) -> poem::Result<()> {
+    let stored = match &job {
+        Job::Prove {
+            circuit,
+            payload,
+            owner,
+            ..
+        } => {
+            worker::stored_path(payload.as_ref()).map(|p| (*circuit, p.to_string(), owner.clone()))
+        }
+        Job::Replay { .. } | Job::Quit => None,
+    };
+
+    // indexed before sending, so a worker never picks up an unindexed job
+    if let Some((circuit, path, owner)) = &stored {
+        pending.enqueue(*circuit, path, owner.clone());
+    }
+
+    if let Err(e) = pool.send(job) {
+        if let Some((circuit, path, _)) = &stored {
+            pending.take(*circuit, path);
+        }
+
+        return Err(perr(format!("failed to submit prove job: {e}")));
+    }
+
+    Ok(())
+}
+
+/// Executes the controller circuit for the sample arguments, returning its cycle count.
+fn cycle_count(
+    ctx: &Context,
+    vm: &ServiceVm,
+    zkvm: &ProverScheduler,
+    sample: Value,
+) -> anyhow::Result<u64> {
+    let witnesses = ctx.get_circuit_witnesses(vm, sample)?;
+    let witness = ctx.get_coprocessor_witness(witnesses)?;
+
+    zkvm.cycles(ctx, witness)
+}
+
+fn parse_codec(codec: Option<&str>) -> poem::Result<WitnessEncoding> {
+    Ok(codec
+        .map(str::parse::<WitnessEncoding>)
+        .transpose()
+        .map_err(|_| r400())?
+        .unwrap_or_default())
+}
+
+fn register_controller(
+    registry: &Registry,
+    vm: &ServiceVm,
+    zkvm: &ProverScheduler,
+    ctx: &Context,
+    controller: ControllerData,
+    sample: Option<Value>,
+) -> poem::Result<RegisterControllerResponse> {
+    let elf_size = controller.circuit.len();
+    let controller = registry
+        .register_controller(vm, zkvm, ctx, controller)
+        .map_err(perr)?;
+
+    // the controller is registered; a failed sample execution shouldn't fail the request
+    let cycles = sample.and_then(|sample| {
+        let ctx = ctx.clone().with_controller(controller);
+
+        cycle_count(&ctx, vm, zkvm, sample)
+            .inspect_err(|e| tracing::warn!("failed to execute the sample arguments: {e}"))
+            .ok()
+    });
+
+    Ok(RegisterControllerResponse {
+        controller: hex::encode(controller),
+        elf_size,
+        cycles,
+    })
+}
+
+fn upload_err(err: UploadError) -> PoemError {
+    let status = match err {
+        UploadError::UnknownSession => StatusCode::NOT_FOUND,
+        UploadError::Overflow { .. } => StatusCode::BAD_REQUEST,
+        UploadError::Gap { .. } | UploadError::Incomplete { .. } => StatusCode::CONFLICT,
+        UploadError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        UploadError::TooManySessions { .. } => StatusCode::TOO_MANY_REQUESTS,
+    };
+
+    PoemError::from_string(err.to_string(), status)
+}
+
 fn perr<E: ToString>(err: E) -> PoemError {
     PoemError::from_string(err.to_string(), StatusCode::INTERNAL_SERVER_ERROR)
 }
@@ -925,6 +2093,14 @@ fn r404() -> poem::Error {
     poem::Error::from_status(StatusCode::NOT_FOUND)
 }
 
+fn r408() -> poem::Error {
+    poem::Error::from_status(StatusCode::REQUEST_TIMEOUT)
+}
+
+fn r410() -> poem::Error {
+    poem::Error::from_status(StatusCode::GONE)
+}
+
 fn r500() -> poem::Error {
     poem::Error::from_status(StatusCode::INTERNAL_SERVER_ERROR)
 }
@@ -1,15 +1,25 @@
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use clap::Parser;
 use poem::{listener::TcpListener, EndpointExt as _, Route};
 use poem_openapi::OpenApiService;
 use tracing_subscriber::{fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _, EnvFilter};
-use valence_coprocessor::Registry;
+use valence_coprocessor::{utils, Registry};
 use valence_coprocessor_prover::scheduler::ProverScheduler;
-use valence_coprocessor_redis::RedisBackend;
+use valence_coprocessor_redis::Codec;
 use valence_coprocessor_service::{
-    api::Api, data::ServiceBackend, middleware, worker::Pool, Historical, ServiceVm,
+    api::{Api, ServerInfoResponse},
+    data::BackendKind,
+    health::Readiness,
+    logs::{AdminToken, ServiceLog},
+    middleware,
+    proofs::{ProofStore, RetentionPolicy},
+    upload::Uploads,
+    witnesses::LastWitnesses,
+    worker::Pool,
+    Historical, ServiceVm,
 };
+use valence_coprocessor_wasm::host::{self, breaker::BreakerPolicy};
 
 #[derive(Parser)]
 struct Cli {
@@ -17,49 +27,153 @@ struct Cli {
     #[arg(short, long, value_name = "SOCKET", default_value = "0.0.0.0:37281")]
     bind: SocketAddr,
 
-    /// Socket to the Redis data backend. Fallback to memory data.
+    /// Data backend. Defaults to redis if a socket is provided, and memory otherwise.
+    #[arg(long, env, value_enum, value_name = "BACKEND")]
+    backend: Option<BackendKind>,
+
+    /// Socket to the Redis data backend.
     #[arg(short, long, env, value_name = "REDIS")]
     redis: Option<String>,
 
+    /// Compression of the values written to the Redis data backend, as `none`, `zstd` or `lz4`.
+    ///
+    /// Values are read regardless of the codec they were written with.
+    #[arg(long, env, value_name = "CODEC", default_value_t = Codec::None)]
+    compression: Codec,
+
     /// Socket to the Prover service backend.
     #[arg(short, long, value_name = "PROVER")]
     prover: Option<String>,
 
+    /// Route consecutive proofs of a circuit to the prover that has its proving key warm.
+    #[arg(long, env)]
+    prover_affinity: bool,
+
+    /// The provers are mock provers; their proofs are verified as mock proofs.
+    #[arg(long, env)]
+    prover_mock: bool,
+
     /// Cache capacity
     #[arg(short, long, value_name = "CAPACITY", default_value_t = 100)]
     capacity: usize,
+
+    /// Maximum size, in bytes, of a HTTP response read on behalf of a controller.
+    #[arg(long, env, value_name = "BYTES", default_value_t = utils::HTTP_MAX_RESPONSE_SIZE)]
+    http_max_response: usize,
+
+    /// Maximum timeout, in milliseconds, of a HTTP request made on behalf of a controller.
+    #[arg(long, env, value_name = "MILLISECONDS", default_value_t = utils::HTTP_MAX_TIMEOUT_MS)]
+    http_max_timeout: u64,
+
+    /// Maximum number of witnesses a controller can return.
+    #[arg(long, env, value_name = "COUNT", default_value_t = host::MAX_WITNESSES)]
+    max_witnesses: usize,
+
+    /// Consecutive failures of a RPC endpoint before its calls fail fast. `0` disables it.
+    #[arg(long, env, value_name = "FAILURES", default_value_t = BreakerPolicy::RECOMMENDED.threshold)]
+    rpc_breaker_threshold: u32,
+
+    /// Time, in seconds, calls to a failing RPC endpoint fail fast before it is retried.
+    #[arg(long, env, value_name = "SECONDS", default_value_t = BreakerPolicy::RECOMMENDED.cooldown.as_secs())]
+    rpc_breaker_cooldown: u64,
+
+    /// Backup of a RPC endpoint, as `PRIMARY=BACKUP`. Can be provided multiple times.
+    #[arg(long, value_name = "PRIMARY=BACKUP", value_parser = parse_backup)]
+    rpc_backup: Vec<(String, String)>,
+
+    /// Maximum age, in seconds, of a stored proof result before it is deleted.
+    #[arg(long, env, value_name = "SECONDS")]
+    proof_max_age: Option<u64>,
+
+    /// Maximum number of stored proof results per circuit.
+    #[arg(long, env, value_name = "COUNT")]
+    proof_max_count: Option<usize>,
+
+    /// Maximum total size, in bytes, of the stored proof results.
+    #[arg(long, env, value_name = "BYTES")]
+    proof_max_size: Option<u64>,
+
+    /// Token granting access to the admin endpoints. If unset, they are disabled.
+    #[arg(long, env, value_name = "TOKEN")]
+    admin_token: Option<String>,
+
+    /// Maximum size, in bytes, of a domain block payload, once decoded.
+    #[arg(long, env, value_name = "BYTES", default_value_t = middleware::MAX_BLOCK_PAYLOAD)]
+    max_block_payload: usize,
+
+    /// Maximum size, in bytes, of a circuit uploaded in chunks.
+    #[arg(long, env, value_name = "BYTES", default_value_t = Uploads::MAX_CIRCUIT_SIZE)]
+    max_upload_size: usize,
+
+    /// Maximum number of chunked circuit uploads in progress.
+    #[arg(long, env, value_name = "COUNT", default_value_t = Uploads::MAX_SESSIONS)]
+    max_uploads: usize,
+
+    /// Time, in seconds, a chunked circuit upload is kept without receiving a chunk.
+    #[arg(long, env, value_name = "SECONDS", default_value_t = Uploads::TTL.as_secs())]
+    upload_ttl: u64,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let Cli {
         bind,
+        backend,
         redis,
+        compression,
         prover,
+        prover_affinity,
+        prover_mock,
         capacity,
+        http_max_response,
+        http_max_timeout,
+        max_witnesses,
+        rpc_breaker_threshold,
+        rpc_breaker_cooldown,
+        rpc_backup,
+        proof_max_age,
+        proof_max_count,
+        proof_max_size,
+        admin_token,
+        max_block_payload,
+        max_upload_size,
+        max_uploads,
+        upload_ttl,
     } = Cli::parse();
 
     let filter_layer = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
     let fmt_layer = fmt::layer().with_target(false);
+    let log = ServiceLog::default();
 
     tracing_subscriber::registry()
         .with(filter_layer)
         .with(fmt_layer)
+        .with(log.clone())
         .init();
 
-    let data = match redis {
-        Some(redis) => RedisBackend::open(format!("redis://{redis}/"))?.into(),
-        None => ServiceBackend::Memory(Default::default()),
-    };
+    let backend = BackendKind::select(backend, redis.as_deref());
+    let data = backend.open(redis.as_deref(), compression)?;
 
-    tracing::info!("service backend set to `{}`...", data);
+    tracing::info!("service backend set to `{}`...", backend);
 
     let registry = Registry::from(data.clone());
-    let vm = ServiceVm::new(capacity)?;
+    let vm = ServiceVm::new(capacity)?
+        .with_http_max_response(http_max_response)
+        .with_http_max_timeout(http_max_timeout)
+        .with_max_witnesses(max_witnesses)
+        .with_endpoint_breaker(BreakerPolicy {
+            threshold: rpc_breaker_threshold,
+            cooldown: Duration::from_secs(rpc_breaker_cooldown),
+        });
+    let vm = rpc_backup.iter().fold(vm, |vm, (primary, backup)| {
+        vm.with_endpoint_backup(primary, backup)
+    });
 
     tracing::info!("initiating prover scheduler...");
 
-    let zkvm = ProverScheduler::default();
+    let zkvm = ProverScheduler::default()
+        .with_affinity(prover_affinity)
+        .with_mock(prover_mock);
 
     if let Some(p) = &prover {
         zkvm.push(None, p);
@@ -71,24 +185,58 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::info!("initiating pool...");
 
-    let pool = Pool::new(historical.clone(), vm.clone(), zkvm.clone()).run();
+    let proofs = ProofStore::new(RetentionPolicy {
+        max_age: proof_max_age.map(Duration::from_secs),
+        max_count: proof_max_count,
+        max_size: proof_max_size,
+        ..Default::default()
+    });
+
+    let pool =
+        Pool::new(historical.clone(), vm.clone(), zkvm.clone()).with_proof_store(proofs.clone());
+    let stats = pool.stats();
+    let pending = pool.pending();
+    let pool = pool.run();
 
     tracing::info!("registry loaded...");
 
     let api_service = OpenApiService::new(Api, env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
         .server(format!("http://{}/api", &bind));
 
+    let spec: serde_json::Value = serde_json::from_str(&api_service.spec())?;
+    let endpoints = spec["paths"]
+        .as_object()
+        .map(|p| p.keys().cloned().collect())
+        .unwrap_or_default();
+    let info =
+        ServerInfoResponse::new(endpoints, backend).with_max_block_payload(max_block_payload);
+
     let app = Route::new()
         .nest("/", api_service.swagger_ui())
         .nest("/spec", api_service.spec_endpoint())
         .nest("/spec/yaml", api_service.spec_endpoint_yaml())
         .nest("/api", api_service)
         .around(middleware::context)
+        .around(middleware::block_payload)
         .data(registry)
         .data(vm)
         .data(zkvm)
         .data(historical)
-        .data(pool);
+        .data(pool)
+        .data(pending)
+        .data(stats)
+        .data(proofs)
+        .data(Readiness::default())
+        .data(LastWitnesses::default())
+        .data(
+            Uploads::default()
+                .with_max_size(max_upload_size)
+                .with_max_sessions(max_uploads)
+                .with_ttl(Duration::from_secs(upload_ttl)),
+        )
+        .data(log)
+        .data(AdminToken(admin_token))
+        .data(info);
 
     tracing::info!("API loaded, listening on `{}`...", &bind);
 
@@ -96,3 +244,11 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn parse_backup(s: &str) -> anyhow::Result<(String, String)> {
+    let (primary, backup) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("expected `PRIMARY=BACKUP`"))?;
+
+    Ok((primary.into(), backup.into()))
+}
@@ -1,7 +1,8 @@
 use std::fmt;
 
+use clap::ValueEnum;
 use valence_coprocessor::{DataBackend, MemoryBackend};
-use valence_coprocessor_redis::RedisBackend;
+use valence_coprocessor_redis::{Codec, RedisBackend};
 
 #[derive(Debug, Clone)]
 pub enum ServiceBackend {
@@ -9,6 +10,62 @@ pub enum ServiceBackend {
     Redis(RedisBackend),
 }
 
+/// A data backend selectable at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BackendKind {
+    /// Volatile in-memory backend.
+    Memory,
+    /// Redis backend.
+    Redis,
+}
+
+impl BackendKind {
+    /// Returns the backend name.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::Memory => "memory",
+            BackendKind::Redis => "redis",
+        }
+    }
+
+    /// Selects the backend, inferring it from the connection parameters if not explicit.
+    pub fn select(kind: Option<Self>, redis: Option<&str>) -> Self {
+        match (kind, redis) {
+            (Some(k), _) => k,
+            (None, Some(_)) => BackendKind::Redis,
+            (None, None) => BackendKind::Memory,
+        }
+    }
+
+    /// Opens the backend with its connection parameters, validating them.
+    ///
+    /// The values written to redis are compressed with `compression`; the memory backend doesn't
+    /// compress its values.
+    pub fn open(&self, redis: Option<&str>, compression: Codec) -> anyhow::Result<ServiceBackend> {
+        match (self, redis) {
+            (BackendKind::Memory, Some(_)) => {
+                anyhow::bail!("the memory backend doesn't accept a redis socket")
+            }
+            (BackendKind::Memory, None) if compression != Codec::None => {
+                anyhow::bail!("the memory backend doesn't compress its values")
+            }
+            (BackendKind::Memory, None) => Ok(ServiceBackend::Memory(Default::default())),
+            (BackendKind::Redis, Some(r)) => Ok(RedisBackend::open(format!("redis://{r}/"))?
+                .with_compression(compression)
+                .into()),
+            (BackendKind::Redis, None) => {
+                anyhow::bail!("the redis backend requires a redis socket")
+            }
+        }
+    }
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl Default for ServiceBackend {
     fn default() -> Self {
         Self::Memory(Default::default())
@@ -56,6 +113,44 @@ impl DataBackend for ServiceBackend {
         }
     }
 
+    fn set_many(&self, prefix: &[u8], entries: &[(Vec<u8>, Vec<u8>)]) -> anyhow::Result<()> {
+        match self {
+            ServiceBackend::Memory(b) => b.set_many(prefix, entries),
+            ServiceBackend::Redis(b) => b.set_many(prefix, entries),
+        }
+    }
+
+    fn remove_many(&self, prefix: &[u8], keys: &[Vec<u8>]) -> anyhow::Result<()> {
+        match self {
+            ServiceBackend::Memory(b) => b.remove_many(prefix, keys),
+            ServiceBackend::Redis(b) => b.remove_many(prefix, keys),
+        }
+    }
+
+    fn scan_prefix_limited(
+        &self,
+        prefix: &[u8],
+        max: usize,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        match self {
+            ServiceBackend::Memory(b) => b.scan_prefix_limited(prefix, max),
+            ServiceBackend::Redis(b) => b.scan_prefix_limited(prefix, max),
+        }
+    }
+
+    fn compare_and_swap(
+        &self,
+        prefix: &[u8],
+        key: &[u8],
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> anyhow::Result<bool> {
+        match self {
+            ServiceBackend::Memory(b) => b.compare_and_swap(prefix, key, expected, data),
+            ServiceBackend::Redis(b) => b.compare_and_swap(prefix, key, expected, data),
+        }
+    }
+
     fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
         match self {
             ServiceBackend::Memory(b) => b.get_bulk(prefix, key),
@@ -71,6 +166,16 @@ impl DataBackend for ServiceBackend {
     }
 }
 
+impl ServiceBackend {
+    /// Returns the kind of the backend.
+    pub const fn kind(&self) -> BackendKind {
+        match self {
+            ServiceBackend::Memory(_) => BackendKind::Memory,
+            ServiceBackend::Redis(_) => BackendKind::Redis,
+        }
+    }
+}
+
 impl fmt::Display for ServiceBackend {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
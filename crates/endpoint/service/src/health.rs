@@ -0,0 +1,47 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{Context, ServiceVm};
+
+#[derive(Debug, Clone)]
+struct Probe {
+    at: Instant,
+    result: Result<(), String>,
+}
+
+/// Cached readiness of the service runtime.
+#[derive(Debug, Clone, Default)]
+pub struct Readiness {
+    last: Arc<Mutex<Option<Probe>>>,
+}
+
+impl Readiness {
+    /// Time a probe result is reused before the runtime is probed again.
+    pub const TTL: Duration = Duration::from_secs(10);
+
+    /// Returns the result of the last runtime probe, probing again if it expired.
+    pub fn check(&self, vm: &ServiceVm, ctx: &Context) -> Result<(), String> {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(p) = last.as_ref() {
+            if p.at.elapsed() < Self::TTL {
+                return p.result.clone();
+            }
+        }
+
+        let res = vm.probe(ctx).map_err(|e| e.to_string());
+
+        if let Err(e) = &res {
+            tracing::warn!("runtime readiness probe failed: {e}");
+        }
+
+        last.replace(Probe {
+            at: Instant::now(),
+            result: res.clone(),
+        });
+
+        res
+    }
+}
@@ -1,6 +1,12 @@
 pub mod api;
 pub mod data;
+pub mod health;
+pub mod logs;
 pub mod middleware;
+pub mod proofs;
+pub mod published;
+pub mod upload;
+pub mod witnesses;
 pub mod worker;
 
 use data::ServiceBackend;
@@ -0,0 +1,312 @@
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    str::FromStr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flume::{Receiver, Sender, TrySendError};
+use serde::Serialize;
+use subtle::ConstantTimeEq as _;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// Token required by the admin endpoints.
+///
+/// If no token is configured, the admin endpoints are disabled.
+#[derive(Debug, Clone, Default)]
+pub struct AdminToken(pub Option<String>);
+
+impl AdminToken {
+    /// Returns `true` if the provided token grants admin access.
+    ///
+    /// The tokens are compared in constant time, so the comparison doesn't leak how much of the
+    /// token matched.
+    pub fn verify(&self, token: Option<&str>) -> bool {
+        match (&self.0, token) {
+            (Some(t), Some(p)) => t.as_bytes().ct_eq(p.as_bytes()).into(),
+            _ => false,
+        }
+    }
+}
+
+/// A line of the service log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogLine {
+    /// UNIX timestamp of the event, in milliseconds.
+    pub timestamp: u64,
+
+    /// Level of the event.
+    pub level: String,
+
+    /// Module that emitted the event.
+    pub target: String,
+
+    /// Message of the event.
+    pub message: String,
+
+    /// Circuit in scope of the event, if any.
+    pub circuit: Option<String>,
+
+    /// Request in scope of the event, if any.
+    pub request_id: Option<String>,
+}
+
+/// A filter of the service log lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    /// Only lines in scope of this circuit.
+    pub circuit: Option<String>,
+
+    /// Only lines in scope of this request.
+    pub request_id: Option<String>,
+
+    /// Only lines at least as severe as this level.
+    pub level: Option<Level>,
+}
+
+impl LogFilter {
+    /// Parses a filter from its optional components.
+    pub fn parse(
+        circuit: Option<String>,
+        request_id: Option<String>,
+        level: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let level = level
+            .map(Level::from_str)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("invalid log level: {e}"))?;
+
+        Ok(Self {
+            circuit: circuit.map(|c| c.to_lowercase()),
+            request_id,
+            level,
+        })
+    }
+
+    /// Returns `true` if the line matches the filter.
+    pub fn matches(&self, level: Level, line: &LogLine) -> bool {
+        self.level.is_none_or(|l| level <= l)
+            && self
+                .circuit
+                .as_ref()
+                .is_none_or(|c| line.circuit.as_ref() == Some(c))
+            && self
+                .request_id
+                .as_ref()
+                .is_none_or(|r| line.request_id.as_ref() == Some(r))
+    }
+}
+
+type LogSender = Sender<Result<Vec<u8>, io::Error>>;
+
+#[derive(Debug, Default)]
+struct Inner {
+    backlog: VecDeque<(Level, LogLine)>,
+    subscribers: Vec<(LogFilter, LogSender)>,
+}
+
+/// A tracing layer that streams the service log lines to subscribers, as NDJSON.
+///
+/// The circuit and request of a line are taken from the `circuit` and `request_id` fields of the
+/// event, or of its enclosing spans.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ServiceLog {
+    /// Number of recent lines replayed to a new subscriber.
+    pub const BACKLOG: usize = 256;
+
+    /// Number of lines buffered for a subscriber before new lines are dropped.
+    pub const BUFFER: usize = 1024;
+
+    /// Subscribes to the lines matching the filter, starting with the matching recent lines.
+    pub fn subscribe(&self, filter: LogFilter) -> Receiver<Result<Vec<u8>, io::Error>> {
+        let (tx, rx) = flume::bounded(Self::BUFFER);
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        inner
+            .backlog
+            .iter()
+            .filter(|(level, line)| filter.matches(*level, line))
+            .for_each(|(_, line)| {
+                tx.try_send(Ok(Self::encode(line))).ok();
+            });
+
+        inner.subscribers.push((filter, tx));
+
+        rx
+    }
+
+    fn publish(&self, level: Level, line: LogLine) {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // a slow subscriber misses lines; a closed one is dropped
+        inner.subscribers.retain(|(filter, tx)| {
+            !filter.matches(level, &line)
+                || !matches!(
+                    tx.try_send(Ok(Self::encode(&line))),
+                    Err(TrySendError::Disconnected(_))
+                )
+        });
+
+        if inner.backlog.len() == Self::BACKLOG {
+            inner.backlog.pop_front();
+        }
+
+        inner.backlog.push_back((level, line));
+    }
+
+    fn encode(line: &LogLine) -> Vec<u8> {
+        let mut bytes = serde_json::to_vec(line).unwrap_or_default();
+
+        bytes.push(b'\n');
+        bytes
+    }
+}
+
+#[derive(Debug, Default)]
+struct Fields {
+    message: Option<String>,
+    circuit: Option<String>,
+    request_id: Option<String>,
+}
+
+impl Fields {
+    fn set(&mut self, field: &Field, value: String) {
+        if value.is_empty() {
+            return;
+        }
+
+        match field.name() {
+            "message" => self.message = Some(value),
+            "circuit" => self.circuit = Some(value.to_lowercase()),
+            "request_id" => self.request_id = Some(value),
+            _ => (),
+        }
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, format!("{value:?}"));
+    }
+}
+
+impl<S> Layer<S> for ServiceLog
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+
+        attrs.record(&mut fields);
+
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(fields);
+        }
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<Fields>() {
+                values.record(fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+
+        event.record(&mut fields);
+
+        for span in ctx.event_scope(event).into_iter().flatten() {
+            if let Some(f) = span.extensions().get::<Fields>() {
+                fields.circuit = fields.circuit.or_else(|| f.circuit.clone());
+                fields.request_id = fields.request_id.or_else(|| f.request_id.clone());
+            }
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let metadata = event.metadata();
+        let line = LogLine {
+            timestamp,
+            level: metadata.level().to_string(),
+            target: metadata.target().into(),
+            message: fields.message.unwrap_or_default(),
+            circuit: fields.circuit,
+            request_id: fields.request_id,
+        };
+
+        self.publish(*metadata.level(), line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use super::*;
+
+    fn lines(rx: &Receiver<Result<Vec<u8>, io::Error>>) -> Vec<serde_json::Value> {
+        rx.try_iter()
+            .map(|l| serde_json::from_slice(&l.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn log_lines_are_filtered_by_scope_and_level() {
+        let log = ServiceLog::default();
+        let subscriber = tracing_subscriber::registry().with(log.clone());
+
+        let circuit = log.subscribe(LogFilter::parse(Some("AB".into()), None, None).unwrap());
+        let request = log.subscribe(LogFilter::parse(None, Some("r1".into()), None).unwrap());
+        let warn = log.subscribe(LogFilter::parse(None, None, Some("warn")).unwrap());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("request", request_id = "r1").entered();
+
+            tracing::info!(circuit = "ab", "proving");
+            tracing::warn!(circuit = "cd", "slow prover");
+        });
+
+        let circuit = lines(&circuit);
+
+        assert_eq!(circuit.len(), 1);
+        assert_eq!(circuit[0]["message"], "proving");
+        assert_eq!(circuit[0]["request_id"], "r1");
+
+        assert_eq!(lines(&request).len(), 2);
+
+        let warn = lines(&warn);
+
+        assert_eq!(warn.len(), 1);
+        assert_eq!(warn[0]["circuit"], "cd");
+        assert_eq!(warn[0]["level"], "WARN");
+
+        // recent lines are replayed to new subscribers
+        let replay = log.subscribe(LogFilter::default());
+
+        assert_eq!(lines(&replay).len(), 2);
+
+        assert!(!AdminToken(None).verify(Some("secret")));
+        assert!(!AdminToken(Some("secret".into())).verify(None));
+        assert!(AdminToken(Some("secret".into())).verify(Some("secret")));
+        assert!(!AdminToken(Some("secret".into())).verify(Some("secreT")));
+        assert!(!AdminToken(Some("secret".into())).verify(Some("secret2")));
+    }
+}
@@ -1,12 +1,21 @@
+use std::io::Read as _;
+
 use poem::{
-    http::{Method, StatusCode},
+    http::{header, Method, StatusCode},
     Body, Endpoint, Error, Request,
 };
 use serde_json::Value;
+use tracing::Instrument as _;
 use valence_coprocessor::Hash;
 use valence_crypto_utils::Ecdsa;
 
-use crate::Historical;
+use crate::{api::ServerInfoResponse, Historical};
+
+/// Default maximum size, in bytes, of a domain block payload.
+pub const MAX_BLOCK_PAYLOAD: usize = 4 * 1024 * 1024;
+
+/// Content encoding accepted for the domain block payloads, in addition to the identity.
+pub const BLOCK_ENCODING: &str = "zstd";
 
 pub async fn context<E: Endpoint>(next: E, mut req: Request) -> poem::Result<E::Output> {
     // currently, controller id is optional to prevent breaking changes
@@ -76,11 +85,167 @@ pub async fn context<E: Endpoint>(next: E, mut req: Request) -> poem::Result<E::
 
     ext.insert(ctx);
 
+    // scopes the service log lines of the request
+    let request_id = req.header("x-request-id").unwrap_or_default().to_string();
+    let circuit = match controller == Hash::default() {
+        true => path_controller(req.uri().path()).unwrap_or_default(),
+        false => hex::encode(controller),
+    };
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = request_id.as_str(),
+        circuit = circuit.as_str()
+    );
+
+    next.call(req).instrument(span).await
+}
+
+/// Decodes the domain block payloads, rejecting the ones larger than the advertised limit.
+///
+/// The limit applies to the decoded payload.
+pub async fn block_payload<E: Endpoint>(next: E, mut req: Request) -> poem::Result<E::Output> {
+    if req.method() != Method::POST || !is_block_path(req.uri().path()) {
+        return next.call(req).await;
+    }
+
+    let limit = req
+        .extensions()
+        .get::<ServerInfoResponse>()
+        .map(|i| i.max_block_payload)
+        .unwrap_or(MAX_BLOCK_PAYLOAD);
+
+    let too_large = || {
+        Error::from_string(
+            format!("the block payload exceeds the limit of {limit} bytes"),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        )
+    };
+
+    let encoding = req.header(header::CONTENT_ENCODING).map(str::to_lowercase);
+    let body = req
+        .take_body()
+        .into_bytes_limit(limit)
+        .await
+        .map_err(|_| too_large())?;
+
+    let body = match encoding.as_deref() {
+        None | Some("identity") => body.to_vec(),
+
+        Some(BLOCK_ENCODING) => {
+            let mut decoded = Vec::with_capacity(body.len());
+
+            zstd::stream::read::Decoder::new(body.as_ref())
+                .and_then(|d| d.take(limit as u64 + 1).read_to_end(&mut decoded))
+                .map_err(|e| Error::from_string(e.to_string(), StatusCode::BAD_REQUEST))?;
+
+            if decoded.len() > limit {
+                return Err(too_large());
+            }
+
+            req.headers_mut().remove(header::CONTENT_ENCODING);
+            req.headers_mut().remove(header::CONTENT_LENGTH);
+
+            decoded
+        }
+
+        Some(e) => {
+            return Err(Error::from_string(
+                format!("unsupported block payload encoding `{e}`"),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ))
+        }
+    };
+
+    req.set_body(Body::from_vec(body));
+
     next.call(req).await
 }
 
+fn is_block_path(path: &str) -> bool {
+    let mut segments = path
+        .trim_end_matches('/')
+        .split('/')
+        .skip_while(|s| *s != "registry");
+
+    matches!(
+        (segments.next(), segments.next(), segments.next(), segments.next()),
+        (Some("registry"), Some("domain"), Some(d), None) if !d.is_empty()
+    )
+}
+
+fn path_controller(path: &str) -> Option<String> {
+    path.split('/')
+        .skip_while(|s| *s != "controller")
+        .nth(1)
+        .filter(|c| c.len() == 64)
+        .map(str::to_lowercase)
+}
+
 fn try_str_to_hash(hash: &str) -> poem::Result<Hash> {
     let bytes = hex::decode(hash).map_err(|_| Error::from_status(StatusCode::BAD_REQUEST))?;
 
     Hash::try_from(bytes).map_err(|_| Error::from_status(StatusCode::BAD_REQUEST))
 }
+
+#[cfg(test)]
+mod tests {
+    use poem::{endpoint::make, EndpointExt as _};
+
+    use super::*;
+    use crate::data::BackendKind;
+
+    async fn call(path: &str, encoding: Option<&str>, body: Vec<u8>) -> (StatusCode, Vec<u8>) {
+        let info = ServerInfoResponse::new(vec![], BackendKind::Memory).with_max_block_payload(64);
+        let ep = make(|mut req: Request| async move { req.take_body().into_vec().await.unwrap() })
+            .around(block_payload)
+            .data(info);
+
+        let mut req = Request::builder().method(Method::POST).uri_str(path);
+
+        if let Some(e) = encoding {
+            req = req.header(header::CONTENT_ENCODING, e);
+        }
+
+        match ep.call(req.body(body)).await {
+            Ok(r) => (StatusCode::OK, r),
+            Err(e) => (
+                e.status(),
+                e.into_response().into_body().into_vec().await.unwrap(),
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn block_payloads_are_decoded_and_limited() {
+        let payload = br#"{"number":1}"#.to_vec();
+        let compressed = zstd::encode_all(payload.as_slice(), 0).unwrap();
+
+        let decoded = call("/api/registry/domain/eth", Some("zstd"), compressed).await;
+
+        assert_eq!(decoded, (StatusCode::OK, payload.clone()));
+        assert_eq!(
+            call("/api/registry/domain/eth", None, payload.clone()).await,
+            (StatusCode::OK, payload)
+        );
+
+        // the limit applies to the decoded payload
+        let large = vec![b'a'; 65];
+        let compressed = zstd::encode_all(large.as_slice(), 0).unwrap();
+
+        assert!(compressed.len() < 64);
+
+        let (status, _) = call("/api/registry/domain/eth", Some("zstd"), compressed).await;
+
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let (status, _) = call("/api/registry/domain/eth", Some("br"), vec![]).await;
+
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // other routes are untouched
+        let (status, body) = call("/api/registry/domain", None, large.clone()).await;
+
+        assert_eq!((status, body), (StatusCode::OK, large));
+    }
+}
@@ -0,0 +1,436 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use valence_coprocessor::{DataBackend as _, Hash};
+
+use crate::Historical;
+
+/// Retention limits of the proof results.
+///
+/// A proof result is reaped if it exceeds any of the limits, oldest first. Proofs accessed within
+/// the grace period are never reaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Maximum age of a proof result.
+    pub max_age: Option<Duration>,
+
+    /// Maximum number of proof results per circuit.
+    pub max_count: Option<usize>,
+
+    /// Maximum total size of the proof results, in bytes.
+    pub max_size: Option<u64>,
+
+    /// Time a proof result is protected after being stored or read.
+    pub grace: Duration,
+
+    /// Interval between enforcements of the policy.
+    pub interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: None,
+            max_count: None,
+            max_size: None,
+            grace: Self::DEFAULT_GRACE,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Default protection time of a recently accessed proof result.
+    pub const DEFAULT_GRACE: Duration = Duration::from_secs(300);
+
+    /// Default interval between enforcements of the policy.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Returns `true` if any limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.max_count.is_some() || self.max_size.is_some()
+    }
+}
+
+/// The inputs a stored proof result was computed from, so it can be replayed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofProvenance {
+    /// Arguments of the circuit witnesses.
+    pub args: Value,
+
+    /// Co-processor root the proof was computed against.
+    pub root: Hash,
+
+    /// Base64 public inputs of the proof.
+    pub inputs: String,
+
+    /// Commitment to the public inputs of the proof.
+    pub commitment: Hash,
+
+    /// Signer of the prove request, if signed.
+    #[serde(default)]
+    pub owner: Option<Vec<u8>>,
+}
+
+impl ProofProvenance {
+    /// Prefix of the provenance of the stored proofs, indexed by circuit and path.
+    pub const PREFIX: &[u8] = b"proof-provenance";
+
+    fn key(circuit: &Hash, path: &str) -> Vec<u8> {
+        [circuit.as_slice(), path.as_bytes()].concat()
+    }
+
+    /// Saves the provenance of the proof stored on the provided path of the circuit storage.
+    pub fn save(&self, historical: &Historical, circuit: &Hash, path: &str) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(self)?;
+
+        historical
+            .data()
+            .set(Self::PREFIX, &Self::key(circuit, path), &bytes)?;
+
+        Ok(())
+    }
+
+    /// Loads the provenance of the proof stored on the provided path of the circuit storage.
+    pub fn load(
+        historical: &Historical,
+        circuit: &Hash,
+        path: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        historical
+            .data()
+            .get(Self::PREFIX, &Self::key(circuit, path))?
+            .map(|b| Ok(serde_json::from_slice(&b)?))
+            .transpose()
+    }
+
+    /// Returns `true` if the provided signer submitted the proof, and may replay it.
+    ///
+    /// Proofs of unsigned requests are only replayable with the admin token.
+    pub fn is_owned_by(&self, signer: Option<&[u8]>) -> bool {
+        matches!((self.owner.as_deref(), signer), (Some(o), Some(s)) if o == s)
+    }
+
+    /// Removes the provenance of the proof stored on the provided path of the circuit storage.
+    pub fn remove(historical: &Historical, circuit: &Hash, path: &str) -> anyhow::Result<()> {
+        historical
+            .data()
+            .remove(Self::PREFIX, &Self::key(circuit, path))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    circuit: Hash,
+    path: String,
+    owner: Option<Vec<u8>>,
+    size: u64,
+    stored: Instant,
+    accessed: Instant,
+}
+
+/// Index of the proof results stored by the workers, enforcing a [RetentionPolicy].
+///
+/// Only proofs stored after the service started are tracked; a proof is indexed once its result
+/// is delivered to the controller, so in-flight jobs are never reaped.
+#[derive(Debug, Clone, Default)]
+pub struct ProofStore {
+    policy: RetentionPolicy,
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl ProofStore {
+    /// Creates a new store enforcing the provided policy.
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            policy,
+            entries: Default::default(),
+        }
+    }
+
+    /// Returns the retention policy of the store.
+    pub fn policy(&self) -> &RetentionPolicy {
+        &self.policy
+    }
+
+    /// Records a proof result stored on the provided path of the circuit storage.
+    pub fn record(&self, circuit: Hash, path: &str, owner: Option<Vec<u8>>, size: u64) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+
+        entries.retain(|e| e.circuit != circuit || e.path != path);
+        entries.push(Entry {
+            circuit,
+            path: path.into(),
+            owner,
+            size,
+            stored: now,
+            accessed: now,
+        });
+    }
+
+    /// Marks the proof result on the provided path as recently accessed.
+    pub fn touch(&self, circuit: &Hash, path: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(e) = entries
+            .iter_mut()
+            .find(|e| &e.circuit == circuit && e.path == path)
+        {
+            e.accessed = Instant::now();
+        }
+    }
+
+    /// Returns the number of tracked proof results and their total size, in bytes.
+    pub fn usage(&self) -> (usize, u64) {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        (entries.len(), entries.iter().map(|e| e.size).sum())
+    }
+
+    /// Removes the proof results that exceed the policy from the index, returning them.
+    fn expire(&self, now: Instant) -> Vec<Entry> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let policy = &self.policy;
+
+        entries.sort_by_key(|e| e.stored);
+
+        let mut expired = vec![false; entries.len()];
+        let protected = |e: &Entry| now.saturating_duration_since(e.accessed) < policy.grace;
+
+        if let Some(max) = policy.max_age {
+            for (i, e) in entries.iter().enumerate() {
+                expired[i] |= now.saturating_duration_since(e.stored) > max;
+            }
+        }
+
+        if let Some(max) = policy.max_count {
+            let mut counts: HashMap<Hash, usize> = HashMap::new();
+
+            for (e, _) in entries.iter().zip(&expired).filter(|(_, x)| !**x) {
+                *counts.entry(e.circuit).or_default() += 1;
+            }
+
+            for (i, e) in entries.iter().enumerate() {
+                if expired[i] {
+                    continue;
+                }
+
+                let count = counts.get_mut(&e.circuit).expect("counted above");
+
+                if *count > max {
+                    expired[i] = true;
+                    *count -= 1;
+                }
+            }
+        }
+
+        if let Some(max) = policy.max_size {
+            let mut size: u64 = entries
+                .iter()
+                .zip(&expired)
+                .filter(|(_, x)| !**x)
+                .map(|(e, _)| e.size)
+                .sum();
+
+            for (i, e) in entries.iter().enumerate() {
+                if size <= max {
+                    break;
+                }
+
+                if !expired[i] && !protected(e) {
+                    expired[i] = true;
+                    size -= e.size;
+                }
+            }
+        }
+
+        let mut reaped = Vec::new();
+        let mut i = 0;
+
+        entries.retain(|e| {
+            let reap = expired[i] && !protected(e);
+
+            if reap {
+                reaped.push(e.clone());
+            }
+
+            i += 1;
+            !reap
+        });
+
+        reaped
+    }
+
+    /// Deletes the proof results that exceed the policy, returning the number of reaped proofs.
+    pub fn reap(&self, historical: &Historical) -> usize {
+        let expired = self.expire(Instant::now());
+        let count = expired.len();
+
+        for e in expired {
+            let mut ctx = historical.context(e.circuit);
+
+            if let Some(o) = e.owner {
+                ctx = ctx.with_owner(o);
+            }
+
+            if let Err(err) = ProofProvenance::remove(historical, &e.circuit, &e.path) {
+                tracing::warn!(
+                    "failed to remove the provenance of proof `{}` of circuit `{}`: {err}",
+                    e.path,
+                    hex::encode(e.circuit)
+                );
+            }
+
+            match ctx.remove_storage_file(&e.path) {
+                Ok(_) => tracing::debug!(
+                    "reaped proof `{}` of circuit `{}`",
+                    e.path,
+                    hex::encode(e.circuit)
+                ),
+                Err(err) => tracing::warn!(
+                    "failed to reap proof `{}` of circuit `{}`: {err}",
+                    e.path,
+                    hex::encode(e.circuit)
+                ),
+            }
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_coprocessor::MemoryBackend;
+
+    use super::*;
+
+    fn store(policy: RetentionPolicy) -> ProofStore {
+        ProofStore::new(RetentionPolicy {
+            grace: Duration::ZERO,
+            ..policy
+        })
+    }
+
+    fn paths(entries: &[Entry]) -> Vec<&str> {
+        entries.iter().map(|e| e.path.as_str()).collect()
+    }
+
+    #[test]
+    fn retention_reaps_oldest_over_count_per_circuit() {
+        let store = store(RetentionPolicy {
+            max_count: Some(2),
+            ..Default::default()
+        });
+
+        let a = Hash::default();
+        let b = [1; 32];
+
+        store.record(a, "/a/1.bin", None, 10);
+        store.record(a, "/a/2.bin", None, 10);
+        store.record(b, "/b/1.bin", None, 10);
+        store.record(a, "/a/3.bin", None, 10);
+
+        let reaped = store.expire(Instant::now() + Duration::from_secs(1));
+
+        assert_eq!(paths(&reaped), vec!["/a/1.bin"]);
+        assert_eq!(store.usage(), (3, 30));
+    }
+
+    #[test]
+    fn retention_reaps_over_size_and_spares_recent_accesses() {
+        let store = ProofStore::new(RetentionPolicy {
+            max_size: Some(15),
+            grace: Duration::from_secs(60),
+            ..Default::default()
+        });
+
+        let circuit = Hash::default();
+
+        store.record(circuit, "/1.bin", None, 10);
+        store.record(circuit, "/2.bin", None, 10);
+
+        assert!(store.expire(Instant::now()).is_empty());
+
+        let later = Instant::now() + Duration::from_secs(120);
+        let reaped = store.expire(later);
+
+        assert_eq!(paths(&reaped), vec!["/1.bin"]);
+        assert_eq!(store.usage(), (1, 10));
+    }
+
+    #[test]
+    fn provenance_is_removed_with_the_reaped_proof() {
+        let historical = Historical::load(MemoryBackend::default().into()).unwrap();
+        let store = store(RetentionPolicy {
+            max_count: Some(1),
+            ..Default::default()
+        });
+
+        let circuit = Hash::default();
+        let provenance = ProofProvenance {
+            args: serde_json::json!({"value": 42}),
+            root: [1; 32],
+            inputs: "AQID".into(),
+            commitment: [2; 32],
+            owner: Some(vec![3; 20]),
+        };
+
+        provenance.save(&historical, &circuit, "/1.bin").unwrap();
+
+        let loaded = ProofProvenance::load(&historical, &circuit, "/1.bin").unwrap();
+
+        assert_eq!(loaded, Some(provenance));
+
+        store.record(circuit, "/1.bin", None, 10);
+        store.record(circuit, "/2.bin", None, 10);
+
+        assert_eq!(store.reap(&historical), 1);
+        assert_eq!(
+            ProofProvenance::load(&historical, &circuit, "/1.bin").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn provenance_replay_requires_the_submitter() {
+        let mut provenance = ProofProvenance {
+            args: serde_json::json!({}),
+            root: [1; 32],
+            inputs: String::new(),
+            commitment: [2; 32],
+            owner: Some(vec![3; 20]),
+        };
+
+        assert!(provenance.is_owned_by(Some(&[3; 20])));
+        assert!(!provenance.is_owned_by(Some(&[4; 20])));
+        assert!(!provenance.is_owned_by(None));
+
+        provenance.owner = None;
+
+        assert!(!provenance.is_owned_by(None));
+        assert!(!provenance.is_owned_by(Some(&[3; 20])));
+    }
+
+    #[test]
+    fn provenance_without_owner_still_loads() {
+        let legacy = serde_json::json!({
+            "args": {},
+            "root": Hash::from([1; 32]),
+            "inputs": "",
+            "commitment": Hash::from([2; 32]),
+        });
+
+        let provenance: ProofProvenance = serde_json::from_value(legacy).unwrap();
+
+        assert_eq!(provenance.owner, None);
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use valence_coprocessor::{DataBackend as _, Hash};
+
+use crate::Historical;
+
+/// A co-processor root published on-chain.
+///
+/// Only the most recent publication of each chain is retained.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedRoot {
+    /// The published co-processor root.
+    pub root: Hash,
+
+    /// Chain the root was published to.
+    pub chain: String,
+
+    /// Hash of the publication transaction.
+    pub tx: String,
+
+    /// Address of the contract that holds the root, if any.
+    pub contract: Option<String>,
+
+    /// Block number of the publication transaction, if known.
+    pub block: Option<u64>,
+
+    /// UNIX timestamp, in seconds, of when the publication was recorded.
+    pub published_at: u64,
+}
+
+impl PublishedRoot {
+    /// Prefix of the published roots, indexed by chain.
+    pub const PREFIX: &[u8] = b"published-root";
+
+    /// Records the publication, replacing the previous one of the chain.
+    pub fn save(&self, historical: &Historical) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(self)?;
+
+        historical
+            .data()
+            .set(Self::PREFIX, self.chain.as_bytes(), &bytes)?;
+
+        Ok(())
+    }
+
+    /// Loads the most recent publication of the chain.
+    pub fn load(historical: &Historical, chain: &str) -> anyhow::Result<Option<Self>> {
+        historical
+            .data()
+            .get(Self::PREFIX, chain.as_bytes())?
+            .map(|b| Ok(serde_json::from_slice(&b)?))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use valence_coprocessor::MemoryBackend;
+
+    use super::*;
+
+    #[test]
+    fn the_latest_publication_is_kept_per_chain() {
+        let historical = Historical::load(MemoryBackend::default().into()).unwrap();
+        let published = |root: Hash, chain: &str, block| PublishedRoot {
+            root,
+            chain: chain.into(),
+            tx: format!("0x{:02x}", root[0]),
+            contract: Some("0xc0ffee".into()),
+            block: Some(block),
+            published_at: block,
+        };
+
+        assert_eq!(PublishedRoot::load(&historical, "ethereum").unwrap(), None);
+
+        let first = published([1; 32], "ethereum", 10);
+        let second = published([2; 32], "ethereum", 11);
+        let other = published([3; 32], "neutron", 12);
+
+        first.save(&historical).unwrap();
+        second.save(&historical).unwrap();
+        other.save(&historical).unwrap();
+
+        assert_eq!(
+            PublishedRoot::load(&historical, "ethereum").unwrap(),
+            Some(second)
+        );
+        assert_eq!(
+            PublishedRoot::load(&historical, "neutron").unwrap(),
+            Some(other)
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use valence_coprocessor::{ControllerData, WitnessEncoding};
+
+/// A controller deploy whose circuit is uploaded in chunks.
+#[derive(Debug, Clone)]
+struct Upload {
+    at: Instant,
+    controller: Vec<u8>,
+    nonce: u64,
+    codec: WitnessEncoding,
+    size: usize,
+    circuit: Vec<u8>,
+}
+
+/// A failure to append a chunk to, or finalize, an upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadError {
+    /// No upload in progress under the session id.
+    UnknownSession,
+
+    /// The chunk starts after the received bytes, so a previous chunk is missing.
+    Gap { received: usize },
+
+    /// The chunk exceeds the declared size of the circuit.
+    Overflow { size: usize },
+
+    /// The circuit isn't fully received.
+    Incomplete { received: usize, size: usize },
+
+    /// The declared size of the circuit exceeds the maximum.
+    TooLarge { size: usize, max: usize },
+
+    /// The maximum number of uploads in progress is reached.
+    TooManySessions { max: usize },
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSession => write!(f, "unknown or expired upload session"),
+            Self::Gap { received } => write!(f, "expected a chunk at offset {received}"),
+            Self::Overflow { size } => write!(f, "the chunk exceeds the circuit size of {size}"),
+            Self::Incomplete { received, size } => {
+                write!(f, "received {received} of {size} bytes of the circuit")
+            }
+            Self::TooLarge { size, max } => {
+                write!(f, "the circuit size of {size} exceeds the maximum of {max}")
+            }
+            Self::TooManySessions { max } => {
+                write!(f, "the maximum of {max} uploads in progress is reached")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Controller deploys in progress, uploaded in chunks.
+///
+/// Sessions are discarded after their time to live, [`Uploads::TTL`] by default, without a
+/// received chunk.
+#[derive(Debug, Clone)]
+pub struct Uploads {
+    sessions: Arc<Mutex<HashMap<String, Upload>>>,
+    max_size: usize,
+    max_sessions: usize,
+    ttl: Duration,
+}
+
+impl Default for Uploads {
+    fn default() -> Self {
+        Self {
+            sessions: Default::default(),
+            max_size: Self::MAX_CIRCUIT_SIZE,
+            max_sessions: Self::MAX_SESSIONS,
+            ttl: Self::TTL,
+        }
+    }
+}
+
+impl Uploads {
+    /// Default time an upload is kept without receiving a chunk.
+    pub const TTL: Duration = Duration::from_secs(600);
+
+    /// Default maximum size, in bytes, of an uploaded circuit.
+    pub const MAX_CIRCUIT_SIZE: usize = 64 * 1024 * 1024;
+
+    /// Default maximum number of uploads in progress.
+    pub const MAX_SESSIONS: usize = 4;
+
+    /// Sets the maximum size, in bytes, of an uploaded circuit.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sets the maximum number of uploads in progress.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    /// Sets the time an upload is kept without receiving a chunk.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Starts the upload of a circuit with the declared size, returning its session id.
+    ///
+    /// The declared size is bounded by the maximum circuit size, and the circuit buffer grows as
+    /// the chunks are received rather than being allocated upfront. The session id is random, as
+    /// it is the only credential of the upload.
+    pub fn start(
+        &self,
+        controller: Vec<u8>,
+        nonce: u64,
+        codec: WitnessEncoding,
+        size: usize,
+    ) -> Result<String, UploadError> {
+        if size > self.max_size {
+            return Err(UploadError::TooLarge {
+                size,
+                max: self.max_size,
+            });
+        }
+
+        let session = hex::encode(rand::random::<[u8; 16]>());
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+
+        sessions.retain(|_, u| u.at.elapsed() < self.ttl);
+
+        if sessions.len() >= self.max_sessions {
+            return Err(UploadError::TooManySessions {
+                max: self.max_sessions,
+            });
+        }
+
+        sessions.insert(
+            session.clone(),
+            Upload {
+                at: Instant::now(),
+                controller,
+                nonce,
+                codec,
+                size,
+                circuit: Vec::new(),
+            },
+        );
+
+        Ok(session)
+    }
+
+    /// Returns the number of circuit bytes received by the session.
+    pub fn received(&self, session: &str) -> Result<usize, UploadError> {
+        let sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+
+        sessions
+            .get(session)
+            .filter(|u| u.at.elapsed() < self.ttl)
+            .map(|u| u.circuit.len())
+            .ok_or(UploadError::UnknownSession)
+    }
+
+    /// Appends a chunk of the circuit starting at `offset`, returning the received length.
+    ///
+    /// Bytes already received are skipped, so a chunk can be retried.
+    pub fn append(&self, session: &str, offset: usize, data: &[u8]) -> Result<usize, UploadError> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let upload = sessions
+            .get_mut(session)
+            .filter(|u| u.at.elapsed() < self.ttl)
+            .ok_or(UploadError::UnknownSession)?;
+
+        let received = upload.circuit.len();
+
+        if offset > received {
+            return Err(UploadError::Gap { received });
+        }
+
+        if offset.saturating_add(data.len()) > upload.size {
+            return Err(UploadError::Overflow { size: upload.size });
+        }
+
+        let skip = (received - offset).min(data.len());
+
+        upload.circuit.extend_from_slice(&data[skip..]);
+        upload.at = Instant::now();
+
+        Ok(upload.circuit.len())
+    }
+
+    /// Completes the upload, returning the controller to be registered.
+    ///
+    /// The session is kept if the circuit isn't fully received.
+    pub fn finalize(&self, session: &str) -> Result<ControllerData, UploadError> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        let upload = sessions
+            .get(session)
+            .filter(|u| u.at.elapsed() < self.ttl)
+            .ok_or(UploadError::UnknownSession)?;
+
+        if upload.circuit.len() != upload.size {
+            return Err(UploadError::Incomplete {
+                received: upload.circuit.len(),
+                size: upload.size,
+            });
+        }
+
+        let upload = sessions
+            .remove(session)
+            .ok_or(UploadError::UnknownSession)?;
+
+        Ok(ControllerData {
+            controller: upload.controller,
+            circuit: upload.circuit,
+            nonce: upload.nonce,
+            codec: upload.codec,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_failed_chunk_is_retried_without_the_earlier_ones() {
+        let uploads = Uploads::default();
+        let circuit: Vec<u8> = (0..10).collect();
+        let session = uploads
+            .start(b"controller".to_vec(), 3, WitnessEncoding::Raw, 10)
+            .unwrap();
+
+        assert_eq!(uploads.append(&session, 0, &circuit[..4]), Ok(4));
+        assert_eq!(
+            uploads.append(&session, 8, &circuit[8..]),
+            Err(UploadError::Gap { received: 4 })
+        );
+        assert_eq!(
+            uploads.finalize(&session).unwrap_err(),
+            UploadError::Incomplete {
+                received: 4,
+                size: 10
+            }
+        );
+
+        // a retried chunk overlapping the received bytes only appends the missing ones
+        assert_eq!(uploads.received(&session), Ok(4));
+        assert_eq!(uploads.append(&session, 0, &circuit[..8]), Ok(8));
+        assert_eq!(
+            uploads.append(&session, 8, &[0; 3]),
+            Err(UploadError::Overflow { size: 10 })
+        );
+        assert_eq!(uploads.append(&session, 8, &circuit[8..]), Ok(10));
+
+        let controller = uploads.finalize(&session).unwrap();
+
+        assert_eq!(controller.controller, b"controller");
+        assert_eq!(controller.circuit, circuit);
+        assert_eq!(controller.nonce, 3);
+        assert_eq!(uploads.received(&session), Err(UploadError::UnknownSession));
+    }
+
+    #[test]
+    fn oversized_circuits_and_excess_sessions_are_rejected() {
+        let uploads = Uploads::default().with_max_size(10).with_max_sessions(2);
+        let start = |size| uploads.start(b"controller".to_vec(), 0, WitnessEncoding::Raw, size);
+
+        assert_eq!(
+            start(usize::MAX),
+            Err(UploadError::TooLarge {
+                size: usize::MAX,
+                max: 10
+            })
+        );
+
+        let session = start(10).unwrap();
+
+        start(4).unwrap();
+
+        assert_eq!(start(4), Err(UploadError::TooManySessions { max: 2 }));
+
+        uploads.append(&session, 0, &[0; 10]).unwrap();
+        uploads.finalize(&session).unwrap();
+
+        assert!(start(4).is_ok());
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let uploads = Uploads::default().with_ttl(Duration::ZERO);
+        let session = uploads
+            .start(b"controller".to_vec(), 0, WitnessEncoding::Raw, 4)
+            .unwrap();
+
+        assert_eq!(uploads.received(&session), Err(UploadError::UnknownSession));
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use valence_coprocessor::{Hash, WitnessCoprocessor};
+
+#[derive(Debug, Clone)]
+struct Entry {
+    at: Instant,
+    witness: WitnessCoprocessor,
+}
+
+/// Most recent witness set emitted per controller, kept for debugging.
+#[derive(Debug, Clone, Default)]
+pub struct LastWitnesses {
+    entries: Arc<Mutex<HashMap<Hash, Entry>>>,
+}
+
+impl LastWitnesses {
+    /// Time a witness set is kept before being discarded.
+    pub const TTL: Duration = Duration::from_secs(300);
+
+    /// Records the witness set emitted for the controller, discarding expired entries.
+    pub fn record(&self, controller: Hash, witness: &WitnessCoprocessor) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries.retain(|_, e| e.at.elapsed() < Self::TTL);
+        entries.insert(
+            controller,
+            Entry {
+                at: Instant::now(),
+                witness: witness.clone(),
+            },
+        );
+    }
+
+    /// Returns the last witness set emitted for the controller, if not expired.
+    pub fn get(&self, controller: &Hash) -> Option<WitnessCoprocessor> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        entries
+            .get(controller)
+            .filter(|e| e.at.elapsed() < Self::TTL)
+            .map(|e| e.witness.clone())
+    }
+}
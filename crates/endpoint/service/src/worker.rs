@@ -1,28 +1,273 @@
-use std::{thread, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use flume::{Receiver, Sender};
 use serde_json::{json, Value};
-use valence_coprocessor::{Hash, WitnessCoprocessor, ZkVm as _};
+use valence_coprocessor::{Hash, Hasher as _, Proof, WitnessCoprocessor, ZkVm as _};
 use valence_coprocessor_prover::scheduler::ProverScheduler;
+use valence_coprocessor_sp1::Sp1Hasher;
 
-use crate::{Historical, ServiceVm};
+use crate::{
+    proofs::{ProofProvenance, ProofStore},
+    Historical, ServiceVm,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Job {
     Prove {
         circuit: Hash,
+        args: Value,
         witness: WitnessCoprocessor,
         payload: Option<Value>,
         owner: Option<Vec<u8>>,
+        deadline: Option<u64>,
+        fresh: bool,
+        request_id: Option<String>,
+    },
+    Replay {
+        circuit: Hash,
+        witness: WitnessCoprocessor,
+        owner: Option<Vec<u8>>,
+        reply: Reply,
     },
     Quit,
 }
 
+/// Channel a worker answers a replay job on.
+#[derive(Debug, Clone)]
+pub struct Reply(pub Sender<Result<Proof, String>>);
+
+impl PartialEq for Reply {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.same_channel(&other.0)
+    }
+}
+
+impl Eq for Reply {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Ack {
     Kill,
 }
 
+/// Shared counters of the pool workload.
+#[derive(Debug, Clone)]
+pub struct PoolStats {
+    tx: Sender<Job>,
+    max_workers: usize,
+    in_flight: Arc<AtomicUsize>,
+    average_ms: Arc<AtomicU64>,
+}
+
+impl PoolStats {
+    /// Number of jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.tx.len()
+    }
+
+    /// Number of spawned workers.
+    pub fn workers(&self) -> usize {
+        self.tx.receiver_count().saturating_sub(1)
+    }
+
+    /// Maximum number of concurrent workers.
+    pub fn max_workers(&self) -> usize {
+        self.max_workers
+    }
+
+    /// Number of jobs currently being proven.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Moving average of the proving time, in milliseconds.
+    pub fn average_ms(&self) -> u64 {
+        self.average_ms.load(Ordering::Relaxed)
+    }
+
+    /// Rough estimate of the time, in milliseconds, a new job waits before being proven.
+    pub fn estimated_wait_ms(&self) -> u64 {
+        let workers = self.workers().max(1) as u64;
+        let pending = (self.queued() + self.in_flight()) as u64;
+
+        pending.saturating_mul(self.average_ms()) / workers
+    }
+
+    fn record(&self, elapsed: Duration) {
+        let elapsed = elapsed.as_millis() as u64;
+        let average = self.average_ms();
+        let average = match average {
+            0 => elapsed,
+            a => (a * 7 + elapsed) / 8,
+        };
+
+        self.average_ms.store(average, Ordering::Relaxed);
+    }
+}
+
+/// Registry of in-flight proofs, so identical concurrent jobs share a single computation.
+#[derive(Debug, Clone, Default)]
+pub struct Coalescer {
+    jobs: Arc<Mutex<HashMap<Hash, Vec<Option<Value>>>>>,
+}
+
+impl Coalescer {
+    /// Computes the key of a job from its canonical request.
+    ///
+    /// Fails if the witness can't be serialized, so unrelated jobs never share a key.
+    pub fn key(
+        circuit: &Hash,
+        witness: &WitnessCoprocessor,
+        owner: Option<&[u8]>,
+    ) -> anyhow::Result<Hash> {
+        let witness = serde_json::to_vec(witness)?;
+        let owner = owner.unwrap_or_default();
+
+        Ok(Sp1Hasher::digest([circuit.as_slice(), owner, &witness]))
+    }
+
+    /// Runs `prove` unless a job with the same key is already in flight.
+    ///
+    /// If the job is in flight, its payload is attached to it and `None` is returned. Otherwise,
+    /// returns the result of `prove` with the payloads of every coalesced request, including the
+    /// provided one.
+    pub fn run<T, F>(
+        &self,
+        key: Hash,
+        payload: Option<Value>,
+        prove: F,
+    ) -> Option<(T, Vec<Option<Value>>)>
+    where
+        F: FnOnce() -> T,
+    {
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+
+            if let Some(waiters) = jobs.get_mut(&key) {
+                waiters.push(payload);
+                return None;
+            }
+
+            jobs.insert(key, Vec::new());
+        }
+
+        let res = prove();
+
+        let mut payloads = self
+            .jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&key)
+            .unwrap_or_default();
+
+        payloads.insert(0, payload);
+
+        Some((res, payloads))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Pending {
+    /// Owners of the queued jobs, in submission order.
+    owners: VecDeque<Option<Vec<u8>>>,
+    cancelled: usize,
+}
+
+/// Outcome of a cancel request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancel {
+    /// The queued jobs of the path were cancelled.
+    Cancelled,
+
+    /// No job of the path was waiting for a worker.
+    NotQueued,
+
+    /// The caller didn't submit every queued job of the path.
+    Forbidden,
+}
+
+/// Proof jobs waiting for a worker, indexed by circuit and storage path so they can be cancelled.
+///
+/// A job leaves the index once a worker picks it up; from then on, it can no longer be cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct PendingJobs {
+    jobs: Arc<Mutex<HashMap<(Hash, String), Pending>>>,
+}
+
+impl PendingJobs {
+    /// Records a job of `owner` queued to store its proof on the path of the circuit storage.
+    pub fn enqueue(&self, circuit: Hash, path: &str, owner: Option<Vec<u8>>) {
+        self.jobs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((circuit, path.into()))
+            .or_default()
+            .owners
+            .push_back(owner);
+    }
+
+    /// Cancels the queued jobs of the path on behalf of `signer`.
+    ///
+    /// Only the admin, or the signer of every queued job, may cancel; unsigned jobs can only be
+    /// cancelled by the admin. Jobs already picked up by a worker, or completed, are not queued.
+    pub fn cancel(&self, circuit: Hash, path: &str, signer: Option<&[u8]>, admin: bool) -> Cancel {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let p = match jobs.get_mut(&(circuit, path.into())) {
+            Some(p) if p.owners.len() > p.cancelled => p,
+            _ => return Cancel::NotQueued,
+        };
+
+        let owned = signer.is_some() && p.owners.iter().all(|o| o.as_deref() == signer);
+
+        if !admin && !owned {
+            return Cancel::Forbidden;
+        }
+
+        p.cancelled = p.owners.len();
+
+        Cancel::Cancelled
+    }
+
+    /// Removes a job picked up by a worker, returning `true` if it was cancelled.
+    pub fn take(&self, circuit: Hash, path: &str) -> bool {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (circuit, path.to_string());
+
+        let p = match jobs.get_mut(&key) {
+            Some(p) => p,
+            None => return false,
+        };
+
+        p.owners.pop_front();
+
+        let cancelled = p.cancelled > 0;
+
+        p.cancelled = p.cancelled.saturating_sub(1);
+
+        if p.owners.is_empty() {
+            jobs.remove(&key);
+        }
+
+        cancelled
+    }
+}
+
+/// Returns the storage path of a `store` payload.
+pub fn stored_path(payload: Option<&Value>) -> Option<&str> {
+    payload
+        .filter(|p| p["cmd"] == "store")
+        .and_then(|p| p["path"].as_str())
+}
+
 pub struct Pool {
     tx: Sender<Job>,
     rx: Receiver<Job>,
@@ -37,6 +282,11 @@ pub struct Pool {
     historical: Historical,
     vm: ServiceVm,
     zkvm: ProverScheduler,
+    in_flight: Arc<AtomicUsize>,
+    average_ms: Arc<AtomicU64>,
+    coalescer: Coalescer,
+    proofs: ProofStore,
+    pending: PendingJobs,
 }
 
 impl Pool {
@@ -58,6 +308,11 @@ impl Pool {
             historical,
             vm,
             zkvm,
+            in_flight: Default::default(),
+            average_ms: Default::default(),
+            coalescer: Default::default(),
+            proofs: Default::default(),
+            pending: Default::default(),
         }
     }
 
@@ -86,11 +341,32 @@ impl Pool {
         self
     }
 
+    /// Sets the store that indexes the proof results, enforcing its retention policy.
+    pub fn with_proof_store(mut self, proofs: ProofStore) -> Self {
+        self.proofs = proofs;
+        self
+    }
+
     pub fn run(mut self) -> Sender<Job> {
         let tx = self.tx.clone();
 
         self.scale();
 
+        if self.proofs.policy().is_enabled() {
+            let proofs = self.proofs.clone();
+            let historical = self.historical.clone();
+
+            thread::spawn(move || loop {
+                thread::sleep(proofs.policy().interval);
+
+                let reaped = proofs.reap(&historical);
+
+                if reaped > 0 {
+                    tracing::info!("reaped {reaped} proof results");
+                }
+            });
+        }
+
         thread::spawn(move || {
             thread::sleep(self.frequency);
 
@@ -108,6 +384,20 @@ impl Pool {
         self.tx.len()
     }
 
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            tx: self.tx.clone(),
+            max_workers: self.max_workers,
+            in_flight: self.in_flight.clone(),
+            average_ms: self.average_ms.clone(),
+        }
+    }
+
+    /// Returns the index of the jobs waiting for a worker.
+    pub fn pending(&self) -> PendingJobs {
+        self.pending.clone()
+    }
+
     pub fn scale(&mut self) {
         tracing::debug!("scaling workers...");
 
@@ -164,6 +454,10 @@ impl Pool {
             zkvm: self.zkvm.clone(),
             rx: self.rx.clone(),
             tx: self.ack_tx.clone(),
+            stats: self.stats(),
+            coalescer: self.coalescer.clone(),
+            proofs: self.proofs.clone(),
+            pending: self.pending.clone(),
         }
     }
 }
@@ -175,25 +469,101 @@ pub struct Worker {
     zkvm: ProverScheduler,
     rx: Receiver<Job>,
     tx: Sender<Ack>,
+    stats: PoolStats,
+    coalescer: Coalescer,
+    proofs: ProofStore,
+    pending: PendingJobs,
 }
 
 impl Worker {
+    #[allow(clippy::too_many_arguments)]
     pub fn prove(
         &self,
         controller: Hash,
+        witness_args: Value,
         witness: WitnessCoprocessor,
         payload: Option<Value>,
         owner: Option<Vec<u8>>,
+        deadline: Option<u64>,
+        fresh: bool,
+        request_id: Option<String>,
     ) {
+        let _span = prove_span(&controller, request_id.as_deref()).entered();
+
         tracing::debug!("worker recv: {}", hex::encode(controller));
 
+        if let Some(path) = stored_path(payload.as_ref()) {
+            if self.pending.take(controller, path) {
+                tracing::debug!(
+                    "abandoning proof `{path}` for `{}`; cancelled",
+                    hex::encode(controller)
+                );
+
+                self.abandon(controller, payload, owner, Abandoned::Cancelled);
+
+                return;
+            }
+        }
+
+        if is_expired(deadline) {
+            tracing::debug!(
+                "abandoning proof for `{}`; deadline expired",
+                hex::encode(controller)
+            );
+
+            self.abandon(controller, payload, owner, Abandoned::Expired);
+
+            return;
+        }
+
+        let key = match Coalescer::key(&controller, &witness, owner.as_deref()) {
+            Ok(k) => Some(k),
+            Err(e) => {
+                tracing::warn!(
+                    "failed to compute the job key for `{}`; proving without coalescing: {e}",
+                    hex::encode(controller)
+                );
+
+                None
+            }
+        };
+        let root = witness.root;
         let mut ctx = self.historical.context(controller);
 
-        if let Some(o) = owner {
+        if let Some(o) = owner.clone() {
             ctx = ctx.with_owner(o);
         }
 
-        let res = self.zkvm.prove(&ctx, witness);
+        let prove = || {
+            let start = Instant::now();
+
+            self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+            let res = self.zkvm.prove(&ctx, witness);
+
+            self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+            self.stats.record(start.elapsed());
+
+            res
+        };
+
+        // a fresh run is never coalesced with an in-flight job
+        let res = match key {
+            Some(k) if !fresh => self.coalescer.run(k, payload, prove),
+            _ => Some((prove(), vec![payload])),
+        };
+
+        let (res, payloads) = match res {
+            Some(r) => r,
+            None => {
+                tracing::debug!(
+                    "identical proof for `{}` in flight; request coalesced",
+                    hex::encode(controller)
+                );
+
+                return;
+            }
+        };
 
         tracing::debug!(
             "worker received proof: {}, {}",
@@ -202,30 +572,118 @@ impl Worker {
         );
 
         let log = ctx.get_log().unwrap_or_default();
-        let mut args = json!({
-            "success": res.is_ok(),
-            "log": log,
-            "payload": payload,
-        });
+        let proof =
+            match res.and_then(|p| Ok((p.input_commitment()?, p.inputs.clone(), p.to_base64()))) {
+                Ok(p) => Some(p),
+                Err(e) => {
+                    tracing::debug!("error on computed proof: {e}");
+                    None
+                }
+            };
+
+        for payload in payloads {
+            let mut args = json!({
+                "success": proof.is_some(),
+                "log": log,
+                "payload": payload,
+            });
+
+            if let Some((commitment, _, p)) = &proof {
+                args["proof"] = p.as_str().into();
+                args["commitment"] = hex::encode(commitment).into();
+            }
+
+            tracing::debug!(
+                "proof received from worker; submitting to `{}`...",
+                serde_json::to_string(&args).unwrap_or_default()
+            );
 
-        match res {
-            Ok(p) => args["proof"] = p.to_base64().into(),
-            Err(e) => tracing::debug!("error on computed proof: {e}"),
+            let res = ctx.entrypoint(&self.vm, args.clone());
+
+            let path = stored_path(payload.as_ref());
+
+            if let (Some(path), Some((commitment, inputs, p)), Ok(_)) = (path, &proof, &res) {
+                self.proofs
+                    .record(controller, path, owner.clone(), p.len() as u64);
+
+                let provenance = ProofProvenance {
+                    args: witness_args.clone(),
+                    root,
+                    inputs: inputs.clone(),
+                    commitment: *commitment,
+                    owner: owner.clone(),
+                };
+
+                if let Err(e) = provenance.save(&self.historical, &controller, path) {
+                    tracing::warn!("failed to save the provenance of proof `{path}`: {e}");
+                }
+            }
+
+            match res {
+                Ok(res) => tracing::debug!("response received from controller: {res:?}"),
+                Err(e) => tracing::debug!(
+                    "failed to call controller `{}` entrypoint with args `{args:?}: {e}`",
+                    hex::encode(controller)
+                ),
+            }
         }
+    }
 
-        tracing::debug!(
-            "proof received from worker; submitting to `{}`...",
-            serde_json::to_string(&args).unwrap_or_default()
-        );
+    /// Submits the terminal status of a job abandoned before proving to the controller, so
+    /// pollers of its path resolve.
+    fn abandon(
+        &self,
+        controller: Hash,
+        payload: Option<Value>,
+        owner: Option<Vec<u8>>,
+        reason: Abandoned,
+    ) {
+        let mut ctx = self.historical.context(controller);
 
-        let res = ctx.entrypoint(&self.vm, args.clone());
+        if let Some(o) = owner {
+            ctx = ctx.with_owner(o);
+        }
 
-        match res {
-            Ok(res) => tracing::debug!("response received from controller: {res:?}"),
-            Err(e) => tracing::debug!(
-                "failed to call controller `{}` entrypoint with args `{args:?}: {e}`",
+        if let Err(e) = ctx.entrypoint(&self.vm, reason.status(payload)) {
+            tracing::debug!(
+                "failed to store the abandoned status for controller `{}`: {e}",
                 hex::encode(controller)
-            ),
+            );
+        }
+    }
+
+    /// Proves a replay witness, answering on the reply channel.
+    ///
+    /// A replay is never coalesced nor submitted to the controller entrypoint.
+    pub fn replay(
+        &self,
+        controller: Hash,
+        witness: WitnessCoprocessor,
+        owner: Option<Vec<u8>>,
+        reply: Reply,
+    ) {
+        let _span = tracing::info_span!("replay", circuit = hex::encode(controller)).entered();
+
+        let mut ctx = self.historical.context(controller);
+
+        if let Some(o) = owner {
+            ctx = ctx.with_owner(o);
+        }
+
+        let start = Instant::now();
+
+        self.stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        let res = self.zkvm.prove(&ctx, witness);
+
+        self.stats.in_flight.fetch_sub(1, Ordering::Relaxed);
+        self.stats.record(start.elapsed());
+
+        if reply.0.send(res.map_err(|e| e.to_string())).is_err() {
+            tracing::debug!(
+                "replay of `{}` abandoned by the requester",
+                hex::encode(controller)
+            );
         }
     }
 
@@ -235,10 +693,22 @@ impl Worker {
                 match j {
                     Job::Prove {
                         circuit,
+                        args,
                         witness,
                         payload,
                         owner,
-                    } => self.prove(circuit, witness, payload, owner),
+                        deadline,
+                        fresh,
+                        request_id,
+                    } => self.prove(
+                        circuit, args, witness, payload, owner, deadline, fresh, request_id,
+                    ),
+                    Job::Replay {
+                        circuit,
+                        witness,
+                        owner,
+                        reply,
+                    } => self.replay(circuit, witness, owner, reply),
                     Job::Quit => {
                         self.tx.send(Ack::Kill).ok();
                         break;
@@ -248,3 +718,329 @@ impl Worker {
         });
     }
 }
+
+/// Returns the span of a prove job, scoping its log lines to the circuit and the request that
+/// submitted it.
+///
+/// The worker runs outside of the request span, so the request ID is carried by the job.
+pub fn prove_span(circuit: &Hash, request_id: Option<&str>) -> tracing::Span {
+    tracing::info_span!(
+        "prove",
+        circuit = hex::encode(circuit),
+        request_id = request_id.unwrap_or_default()
+    )
+}
+
+/// Reason a job was abandoned before proving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abandoned {
+    /// The job was cancelled while queued.
+    Cancelled,
+
+    /// The deadline of the job passed before a worker picked it up.
+    Expired,
+}
+
+impl Abandoned {
+    /// Returns the terminal status submitted to the controller for the job payload.
+    ///
+    /// The status is a failure flagged with `cancelled` or `expired`.
+    pub fn status(&self, payload: Option<Value>) -> Value {
+        let (flag, log) = match self {
+            Abandoned::Cancelled => (
+                "cancelled",
+                "the proof was cancelled before a worker picked it up",
+            ),
+            Abandoned::Expired => (
+                "expired",
+                "the proof deadline expired before a worker picked it up",
+            ),
+        };
+
+        json!({
+            "success": false,
+            flag: true,
+            "log": [log],
+            "payload": payload,
+        })
+    }
+}
+
+/// Returns `true` if the provided UNIX timestamp deadline (in seconds) has passed.
+pub fn is_expired(deadline: Option<u64>) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    deadline.is_some_and(|d| d <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    use super::*;
+    use crate::logs::{LogFilter, ServiceLog};
+
+    #[test]
+    fn coalescer_proves_identical_jobs_once() {
+        let coalescer = Coalescer::default();
+        let executions = Arc::new(AtomicUsize::new(0));
+        let key = Hash::default();
+
+        let (started_tx, started) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+
+        let leader = {
+            let coalescer = coalescer.clone();
+            let executions = executions.clone();
+
+            thread::spawn(move || {
+                coalescer.run(key, Some(json!(1)), || {
+                    executions.fetch_add(1, Ordering::SeqCst);
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                })
+            })
+        };
+
+        started.recv().unwrap();
+
+        let follower = coalescer.run(key, Some(json!(2)), || {
+            executions.fetch_add(1, Ordering::SeqCst);
+        });
+
+        release.send(()).unwrap();
+
+        let (_, payloads) = leader.join().unwrap().unwrap();
+
+        assert!(follower.is_none());
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+        assert_eq!(payloads, vec![Some(json!(1)), Some(json!(2))]);
+    }
+
+    #[test]
+    fn coalescer_keys_distinguish_the_requests() {
+        let circuit = Hash::default();
+        let witness = WitnessCoprocessor {
+            version: WitnessCoprocessor::FORMAT_VERSION,
+            root: Hash::default(),
+            proofs: vec![],
+            witnesses: vec![],
+            encoding: Default::default(),
+        };
+        let key = Coalescer::key(&circuit, &witness, None).unwrap();
+
+        assert_eq!(key, Coalescer::key(&circuit, &witness, None).unwrap());
+        assert_ne!(key, Coalescer::key(&[1; 32], &witness, None).unwrap());
+        assert_ne!(
+            key,
+            Coalescer::key(&circuit, &witness, Some(b"alice")).unwrap()
+        );
+
+        let other = WitnessCoprocessor {
+            root: [1; 32],
+            ..witness.clone()
+        };
+
+        assert_ne!(key, Coalescer::key(&circuit, &other, None).unwrap());
+    }
+
+    #[test]
+    fn pool_stats_estimate_the_wait_across_workers() {
+        let (tx, rx) = flume::unbounded();
+        let stats = PoolStats {
+            tx,
+            max_workers: 8,
+            in_flight: Default::default(),
+            average_ms: Default::default(),
+        };
+
+        assert_eq!(stats.workers(), 0);
+        assert_eq!(stats.estimated_wait_ms(), 0);
+
+        stats.record(Duration::from_millis(1000));
+        stats.record(Duration::from_millis(200));
+
+        assert_eq!(stats.average_ms(), 900);
+
+        let _workers = [rx.clone(), rx.clone()];
+
+        for _ in 0..3 {
+            stats.tx.send(Job::Quit).unwrap();
+        }
+
+        assert_eq!(stats.workers(), 2);
+        assert_eq!(stats.queued(), 3);
+
+        // three jobs over two workers wait one and a half proofs, not one
+        assert_eq!(stats.estimated_wait_ms(), 1350);
+
+        stats.in_flight.fetch_add(1, Ordering::Relaxed);
+
+        assert_eq!(stats.in_flight(), 1);
+        assert_eq!(stats.estimated_wait_ms(), 1800);
+    }
+
+    #[test]
+    fn only_queued_jobs_are_cancelled() {
+        let pending = PendingJobs::default();
+        let circuit = Hash::default();
+
+        let owner = Some(&b"alice"[..]);
+
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", owner, false),
+            Cancel::NotQueued
+        );
+
+        pending.enqueue(circuit, "/a.bin", owner.map(<[u8]>::to_vec));
+        pending.enqueue(circuit, "/b.bin", owner.map(<[u8]>::to_vec));
+
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", owner, false),
+            Cancel::Cancelled
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", owner, false),
+            Cancel::NotQueued
+        );
+
+        assert!(pending.take(circuit, "/a.bin"));
+        assert!(!pending.take(circuit, "/b.bin"));
+
+        // the job was picked up by a worker, and can't be cancelled anymore
+        assert_eq!(
+            pending.cancel(circuit, "/b.bin", owner, false),
+            Cancel::NotQueued
+        );
+    }
+
+    #[test]
+    fn only_the_submitter_or_the_admin_cancels() {
+        let pending = PendingJobs::default();
+        let circuit = Hash::default();
+        let alice = Some(&b"alice"[..]);
+        let bob = Some(&b"bob"[..]);
+
+        pending.enqueue(circuit, "/a.bin", alice.map(<[u8]>::to_vec));
+        pending.enqueue(circuit, "/b.bin", alice.map(<[u8]>::to_vec));
+        pending.enqueue(circuit, "/b.bin", bob.map(<[u8]>::to_vec));
+        pending.enqueue(circuit, "/c.bin", None);
+
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", bob, false),
+            Cancel::Forbidden
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", None, false),
+            Cancel::Forbidden
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/a.bin", alice, false),
+            Cancel::Cancelled
+        );
+
+        // a path shared by several submitters is only cancelled by the admin
+        assert_eq!(
+            pending.cancel(circuit, "/b.bin", alice, false),
+            Cancel::Forbidden
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/b.bin", None, true),
+            Cancel::Cancelled
+        );
+
+        // unsigned jobs have no submitter to match
+        assert_eq!(
+            pending.cancel(circuit, "/c.bin", None, false),
+            Cancel::Forbidden
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/c.bin", alice, false),
+            Cancel::Forbidden
+        );
+        assert_eq!(
+            pending.cancel(circuit, "/c.bin", None, true),
+            Cancel::Cancelled
+        );
+
+        assert!(pending.take(circuit, "/b.bin"));
+        assert!(pending.take(circuit, "/b.bin"));
+        assert!(!pending.take(circuit, "/b.bin"));
+    }
+
+    #[test]
+    fn abandoned_jobs_store_a_terminal_failure() {
+        let payload = Some(json!({"cmd": "store", "path": "/a.bin"}));
+
+        assert!(is_expired(Some(0)));
+        assert!(!is_expired(Some(u64::MAX)));
+        assert!(!is_expired(None));
+
+        for (reason, flag) in [
+            (Abandoned::Cancelled, "cancelled"),
+            (Abandoned::Expired, "expired"),
+        ] {
+            let status = reason.status(payload.clone());
+
+            assert_eq!(status["success"], false);
+            assert_eq!(status[flag], true);
+            assert_eq!(status["payload"], payload.clone().unwrap());
+            assert_eq!(stored_path(status.get("payload")), Some("/a.bin"));
+            assert_eq!(status["log"].as_array().unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn prove_span_scopes_the_log_to_the_request() {
+        let log = ServiceLog::default();
+        let subscriber = tracing_subscriber::registry().with(log.clone());
+        let rx = log.subscribe(LogFilter::parse(None, Some("r1".into()), None).unwrap());
+        let circuit = Hash::from([0xab; 32]);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = prove_span(&circuit, Some("r1")).entered();
+
+            tracing::info!("proving");
+        });
+
+        let lines: Vec<Value> = rx
+            .try_iter()
+            .map(|l| serde_json::from_slice(&l.unwrap()).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["message"], "proving");
+        assert_eq!(lines[0]["circuit"], hex::encode(circuit));
+    }
+
+    #[test]
+    fn replay_jobs_are_told_apart_by_their_reply_channel() {
+        let (a, a_rx) = flume::bounded(1);
+        let (b, _b_rx) = flume::bounded(1);
+
+        let job = |reply| Job::Replay {
+            circuit: Hash::default(),
+            witness: WitnessCoprocessor {
+                version: WitnessCoprocessor::FORMAT_VERSION,
+                root: Hash::default(),
+                proofs: vec![],
+                witnesses: vec![],
+                encoding: Default::default(),
+            },
+            owner: None,
+            reply: Reply(reply),
+        };
+
+        assert_eq!(job(a.clone()), job(a.clone()));
+        assert_ne!(job(a.clone()), job(b));
+
+        Reply(a).0.send(Err("failed".into())).unwrap();
+
+        assert_eq!(a_rx.recv().unwrap().unwrap_err(), "failed");
+    }
+}
@@ -1,10 +1,10 @@
 use core::marker::PhantomData;
 
 use alloc::vec::Vec;
-use valence_coprocessor_types::{DataBackend, Hash, Hasher};
+use valence_coprocessor_types::{DataBackend, Hash, Hasher, HASH_LEN};
 use zerocopy::{IntoBytes as _, TryFromBytes as _};
 
-use crate::{Smt, SmtChildren};
+use crate::{Smt, SmtChildren, SmtCorruption};
 
 impl<D, H> Smt<D, H>
 where
@@ -20,9 +20,15 @@ where
     /// Prefix used for key nodes.
     pub const PREFIX_KEY: &[u8] = b"smt-key";
 
+    /// Prefix used for values stored out-of-line.
+    pub const PREFIX_VALUE: &[u8] = b"smt-value";
+
     /// Default namespace.
     pub const DEFAULT_NAMESPACE: &[u8] = b"smt";
 
+    /// Maximum depth of a traversal, equal to the bit length of the keys.
+    pub const MAX_DEPTH: usize = HASH_LEN * 8;
+
     /// Returns a stateless empty root to be used for newly allocated sparse Merkle trees.
     ///
     /// This is a cryptographic stateless computation and won't touch the data backend.
@@ -35,6 +41,14 @@ where
         Ok(node == &Hash::default() || self.has_node_key(node)?)
     }
 
+    pub(crate) fn ensure_depth(node: &Hash, depth: usize) -> anyhow::Result<()> {
+        if depth >= Self::MAX_DEPTH {
+            return Err(anyhow::Error::msg(SmtCorruption { node: *node, depth }));
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn get_children(&self, parent: &Hash) -> anyhow::Result<Option<SmtChildren>> {
         let data = match self.d.get(&self.namespace_node, parent)? {
             Some(d) => d,
@@ -64,6 +78,30 @@ where
             .transpose()
     }
 
+    /// Inserts the children of many parents, writing them in a single batch.
+    ///
+    /// Unlike [`Smt::insert_children`], the overwritten children aren't returned.
+    pub(crate) fn insert_children_batch(
+        &self,
+        nodes: &[(Hash, SmtChildren)],
+    ) -> anyhow::Result<()> {
+        let parents: Vec<_> = nodes.iter().map(|(parent, _)| parent.to_vec()).collect();
+        let entries: Vec<_> = nodes
+            .iter()
+            .map(|(parent, children)| (parent.to_vec(), children.as_bytes().to_vec()))
+            .collect();
+
+        self.d.remove_many(&self.namespace_key, &parents)?;
+        self.d.set_many(&self.namespace_node, &entries)
+    }
+
+    /// Removes the children of many parents in a single batch.
+    pub(crate) fn remove_children_batch(&self, parents: &[Hash]) -> anyhow::Result<()> {
+        let parents: Vec<_> = parents.iter().map(|parent| parent.to_vec()).collect();
+
+        self.d.remove_many(&self.namespace_node, &parents)
+    }
+
     pub(crate) fn remove_children(&self, parent: &Hash) -> anyhow::Result<Option<SmtChildren>> {
         let data = match self.d.remove(&self.namespace_node, parent)? {
             Some(d) => d,
@@ -107,6 +145,72 @@ where
         self.d.remove(&self.namespace_data, key)
     }
 
+    /// Returns the backend key of a value stored out-of-line.
+    ///
+    /// The key includes the leaf committing to the value, so every version of the value is
+    /// stored separately, and a root resolves the version it commits to.
+    fn value_key(key: &Hash, leaf: &Hash) -> Hash {
+        H::digest([key.as_slice(), leaf.as_slice()])
+    }
+
+    pub(crate) fn insert_key_value(
+        &self,
+        key: &Hash,
+        leaf: &Hash,
+        value: &[u8],
+    ) -> anyhow::Result<()> {
+        self.d
+            .set(&self.namespace_value, &Self::value_key(key, leaf), value)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn remove_key_value(&self, key: &Hash, leaf: &Hash) -> anyhow::Result<()> {
+        self.d
+            .remove(&self.namespace_value, &Self::value_key(key, leaf))?;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_leaf_value(
+        &self,
+        key: &Hash,
+        leaf: &Hash,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        self.d
+            .get(&self.namespace_value, &Self::value_key(key, leaf))
+    }
+
+    /// Returns the leaf of the current data of the key, if its value is stored out-of-line.
+    fn current_value_leaf(&self, key: &Hash) -> anyhow::Result<Option<Hash>> {
+        let leaf = match self.get_key_data(key)? {
+            Some(d) if d.len() == HASH_LEN => H::hash(&d),
+            _ => return Ok(None),
+        };
+
+        Ok(self
+            .d
+            .has(&self.namespace_value, &Self::value_key(key, &leaf))?
+            .then_some(leaf))
+    }
+
+    /// Returns `true` if the current value of the key is stored out-of-line.
+    pub fn is_out_of_line(&self, key: &Hash) -> anyhow::Result<bool> {
+        Ok(self.current_value_leaf(key)?.is_some())
+    }
+
+    /// Returns the full current value associated with the key.
+    ///
+    /// Unlike [`Smt::get_key_data`], values stored out-of-line are resolved instead of returning
+    /// their hash. To resolve the value committed by a given root, use
+    /// [`Smt::get_opening_with_value`].
+    pub fn get_key_value(&self, key: &Hash) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.current_value_leaf(key)? {
+            Some(leaf) => self.get_leaf_value(key, &leaf),
+            None => self.get_key_data(key),
+        }
+    }
+
     pub(crate) fn insert_key_data(
         &self,
         key: &Hash,
@@ -137,6 +241,8 @@ where
             namespace_node: Hash::default(),
             namespace_data: Hash::default(),
             namespace_key: Hash::default(),
+            namespace_value: Hash::default(),
+            inline_threshold: None,
             d: Default::default(),
             h: PhantomData,
         }
@@ -154,6 +260,8 @@ where
             namespace_node: Hash::default(),
             namespace_data: Hash::default(),
             namespace_key: Hash::default(),
+            namespace_value: Hash::default(),
+            inline_threshold: self.inline_threshold,
             d: self.d.clone(),
             h: PhantomData,
         }
@@ -171,6 +279,8 @@ where
             namespace_node: Hash::default(),
             namespace_data: Hash::default(),
             namespace_key: Hash::default(),
+            namespace_value: Hash::default(),
+            inline_threshold: None,
             d,
             h: PhantomData,
         }
@@ -194,6 +304,17 @@ where
         self.namespace_node = H::digest([Self::PREFIX_NODE, namespace.as_ref()]);
         self.namespace_data = H::digest([Self::PREFIX_DATA, namespace.as_ref()]);
         self.namespace_key = H::digest([Self::PREFIX_KEY, namespace.as_ref()]);
+        self.namespace_value = H::digest([Self::PREFIX_VALUE, namespace.as_ref()]);
+        self
+    }
+
+    /// Stores values larger than `threshold` bytes out-of-line.
+    ///
+    /// Such values are kept in the data backend under [`Smt::PREFIX_VALUE`], and the tree commits
+    /// to their hash instead: the leaf data is `H::hash(value)`, and openings are verified with
+    /// [`Smt::verify_out_of_line`]. Values up to the threshold are stored inline.
+    pub fn with_inline_threshold(mut self, threshold: usize) -> Self {
+        self.inline_threshold = Some(threshold);
         self
     }
 }
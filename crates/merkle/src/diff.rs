@@ -0,0 +1,260 @@
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use valence_coprocessor_types::{DataBackend, Hash, Hasher};
+
+use crate::{Smt, SmtChildren};
+
+/// The change of a key between two roots of a sparse Merkle tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtChange {
+    /// The key is absent from the old root, and present on the new root.
+    Inserted,
+    /// The key is present on both roots, with different leaves.
+    Updated,
+    /// The key is present on the old root, and absent from the new root.
+    Removed,
+}
+
+/// A page of the changed keys between two roots of a sparse Merkle tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SmtDiff {
+    /// The changed keys, in ascending order.
+    pub changes: Vec<(Hash, SmtChange)>,
+    /// Cursor of the next page, if the diff was truncated.
+    pub next: Option<Hash>,
+}
+
+enum Node {
+    Empty,
+    Leaf(Hash),
+    Inner(SmtChildren),
+}
+
+struct Page<'a> {
+    after: Option<&'a Hash>,
+    limit: usize,
+    changes: Vec<(Hash, SmtChange)>,
+    truncated: bool,
+}
+
+impl Page<'_> {
+    /// Pushes a change, returning `false` if the page is full.
+    fn push(&mut self, key: Hash, change: SmtChange) -> bool {
+        if self.after.is_some_and(|a| &key <= a) {
+            return true;
+        }
+
+        if self.changes.len() == self.limit {
+            self.truncated = true;
+            return false;
+        }
+
+        self.changes.push((key, change));
+
+        true
+    }
+
+    /// Returns the subtrees of the children that may contain keys after the cursor.
+    ///
+    /// `bound` is `true` if the path of the parent is a prefix of the cursor; the returned flags
+    /// are the `bound` of the children.
+    fn children(&self, depth: usize, bound: bool) -> (Option<bool>, bool) {
+        match self.after {
+            Some(a) if bound => {
+                let bit = (a[depth / 8] >> (7 - depth % 8)) & 1;
+
+                if bit == 0 {
+                    (Some(true), false)
+                } else {
+                    (None, true)
+                }
+            }
+            _ => (Some(false), false),
+        }
+    }
+}
+
+impl<D, H> Smt<D, H>
+where
+    D: DataBackend,
+    H: Hasher,
+{
+    /// Computes the keys that changed from the `old` root to the `new` root.
+    ///
+    /// The changes are returned in ascending key order, starting after the `after` cursor, and
+    /// bounded by `limit`. If more changes are available, [`SmtDiff::next`] is the cursor of the
+    /// next page.
+    ///
+    /// Subtrees shared by both roots are skipped, so the cost is proportional to the changes.
+    ///
+    /// The keys are resolved from the leaves; as with [`Smt::get_keyed_opening`], leaves of
+    /// identical data resolve to the same key.
+    pub fn diff(
+        &self,
+        old: Hash,
+        new: Hash,
+        after: Option<&Hash>,
+        limit: usize,
+    ) -> anyhow::Result<SmtDiff> {
+        let mut page = Page {
+            after,
+            limit,
+            changes: Vec::with_capacity(limit.min(1024)),
+            truncated: false,
+        };
+
+        self.diff_nodes(&mut page, old, new, 0, true)?;
+
+        let next = if page.truncated {
+            page.changes.last().map(|(k, _)| *k).or(after.copied())
+        } else {
+            None
+        };
+
+        Ok(SmtDiff {
+            changes: page.changes,
+            next,
+        })
+    }
+
+    fn node(&self, node: &Hash) -> anyhow::Result<Node> {
+        if node == &Hash::default() {
+            return Ok(Node::Empty);
+        }
+
+        if let Some(key) = self.get_node_key(node)? {
+            return Ok(Node::Leaf(key));
+        }
+
+        match self.get_children(node)? {
+            Some(c) => Ok(Node::Inner(c)),
+            None => anyhow::bail!("the node {node:x?} isn't part of the tree"),
+        }
+    }
+
+    fn diff_nodes(
+        &self,
+        page: &mut Page,
+        old: Hash,
+        new: Hash,
+        depth: usize,
+        bound: bool,
+    ) -> anyhow::Result<bool> {
+        if old == new {
+            return Ok(true);
+        }
+
+        match (self.node(&old)?, self.node(&new)?) {
+            (Node::Inner(o), Node::Inner(n)) => {
+                Self::ensure_depth(&new, depth)?;
+
+                let (left, right) = page.children(depth, bound);
+
+                if let Some(bound) = left {
+                    if !self.diff_nodes(page, o.left, n.left, depth + 1, bound)? {
+                        return Ok(false);
+                    }
+                }
+
+                self.diff_nodes(page, o.right, n.right, depth + 1, right)
+            }
+
+            (Node::Empty, _) => self.walk(page, new, depth, bound, &mut |page, key, _| {
+                page.push(key, SmtChange::Inserted)
+            }),
+
+            (_, Node::Empty) => self.walk(page, old, depth, bound, &mut |page, key, _| {
+                page.push(key, SmtChange::Removed)
+            }),
+
+            (Node::Leaf(key), _) => {
+                self.diff_leaf(page, key, old, new, depth, bound, SmtChange::Removed)
+            }
+
+            (_, Node::Leaf(key)) => {
+                self.diff_leaf(page, key, new, old, depth, bound, SmtChange::Inserted)
+            }
+        }
+    }
+
+    /// Compares a single leaf against a subtree.
+    ///
+    /// `change` is the change of the leaf if its key is absent from the subtree; the keys of the
+    /// subtree are reported with the opposite change.
+    #[allow(clippy::too_many_arguments)]
+    fn diff_leaf(
+        &self,
+        page: &mut Page,
+        key: Hash,
+        leaf: Hash,
+        subtree: Hash,
+        depth: usize,
+        bound: bool,
+        change: SmtChange,
+    ) -> anyhow::Result<bool> {
+        let opposite = match change {
+            SmtChange::Removed => SmtChange::Inserted,
+            _ => SmtChange::Removed,
+        };
+
+        let mut pending = true;
+        let complete = self.walk(page, subtree, depth, bound, &mut |page, k, l| {
+            if pending && key < k {
+                pending = false;
+
+                if !page.push(key, change) {
+                    return false;
+                }
+            }
+
+            if k == key {
+                pending = false;
+
+                return l == leaf || page.push(k, SmtChange::Updated);
+            }
+
+            page.push(k, opposite)
+        })?;
+
+        if complete && pending {
+            return Ok(page.push(key, change));
+        }
+
+        Ok(complete)
+    }
+
+    /// Visits the leaves of the subtree in ascending key order, skipping the ones before the
+    /// cursor.
+    ///
+    /// Returns `false` if the visit was interrupted.
+    fn walk<F>(
+        &self,
+        page: &mut Page,
+        node: Hash,
+        depth: usize,
+        bound: bool,
+        f: &mut F,
+    ) -> anyhow::Result<bool>
+    where
+        F: FnMut(&mut Page, Hash, Hash) -> bool,
+    {
+        match self.node(&node)? {
+            Node::Empty => Ok(true),
+            Node::Leaf(key) => Ok(f(page, key, node)),
+            Node::Inner(c) => {
+                Self::ensure_depth(&node, depth)?;
+
+                let (left, right) = page.children(depth, bound);
+
+                if let Some(bound) = left {
+                    if !self.walk(page, c.left, depth + 1, bound, f)? {
+                        return Ok(false);
+                    }
+                }
+
+                self.walk(page, c.right, depth + 1, right, f)
+            }
+        }
+    }
+}
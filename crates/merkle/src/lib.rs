@@ -6,9 +6,11 @@ extern crate alloc;
 
 mod boilerplate;
 mod compound;
+mod diff;
 mod mutate;
 mod smt;
 mod verify;
 
 pub use compound::*;
+pub use diff::*;
 pub use smt::*;
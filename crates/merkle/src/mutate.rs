@@ -9,12 +9,63 @@ where
     H: Hasher,
 {
     /// Inserts a leaf into the tree.
+    ///
+    /// A leaf sits right below the depth at which its key diverges from every other key, so the
+    /// root depends only on the leaves of the tree, not on the order they were inserted.
+    ///
+    /// If the key is already present, its value is overwritten: the last write wins. See
+    /// [`Smt::insert_if_absent`] to preserve the current value, and [`Smt::replace`] to retrieve
+    /// it.
+    ///
+    /// If the data is larger than the inline threshold, it is stored out-of-line and the leaf
+    /// commits to its hash. See [`Smt::with_inline_threshold`].
     pub fn insert(&self, root: Hash, key: &Hash, data: &[u8]) -> anyhow::Result<Hash> {
+        if self.inline_threshold.is_some_and(|t| data.len() > t) {
+            let digest = H::hash(data);
+            let leaf = H::hash(&digest);
+            let root = self.insert_with_leaf(root, key, leaf, &digest)?;
+
+            self.insert_key_value(key, &leaf, data)?;
+
+            return Ok(root);
+        }
+
         let leaf = H::hash(data);
 
         self.insert_with_leaf(root, key, leaf, data)
     }
 
+    /// Inserts a leaf into the tree only if the key is absent from `root`.
+    ///
+    /// Returns the resulting root, and `true` if the leaf was written.
+    pub fn insert_if_absent(
+        &self,
+        root: Hash,
+        key: &Hash,
+        data: &[u8],
+    ) -> anyhow::Result<(Hash, bool)> {
+        if self.get_opening(root, key)?.is_some() {
+            return Ok((root, false));
+        }
+
+        Ok((self.insert(root, key, data)?, true))
+    }
+
+    /// Inserts a leaf into the tree, overwriting the current value of the key.
+    ///
+    /// Returns the resulting root, and the previous value of the key in `root`, if present.
+    pub fn replace(
+        &self,
+        root: Hash,
+        key: &Hash,
+        data: &[u8],
+    ) -> anyhow::Result<(Hash, Option<Vec<u8>>)> {
+        let previous = self.get_opening_with_value(root, key)?.map(|(_, v)| v);
+        let root = self.insert(root, key, data)?;
+
+        Ok((root, previous))
+    }
+
     /// Overrides the leaf computation by accepting the provided value instead of hashing the data.
     ///
     /// The data will still be associated with the key.
@@ -78,7 +129,9 @@ where
             };
             let mut root = children.parent::<H>();
 
-            self.insert_children(&root, &children)?;
+            let mut nodes = Vec::with_capacity(depth + 1);
+
+            nodes.push((root, children));
 
             while depth > 0 {
                 depth -= 1;
@@ -95,24 +148,26 @@ where
 
                 root = children.parent::<H>();
 
-                self.insert_children(&root, &children)?;
+                nodes.push((root, children));
             }
 
+            self.insert_children_batch(&nodes)?;
+
             return Ok(root);
         }
 
         let mut node = root;
         let mut opening = Vec::with_capacity(HASH_LEN * 8);
 
+        // the mutations of the path are written in a single batch
+        let mut nodes = Vec::with_capacity(HASH_LEN * 8);
+
         // traverse until leaf
         while let Some(SmtChildren { left, right }) = self.get_children(&node)? {
+            Self::ensure_depth(&node, depth)?;
+
             let i = depth / 8;
             let j = depth % 8;
-
-            if i == HASH_LEN {
-                anyhow::bail!("tree collision over maximum depth");
-            }
-
             let bit = (key[i] >> (7 - j)) & 1;
             let sibling = if bit == 0 { right } else { left };
 
@@ -122,20 +177,9 @@ where
 
             depth += 1;
 
-            // empty leaf override
+            // the leaf takes the empty slot, so its depth depends only on the keys of the tree
             if node == Hash::default() {
-                let i = depth / 8;
-                let j = depth % 8;
-                let bit = (key[i] >> (7 - j)) & 1;
-
-                let children = SmtChildren {
-                    left: if bit == 0 { leaf } else { Hash::default() },
-                    right: if bit == 0 { Hash::default() } else { leaf },
-                };
-
-                node = children.parent::<H>();
-
-                self.insert_children(&node, &children)?;
+                node = leaf;
 
                 break;
             }
@@ -173,7 +217,7 @@ where
 
                 node = children.parent::<H>();
 
-                self.insert_children(&node, &children)?;
+                nodes.push((node, children));
 
                 break;
             }
@@ -194,21 +238,132 @@ where
 
             node = children.parent::<H>();
 
-            self.insert_children(&node, &children)?;
+            nodes.push((node, children));
         }
 
+        self.insert_children_batch(&nodes)?;
+
+        Ok(node)
+    }
+
+    /// Removes the leaf of the key from the tree, returning the new root.
+    ///
+    /// The keys of the tree must be derived from the data of their leaves under `context`, so the
+    /// removed leaf can be checked against the key.
+    ///
+    /// Internal nodes left with a single leaf are collapsed into it, and empty subtrees into the
+    /// empty-subtree hash. The leaf, its data, and the internal nodes on its path are removed from
+    /// the backend, so no orphaned node is left behind. `root` is consumed: it, and any other root
+    /// sharing these nodes, can no longer open the keys under them, as with [`Smt::prune`].
+    ///
+    /// Returns `root` unchanged if the key is absent.
+    pub fn remove(&self, root: Hash, context: &str, key: &Hash) -> anyhow::Result<Hash> {
+        let keyed = self.get_keyed_opening(root, key)?;
+
+        if keyed.key.as_ref() != Some(key) {
+            return Ok(root);
+        }
+
+        let data = match self.get_leaf_value(key, &keyed.node)? {
+            Some(v) => v,
+            None => self
+                .get_key_data(key)?
+                .ok_or_else(|| anyhow::anyhow!("no data associated with key {key:x?}"))?,
+        };
+
+        anyhow::ensure!(
+            &H::key(context, &data) == key,
+            "the key {key:x?} isn't derived from its data under the context `{context}`"
+        );
+
+        // the internal nodes from the root down to the leaf
+        let mut stale = Vec::with_capacity(keyed.opening.len());
+        let mut parent = root;
+
+        for depth in 0..keyed.opening.len() {
+            let children = self.get_children(&parent)?.ok_or_else(|| {
+                anyhow::anyhow!("inconsistent tree state; node {parent:x?} has no children")
+            })?;
+
+            stale.push(parent);
+
+            let i = depth / 8;
+            let j = depth % 8;
+
+            parent = match (key[i] >> (7 - j)) & 1 {
+                0 => children.left,
+                _ => children.right,
+            };
+        }
+
+        self.remove_children_batch(&stale)?;
+
+        self.remove_node_key(&keyed.node)?;
+        self.remove_key_value(key, &keyed.node)?;
+        self.remove_key_data(key)?;
+
+        let mut depth = keyed.opening.len();
+        let mut node = Hash::default();
+        let mut is_leaf = false;
+        let mut nodes = Vec::with_capacity(depth);
+
+        // the opening siblings are ordered from the leaf to the root
+        for sibling in &keyed.opening.path {
+            depth -= 1;
+
+            if node == Hash::default() && sibling == &Hash::default() {
+                continue;
+            }
+
+            // a leaf with an empty sibling is lifted, unless it would become the root
+            if depth > 0 {
+                if node == Hash::default() && self.has_node_key(sibling)? {
+                    node = *sibling;
+                    is_leaf = true;
+                    continue;
+                }
+
+                if is_leaf && sibling == &Hash::default() {
+                    continue;
+                }
+            }
+
+            let i = depth / 8;
+            let j = depth % 8;
+            let bit = (key[i] >> (7 - j)) & 1;
+
+            let children = SmtChildren {
+                left: if bit == 0 { node } else { *sibling },
+                right: if bit == 0 { *sibling } else { node },
+            };
+
+            node = children.parent::<H>();
+            is_leaf = false;
+
+            nodes.push((node, children));
+        }
+
+        self.insert_children_batch(&nodes)?;
+
         Ok(node)
     }
 
     /// Removes an entire subtree along with its linked leaf keys and data.
     pub fn prune(&self, root: &Hash) -> anyhow::Result<()> {
-        // TODO don't recurse here to not overflow the stack on very deep trees
+        self.prune_at(root, 0)
+    }
+
+    fn prune_at(&self, root: &Hash, depth: usize) -> anyhow::Result<()> {
+        // the recursion is bounded by the maximum depth of the tree
         if let Some(SmtChildren { left, right }) = self.get_children(root)? {
-            self.prune(&left)?;
-            self.prune(&right)?;
+            Self::ensure_depth(root, depth)?;
+
+            self.prune_at(&left, depth + 1)?;
+            self.prune_at(&right, depth + 1)?;
         }
 
         if let Some(key) = self.remove_node_key(root)? {
+            self.remove_key_value(&key, root)?;
             self.remove_key_data(&key)?;
         }
 
@@ -1,4 +1,4 @@
-use core::marker::PhantomData;
+use core::{fmt, marker::PhantomData};
 
 use msgpacker::MsgPacker;
 use serde::{Deserialize, Serialize};
@@ -14,6 +14,8 @@ where
     pub(crate) namespace_node: Hash,
     pub(crate) namespace_data: Hash,
     pub(crate) namespace_key: Hash,
+    pub(crate) namespace_value: Hash,
+    pub(crate) inline_threshold: Option<usize>,
     pub(crate) d: D,
     pub(crate) h: PhantomData<H>,
 }
@@ -48,3 +50,25 @@ impl SmtChildren {
         H::merge(&self.left, &self.right)
     }
 }
+
+/// Error returned when a traversal exceeds [`Smt::MAX_DEPTH`].
+///
+/// A well-formed tree can't be deeper than the key bit length, so this signals a corrupted or
+/// cyclic parent-children relationship in the data backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmtCorruption {
+    /// Node at which the traversal was aborted.
+    pub node: Hash,
+    /// Depth reached by the traversal.
+    pub depth: usize,
+}
+
+impl fmt::Display for SmtCorruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tree corruption or cycle detected at node {:x?}; traversal exceeded depth {}",
+            self.node, self.depth
+        )
+    }
+}
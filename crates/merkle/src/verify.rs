@@ -1,6 +1,7 @@
 use alloc::vec::Vec;
 use valence_coprocessor_types::{
-    DataBackend, Hash, Hasher, KeyedOpening, Opening, OpeningNonMembership, Preimage, HASH_LEN,
+    DataBackend, Hash, Hasher, KeyedOpening, Opening, OpeningNonMembership, Preimage,
+    SmtMultiproof, HASH_LEN,
 };
 
 use crate::{Smt, SmtChildren};
@@ -18,6 +19,33 @@ where
         Ok(opening)
     }
 
+    /// Computes a Merkle opening proof for the provided leaf to the root, along with its full
+    /// value.
+    ///
+    /// Values stored out-of-line are resolved from the data backend by the leaf of the root, so
+    /// the value is the one committed by `root` even if the key was updated since; the opening
+    /// still commits to their hash.
+    pub fn get_opening_with_value(
+        &self,
+        root: Hash,
+        key: &Hash,
+    ) -> anyhow::Result<Option<(Opening, Vec<u8>)>> {
+        let keyed = self.get_keyed_opening(root, key)?;
+
+        if keyed.key.as_ref() != Some(key) {
+            return Ok(None);
+        }
+
+        let value = match self.get_leaf_value(key, &keyed.node)? {
+            Some(v) => v,
+            None => self
+                .get_key_data(key)?
+                .ok_or_else(|| anyhow::anyhow!("no value associated with key {key:x?}"))?,
+        };
+
+        Ok(Some((keyed.opening, value)))
+    }
+
     /// Creates a Merkle proof of non-membership.
     pub fn get_non_membership_opening(
         &self,
@@ -25,6 +53,11 @@ where
         key: &Hash,
     ) -> anyhow::Result<OpeningNonMembership> {
         let keyed = self.get_keyed_opening(root, key)?;
+
+        self.non_membership_opening(keyed)
+    }
+
+    fn non_membership_opening(&self, keyed: KeyedOpening) -> anyhow::Result<OpeningNonMembership> {
         let preimage = if keyed.node == Hash::default() {
             Preimage::Zero
         } else {
@@ -42,12 +75,126 @@ where
         })
     }
 
+    /// Creates a proof that the key is absent from the root.
+    ///
+    /// Returns `None` if the key is present. The keys of the tree must be derived from the data of
+    /// their leaves under `context`, so the key of a conflicting leaf can be checked; see
+    /// [`OpeningNonMembership::verify_absence`].
+    pub fn get_absence_opening(
+        &self,
+        context: &str,
+        root: Hash,
+        key: &Hash,
+    ) -> anyhow::Result<Option<OpeningNonMembership>> {
+        let keyed = self.get_keyed_opening(root, key)?;
+        let leaf = keyed.key;
+
+        if leaf.as_ref() == Some(key) {
+            return Ok(None);
+        }
+
+        let proof = self.non_membership_opening(keyed)?;
+
+        if let Preimage::Data(data) = &proof.preimage {
+            let leaf =
+                leaf.ok_or_else(|| anyhow::anyhow!("inconsistent tree state; leaf without key"))?;
+
+            anyhow::ensure!(
+                H::key(context, data) == leaf,
+                "the key {leaf:x?} isn't derived from its data under the context `{context}`"
+            );
+        }
+
+        Ok(Some(proof))
+    }
+
+    /// Creates a single proof of many keys to the root.
+    ///
+    /// The internal nodes shared by the paths of the keys are omitted, so the proof is smaller than
+    /// the individual openings of correlated keys. Fails if a key is absent, or isn't derived from
+    /// the data of its leaf under `context`.
+    pub fn get_multiproof(
+        &self,
+        context: &str,
+        root: Hash,
+        keys: &[Hash],
+    ) -> anyhow::Result<SmtMultiproof> {
+        let mut keys = keys.to_vec();
+
+        keys.sort_unstable();
+        keys.dedup();
+
+        anyhow::ensure!(!keys.is_empty(), "a multiproof requires at least one key");
+
+        let mut proof = SmtMultiproof::default();
+
+        self.collect_multiproof(context, root, 0, &keys, &mut proof)?;
+
+        Ok(proof)
+    }
+
+    fn collect_multiproof(
+        &self,
+        context: &str,
+        node: Hash,
+        depth: usize,
+        keys: &[Hash],
+        proof: &mut SmtMultiproof,
+    ) -> anyhow::Result<()> {
+        let children = match self.has_node_key(&node)? {
+            true => None,
+            false => self.get_children(&node)?,
+        };
+
+        let SmtChildren { left, right } = match children {
+            Some(c) => c,
+            None => {
+                let leaf = self.get_node_key(&node)?;
+                let absent = keys.iter().find(|k| leaf.as_ref() != Some(*k));
+
+                if let Some(k) = absent.or(keys.get(1)) {
+                    anyhow::bail!("the key {k:x?} isn't present in the tree");
+                }
+
+                let data = self
+                    .get_key_data(&keys[0])?
+                    .ok_or_else(|| anyhow::anyhow!("no data associated with key {:x?}", keys[0]))?;
+
+                anyhow::ensure!(
+                    H::key(context, &data) == keys[0],
+                    "the key {:x?} isn't derived from its data under the context `{context}`",
+                    keys[0]
+                );
+
+                proof.depths.push(depth as u16);
+
+                return Ok(());
+            }
+        };
+
+        Self::ensure_depth(&node, depth)?;
+
+        let i = depth / 8;
+        let j = depth % 8;
+        let split = keys.partition_point(|k| (k[i] >> (7 - j)) & 1 == 0);
+
+        for (child, keys) in [(left, &keys[..split]), (right, &keys[split..])] {
+            if keys.is_empty() {
+                proof.siblings.push(child);
+            } else {
+                self.collect_multiproof(context, child, depth + 1, keys, proof)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Computes a Merkle opening proof for the provided leaf to the root.
     ///
     /// Note: the returned node may not be the one with the target key. The routine will return the
     /// first leaf that matches the path provided by the key.
     pub fn get_keyed_opening(&self, root: Hash, key: &Hash) -> anyhow::Result<KeyedOpening> {
-        let (mut i, mut j) = (0, 0);
+        let mut depth = 0;
         let mut leaf_node = root;
         let mut opening = Vec::with_capacity(HASH_LEN * 8);
 
@@ -55,10 +202,12 @@ where
             // is current node a leaf?
             if self.has_node_key(&leaf_node)? {
                 break;
-            } else if i == HASH_LEN {
-                anyhow::bail!("The provided key was depleted without a leaf opening.");
             }
 
+            Self::ensure_depth(&leaf_node, depth)?;
+
+            let i = depth / 8;
+            let j = depth % 8;
             let bit = (key[i] >> (7 - j)) & 1;
 
             if bit == 0 {
@@ -69,14 +218,7 @@ where
                 opening.push(left);
             };
 
-            j += 1;
-
-            if j == 8 && i == HASH_LEN {
-                break;
-            } else if j == 8 {
-                j = 0;
-                i += 1;
-            }
+            depth += 1;
         }
 
         opening.reverse();
@@ -98,6 +240,48 @@ where
         opening.verify::<H>(root, key, &value)
     }
 
+    /// Verifies a Merkle opening of a value stored out-of-line, against the hash of the value.
+    pub fn verify_out_of_line(opening: &Opening, root: &Hash, key: &Hash, value: &[u8]) -> bool {
+        Self::verify(opening, root, key, &H::hash(value))
+    }
+
+    /// Verifies a keyed Merkle opening generated via [`Smt::get_keyed_opening`] under the key
+    /// derived from `context` and `data`.
+    ///
+    /// An opening path only binds the key bits up to its depth, so the same opening may verify
+    /// under keys of different contexts that share that prefix. Checking the embedded key against
+    /// the one derived from the context rejects proofs inserted under another namespace.
+    pub fn verify_with_context(
+        opening: &KeyedOpening,
+        root: &Hash,
+        context: &str,
+        data: &[u8],
+    ) -> bool {
+        let key = H::key(context, data);
+
+        opening.key == Some(key) && opening.node == H::hash(data) && opening.verify::<H>(root)
+    }
+
+    /// Verifies a proof generated via [`Smt::get_absence_opening`].
+    pub fn verify_absence(
+        context: &str,
+        root: &Hash,
+        key: &Hash,
+        proof: &OpeningNonMembership,
+    ) -> bool {
+        proof.verify_absence::<H>(context, root, key)
+    }
+
+    /// Verifies a proof generated via [`Smt::get_multiproof`] of the keys with their data.
+    pub fn verify_multiproof(
+        context: &str,
+        root: &Hash,
+        kvs: &[(Hash, &[u8])],
+        proof: &SmtMultiproof,
+    ) -> bool {
+        proof.verify::<H>(context, root, kvs)
+    }
+
     /// Verifies a non-membership proof.
     pub fn verify_non_membership(
         proof: &OpeningNonMembership,
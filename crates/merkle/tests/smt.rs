@@ -1,6 +1,18 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
 use proptest::collection;
-use valence_coprocessor::{CompoundOpeningBuilder, MemorySmt};
-use valence_coprocessor_types::{Blake3Hasher, Hasher, Opening};
+use valence_coprocessor::{
+    CompoundOpeningBuilder, MemoryBackend, MemorySmt, Smt, SmtChange, SmtChildren, SmtCorruption,
+};
+use valence_coprocessor_types::{
+    Blake3Hasher, DataBackend, Hash, Hasher, KeyedOpening, Opening, OpeningNonMembership, Preimage,
+};
 
 use proptest::prelude::*;
 
@@ -25,6 +37,336 @@ fn single_node_opening() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn context_mismatch_opening() -> anyhow::Result<()> {
+    let data = b"Do not go gentle into that good night";
+
+    let tree = MemorySmt::default();
+    let key = Blake3Hasher::key("poem", data);
+
+    let root = MemorySmt::empty_tree_root();
+    let root = tree.insert(root, &key, data)?;
+    let proof = tree.get_keyed_opening(root, &key)?;
+
+    assert!(MemorySmt::verify_with_context(&proof, &root, "poem", data));
+    assert!(!MemorySmt::verify_with_context(&proof, &root, "song", data));
+
+    Ok(())
+}
+
+#[test]
+fn zk_merkle_opening_round_trip() -> anyhow::Result<()> {
+    let tree = MemorySmt::default();
+    let mut root = MemorySmt::empty_tree_root();
+
+    for line in [
+        "Hope is the thing with feathers",
+        "That perches in the soul",
+    ] {
+        root = tree.insert(
+            root,
+            &Blake3Hasher::key("poem", line.as_bytes()),
+            line.as_bytes(),
+        )?;
+    }
+
+    let key = Blake3Hasher::key("poem", b"That perches in the soul");
+    let proof = tree.get_keyed_opening(root, &key)?;
+    let zk = proof.clone().into_zk_merkle_opening(&root);
+
+    assert_eq!(zk.root, root.to_vec());
+    assert_eq!(zk.siblings.len(), proof.opening.path.len());
+
+    let (r, opening) = KeyedOpening::from_zk_merkle_opening(&zk)?;
+
+    assert_eq!(r, root);
+    assert_eq!(opening, proof);
+    assert!(opening.verify::<Blake3Hasher>(&root));
+
+    let mut invalid = zk.clone();
+
+    invalid.value.pop();
+
+    assert!(KeyedOpening::from_zk_merkle_opening(&invalid).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn insert_overwrites_and_insert_if_absent_preserves() -> anyhow::Result<()> {
+    let tree = MemorySmt::default();
+    let key = Blake3Hasher::key("poem", b"title");
+    let other = Blake3Hasher::key("poem", b"author");
+
+    let root = MemorySmt::empty_tree_root();
+    let root = tree.insert(root, &other, b"Frost")?;
+
+    let (root, written) = tree.insert_if_absent(root, &key, b"The Road Not Taken")?;
+
+    assert!(written);
+
+    let (same, written) = tree.insert_if_absent(root, &key, b"Fire and Ice")?;
+
+    assert!(!written);
+    assert_eq!(same, root);
+
+    let (_, value) = tree.get_opening_with_value(root, &key)?.unwrap();
+
+    assert_eq!(value, b"The Road Not Taken");
+
+    let (replaced, previous) = tree.replace(root, &key, b"Fire and Ice")?;
+
+    assert_eq!(previous.as_deref(), Some(&b"The Road Not Taken"[..]));
+    assert_eq!(replaced, tree.insert(root, &key, b"Fire and Ice")?);
+
+    let (opening, value) = tree.get_opening_with_value(replaced, &key)?.unwrap();
+
+    assert_eq!(value, b"Fire and Ice");
+    assert!(MemorySmt::verify(
+        &opening,
+        &replaced,
+        &key,
+        b"Fire and Ice"
+    ));
+
+    let absent = Blake3Hasher::key("poem", b"year");
+    let (_, previous) = tree.replace(replaced, &absent, b"1916")?;
+
+    assert!(previous.is_none());
+
+    Ok(())
+}
+
+/// A backend that reports a node as its own left and right children.
+#[derive(Clone, Default)]
+struct CyclicBackend {
+    inner: MemoryBackend,
+    node: Hash,
+}
+
+impl DataBackend for CyclicBackend {
+    fn get(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let namespace =
+            Blake3Hasher::digest([MemorySmt::PREFIX_NODE, MemorySmt::DEFAULT_NAMESPACE]);
+
+        if prefix == namespace && key == self.node {
+            let children = SmtChildren {
+                left: self.node,
+                right: self.node,
+            };
+
+            return Ok(Some(children.as_ref().to_vec()));
+        }
+
+        self.inner.get(prefix, key)
+    }
+
+    fn has(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<bool> {
+        self.inner.has(prefix, key)
+    }
+
+    fn remove(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.remove(prefix, key)
+    }
+
+    fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.set(prefix, key, data)
+    }
+
+    fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.get_bulk(prefix, key)
+    }
+
+    fn set_bulk(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        self.inner.set_bulk(prefix, key, data)
+    }
+}
+
+/// A backend that counts the single and batched writes.
+#[derive(Clone, Default)]
+struct CountingBackend {
+    inner: MemoryBackend,
+    sets: Arc<AtomicUsize>,
+    removes: Arc<AtomicUsize>,
+    batches: Arc<AtomicUsize>,
+}
+
+impl DataBackend for CountingBackend {
+    fn get(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.get(prefix, key)
+    }
+
+    fn has(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<bool> {
+        self.inner.has(prefix, key)
+    }
+
+    fn remove(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.removes.fetch_add(1, Ordering::Relaxed);
+        self.inner.remove(prefix, key)
+    }
+
+    fn remove_many(&self, prefix: &[u8], keys: &[Vec<u8>]) -> anyhow::Result<()> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+
+        keys.iter()
+            .try_for_each(|k| self.inner.remove(prefix, k).map(|_| ()))
+    }
+
+    fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.sets.fetch_add(1, Ordering::Relaxed);
+        self.inner.set(prefix, key, data)
+    }
+
+    fn set_many(&self, prefix: &[u8], entries: &[(Vec<u8>, Vec<u8>)]) -> anyhow::Result<()> {
+        self.batches.fetch_add(1, Ordering::Relaxed);
+
+        entries
+            .iter()
+            .try_for_each(|(k, v)| self.inner.set(prefix, k, v).map(|_| ()))
+    }
+
+    fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        self.inner.get_bulk(prefix, key)
+    }
+
+    fn set_bulk(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<()> {
+        self.inner.set_bulk(prefix, key, data)
+    }
+}
+
+#[test]
+fn insert_writes_the_path_in_a_batch() -> anyhow::Result<()> {
+    let backend = CountingBackend::default();
+    let tree = Smt::<CountingBackend, Blake3Hasher>::from(backend.clone());
+    let mut root = Smt::<CountingBackend, Blake3Hasher>::empty_tree_root();
+
+    let leaves: Vec<_> = (0u32..64)
+        .map(|i| {
+            (
+                Blake3Hasher::key("batch", &i.to_le_bytes()),
+                i.to_be_bytes(),
+            )
+        })
+        .collect();
+
+    for (key, data) in &leaves {
+        let empty = root == Smt::<CountingBackend, Blake3Hasher>::empty_tree_root();
+
+        backend.sets.store(0, Ordering::Relaxed);
+        backend.removes.store(0, Ordering::Relaxed);
+
+        root = tree.insert(root, key, data)?;
+
+        // only the key data and the leaf key are written outside of the batch, along with the
+        // children of a new root
+        let sets = if empty { 3 } else { 2 };
+        let removes = if empty { 1 } else { 0 };
+
+        assert!(backend.sets.load(Ordering::Relaxed) <= sets);
+        assert!(backend.removes.load(Ordering::Relaxed) <= removes);
+    }
+
+    assert!(backend.batches.load(Ordering::Relaxed) > 0);
+
+    for (key, data) in &leaves {
+        let opening = tree.get_opening(root, key)?.unwrap();
+
+        assert!(Smt::<CountingBackend, Blake3Hasher>::verify(
+            &opening, &root, key, data
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cyclic_node_is_reported_as_corruption() {
+    let root = [0xab; 32];
+    let backend = CyclicBackend {
+        inner: MemoryBackend::default(),
+        node: root,
+    };
+
+    let tree = Smt::<CyclicBackend, Blake3Hasher>::from(backend);
+    let key = Blake3Hasher::key("poem", b"The fog comes on little cat feet");
+
+    let err = tree.get_keyed_opening(root, &key).unwrap_err();
+    let corruption = err.downcast_ref::<SmtCorruption>().unwrap();
+
+    assert_eq!(corruption.node, root);
+    assert_eq!(corruption.depth, MemorySmt::MAX_DEPTH);
+
+    let err = tree.insert(root, &key, b"data").unwrap_err();
+    assert!(err.downcast_ref::<SmtCorruption>().is_some());
+
+    let err = tree.prune(&root).unwrap_err();
+    assert!(err.downcast_ref::<SmtCorruption>().is_some());
+}
+
+#[test]
+fn large_values_are_stored_out_of_line() -> anyhow::Result<()> {
+    let tree = MemorySmt::default().with_inline_threshold(16);
+    let small = b"inline";
+    let large = [0xab; 64];
+
+    let small_key = Blake3Hasher::key("poem", small);
+    let large_key = Blake3Hasher::key("poem", &large);
+
+    let root = MemorySmt::empty_tree_root();
+    let root = tree.insert(root, &small_key, small)?;
+    let root = tree.insert(root, &large_key, &large)?;
+
+    assert!(!tree.is_out_of_line(&small_key)?);
+    assert!(tree.is_out_of_line(&large_key)?);
+
+    let digest = Blake3Hasher::hash(&large);
+    assert_eq!(tree.get_key_data(&large_key)?.unwrap(), digest.to_vec());
+
+    let (opening, value) = tree.get_opening_with_value(root, &large_key)?.unwrap();
+    assert_eq!(value, large.to_vec());
+    assert!(MemorySmt::verify_out_of_line(
+        &opening, &root, &large_key, &value
+    ));
+    assert!(!MemorySmt::verify(&opening, &root, &large_key, &value));
+
+    let (opening, value) = tree.get_opening_with_value(root, &small_key)?.unwrap();
+    assert_eq!(value, small.to_vec());
+    assert!(MemorySmt::verify(&opening, &root, &small_key, &value));
+
+    let root = tree.insert(root, &large_key, small)?;
+    assert!(!tree.is_out_of_line(&large_key)?);
+
+    let opening = tree.get_opening(root, &large_key)?.unwrap();
+    assert!(MemorySmt::verify(&opening, &root, &large_key, small));
+
+    Ok(())
+}
+
+#[test]
+fn out_of_line_values_are_resolved_per_root() -> anyhow::Result<()> {
+    let tree = MemorySmt::default().with_inline_threshold(16);
+    let key = Blake3Hasher::key("poem", b"stanza");
+    let first = [0xab; 64];
+    let second = [0xcd; 64];
+
+    let root = MemorySmt::empty_tree_root();
+    let first_root = tree.insert(root, &key, &first)?;
+    let (second_root, previous) = tree.replace(first_root, &key, &second)?;
+
+    assert_eq!(previous, Some(first.to_vec()));
+    assert_eq!(tree.get_key_value(&key)?, Some(second.to_vec()));
+
+    // the prior root still resolves the value it commits to
+    for (root, expected) in [(first_root, first), (second_root, second)] {
+        let (opening, value) = tree.get_opening_with_value(root, &key)?.unwrap();
+
+        assert_eq!(value, expected.to_vec());
+        assert!(MemorySmt::verify_out_of_line(&opening, &root, &key, &value));
+    }
+
+    Ok(())
+}
+
 #[test]
 fn double_node_opening() -> anyhow::Result<()> {
     let context = "poem";
@@ -348,7 +690,7 @@ fn deep_opening() -> anyhow::Result<()> {
     let p1 = tree.get_opening(root, &k1)?.unwrap();
 
     assert_eq!(p0.len(), 1);
-    assert_eq!(p1.len(), 2);
+    assert_eq!(p1.len(), 1);
 
     assert!(MemorySmt::verify(&p0, &root, &k0, &n[0].to_le_bytes()));
     assert!(MemorySmt::verify(&p1, &root, &k1, &n[1].to_le_bytes()));
@@ -375,7 +717,7 @@ fn deep_opening() -> anyhow::Result<()> {
     let p2 = tree.get_opening(root, &k2)?.unwrap();
 
     assert_eq!(p0.len(), 4);
-    assert_eq!(p1.len(), 2);
+    assert_eq!(p1.len(), 1);
     assert_eq!(p2.len(), 4);
 
     assert!(MemorySmt::verify(&p0, &root, &k0, &n[0].to_le_bytes()));
@@ -502,7 +844,7 @@ fn compound_opening() -> anyhow::Result<()> {
     proofs[3] = tree.get_opening(roots[1], &keys[3])?.unwrap();
 
     assert_eq!(proofs[2].len(), 1);
-    assert_eq!(proofs[3].len(), 2);
+    assert_eq!(proofs[3].len(), 1);
 
     assert!(MemorySmt::verify(&proofs[2], &roots[1], &keys[2], data[2]));
     assert!(MemorySmt::verify(&proofs[3], &roots[1], &keys[3], data[3]));
@@ -528,7 +870,120 @@ fn compound_opening() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn diff_lists_changed_keys_by_page() -> anyhow::Result<()> {
+    let tree = MemorySmt::default();
+    let key = |i: u32| Blake3Hasher::key("diff", &i.to_le_bytes());
+
+    let mut old = MemorySmt::empty_tree_root();
+
+    for i in 0..10u32 {
+        old = tree.insert(old, &key(i), &i.to_le_bytes())?;
+    }
+
+    let mut new = tree.insert(old, &key(3), b"updated")?;
+
+    new = tree.insert(new, &key(10), &10u32.to_le_bytes())?;
+    new = tree.insert(new, &key(11), &11u32.to_le_bytes())?;
+
+    // re-writing the same value is not a change
+    new = tree.insert(new, &key(4), &4u32.to_le_bytes())?;
+
+    let mut expected = vec![
+        (key(3), SmtChange::Updated),
+        (key(10), SmtChange::Inserted),
+        (key(11), SmtChange::Inserted),
+    ];
+
+    expected.sort();
+
+    let diff = tree.diff(old, new, None, 100)?;
+
+    assert_eq!(diff.changes, expected);
+    assert_eq!(diff.next, None);
+
+    let reverse: Vec<_> = expected
+        .iter()
+        .map(|(k, c)| match c {
+            SmtChange::Inserted => (*k, SmtChange::Removed),
+            c => (*k, *c),
+        })
+        .collect();
+
+    assert_eq!(tree.diff(new, old, None, 100)?.changes, reverse);
+
+    let mut after = None;
+    let mut paged = vec![];
+
+    loop {
+        let page = tree.diff(old, new, after.as_ref(), 1)?;
+
+        paged.extend(page.changes);
+
+        match page.next {
+            Some(n) => after = Some(n),
+            None => break,
+        }
+    }
+
+    assert_eq!(paged, expected);
+    assert!(tree.diff(old, old, None, 100)?.changes.is_empty());
+
+    Ok(())
+}
+
 proptest! {
+    #[test]
+    fn diff_matches_the_changed_entries(
+        old in collection::btree_map(0u8..64, 0u8..4, 0..32),
+        new in collection::btree_map(0u8..64, 0u8..4, 0..32),
+        limit in 1usize..8,
+    ) {
+        let tree = MemorySmt::default();
+        let key = |i: u8| Blake3Hasher::key("diff", &[i]);
+        let mut expected = BTreeMap::new();
+
+        let mut old_root = MemorySmt::empty_tree_root();
+        let mut new_root = MemorySmt::empty_tree_root();
+
+        for (k, v) in &old {
+            old_root = tree.insert(old_root, &key(*k), &[*k, *v]).unwrap();
+        }
+
+        for (k, v) in &new {
+            new_root = tree.insert(new_root, &key(*k), &[*k, *v]).unwrap();
+        }
+
+        for (k, v) in &old {
+            match new.get(k) {
+                None => expected.insert(key(*k), SmtChange::Removed),
+                Some(n) if n != v => expected.insert(key(*k), SmtChange::Updated),
+                Some(_) => None,
+            };
+        }
+
+        for k in new.keys().filter(|k| !old.contains_key(k)) {
+            expected.insert(key(*k), SmtChange::Inserted);
+        }
+
+        let mut after = None;
+        let mut changes = vec![];
+
+        loop {
+            let page = tree.diff(old_root, new_root, after.as_ref(), limit).unwrap();
+
+            assert!(page.changes.len() <= limit);
+
+            changes.extend(page.changes);
+
+            match page.next {
+                Some(n) => after = Some(n),
+                None => break,
+            }
+        }
+
+        assert_eq!(changes, expected.into_iter().collect::<Vec<_>>());
+    }
     #[test]
     fn memory_property_check(numbers in collection::vec(0u32..u32::MAX, 1..100)) {
         let context = "property";
@@ -558,3 +1013,213 @@ proptest! {
         }
     }
 }
+
+proptest! {
+    #[test]
+    fn absence_verifies_only_for_keys_not_inserted(
+        inserted in collection::btree_set(any::<u16>(), 0..48),
+        probes in collection::vec(any::<u16>(), 1..16),
+    ) {
+        let context = "nonce";
+        let tree = MemorySmt::default();
+        let mut root = MemorySmt::empty_tree_root();
+
+        for n in &inserted {
+            let data = n.to_le_bytes();
+
+            root = tree.insert(root, &Blake3Hasher::key(context, &data), &data).unwrap();
+        }
+
+        for n in probes.iter().chain(&inserted) {
+            let data = n.to_le_bytes();
+            let key = Blake3Hasher::key(context, &data);
+            let proof = tree.get_absence_opening(context, root, &key).unwrap();
+
+            if inserted.contains(n) {
+                prop_assert!(proof.is_none());
+
+                // an opening to the leaf of the key doesn't prove its absence
+                let forged = OpeningNonMembership {
+                    preimage: Preimage::Data(data.to_vec()),
+                    opening: tree.get_opening(root, &key).unwrap().unwrap(),
+                };
+
+                prop_assert!(!MemorySmt::verify_absence(context, &root, &key, &forged));
+            } else {
+                let proof = proof.unwrap();
+
+                prop_assert!(MemorySmt::verify_absence(context, &root, &key, &proof));
+
+                // the proof is bound to the key and the root
+                for m in &inserted {
+                    let other = Blake3Hasher::key(context, &m.to_le_bytes());
+
+                    prop_assert!(!MemorySmt::verify_absence(context, &root, &other, &proof));
+                }
+            }
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn removed_keys_are_absent_and_survivors_verify(
+        entries in collection::btree_map(any::<u16>(), any::<bool>(), 1..48),
+    ) {
+        let context = "nonce";
+        let backend = MemoryBackend::default();
+        let tree = MemorySmt::from(backend.clone());
+        let key = |n: &u16| Blake3Hasher::key(context, &n.to_le_bytes());
+        let mut root = MemorySmt::empty_tree_root();
+
+        for n in entries.keys() {
+            root = tree.insert(root, &key(n), &n.to_le_bytes()).unwrap();
+        }
+
+        // the tree is built under the default namespace
+        let namespace = Hash::default();
+
+        for (n, _) in entries.iter().filter(|(_, r)| **r) {
+            let prior = root;
+
+            root = tree.remove(root, context, &key(n)).unwrap();
+
+            // the replaced root and the data of the key aren't left behind
+            prop_assert!(!backend.has(&namespace, &prior).unwrap());
+            prop_assert!(!backend.has(&namespace, &key(n)).unwrap());
+
+            // removing an absent key is a no-op
+            prop_assert_eq!(tree.remove(root, context, &key(n)).unwrap(), root);
+        }
+
+        // the root is the one of a tree built from the surviving keys only
+        let fresh = MemorySmt::default();
+        let mut survivors = MemorySmt::empty_tree_root();
+
+        for (n, _) in entries.iter().filter(|(_, r)| !**r) {
+            survivors = fresh.insert(survivors, &key(n), &n.to_le_bytes()).unwrap();
+        }
+
+        prop_assert_eq!(root, survivors);
+
+        for (n, removed) in &entries {
+            let data = n.to_le_bytes();
+            let key = key(n);
+
+            if *removed {
+                let proof = tree.get_absence_opening(context, root, &key).unwrap().unwrap();
+
+                prop_assert!(tree.get_opening(root, &key).unwrap().is_none());
+                prop_assert!(MemorySmt::verify_absence(context, &root, &key, &proof));
+            } else {
+                let proof = tree.get_opening(root, &key).unwrap().unwrap();
+
+                prop_assert!(MemorySmt::verify(&proof, &root, &key, &data));
+            }
+        }
+
+        // the collapsed tree accepts the removed keys back
+        for (n, _) in entries.iter().filter(|(_, r)| **r) {
+            root = tree.insert(root, &key(n), &n.to_le_bytes()).unwrap();
+        }
+
+        for n in entries.keys() {
+            let data = n.to_le_bytes();
+            let proof = tree.get_opening(root, &key(n)).unwrap().unwrap();
+
+            prop_assert!(MemorySmt::verify(&proof, &root, &key(n), &data));
+        }
+
+        for n in entries.keys() {
+            root = tree.remove(root, context, &key(n)).unwrap();
+        }
+
+        prop_assert_eq!(root, MemorySmt::empty_tree_root());
+    }
+}
+
+#[test]
+fn multiproof_is_smaller_than_the_openings_of_clustered_keys() {
+    let context = "slots";
+    let tree = MemorySmt::default();
+    let mut root = MemorySmt::empty_tree_root();
+    let mut leaves = BTreeMap::new();
+
+    for n in 0u32..512 {
+        let data = n.to_le_bytes().to_vec();
+        let key = Blake3Hasher::key(context, &data);
+
+        root = tree.insert(root, &key, &data).unwrap();
+        leaves.insert(key, data);
+    }
+
+    // adjacent keys share most of their paths; every 16th key spreads across the tree
+    let clustered: Vec<_> = leaves.keys().skip(200).take(32).copied().collect();
+    let random: Vec<_> = leaves.keys().step_by(16).copied().collect();
+
+    let size = |keys: &[Hash]| {
+        let proof = tree.get_multiproof(context, root, keys).unwrap();
+        let kvs: Vec<_> = keys.iter().map(|k| (*k, leaves[k].as_slice())).collect();
+
+        assert!(MemorySmt::verify_multiproof(context, &root, &kvs, &proof));
+
+        let openings: usize = keys
+            .iter()
+            .map(|k| msgpacker::pack_to_vec(&tree.get_opening(root, k).unwrap().unwrap()).len())
+            .sum();
+
+        (msgpacker::pack_to_vec(&proof).len(), openings)
+    };
+
+    let (clustered, clustered_openings) = size(&clustered);
+    let (random, random_openings) = size(&random);
+
+    assert!(clustered * 4 < clustered_openings);
+    assert!(random < random_openings);
+    assert!(clustered < random);
+}
+
+proptest! {
+    #[test]
+    fn multiproof_verifies_only_the_proven_data(
+        inserted in collection::btree_set(any::<u16>(), 1..48),
+        proven in collection::vec(any::<prop::sample::Index>(), 1..16),
+    ) {
+        let context = "slots";
+        let tree = MemorySmt::default();
+        let inserted: Vec<_> = inserted.into_iter().collect();
+        let key = |n: &u16| Blake3Hasher::key(context, &n.to_le_bytes());
+        let mut root = MemorySmt::empty_tree_root();
+
+        for n in &inserted {
+            root = tree.insert(root, &key(n), &n.to_le_bytes()).unwrap();
+        }
+
+        let mut proven: Vec<_> = proven.iter().map(|i| *i.get(&inserted)).collect();
+
+        proven.sort_unstable();
+        proven.dedup();
+
+        let keys: Vec<_> = proven.iter().map(key).collect();
+        let data: Vec<_> = proven.iter().map(|n| n.to_le_bytes()).collect();
+        let kvs: Vec<_> = keys.iter().copied().zip(data.iter().map(|d| d.as_slice())).collect();
+        let proof = tree.get_multiproof(context, root, &keys).unwrap();
+
+        prop_assert!(MemorySmt::verify_multiproof(context, &root, &kvs, &proof));
+
+        // tampered data, a missing key, or a foreign root are rejected
+        let other = u16::MAX - proven[0];
+        let other_data = other.to_le_bytes();
+        let mut tampered = kvs.clone();
+
+        tampered[0] = (key(&other), other_data.as_slice());
+
+        prop_assert!(!MemorySmt::verify_multiproof(context, &root, &tampered, &proof));
+        prop_assert!(!MemorySmt::verify_multiproof(context, &root, &kvs[1..], &proof));
+        prop_assert!(!MemorySmt::verify_multiproof(context, &[0xff; 32], &kvs, &proof));
+
+        if !inserted.contains(&other) {
+            prop_assert!(tree.get_multiproof(context, root, &[key(&other)]).is_err());
+        }
+    }
+}
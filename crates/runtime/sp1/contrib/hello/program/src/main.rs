@@ -5,7 +5,7 @@ use valence_coprocessor::{Witness, WitnessCoprocessor};
 use valence_coprocessor_sp1::Sp1Hasher;
 
 pub fn main() {
-    let w = sp1_zkvm::io::read::<WitnessCoprocessor>();
+    let w = WitnessCoprocessor::decode(&sp1_zkvm::io::read_vec()).unwrap();
     let w = w.validate::<Sp1Hasher>().unwrap();
 
     let w = match &w.witnesses[0] {
@@ -4,9 +4,10 @@ use std::sync::{Arc, Mutex};
 use lru::LruCache;
 use serde::{de::DeserializeOwned, Serialize};
 use sp1_sdk::{
-    CpuProver, CudaProver, NetworkProver, Prover as _, ProverClient, SP1Proof,
+    CpuProver, CudaProver, HashableKey as _, NetworkProver, Prover as _, ProverClient, SP1Proof,
     SP1ProofWithPublicValues, SP1ProvingKey, SP1PublicValues, SP1Stdin, SP1VerifyingKey,
 };
+use sp1_verifier::{Groth16Verifier, GROTH16_VK_BYTES};
 use valence_coprocessor::{
     Base64, DataBackend, ExecutionContext, Hash, Proof, WitnessCoprocessor, ZkVm,
 };
@@ -229,3 +230,59 @@ impl ZkVm for Sp1ZkVm {
         }
     }
 }
+
+/// Verifies a batch of Groth16 proofs of the same circuit.
+///
+/// The bincode encoded verifying key is decoded once for the whole batch. The results are
+/// positionally aligned with the proofs; a proof that can't be decoded, or isn't a Groth16 proof,
+/// is reported as invalid.
+pub fn verify_groth16_batch(vk: &[u8], proofs: &[Proof]) -> anyhow::Result<Vec<bool>> {
+    let vk: SP1VerifyingKey = bincode::deserialize(vk)?;
+    let vk = vk.bytes32();
+
+    let results = proofs
+        .iter()
+        .map(|p| {
+            p.decode()
+                .map(|(proof, inputs)| {
+                    Groth16Verifier::verify(&proof, &inputs, &vk, &GROTH16_VK_BYTES).is_ok()
+                })
+                .unwrap_or(false)
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Verifies a proof of the mock prover.
+///
+/// Mock proofs are the bincode encoded proof with its public values, verified with the bincode
+/// encoded verifying key.
+pub fn verify_mock(vk: &[u8], proof: &Proof) -> anyhow::Result<bool> {
+    let vk: SP1VerifyingKey = bincode::deserialize(vk)?;
+    let (proof, _) = proof.decode()?;
+    let proof: SP1ProofWithPublicValues = bincode::deserialize(&proof)?;
+
+    Ok(Sp1ZkVm::mock().verify(&vk, &proof))
+}
+
+/// Verifies a batch of proofs of the mock prover of the same circuit.
+///
+/// Follows [`verify_groth16_batch`]: the verifying key is decoded once, and a proof that can't be
+/// decoded is reported as invalid.
+pub fn verify_mock_batch(vk: &[u8], proofs: &[Proof]) -> anyhow::Result<Vec<bool>> {
+    let vk: SP1VerifyingKey = bincode::deserialize(vk)?;
+    let zkvm = Sp1ZkVm::mock();
+
+    let results = proofs
+        .iter()
+        .map(|p| {
+            p.decode()
+                .ok()
+                .and_then(|(proof, _)| bincode::deserialize(&proof).ok())
+                .is_some_and(|proof: SP1ProofWithPublicValues| zkvm.verify(&vk, &proof))
+        })
+        .collect();
+
+    Ok(results)
+}
@@ -1,9 +1,10 @@
 use std::{env, fs, path::PathBuf, process::Command};
 
+use sp1_sdk::SP1ProofWithPublicValues;
 use valence_coprocessor::{
-    mocks::MockVm, ControllerData, Historical, MemoryBackend, Registry, Witness, ZkVm as _,
+    mocks::MockVm, ControllerData, Historical, MemoryBackend, Proof, Registry, Witness, ZkVm as _,
 };
-use valence_coprocessor_sp1::{Mode, Sp1ZkVm};
+use valence_coprocessor_sp1::{verify_groth16_batch, Mode, Sp1ZkVm};
 
 fn get_hello_bytes() -> Vec<u8> {
     let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -61,3 +62,26 @@ fn deploy_hello() {
 
     assert_eq!(output, "Hello, Valence!");
 }
+
+#[test]
+fn groth16_batches_verify_positionally() {
+    let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let sample = PathBuf::from(dir)
+        .join("assets")
+        .join("sample-groth16-proof");
+
+    let vk = fs::read(sample.join("hello.vk")).unwrap();
+    let sp1 = SP1ProofWithPublicValues::load(sample.join("hello.proof")).unwrap();
+
+    let valid = Proof::new(sp1.bytes(), sp1.public_values.to_vec());
+    let forged = Proof::new(sp1.bytes(), b"other inputs");
+    let malformed = Proof {
+        proof: "not base64".into(),
+        inputs: String::new(),
+    };
+
+    let results = verify_groth16_batch(&vk, &[valid.clone(), forged, malformed, valid]).unwrap();
+
+    assert_eq!(results, [true, false, false, true]);
+    assert!(verify_groth16_batch(b"not a vk", &[]).is_err());
+}
@@ -0,0 +1,16 @@
+#![no_std]
+
+use valence_coprocessor_wasm::abi;
+
+extern crate alloc;
+
+#[no_mangle]
+pub extern "C" fn entrypoint() {
+    let args = abi::args().unwrap();
+
+    if let Some(code) = args["code"].as_u64() {
+        abi::abort(code as u32, "invalid condition");
+    }
+
+    abi::ret(&args).unwrap();
+}
@@ -0,0 +1,24 @@
+#![no_std]
+
+use valence_coprocessor_wasm::abi;
+
+extern crate alloc;
+
+#[no_mangle]
+pub extern "C" fn entrypoint() {
+    let args = abi::args().unwrap();
+
+    let label = args["label"].as_str().unwrap();
+    let previous = abi::get_commit(label).unwrap();
+
+    let commitment = match args["data"].as_str() {
+        Some(d) => Some(abi::commit(label, d.as_bytes()).unwrap()),
+        None => None,
+    };
+
+    abi::ret(&serde_json::json!({
+        "previous": previous,
+        "commitment": commitment,
+    }))
+    .unwrap();
+}
@@ -7,8 +7,12 @@ extern crate alloc;
 #[no_mangle]
 pub extern "C" fn entrypoint() {
     let controller = abi::get_controller().unwrap();
+    let metadata = abi::get_controller_metadata().unwrap();
 
-    let ret = serde_json::json!(controller);
+    let ret = serde_json::json!({
+        "controller": controller,
+        "metadata": metadata,
+    });
 
     abi::ret(&ret).unwrap();
 }
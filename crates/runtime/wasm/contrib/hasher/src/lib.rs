@@ -0,0 +1,22 @@
+#![no_std]
+
+use valence_coprocessor_wasm::abi;
+
+extern crate alloc;
+
+#[no_mangle]
+pub extern "C" fn entrypoint() {
+    let args = abi::args().unwrap();
+
+    let data = args["data"].as_str().unwrap();
+    let a = abi::hash(data.as_bytes()).unwrap();
+    let b = abi::hash(&a).unwrap();
+    let node = abi::merge(&a, &b).unwrap();
+
+    let ret = serde_json::json!({
+        "hash": a,
+        "node": node,
+    });
+
+    abi::ret(&ret).unwrap();
+}
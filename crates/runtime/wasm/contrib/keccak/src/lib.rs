@@ -0,0 +1,15 @@
+#![no_std]
+
+use valence_coprocessor_wasm::abi;
+
+extern crate alloc;
+
+#[no_mangle]
+pub extern "C" fn entrypoint() {
+    let args = abi::args().unwrap();
+
+    let data = args["data"].as_str().unwrap();
+    let hash = abi::keccak256(data.as_bytes()).unwrap();
+
+    abi::ret(&serde_json::json!(hash)).unwrap();
+}
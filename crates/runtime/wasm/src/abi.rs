@@ -1,18 +1,36 @@
 #![allow(static_mut_refs)]
 #![allow(dead_code)]
 
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 
 use serde_json::Value;
 use valence_coprocessor::{
-    CompoundOpening, FileSystem, Hash, HistoricalUpdate, StateProof, ValidatedDomainBlock, Witness,
+    Base64, CompoundOpening, ControllerMetadata, ControllerSchema, FileSystem, Hash,
+    HistoricalUpdate, NamedOutputs, Opening, StateProof, ValidatedDomainBlock, Witness,
+    WitnessCodec, WitnessEncoding,
 };
 
 #[cfg(not(feature = "std"))]
 use msgpacker::Unpackable as _;
 
-pub use crate::__log as log;
+pub use crate::{
+    __debug as debug, __error as error, __info as info, __log as log, __log_level as log_level,
+    __trace as trace, __warn as warn,
+};
 pub use alloc::format;
+pub use valence_coprocessor::LogLevel;
+
+/// Host return code for a HTTP response body that exceeds the configured limit.
+pub const RESPONSE_TOO_LARGE: i32 = -23;
+
+/// Host return code for a call to a host function that isn't registered.
+pub const HOST_FUNCTION_NOT_FOUND: i32 = -24;
+
+/// Host return code for a call short-circuited by the circuit breaker of an unhealthy endpoint.
+pub const ENDPOINT_UNAVAILABLE: i32 = -28;
+
+/// Host return code for a HTTP request that exceeded its timeout.
+pub const HTTP_TIMEOUT: i32 = -31;
 
 #[cfg(not(feature = "std"))]
 mod host {
@@ -20,6 +38,8 @@ mod host {
     extern "C" {
         pub(super) fn args(ptr: u32) -> i32;
         pub(super) fn ret(ptr: u32, len: u32) -> i32;
+        pub(super) fn abort(code: u32, ptr: u32, len: u32);
+        pub(super) fn now(ptr: u32) -> i32;
         pub(super) fn get_storage(ptr: u32) -> i32;
         pub(super) fn set_storage(ptr: u32, len: u32) -> i32;
         pub(super) fn get_storage_file(path_ptr: u32, path_len: u32, ptr: u32) -> i32;
@@ -27,7 +47,33 @@ mod host {
         pub(super) fn get_raw_storage(ptr: u32) -> i32;
         pub(super) fn set_raw_storage(ptr: u32, len: u32) -> i32;
         pub(super) fn get_controller(ptr: u32) -> i32;
+        pub(super) fn get_controller_metadata(ptr: u32) -> i32;
+        pub(super) fn get_witness_encoding(ptr: u32) -> i32;
         pub(super) fn get_historical(ptr: u32) -> i32;
+        pub(super) fn smt_contains(
+            context_ptr: u32,
+            context_len: u32,
+            key_ptr: u32,
+            key_len: u32,
+        ) -> i32;
+        pub(super) fn smt_insert_and_open(
+            context_ptr: u32,
+            context_len: u32,
+            key_ptr: u32,
+            key_len: u32,
+            value_ptr: u32,
+            value_len: u32,
+            ptr: u32,
+        ) -> i32;
+        pub(super) fn commit(
+            label_ptr: u32,
+            label_len: u32,
+            data_ptr: u32,
+            data_len: u32,
+            ptr: u32,
+        ) -> i32;
+        pub(super) fn get_commit(label_ptr: u32, label_len: u32, ptr: u32) -> i32;
+        pub(super) fn list_domains(ptr: u32) -> i32;
         pub(super) fn get_latest_block(domain_ptr: u32, domain_len: u32, ptr: u32) -> i32;
         pub(super) fn get_block_proof(
             domain_ptr: u32,
@@ -35,6 +81,13 @@ mod host {
             block_number_ptr: u32,
             ptr: u32,
         ) -> i32;
+        pub(super) fn get_block_proofs(
+            domain_ptr: u32,
+            domain_len: u32,
+            numbers_ptr: u32,
+            numbers_len: u32,
+            ptr: u32,
+        ) -> i32;
         pub(super) fn get_historical_update(root_ptr: u32, ptr: u32) -> i32;
         pub(super) fn get_state_proof(
             domain_ptr: u32,
@@ -43,7 +96,16 @@ mod host {
             args_len: u32,
             ptr: u32,
         ) -> i32;
+        pub(super) fn get_state_proofs(requests_ptr: u32, requests_len: u32, ptr: u32) -> i32;
         pub(super) fn http(args_ptr: u32, args_len: u32, ptr: u32) -> i32;
+        pub(super) fn http_batch(requests_ptr: u32, requests_len: u32, ptr: u32) -> i32;
+        pub(super) fn host_call(
+            name_ptr: u32,
+            name_len: u32,
+            args_ptr: u32,
+            args_len: u32,
+            ptr: u32,
+        ) -> i32;
         pub(super) fn log(ptr: u32, len: u32) -> i32;
         pub(super) fn alchemy(
             chain_ptr: u32,
@@ -54,14 +116,24 @@ mod host {
             params_len: u32,
             ptr: u32,
         ) -> i32;
+        pub(super) fn hash(data_ptr: u32, data_len: u32, ptr: u32) -> i32;
+        pub(super) fn merge(a_ptr: u32, b_ptr: u32, ptr: u32) -> i32;
+        pub(super) fn keccak256(data_ptr: u32, data_len: u32, ptr: u32) -> i32;
+        pub(super) fn verify_ics23(args_ptr: u32, args_len: u32, ptr: u32) -> i32;
     }
 }
 
 #[cfg(feature = "std")]
 pub(crate) mod use_std {
-    use std::sync::{LazyLock, Mutex};
+    use std::{
+        collections::HashMap,
+        sync::{LazyLock, Mutex},
+    };
 
-    use valence_coprocessor::{CompoundOpening, File, HistoricalUpdate, StateProof};
+    use valence_coprocessor::{
+        Blake3Context, Blake3Hasher, Blake3Historical, CompoundOpening, File, Hasher as _,
+        HistoricalUpdate, MemoryBackend, Registry, StateProof,
+    };
 
     use super::*;
 
@@ -99,8 +171,26 @@ pub(crate) mod use_std {
         /// controller identifier
         pub controller: Hash,
 
+        /// controller deployment metadata
+        pub metadata: Option<ControllerMetadata>,
+
         /// Execution logs.
         pub log: Vec<String>,
+
+        /// Labeled commitments.
+        pub commits: HashMap<String, Hash>,
+
+        /// Pinned clock of the execution, in Unix seconds.
+        pub now: u64,
+
+        /// Co-processor data, as the SMTs of the controller.
+        pub data: MemoryBackend,
+    }
+
+    fn context() -> anyhow::Result<Blake3Context<MemoryBackend>> {
+        let runtime = RUNTIME.lock().unwrap();
+
+        Ok(Blake3Historical::load(runtime.data.clone())?.context(runtime.controller))
     }
 
     pub fn args() -> anyhow::Result<Value> {
@@ -113,6 +203,21 @@ pub(crate) mod use_std {
         Ok(())
     }
 
+    pub fn now() -> anyhow::Result<u64> {
+        Ok(RUNTIME.lock().unwrap().now)
+    }
+
+    pub fn abort(code: u32, message: &str) -> ! {
+        RUNTIME.lock().unwrap().ret = serde_json::json!({
+            "error": {
+                "code": code,
+                "message": message,
+            }
+        });
+
+        panic!("controller aborted with code {code}: {message}")
+    }
+
     pub fn get_storage() -> anyhow::Result<FileSystem> {
         get_raw_storage().map(FileSystem::from_raw_device_unchecked)
     }
@@ -149,16 +254,67 @@ pub(crate) mod use_std {
         Ok(RUNTIME.lock().unwrap().controller)
     }
 
+    pub fn get_controller_metadata() -> anyhow::Result<Option<ControllerMetadata>> {
+        Ok(RUNTIME.lock().unwrap().metadata)
+    }
+
+    pub fn get_witness_encoding() -> anyhow::Result<WitnessEncoding> {
+        context()?.get_witness_encoding()
+    }
+
     pub fn get_historical() -> anyhow::Result<Hash> {
-        todo!()
+        Ok(context()?.get_historical())
+    }
+
+    pub fn smt_contains(context: &str, key: &[u8]) -> anyhow::Result<bool> {
+        self::context()?.smt_contains(context, key)
+    }
+
+    pub fn smt_insert_and_open(
+        context: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> anyhow::Result<(Hash, Opening)> {
+        self::context()?.smt_insert_and_open(context, key, value)
+    }
+
+    pub fn commit(label: &str, data: &[u8]) -> anyhow::Result<Hash> {
+        let commitment = Blake3Context::<MemoryBackend>::commitment(label, data);
+
+        RUNTIME
+            .lock()
+            .unwrap()
+            .commits
+            .insert(label.to_string(), commitment);
+
+        Ok(commitment)
+    }
+
+    pub fn get_commit(label: &str) -> anyhow::Result<Option<Hash>> {
+        Ok(RUNTIME.lock().unwrap().commits.get(label).copied())
     }
 
     pub fn get_latest_block(_domain: &str) -> anyhow::Result<Option<ValidatedDomainBlock>> {
         todo!()
     }
 
-    pub fn get_block_proof(_domain: &str, _block_number: u64) -> anyhow::Result<CompoundOpening> {
-        todo!()
+    pub fn list_domains() -> anyhow::Result<Vec<String>> {
+        let data = RUNTIME.lock().unwrap().data.clone();
+
+        Registry::from(data).list_domains()
+    }
+
+    pub fn get_block_proof(domain: &str, block_number: u64) -> anyhow::Result<CompoundOpening> {
+        context()?.get_block_proof(domain, block_number)
+    }
+
+    pub fn get_block_proofs(domain: &str, numbers: &[u64]) -> anyhow::Result<Vec<CompoundOpening>> {
+        let ctx = context()?;
+
+        numbers
+            .iter()
+            .map(|n| ctx.get_block_proof(domain, *n))
+            .collect()
     }
 
     pub fn get_historical_update(_root: &Hash) -> anyhow::Result<Option<HistoricalUpdate>> {
@@ -169,14 +325,56 @@ pub(crate) mod use_std {
         todo!()
     }
 
+    pub fn get_state_proofs(requests: &[(&str, Value)]) -> anyhow::Result<Vec<StateProof>> {
+        requests
+            .iter()
+            .enumerate()
+            .map(|(i, (domain, args))| {
+                get_state_proof(domain, args).map_err(|e| state_proof_error(i, domain, e))
+            })
+            .collect()
+    }
+
     pub fn http(args: &Value) -> anyhow::Result<Value> {
         valence_coprocessor::utils::http(args)
     }
 
+    pub fn http_batch(requests: &[Value]) -> anyhow::Result<Vec<Result<Value, String>>> {
+        Ok(requests
+            .iter()
+            .map(|r| http(r).map_err(|e| e.to_string()))
+            .collect())
+    }
+
     pub fn alchemy(_chain: &str, _method: &str, _params: &Value) -> anyhow::Result<Value> {
         todo!()
     }
 
+    pub fn host_call(name: &str, args: &Value) -> anyhow::Result<Value> {
+        match name {
+            "http" => http(args),
+            _ => anyhow::bail!("host function `{name}` not found"),
+        }
+    }
+
+    pub fn hash(data: &[u8]) -> anyhow::Result<Hash> {
+        Ok(Blake3Hasher::hash(data))
+    }
+
+    pub fn merge(a: &Hash, b: &Hash) -> anyhow::Result<Hash> {
+        Ok(Blake3Hasher::merge(a, b))
+    }
+
+    pub fn keccak256(data: &[u8]) -> anyhow::Result<Hash> {
+        use sha3::{Digest as _, Keccak256};
+
+        Ok(Keccak256::digest(data).into())
+    }
+
+    pub fn verify_ics23(args: &Value) -> anyhow::Result<bool> {
+        crate::host::valence::verify_ics23_args(args)
+    }
+
     pub fn __value_to_context_log(log: &str) -> anyhow::Result<()> {
         RUNTIME.lock().unwrap().log.push(log.to_string());
 
@@ -231,6 +429,63 @@ pub fn ret(value: &Value) -> anyhow::Result<()> {
     }
 }
 
+/// Returns the clock of the execution, in Unix seconds.
+///
+/// This is *not* the wall clock. The host pins the value to the time at which the historical root
+/// of the execution was recorded, so it is deterministic: re-running the witness generation
+/// anchored to the same root observes the same value.
+pub fn now() -> anyhow::Result<u64> {
+    #[cfg(feature = "std")]
+    return use_std::now();
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let ptr = BUF.as_ptr() as u32;
+        let len = host::now(ptr);
+
+        anyhow::ensure!(len == 8, "failed to fetch the execution clock");
+
+        valence_coprocessor::utils::decode_u64_le(&BUF[..8])
+    }
+}
+
+/// Aborts the controller execution with a structured error.
+///
+/// The return value is set to `{"error": {"code": code, "message": message}}` and the execution
+/// stops immediately, so controllers can bail from deep in their logic instead of panicking.
+pub fn abort(code: u32, message: &str) -> ! {
+    #[cfg(feature = "std")]
+    use_std::abort(code, message);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let len = core::cmp::min(BUF_LEN, message.len());
+
+        BUF[..len].copy_from_slice(&message.as_bytes()[..len]);
+
+        let ptr = BUF.as_ptr() as u32;
+
+        host::abort(code, ptr, len as u32);
+
+        #[cfg(target_arch = "wasm32")]
+        core::arch::wasm32::unreachable();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        loop {}
+    }
+}
+
+/// Set multiple named return values to the host.
+///
+/// The outputs are wrapped into a [`NamedOutputs`] envelope, so clients can fetch each value by
+/// name.
+pub fn ret_named(outputs: &[(&str, Value)]) -> anyhow::Result<()> {
+    let outputs: NamedOutputs = outputs.iter().map(|(k, v)| (*k, v.clone())).collect();
+    let outputs = serde_json::to_value(outputs)?;
+
+    ret(&outputs)
+}
+
 pub fn get_storage() -> anyhow::Result<FileSystem> {
     #[cfg(feature = "std")]
     return use_std::get_storage();
@@ -359,6 +614,65 @@ pub fn get_controller() -> anyhow::Result<Hash> {
     }
 }
 
+/// Get the deployment metadata of the current controller.
+///
+/// The metadata contains the identifier, nonce, and circuit hash assigned by the registry, and can
+/// be used to namespace storage or logs. Returns `None` for controllers deployed before the
+/// registry recorded metadata.
+pub fn get_controller_metadata() -> anyhow::Result<Option<ControllerMetadata>> {
+    #[cfg(feature = "std")]
+    return use_std::get_controller_metadata();
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let ptr = BUF.as_ptr() as u32;
+        let len = host::get_controller_metadata(ptr);
+
+        anyhow::ensure!(len >= 0, "failed to read controller metadata");
+        anyhow::ensure!(len as usize <= BUF_LEN, "controller metadata too large");
+
+        Option::unpack(&BUF[..len as usize])
+            .map(|(_, m)| m)
+            .map_err(|e| anyhow::anyhow!("error unpacking controller metadata: {e}"))
+    }
+}
+
+/// Get the witness data encoding selected when the controller was deployed.
+///
+/// The circuit decodes the data witnesses with the same encoding.
+pub fn get_witness_encoding() -> anyhow::Result<WitnessEncoding> {
+    #[cfg(feature = "std")]
+    return use_std::get_witness_encoding();
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let ptr = BUF.as_ptr() as u32;
+        let len = host::get_witness_encoding(ptr);
+
+        anyhow::ensure!(len == 1, "failed to read witness encoding");
+
+        WitnessEncoding::try_from_byte(BUF[0])
+    }
+}
+
+/// Encodes a data witness with the provided codec.
+///
+/// Fails if the codec doesn't implement the encoding selected when the controller was deployed.
+pub fn encode_witness<C, T>(value: &T) -> anyhow::Result<Witness>
+where
+    C: WitnessCodec<T>,
+{
+    let encoding = get_witness_encoding()?;
+
+    anyhow::ensure!(
+        C::ENCODING == encoding,
+        "the controller was deployed with the `{encoding}` encoding, not `{}`",
+        C::ENCODING
+    );
+
+    C::to_witness(value)
+}
+
 /// Get the opening to the provided root on the historical SMT.
 pub fn get_historical() -> anyhow::Result<Hash> {
     #[cfg(feature = "std")]
@@ -375,7 +689,160 @@ pub fn get_historical() -> anyhow::Result<Hash> {
     }
 }
 
+/// Returns the root of the co-processor SMT the controller is executed against.
+///
+/// This is the same root used by [`smt_contains`], so membership checks can be related to a known
+/// root.
+pub fn get_smt_root() -> anyhow::Result<Hash> {
+    get_historical()
+}
+
+/// Returns `true` if the key is a member of the co-processor SMT of the provided context.
+///
+/// The membership is checked under the current root without fetching an opening. The root is
+/// read by the host on each call, so if it advances between [`get_smt_root`] and this call, the
+/// check is relative to the newer root.
+pub fn smt_contains(context: &str, key: &[u8]) -> anyhow::Result<bool> {
+    #[cfg(feature = "std")]
+    return use_std::smt_contains(context, key);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let context_ptr = context.as_ptr() as u32;
+        let context_len = context.len() as u32;
+        let key_ptr = key.as_ptr() as u32;
+        let key_len = key.len() as u32;
+
+        let r = host::smt_contains(context_ptr, context_len, key_ptr, key_len);
+
+        anyhow::ensure!(r >= 0, "failed to check the smt membership");
+
+        Ok(r == 1)
+    }
+}
+
+/// Inserts the value into the co-processor SMT of the provided context, returning the new root and
+/// the opening of the value to it.
+///
+/// The root is maintained by the host per controller and context, so consecutive inserts chain
+/// without tracking the root on the controller. The opening verifies via
+/// `Smt::verify(&opening, &root, &H::key(context, key), value)`.
+///
+/// Inserts made while computing witnesses are staged: they chain within the run, but neither the
+/// root nor the tree is persisted.
+pub fn smt_insert_and_open(
+    context: &str,
+    key: &[u8],
+    value: &[u8],
+) -> anyhow::Result<(Hash, Opening)> {
+    #[cfg(feature = "std")]
+    return use_std::smt_insert_and_open(context, key, value);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let context_ptr = context.as_ptr() as u32;
+        let context_len = context.len() as u32;
+        let key_ptr = key.as_ptr() as u32;
+        let key_len = key.len() as u32;
+        let value_ptr = value.as_ptr() as u32;
+        let value_len = value.len() as u32;
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::smt_insert_and_open(
+            context_ptr,
+            context_len,
+            key_ptr,
+            key_len,
+            value_ptr,
+            value_len,
+            ptr,
+        );
+
+        anyhow::ensure!(len >= 0, "failed to insert into the smt");
+        anyhow::ensure!(len as usize <= BUF_LEN, "arguments too large");
+
+        let len = len as usize;
+        let split = valence_coprocessor::HASH_LEN;
+
+        anyhow::ensure!(len >= split, "invalid smt insert returned by the host");
+
+        let root = Hash::try_from(&BUF[..split])
+            .map_err(|_| anyhow::anyhow!("invalid smt root returned by the host"))?;
+        let opening = Opening::unpack(&BUF[split..len])
+            .map(|(_, o)| o)
+            .map_err(|e| anyhow::anyhow!("error unpacking smt opening: {e}"))?;
+
+        Ok((root, opening))
+    }
+}
+
+/// Stores a labeled commitment of the data, returning it.
+///
+/// The commitment is `H::digest([len(label), label, data])`, with the length of the label as a
+/// little-endian `u64`, and is retained by the host after the execution ends. Commitments are
+/// scoped per controller: a later run of the same controller can read it via [`get_commit`], and
+/// committing again with the same label replaces it.
+pub fn commit(label: &str, data: &[u8]) -> anyhow::Result<Hash> {
+    #[cfg(feature = "std")]
+    return use_std::commit(label, data);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let label_ptr = label.as_ptr() as u32;
+        let label_len = label.len() as u32;
+
+        let data_ptr = data.as_ptr() as u32;
+        let data_len = data.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::commit(label_ptr, label_len, data_ptr, data_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to store commitment");
+
+        Hash::try_from(&BUF[..len as usize])
+            .map_err(|_| anyhow::anyhow!("invalid commitment returned by the host"))
+    }
+}
+
+/// Returns the last commitment stored by the controller under the label.
+pub fn get_commit(label: &str) -> anyhow::Result<Option<Hash>> {
+    #[cfg(feature = "std")]
+    return use_std::get_commit(label);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let label_ptr = label.as_ptr() as u32;
+        let label_len = label.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::get_commit(label_ptr, label_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to read commitment");
+
+        if len == 0 {
+            return Ok(None);
+        }
+
+        Hash::try_from(&BUF[..len as usize])
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("invalid commitment returned by the host"))
+    }
+}
+
 /// Returns the last included block for the provided domain.
+///
+/// Any registered domain can be queried, not only the one of the controller, so a controller can
+/// fetch state proofs from several domains in a single execution.
+///
+/// # Determinism
+///
+/// The block is the live tip of the domain, read when the host function is called, and is *not*
+/// pinned to the historical root of the execution. Witness generation and proving may run at
+/// different times and observe different tips, so the circuit must not depend on the value
+/// returned here. Select the block in the witness generation and carry its number in the
+/// witnesses, opening it with [get_block_proof], which is anchored to the historical root.
 pub fn get_latest_block(domain: &str) -> anyhow::Result<Option<ValidatedDomainBlock>> {
     #[cfg(feature = "std")]
     return use_std::get_latest_block(domain);
@@ -397,6 +864,29 @@ pub fn get_latest_block(domain: &str) -> anyhow::Result<Option<ValidatedDomainBl
     }
 }
 
+/// Returns the names of the registered domains, sorted.
+///
+/// As [get_latest_block], this reads the live registry and is not pinned to the historical root;
+/// a domain registered between the witness generation and the proving is listed only by the
+/// latter. Use it to discover domains, and carry the selected ones in the witnesses.
+pub fn list_domains() -> anyhow::Result<Vec<String>> {
+    #[cfg(feature = "std")]
+    return use_std::list_domains();
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let ptr = BUF.as_ptr() as u32;
+        let len = host::list_domains(ptr);
+
+        anyhow::ensure!(len >= 0, "failed to list the domains");
+        anyhow::ensure!(len as usize <= BUF_LEN, "domains list too large");
+
+        msgpacker::unpack_array(&BUF[..len as usize])
+            .map(|(_, d)| d)
+            .map_err(|e| anyhow::anyhow!("error unpacking domains: {e}"))
+    }
+}
+
 /// Returns a Merkle proof that opens a block number to the historical root.
 pub fn get_block_proof(domain: &str, block_number: u64) -> anyhow::Result<CompoundOpening> {
     #[cfg(feature = "std")]
@@ -407,7 +897,7 @@ pub fn get_block_proof(domain: &str, block_number: u64) -> anyhow::Result<Compou
         let domain_ptr = domain.as_ptr() as u32;
         let domain_len = domain.len() as u32;
 
-        let block_number = block_number.to_le_bytes();
+        let block_number = valence_coprocessor::utils::encode_u64_le(block_number);
         let block_number_ptr = block_number.as_ptr() as u32;
 
         let ptr = BUF.as_ptr() as u32;
@@ -423,6 +913,39 @@ pub fn get_block_proof(domain: &str, block_number: u64) -> anyhow::Result<Compou
     }
 }
 
+/// Returns the Merkle proofs that open each of the block numbers to the historical root.
+///
+/// The proofs are positionally aligned with the block numbers. The host caches block proofs per
+/// historical root, so fetching overlapping windows doesn't recompute them.
+pub fn get_block_proofs(domain: &str, numbers: &[u64]) -> anyhow::Result<Vec<CompoundOpening>> {
+    #[cfg(feature = "std")]
+    return use_std::get_block_proofs(domain, numbers);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let domain_ptr = domain.as_ptr() as u32;
+        let domain_len = domain.len() as u32;
+
+        let numbers: Vec<u8> = numbers
+            .iter()
+            .flat_map(|n| valence_coprocessor::utils::encode_u64_le(*n))
+            .collect();
+        let numbers_ptr = numbers.as_ptr() as u32;
+        let numbers_len = numbers.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::get_block_proofs(domain_ptr, domain_len, numbers_ptr, numbers_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to get block proofs");
+        anyhow::ensure!(len as usize <= BUF_LEN, "arguments too large");
+
+        msgpacker::unpack_array(&BUF[..len as usize])
+            .map(|(_, o)| o)
+            .map_err(|e| anyhow::anyhow!("error unpacking block proofs: {e}"))
+    }
+}
+
 ///  Returns the historical tree update that generated the provided root.
 pub fn get_historical_update(root: &Hash) -> anyhow::Result<Option<HistoricalUpdate>> {
     #[cfg(feature = "std")]
@@ -478,6 +1001,11 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
 
     #[cfg(not(feature = "std"))]
     unsafe {
+        let timeout_ms = args
+            .get("timeout_ms")
+            .and_then(Value::as_u64)
+            .unwrap_or(valence_coprocessor::utils::HTTP_DEFAULT_TIMEOUT_MS);
+
         let args = serde_json::to_vec(args)?;
         let args_ptr = args.as_ptr() as u32;
         let args_len = args.len() as u32;
@@ -486,6 +1014,12 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
 
         let len = host::http(args_ptr, args_len, ptr);
 
+        if len == HTTP_TIMEOUT {
+            return Err(valence_coprocessor::utils::RequestTimeout { timeout_ms }.into());
+        }
+
+        anyhow::ensure!(len != RESPONSE_TOO_LARGE, "http response too large");
+        anyhow::ensure!(len != ENDPOINT_UNAVAILABLE, "http endpoint unavailable");
         anyhow::ensure!(len >= 0, "failed to read http response");
         anyhow::ensure!(len as usize <= BUF_LEN, "arguments too large");
 
@@ -493,6 +1027,105 @@ pub fn http(args: &Value) -> anyhow::Result<Value> {
     }
 }
 
+/// Performs a batch of HTTP requests concurrently on the host, with a single host call.
+///
+/// The requests take the arguments of [`http`]. The results are returned in the order of the
+/// requests; a failed request doesn't fail the batch, but returns its error. The host caps the
+/// total size of the responses to the guest buffer, returning an error for those that don't fit.
+pub fn http_batch(requests: &[Value]) -> anyhow::Result<Vec<Result<Value, String>>> {
+    #[cfg(feature = "std")]
+    return use_std::http_batch(requests);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let args = serde_json::to_vec(requests)?;
+        let args_ptr = args.as_ptr() as u32;
+        let args_len = args.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::http_batch(args_ptr, args_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to read http responses");
+        anyhow::ensure!(len as usize <= BUF_LEN, "http responses too large");
+
+        let results: Vec<Value> = serde_json::from_slice(&BUF[..len as usize])?;
+
+        anyhow::ensure!(
+            results.len() == requests.len(),
+            "the number of http responses doesn't match the number of requests"
+        );
+
+        Ok(results
+            .into_iter()
+            .map(|mut r| match r.get_mut("response") {
+                Some(v) => Ok(v.take()),
+                None => Err(r
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+                    .into()),
+            })
+            .collect())
+    }
+}
+
+/// Fetches the state proofs of the provided `(domain, args)` requests concurrently on the host.
+///
+/// The proofs are returned in the order of the requests. If any request fails, the error reports
+/// its index and domain.
+pub fn get_state_proofs(requests: &[(&str, Value)]) -> anyhow::Result<Vec<StateProof>> {
+    #[cfg(feature = "std")]
+    return use_std::get_state_proofs(requests);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let args: Vec<_> = requests
+            .iter()
+            .map(|(domain, args)| serde_json::json!({"domain": domain, "args": args}))
+            .collect();
+
+        let args = serde_json::to_vec(&args)?;
+        let args_ptr = args.as_ptr() as u32;
+        let args_len = args.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::get_state_proofs(args_ptr, args_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to read state proofs");
+        anyhow::ensure!(len as usize <= BUF_LEN, "state proofs too large");
+
+        let results: Vec<Value> = serde_json::from_slice(&BUF[..len as usize])?;
+
+        anyhow::ensure!(
+            results.len() == requests.len(),
+            "the number of state proofs doesn't match the number of requests"
+        );
+
+        results
+            .into_iter()
+            .zip(requests)
+            .enumerate()
+            .map(|(i, (mut r, (domain, _)))| match r.get_mut("proof") {
+                Some(p) => Ok(serde_json::from_value(p.take())?),
+                None => {
+                    let e = r
+                        .get("error")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unknown error");
+
+                    Err(state_proof_error(i, domain, anyhow::anyhow!("{e}")))
+                }
+            })
+            .collect()
+    }
+}
+
+fn state_proof_error(index: usize, domain: &str, e: anyhow::Error) -> anyhow::Error {
+    anyhow::anyhow!("state proof request {index} for domain `{domain}` failed: {e}")
+}
+
 /// Performs an Alchemy API request.
 ///
 /// # Params
@@ -522,6 +1155,8 @@ pub fn alchemy(chain: &str, method: &str, params: &Value) -> anyhow::Result<Valu
             chain_ptr, chain_len, method_ptr, method_len, params_ptr, params_len, ptr,
         );
 
+        anyhow::ensure!(len != RESPONSE_TOO_LARGE, "alchemy response too large");
+        anyhow::ensure!(len != ENDPOINT_UNAVAILABLE, "alchemy endpoint unavailable");
         anyhow::ensure!(len >= 0, "failed to read alchemy result");
         anyhow::ensure!(len as usize <= BUF_LEN, "arguments too large");
 
@@ -529,13 +1164,167 @@ pub fn alchemy(chain: &str, method: &str, params: &Value) -> anyhow::Result<Valu
     }
 }
 
+/// Calls a host function registered by the operator under the provided name.
+///
+/// The built-in `http` and `alchemy` functions are available as well; `alchemy` expects the
+/// arguments `{"chain", "method", "params"}`.
+pub fn host_call(name: &str, args: &Value) -> anyhow::Result<Value> {
+    #[cfg(feature = "std")]
+    return use_std::host_call(name, args);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let name_ptr = name.as_ptr() as u32;
+        let name_len = name.len() as u32;
+
+        let args = serde_json::to_vec(args)?;
+        let args_ptr = args.as_ptr() as u32;
+        let args_len = args.len() as u32;
+
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::host_call(name_ptr, name_len, args_ptr, args_len, ptr);
+
+        anyhow::ensure!(
+            len != HOST_FUNCTION_NOT_FOUND,
+            "host function `{name}` not found"
+        );
+        anyhow::ensure!(len != RESPONSE_TOO_LARGE, "host response too large");
+        anyhow::ensure!(len >= 0, "failed to call host function `{name}`");
+        anyhow::ensure!(len as usize <= BUF_LEN, "arguments too large");
+
+        Ok(serde_json::from_slice(&BUF[..len as usize])?)
+    }
+}
+
+/// Hashes the data using the hasher of the host.
+///
+/// The result matches the leaf hashing of the sparse Merkle tree.
+pub fn hash(data: &[u8]) -> anyhow::Result<Hash> {
+    #[cfg(feature = "std")]
+    return use_std::hash(data);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let data_ptr = data.as_ptr() as u32;
+        let data_len = data.len() as u32;
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::hash(data_ptr, data_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to compute hash");
+
+        Ok(Hash::try_from(&BUF[..len as usize])?)
+    }
+}
+
+/// Merges two nodes into their parent using the hasher of the host.
+///
+/// `a` is the left child and `b` the right one. On the sparse Merkle tree, a node is on the left
+/// when its key bit at the parent depth is `0`.
+pub fn merge(a: &Hash, b: &Hash) -> anyhow::Result<Hash> {
+    #[cfg(feature = "std")]
+    return use_std::merge(a, b);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let a_ptr = a.as_ptr() as u32;
+        let b_ptr = b.as_ptr() as u32;
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::merge(a_ptr, b_ptr, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to merge nodes");
+
+        Ok(Hash::try_from(&BUF[..len as usize])?)
+    }
+}
+
+/// Computes the Keccak-256 hash of the data, as used by the EVM.
+///
+/// This is the original Keccak padding, and differs from the standardized SHA3-256.
+pub fn keccak256(data: &[u8]) -> anyhow::Result<Hash> {
+    #[cfg(feature = "std")]
+    return use_std::keccak256(data);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let data_ptr = data.as_ptr() as u32;
+        let data_len = data.len() as u32;
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::keccak256(data_ptr, data_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to compute keccak256");
+
+        Ok(Hash::try_from(&BUF[..len as usize])?)
+    }
+}
+
+/// Verifies an ICS23 proof of a cosmos store against a root, e.g. an app hash.
+///
+/// `spec` is the name of the proof spec: `iavl` for the SDK stores, or `tendermint` for the
+/// multistore. If `value` is provided, the proof must be an existence proof of the key/value
+/// pair; otherwise, it must be a non-existence proof of the key. An error is returned if the
+/// proof or the spec is malformed.
+pub fn verify_ics23(
+    proof: &[u8],
+    spec: &str,
+    root: &[u8],
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> anyhow::Result<bool> {
+    let args = serde_json::json!({
+        "proof": Base64::encode(proof),
+        "spec": spec,
+        "root": Base64::encode(root),
+        "key": Base64::encode(key),
+        "value": value.map(Base64::encode),
+    });
+
+    #[cfg(feature = "std")]
+    return use_std::verify_ics23(&args);
+
+    #[cfg(not(feature = "std"))]
+    unsafe {
+        let args = serde_json::to_vec(&args)?;
+        let args_ptr = args.as_ptr() as u32;
+        let args_len = args.len() as u32;
+        let ptr = BUF.as_ptr() as u32;
+
+        let len = host::verify_ics23(args_ptr, args_len, ptr);
+
+        anyhow::ensure!(len >= 0, "failed to verify the ics23 proof");
+
+        Ok(BUF[0] == 1)
+    }
+}
+
 /// Returns the provided witnesses to the context.
+///
+/// The host rejects the execution if the number of witnesses exceeds its configured maximum.
 pub fn ret_witnesses(witnesses: Vec<Witness>) -> anyhow::Result<()> {
     let witnesses = serde_json::to_value(witnesses)?;
 
     ret(&witnesses)
 }
 
+/// Returns the state proof of a domain, from its `get_state_proof` export.
+///
+/// The proof is typically assembled via [`StateProof::build`].
+pub fn ret_state_proof(proof: StateProof) -> anyhow::Result<()> {
+    let proof = serde_json::to_value(proof)?;
+
+    ret(&proof)
+}
+
+/// Returns the entrypoint arguments schema, from the `describe` export of the controller.
+pub fn ret_schema(schema: &ControllerSchema) -> anyhow::Result<()> {
+    let schema = serde_json::to_value(schema)?;
+
+    ret(&schema)
+}
+
 /// Logs a value into the context.
 pub fn __value_to_context_log(log: &str) -> anyhow::Result<()> {
     #[cfg(feature = "std")]
@@ -554,10 +1343,64 @@ pub fn __value_to_context_log(log: &str) -> anyhow::Result<()> {
     }
 }
 
+/// Logs a value into the context, tagged with the provided severity.
+pub fn __value_to_context_log_level(level: LogLevel, log: &str) -> anyhow::Result<()> {
+    __value_to_context_log(&level.tag(log))
+}
+
+/// Logs an info-level line; alias of `info!`.
 #[macro_export]
 macro_rules! __log {
     ($($arg:tt)*) => {
-        $crate::abi::__value_to_context_log(&$crate::abi::format!($($arg)*))
+        $crate::abi::log_level!($crate::abi::LogLevel::Info, $($arg)*)
+    }
+}
+
+/// Logs a line with the provided [`LogLevel`].
+#[macro_export]
+macro_rules! __log_level {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::abi::__value_to_context_log_level($level, &$crate::abi::format!($($arg)*))
+    }
+}
+
+/// Logs a trace-level line.
+#[macro_export]
+macro_rules! __trace {
+    ($($arg:tt)*) => {
+        $crate::abi::log_level!($crate::abi::LogLevel::Trace, $($arg)*)
+    }
+}
+
+/// Logs a debug-level line.
+#[macro_export]
+macro_rules! __debug {
+    ($($arg:tt)*) => {
+        $crate::abi::log_level!($crate::abi::LogLevel::Debug, $($arg)*)
+    }
+}
+
+/// Logs an info-level line.
+#[macro_export]
+macro_rules! __info {
+    ($($arg:tt)*) => {
+        $crate::abi::log_level!($crate::abi::LogLevel::Info, $($arg)*)
+    }
+}
+
+/// Logs a warn-level line.
+#[macro_export]
+macro_rules! __warn {
+    ($($arg:tt)*) => {
+        $crate::abi::log_level!($crate::abi::LogLevel::Warn, $($arg)*)
+    }
+}
+
+/// Logs an error-level line.
+#[macro_export]
+macro_rules! __error {
+    ($($arg:tt)*) => {
+        $crate::abi::log_level!($crate::abi::LogLevel::Error, $($arg)*)
     }
 }
 
@@ -0,0 +1,178 @@
+//! In-process harness to test controllers without a running service or prover.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use valence_coprocessor::{
+    mocks::MockZkVm, Blake3Hasher, Blake3Historical, ControllerData, ExecutionContext, Hash,
+    MemoryBackend, Registry, StateProof, Witness,
+};
+
+use crate::host::{
+    plugin::{HostFunction, HostRegistry},
+    ValenceWasm,
+};
+
+/// The captured output of a controller execution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HarnessOutput {
+    /// The value returned by the controller via `abi::ret`.
+    pub ret: Value,
+
+    /// The log entries of the execution.
+    pub log: Vec<String>,
+}
+
+impl HarnessOutput {
+    /// Parses the returned value as the witnesses of a `get_witnesses` call.
+    pub fn ret_witnesses(&self) -> anyhow::Result<Vec<Witness>> {
+        Ok(serde_json::from_value(self.ret.clone())?)
+    }
+}
+
+/// Runs a compiled controller in-process, with mocked HTTP responses and state proofs.
+///
+/// The controller is registered on a memory backend and executed by [`ValenceWasm`], so the
+/// behavior matches the service for everything but the mocked host calls.
+pub struct ControllerTestHarness {
+    historical: Blake3Historical<MemoryBackend>,
+    vm: ValenceWasm<Blake3Hasher, MemoryBackend>,
+    controller: Hash,
+    http: HashMap<String, Value>,
+    state_proofs: HashMap<String, StateProof>,
+}
+
+impl ControllerTestHarness {
+    /// Loads the compiled WASM bytes of a controller.
+    pub fn new(wasm: Vec<u8>) -> anyhow::Result<Self> {
+        let data = MemoryBackend::default();
+        let historical = Blake3Historical::load(data.clone())?;
+        let vm = ValenceWasm::new(1)?;
+
+        let ctx = historical.context_without_controller();
+        let controller = ControllerData::default().with_controller(wasm);
+        let controller = Registry::from(data).register_controller(
+            &vm,
+            &MockZkVm::default(),
+            &ctx,
+            controller,
+        )?;
+
+        Ok(Self {
+            historical,
+            vm,
+            controller,
+            http: HashMap::new(),
+            state_proofs: HashMap::new(),
+        })
+    }
+
+    /// Returns the identifier of the loaded controller.
+    pub fn controller(&self) -> &Hash {
+        &self.controller
+    }
+
+    /// Returns the execution context of the loaded controller.
+    pub fn context(&self) -> ExecutionContext<Blake3Hasher, MemoryBackend> {
+        self.historical.context(self.controller)
+    }
+
+    /// Mocks the response of `abi::http` for the provided url.
+    ///
+    /// Requests to urls without a mocked response fail.
+    pub fn with_http_response<U: Into<String>>(mut self, url: U, response: Value) -> Self {
+        self.http.insert(url.into(), response);
+        self
+    }
+
+    /// Mocks the state proof returned by `abi::get_state_proof` for the provided domain.
+    pub fn with_state_proof<N: Into<String>>(mut self, domain: N, proof: StateProof) -> Self {
+        self.state_proofs.insert(domain.into(), proof);
+        self
+    }
+
+    /// Sets the maximum number of witnesses the controller can return.
+    pub fn with_max_witnesses(mut self, max: usize) -> Self {
+        self.vm = self.vm.with_max_witnesses(max);
+        self
+    }
+
+    /// Sets a file of the controller storage.
+    pub fn with_storage_file(self, path: &str, contents: &[u8]) -> anyhow::Result<Self> {
+        self.context().set_storage_file(path, contents)?;
+
+        Ok(self)
+    }
+
+    /// Sets the raw storage of the controller.
+    pub fn with_raw_storage(self, storage: &[u8]) -> anyhow::Result<Self> {
+        self.context().set_raw_storage(storage)?;
+
+        Ok(self)
+    }
+
+    /// Runs the provided export of the controller with the mocked arguments.
+    pub fn run(&self, export: &str, args: Value) -> anyhow::Result<HarnessOutput> {
+        let http = self.http.clone();
+        let state_proofs = self.state_proofs.clone();
+
+        let vm = self
+            .vm
+            .clone()
+            .with_host_function(
+                HostRegistry::HTTP,
+                HostFunction::new(move |args| {
+                    let url = args.get("url").and_then(Value::as_str).unwrap_or_default();
+
+                    http.get(url)
+                        .cloned()
+                        .ok_or_else(|| anyhow::anyhow!("no mocked response for `{url}`"))
+                }),
+            )
+            .with_host_function(
+                HostRegistry::STATE_PROOF,
+                HostFunction::new(move |args| {
+                    let domain = args
+                        .get("domain")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+
+                    let proof = state_proofs
+                        .get(domain)
+                        .ok_or_else(|| anyhow::anyhow!("no mocked state proof for `{domain}`"))?;
+
+                    Ok(serde_json::to_value(proof)?)
+                }),
+            );
+
+        let ctx = self.context();
+        let ret = valence_coprocessor::Vm::execute(&vm, &ctx, &self.controller, export, args)?;
+        let log = ctx.get_log()?;
+
+        Ok(HarnessOutput { ret, log })
+    }
+
+    /// Runs the `entrypoint` export of the controller.
+    pub fn entrypoint(&self, args: Value) -> anyhow::Result<HarnessOutput> {
+        self.run(
+            ExecutionContext::<Blake3Hasher, MemoryBackend>::CONTROLLER_ENTRYPOINT,
+            args,
+        )
+    }
+
+    /// Runs the `describe` export of the controller.
+    pub fn describe(&self) -> anyhow::Result<HarnessOutput> {
+        self.run(
+            ExecutionContext::<Blake3Hasher, MemoryBackend>::CONTROLLER_DESCRIBE,
+            Value::Null,
+        )
+    }
+
+    /// Runs the `get_witnesses` export of the controller.
+    pub fn get_witnesses(&self, args: Value) -> anyhow::Result<HarnessOutput> {
+        self.run(
+            ExecutionContext::<Blake3Hasher, MemoryBackend>::CONTROLLER_GET_WITNESSES,
+            args,
+        )
+    }
+}
@@ -0,0 +1,326 @@
+//! Host-side health tracking of the RPC endpoints called by controllers.
+//!
+//! Every endpoint (i.e. the `scheme://host:port` of a URL, without its credentials) has a circuit
+//! breaker. After
+//! [`BreakerPolicy::threshold`] consecutive failures, the breaker opens and calls to the endpoint
+//! fail fast with [`EndpointUnavailable`] until [`BreakerPolicy::cooldown`] elapses. A single
+//! trial call is then let through; it closes the breaker on success, or re-opens it for another
+//! cooldown on failure.
+//!
+//! If a backup is configured for an endpoint, calls fail over to it whenever the primary fails or
+//! its breaker is open.
+//!
+//! The breakers are disabled unless a policy is explicitly set; see [`BreakerPolicy::DISABLED`]
+//! and [`BreakerPolicy::RECOMMENDED`].
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use valence_coprocessor::utils;
+
+/// Thresholds of the endpoint circuit breakers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerPolicy {
+    /// Consecutive failures that open the breaker of an endpoint. `0` disables the breakers.
+    pub threshold: u32,
+
+    /// Time an open breaker short-circuits calls before letting a trial call through.
+    pub cooldown: Duration,
+}
+
+impl BreakerPolicy {
+    /// A policy that never opens the breakers.
+    pub const DISABLED: Self = Self {
+        threshold: 0,
+        cooldown: Duration::from_secs(30),
+    };
+
+    /// The policy recommended for a service calling public endpoints.
+    pub const RECOMMENDED: Self = Self {
+        threshold: 5,
+        cooldown: Duration::from_secs(30),
+    };
+}
+
+/// State of an endpoint circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls go through.
+    Closed,
+
+    /// Calls fail fast until the cooldown elapses.
+    Open,
+
+    /// The cooldown elapsed; the next call is a trial.
+    HalfOpen,
+}
+
+impl fmt::Display for BreakerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakerState::Closed => write!(f, "closed"),
+            BreakerState::Open => write!(f, "open"),
+            BreakerState::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+/// Health of a tracked endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    /// The endpoint, as `scheme://host:port`, without its credentials.
+    pub endpoint: String,
+
+    /// Current state of the breaker.
+    pub state: BreakerState,
+
+    /// Number of consecutive failures.
+    pub consecutive_failures: u32,
+
+    /// Total number of successful calls.
+    pub successes: u64,
+
+    /// Total number of failed calls.
+    pub failures: u64,
+
+    /// Total number of calls short-circuited by the open breaker.
+    pub rejected: u64,
+}
+
+/// Error returned when a call is short-circuited by an open breaker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointUnavailable {
+    /// The unavailable endpoint.
+    pub endpoint: String,
+}
+
+impl fmt::Display for EndpointUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "endpoint `{}` is unavailable; breaker open",
+            self.endpoint
+        )
+    }
+}
+
+impl std::error::Error for EndpointUnavailable {}
+
+#[derive(Debug, Default)]
+struct Entry {
+    consecutive_failures: u32,
+    opened: Option<Instant>,
+    successes: u64,
+    failures: u64,
+    rejected: u64,
+}
+
+/// Circuit breakers of the endpoints, shared across executions.
+#[derive(Debug, Clone)]
+pub struct EndpointBreakers {
+    policy: BreakerPolicy,
+    /// The base URL of the backup of an endpoint, with its credentials.
+    backups: Arc<HashMap<String, String>>,
+    endpoints: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl EndpointBreakers {
+    /// Creates a new set of breakers with the provided policy.
+    pub fn new(policy: BreakerPolicy) -> Self {
+        Self {
+            policy,
+            backups: Default::default(),
+            endpoints: Default::default(),
+        }
+    }
+
+    /// Sets the backup endpoint of `primary`.
+    ///
+    /// Failed over calls keep the path of the original URL, and use the credentials of the backup.
+    pub fn with_backup(mut self, primary: &str, backup: &str) -> Self {
+        let backup = backup[..Self::endpoint_len(backup)].to_string();
+
+        Arc::make_mut(&mut self.backups).insert(Self::endpoint(primary), backup);
+        self
+    }
+
+    /// Sets the policy of the breakers.
+    pub fn with_policy(mut self, policy: BreakerPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns the policy of the breakers.
+    pub fn policy(&self) -> BreakerPolicy {
+        self.policy
+    }
+
+    /// Returns the endpoint of a URL, as `scheme://host:port`.
+    ///
+    /// The userinfo of the URL is stripped, so credentials never end up in the tracked endpoints.
+    pub fn endpoint(url: &str) -> String {
+        let start = url.find("://").map(|i| i + 3).unwrap_or(0);
+        let end = Self::endpoint_len(url);
+        let host = url[start..end]
+            .rfind('@')
+            .map(|i| start + i + 1)
+            .unwrap_or(start);
+
+        format!("{}{}", &url[..start], &url[host..end]).to_lowercase()
+    }
+
+    fn endpoint_len(url: &str) -> usize {
+        let start = url.find("://").map(|i| i + 3).unwrap_or(0);
+
+        url[start..]
+            .find(['/', '?', '#'])
+            .map(|i| start + i)
+            .unwrap_or(url.len())
+    }
+
+    /// Calls `f` with the provided URL, failing over to the backup of its endpoint, if any.
+    ///
+    /// The call fails over if the primary endpoint fails, or if its breaker is open.
+    pub fn call<F>(&self, url: &str, f: F) -> anyhow::Result<Value>
+    where
+        F: Fn(&str) -> anyhow::Result<Value>,
+    {
+        let primary = Self::endpoint(url);
+        let ret = self.guard(&primary, || f(url));
+
+        let backup = match self.backups.get(&primary) {
+            Some(b) if Self::is_failure(&ret) => b,
+            _ => return ret,
+        };
+
+        tracing::debug!("endpoint `{primary}` failed; failing over to `{backup}`...");
+
+        let url = format!("{backup}{}", &url[Self::endpoint_len(url)..]);
+
+        self.guard(&Self::endpoint(backup), || f(&url))
+    }
+
+    /// Calls `f` if the breaker of `endpoint` allows it, recording the outcome.
+    ///
+    /// A call fails if it returns an error, or a response with a server error status.
+    pub fn guard<F>(&self, endpoint: &str, f: F) -> anyhow::Result<Value>
+    where
+        F: FnOnce() -> anyhow::Result<Value>,
+    {
+        if self.policy.threshold == 0 || endpoint.is_empty() {
+            return f();
+        }
+
+        if !self.allow(endpoint) {
+            return Err(EndpointUnavailable {
+                endpoint: endpoint.into(),
+            }
+            .into());
+        }
+
+        let ret = f();
+
+        self.record(endpoint, Self::is_failure(&ret));
+
+        ret
+    }
+
+    fn is_failure(ret: &anyhow::Result<Value>) -> bool {
+        match ret {
+            // the response is oversized, but the endpoint is healthy
+            Err(e) if e.is::<utils::ResponseTooLarge>() => false,
+            Err(_) => true,
+            Ok(r) => r.get("status").and_then(Value::as_u64).unwrap_or(0) >= 500,
+        }
+    }
+
+    /// Returns the health of the tracked endpoints, sorted by endpoint.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let endpoints = self
+            .endpoints
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let mut health: Vec<_> = endpoints
+            .iter()
+            .map(|(endpoint, e)| EndpointHealth {
+                endpoint: endpoint.clone(),
+                state: self.state(e),
+                consecutive_failures: e.consecutive_failures,
+                successes: e.successes,
+                failures: e.failures,
+                rejected: e.rejected,
+            })
+            .collect();
+
+        health.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+
+        health
+    }
+
+    fn state(&self, entry: &Entry) -> BreakerState {
+        match entry.opened {
+            Some(o) if o.elapsed() < self.policy.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+            None => BreakerState::Closed,
+        }
+    }
+
+    fn allow(&self, endpoint: &str) -> bool {
+        let mut endpoints = self
+            .endpoints
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let entry = endpoints.entry(endpoint.into()).or_default();
+
+        match self.state(entry) {
+            BreakerState::Closed => true,
+            BreakerState::Open => {
+                entry.rejected += 1;
+                false
+            }
+            BreakerState::HalfOpen => {
+                // concurrent calls are rejected until the trial completes
+                entry.opened = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    fn record(&self, endpoint: &str, failed: bool) {
+        let mut endpoints = self
+            .endpoints
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let entry = endpoints.entry(endpoint.into()).or_default();
+
+        if !failed {
+            entry.successes += 1;
+            entry.consecutive_failures = 0;
+            entry.opened = None;
+
+            return;
+        }
+
+        entry.failures += 1;
+        entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+
+        if entry.consecutive_failures >= self.policy.threshold {
+            if entry.opened.is_none() {
+                tracing::warn!(
+                    "endpoint `{endpoint}` failed {} times; breaker open",
+                    entry.consecutive_failures
+                );
+            }
+
+            entry.opened = Some(Instant::now());
+        }
+    }
+}
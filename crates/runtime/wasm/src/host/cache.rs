@@ -0,0 +1,109 @@
+//! Host-side cache of historical block proofs.
+
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use lru::LruCache;
+use valence_coprocessor::{CompoundOpening, Hash};
+
+type Key = (Hash, String, u64);
+
+/// Hit and miss counters of a cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of lookups served from the cache.
+    pub hits: u64,
+
+    /// Number of lookups that computed the value.
+    pub misses: u64,
+}
+
+/// A LRU cache of block proofs, shared across executions.
+///
+/// A block proof is keyed by `(historical root, domain, number)`. Since a historical root commits
+/// to an immutable set of blocks, a cached opening never goes stale and can outlive the execution
+/// that computed it.
+#[derive(Debug, Clone)]
+pub struct BlockProofCache {
+    proofs: Option<Arc<Mutex<LruCache<Key, CompoundOpening>>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl Default for BlockProofCache {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl BlockProofCache {
+    /// Default number of cached block proofs.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// Creates a new cache with the provided capacity.
+    ///
+    /// A capacity of `0` disables the cache.
+    pub fn new(capacity: usize) -> Self {
+        let proofs = NonZeroUsize::new(capacity).map(|c| Arc::new(Mutex::new(LruCache::new(c))));
+
+        Self {
+            proofs,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the cached block proof, computing it with `f` if absent.
+    pub fn get_or_insert<F>(
+        &self,
+        root: Hash,
+        domain: &str,
+        number: u64,
+        f: F,
+    ) -> anyhow::Result<CompoundOpening>
+    where
+        F: FnOnce() -> anyhow::Result<CompoundOpening>,
+    {
+        let proofs = match &self.proofs {
+            Some(p) => p,
+            None => return f(),
+        };
+
+        let key = (root, domain.to_string(), number);
+        let cached = proofs
+            .lock()
+            .map_err(|e| anyhow::anyhow!("error locking block proofs: {e}"))?
+            .get(&key)
+            .cloned();
+
+        if let Some(p) = cached {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+
+            return Ok(p);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let proof = f()?;
+
+        proofs
+            .lock()
+            .map_err(|e| anyhow::anyhow!("error locking block proofs: {e}"))?
+            .put(key, proof.clone());
+
+        Ok(proof)
+    }
+
+    /// Returns the hit and miss counters of the cache.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
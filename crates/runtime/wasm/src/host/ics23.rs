@@ -0,0 +1,654 @@
+//! Host-side verifier of ICS23 commitment proofs, as produced by cosmos chains.
+//!
+//! Proofs are the protobuf encoding of a `CommitmentProof`. Existence proofs verify the
+//! membership of a key/value pair, and non-existence proofs verify the absence of a key via its
+//! left and right neighbors. Batch and compressed proofs are not supported.
+
+use anyhow::Context as _;
+use sha2::{Digest as _, Sha256, Sha512, Sha512_256};
+use sha3::Keccak256;
+
+/// Hash function of a proof operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashOp {
+    /// The data is forwarded as-is.
+    NoHash,
+
+    /// SHA-256.
+    Sha256,
+
+    /// SHA-512.
+    Sha512,
+
+    /// Keccak-256.
+    Keccak256,
+
+    /// SHA-512/256.
+    Sha512_256,
+}
+
+impl HashOp {
+    fn from_proto(value: u64) -> anyhow::Result<Self> {
+        match value {
+            0 => Ok(HashOp::NoHash),
+            1 => Ok(HashOp::Sha256),
+            2 => Ok(HashOp::Sha512),
+            3 => Ok(HashOp::Keccak256),
+            6 => Ok(HashOp::Sha512_256),
+            v => anyhow::bail!("unsupported ics23 hash operation `{v}`"),
+        }
+    }
+
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashOp::NoHash => data.to_vec(),
+            HashOp::Sha256 => Sha256::digest(data).to_vec(),
+            HashOp::Sha512 => Sha512::digest(data).to_vec(),
+            HashOp::Keccak256 => Keccak256::digest(data).to_vec(),
+            HashOp::Sha512_256 => Sha512_256::digest(data).to_vec(),
+        }
+    }
+}
+
+/// Length prefix of a hashed leaf component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthOp {
+    /// The data is not prefixed.
+    NoPrefix,
+
+    /// The data is prefixed with its protobuf varint length.
+    VarProto,
+
+    /// The data must be 32 bytes long.
+    Require32Bytes,
+
+    /// The data must be 64 bytes long.
+    Require64Bytes,
+}
+
+impl LengthOp {
+    fn from_proto(value: u64) -> anyhow::Result<Self> {
+        match value {
+            0 => Ok(LengthOp::NoPrefix),
+            1 => Ok(LengthOp::VarProto),
+            7 => Ok(LengthOp::Require32Bytes),
+            8 => Ok(LengthOp::Require64Bytes),
+            v => anyhow::bail!("unsupported ics23 length operation `{v}`"),
+        }
+    }
+
+    fn apply(&self, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match self {
+            LengthOp::NoPrefix => Ok(data),
+            LengthOp::VarProto => {
+                let mut prefixed = encode_varint(data.len() as u64);
+
+                prefixed.extend(data);
+
+                Ok(prefixed)
+            }
+            LengthOp::Require32Bytes => {
+                anyhow::ensure!(data.len() == 32, "expected 32 bytes, got {}", data.len());
+                Ok(data)
+            }
+            LengthOp::Require64Bytes => {
+                anyhow::ensure!(data.len() == 64, "expected 64 bytes, got {}", data.len());
+                Ok(data)
+            }
+        }
+    }
+}
+
+/// Operation that hashes a key/value pair into a leaf.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafOp {
+    pub hash: HashOp,
+    pub prehash_key: HashOp,
+    pub prehash_value: HashOp,
+    pub length: LengthOp,
+    pub prefix: Vec<u8>,
+}
+
+impl LeafOp {
+    fn apply(&self, key: &[u8], value: &[u8]) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(!key.is_empty(), "leaf key is empty");
+        anyhow::ensure!(!value.is_empty(), "leaf value is empty");
+
+        let key = self.length.apply(self.prehash_key.apply(key))?;
+        let value = self.length.apply(self.prehash_value.apply(value))?;
+
+        Ok(self
+            .hash
+            .apply(&[self.prefix.as_slice(), &key, &value].concat()))
+    }
+}
+
+/// Operation that hashes a child into its parent node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerOp {
+    pub hash: HashOp,
+    pub prefix: Vec<u8>,
+    pub suffix: Vec<u8>,
+}
+
+impl InnerOp {
+    fn apply(&self, child: &[u8]) -> anyhow::Result<Vec<u8>> {
+        anyhow::ensure!(!child.is_empty(), "inner child is empty");
+
+        Ok(self
+            .hash
+            .apply(&[self.prefix.as_slice(), child, &self.suffix].concat()))
+    }
+}
+
+/// Proof that a key/value pair is a leaf of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistenceProof {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+    pub leaf: LeafOp,
+
+    /// Path from the leaf to the root.
+    pub path: Vec<InnerOp>,
+}
+
+impl ExistenceProof {
+    /// Computes the root of the tree.
+    pub fn root(&self) -> anyhow::Result<Vec<u8>> {
+        self.path
+            .iter()
+            .try_fold(self.leaf.apply(&self.key, &self.value)?, |node, op| {
+                op.apply(&node)
+            })
+    }
+
+    fn verify(&self, spec: &ProofSpec, root: &[u8]) -> anyhow::Result<bool> {
+        spec.check(self)?;
+
+        Ok(self.root()? == root)
+    }
+}
+
+/// Proof that a key is absent of the tree, via its neighbors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonExistenceProof {
+    pub key: Vec<u8>,
+    pub left: Option<ExistenceProof>,
+    pub right: Option<ExistenceProof>,
+}
+
+/// A decoded ICS23 commitment proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitmentProof {
+    Exist(ExistenceProof),
+    Nonexist(NonExistenceProof),
+}
+
+impl CommitmentProof {
+    /// Decodes the protobuf encoding of a `CommitmentProof`.
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut proof = None;
+
+        for field in Fields::new(bytes) {
+            proof = match field? {
+                (1, Field::Bytes(b)) => Some(CommitmentProof::Exist(decode_existence(b)?)),
+                (2, Field::Bytes(b)) => Some(CommitmentProof::Nonexist(decode_non_existence(b)?)),
+                (3 | 4, _) => anyhow::bail!("batch and compressed ics23 proofs are not supported"),
+                _ => proof,
+            };
+        }
+
+        proof.context("the ics23 proof is empty")
+    }
+}
+
+/// Specification of the tree layout of the proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofSpec {
+    /// Expected leaf operation; the leaf prefix of a proof must start with the spec prefix.
+    pub leaf: LeafOp,
+
+    /// Order of the children of an inner node.
+    pub child_order: Vec<usize>,
+
+    /// Byte-length of a child in an inner node.
+    pub child_size: usize,
+
+    /// Minimum byte-length of an inner prefix, excluding the left children.
+    pub min_prefix_length: usize,
+
+    /// Maximum byte-length of an inner prefix, excluding the left children.
+    pub max_prefix_length: usize,
+
+    /// Hash of the inner nodes.
+    pub inner_hash: HashOp,
+
+    /// Whether the nodes carry the IAVL height, size and version.
+    pub iavl: bool,
+}
+
+impl ProofSpec {
+    /// The spec of the IAVL trees of the cosmos SDK stores.
+    pub fn iavl() -> Self {
+        Self {
+            leaf: LeafOp {
+                hash: HashOp::Sha256,
+                prehash_key: HashOp::NoHash,
+                prehash_value: HashOp::Sha256,
+                length: LengthOp::VarProto,
+                prefix: vec![0],
+            },
+            child_order: vec![0, 1],
+            child_size: 33,
+            min_prefix_length: 4,
+            max_prefix_length: 12,
+            inner_hash: HashOp::Sha256,
+            iavl: true,
+        }
+    }
+
+    /// The spec of the tendermint simple Merkle trees, i.e. the multistore app hash.
+    pub fn tendermint() -> Self {
+        Self {
+            leaf: LeafOp {
+                hash: HashOp::Sha256,
+                prehash_key: HashOp::NoHash,
+                prehash_value: HashOp::Sha256,
+                length: LengthOp::VarProto,
+                prefix: vec![0],
+            },
+            child_order: vec![0, 1],
+            child_size: 32,
+            min_prefix_length: 1,
+            max_prefix_length: 1,
+            inner_hash: HashOp::Sha256,
+            iavl: false,
+        }
+    }
+
+    /// Returns the spec with the provided name (`iavl` or `tendermint`).
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "iavl" => Ok(Self::iavl()),
+            "tendermint" => Ok(Self::tendermint()),
+            s => anyhow::bail!("unknown ics23 proof spec `{s}`"),
+        }
+    }
+
+    fn check(&self, proof: &ExistenceProof) -> anyhow::Result<()> {
+        let leaf = &proof.leaf;
+
+        anyhow::ensure!(
+            leaf.hash == self.leaf.hash
+                && leaf.prehash_key == self.leaf.prehash_key
+                && leaf.prehash_value == self.leaf.prehash_value
+                && leaf.length == self.leaf.length,
+            "the leaf operation doesn't match the spec"
+        );
+        anyhow::ensure!(
+            leaf.prefix.starts_with(&self.leaf.prefix),
+            "the leaf prefix doesn't match the spec"
+        );
+
+        if self.iavl {
+            let remaining = iavl_node(&leaf.prefix, 0)?;
+
+            anyhow::ensure!(remaining == 0, "invalid iavl leaf prefix");
+        }
+
+        let max_left = (self.child_order.len() - 1) * self.child_size;
+
+        for (i, op) in proof.path.iter().enumerate() {
+            anyhow::ensure!(
+                op.hash == self.inner_hash,
+                "the inner hash doesn't match the spec"
+            );
+            anyhow::ensure!(
+                self.leaf.prefix.is_empty() || !op.prefix.starts_with(&self.leaf.prefix),
+                "the inner prefix starts with the leaf prefix"
+            );
+            anyhow::ensure!(
+                op.prefix.len() >= self.min_prefix_length
+                    && op.prefix.len() <= self.max_prefix_length + max_left,
+                "the inner prefix length doesn't match the spec"
+            );
+            anyhow::ensure!(
+                op.suffix.len() % self.child_size == 0 && op.suffix.len() <= max_left,
+                "the inner suffix length doesn't match the spec"
+            );
+
+            if self.iavl {
+                let remaining = iavl_node(&op.prefix, i as i64 + 1)?;
+
+                anyhow::ensure!(
+                    remaining == 1 || remaining == self.child_size + 1,
+                    "invalid iavl inner prefix"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn padding(&self, branch: usize) -> anyhow::Result<(usize, usize, usize)> {
+        let idx = self
+            .child_order
+            .iter()
+            .position(|c| *c == branch)
+            .context("branch not found in the child order")?;
+
+        let prefix = idx * self.child_size;
+        let suffix = (self.child_order.len() - 1 - idx) * self.child_size;
+
+        Ok((
+            prefix + self.min_prefix_length,
+            prefix + self.max_prefix_length,
+            suffix,
+        ))
+    }
+
+    fn has_padding(&self, op: &InnerOp, branch: usize) -> anyhow::Result<bool> {
+        let (min, max, suffix) = self.padding(branch)?;
+
+        Ok(op.prefix.len() >= min && op.prefix.len() <= max && op.suffix.len() == suffix)
+    }
+
+    fn order(&self, op: &InnerOp) -> anyhow::Result<usize> {
+        for branch in 0..self.child_order.len() {
+            if self.has_padding(op, branch)? {
+                return Ok(branch);
+            }
+        }
+
+        anyhow::bail!("the inner operation doesn't match any branch")
+    }
+
+    fn is_left_most(&self, path: &[InnerOp]) -> anyhow::Result<bool> {
+        for op in path {
+            if !self.has_padding(op, 0)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn is_right_most(&self, path: &[InnerOp]) -> anyhow::Result<bool> {
+        let last = self.child_order.len() - 1;
+
+        for op in path {
+            if !self.has_padding(op, last)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn is_left_neighbor(&self, left: &[InnerOp], right: &[InnerOp]) -> anyhow::Result<bool> {
+        let mut left = left.to_vec();
+        let mut right = right.to_vec();
+
+        // strip the shared path from the root
+        loop {
+            let (l, r) = match (left.pop(), right.pop()) {
+                (Some(l), Some(r)) => (l, r),
+                _ => return Ok(false),
+            };
+
+            if l.prefix != r.prefix || l.suffix != r.suffix {
+                return Ok(self.order(&l)? + 1 == self.order(&r)?
+                    && self.is_right_most(&left)?
+                    && self.is_left_most(&right)?);
+            }
+        }
+    }
+}
+
+/// Verifies an ICS23 proof against a root.
+///
+/// If `value` is provided, the proof must be an existence proof of the key/value pair; otherwise,
+/// it must be a non-existence proof of the key. Returns an error if the proof or the spec is
+/// malformed.
+pub fn verify(
+    proof: &[u8],
+    spec: &str,
+    root: &[u8],
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> anyhow::Result<bool> {
+    let spec = ProofSpec::from_name(spec)?;
+
+    match (CommitmentProof::decode(proof)?, value) {
+        (CommitmentProof::Exist(p), Some(value)) => {
+            Ok(p.key == key && p.value == value && p.verify(&spec, root)?)
+        }
+
+        (CommitmentProof::Nonexist(p), None) => {
+            if p.key != key {
+                return Ok(false);
+            }
+
+            if let Some(left) = &p.left {
+                if left.key.as_slice() >= key || !left.verify(&spec, root)? {
+                    return Ok(false);
+                }
+            }
+
+            if let Some(right) = &p.right {
+                if right.key.as_slice() <= key || !right.verify(&spec, root)? {
+                    return Ok(false);
+                }
+            }
+
+            match (&p.left, &p.right) {
+                (Some(left), None) => spec.is_right_most(&left.path),
+                (None, Some(right)) => spec.is_left_most(&right.path),
+                (Some(left), Some(right)) => spec.is_left_neighbor(&left.path, &right.path),
+                (None, None) => anyhow::bail!("the non-existence proof has no neighbors"),
+            }
+        }
+
+        _ => Ok(false),
+    }
+}
+
+/// Reads the IAVL height, size, and version of a node prefix, returning the remaining length.
+fn iavl_node(prefix: &[u8], min_height: i64) -> anyhow::Result<usize> {
+    let mut buf = prefix;
+
+    let height = decode_zigzag(&mut buf)?;
+    let size = decode_zigzag(&mut buf)?;
+    let version = decode_zigzag(&mut buf)?;
+
+    anyhow::ensure!(height >= min_height, "invalid iavl node height");
+    anyhow::ensure!(size >= 0 && version >= 0, "invalid iavl node");
+
+    Ok(buf.len())
+}
+
+fn decode_zigzag(buf: &mut &[u8]) -> anyhow::Result<i64> {
+    let raw = decode_varint(buf)?;
+
+    Ok((raw >> 1) as i64 ^ -((raw & 1) as i64))
+}
+
+fn encode_varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(10);
+
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+    bytes
+}
+
+fn decode_varint(buf: &mut &[u8]) -> anyhow::Result<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let (b, rest) = buf.split_first().context("truncated varint")?;
+
+        *buf = rest;
+        value |= u64::from(b & 0x7f) << shift;
+
+        if b & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    anyhow::bail!("varint overflow")
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterator over the `(number, value)` fields of a protobuf message.
+struct Fields<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn read(&mut self) -> anyhow::Result<(u64, Field<'a>)> {
+        let tag = decode_varint(&mut self.buf)?;
+        let number = tag >> 3;
+
+        let field = match tag & 7 {
+            0 => Field::Varint(decode_varint(&mut self.buf)?),
+            1 | 5 => {
+                let len = if tag & 7 == 1 { 8 } else { 4 };
+
+                anyhow::ensure!(self.buf.len() >= len, "truncated protobuf field");
+                self.buf = &self.buf[len..];
+
+                Field::Fixed
+            }
+            2 => {
+                let len = decode_varint(&mut self.buf)? as usize;
+
+                anyhow::ensure!(self.buf.len() >= len, "truncated protobuf field");
+
+                let (bytes, rest) = self.buf.split_at(len);
+
+                self.buf = rest;
+
+                Field::Bytes(bytes)
+            }
+            w => anyhow::bail!("unsupported protobuf wire type `{w}`"),
+        };
+
+        Ok((number, field))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = anyhow::Result<(u64, Field<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+
+        let field = self.read();
+
+        if field.is_err() {
+            self.buf = &[];
+        }
+
+        Some(field)
+    }
+}
+
+fn decode_existence(bytes: &[u8]) -> anyhow::Result<ExistenceProof> {
+    let mut key = Vec::new();
+    let mut value = Vec::new();
+    let mut leaf = None;
+    let mut path = Vec::new();
+
+    for field in Fields::new(bytes) {
+        match field? {
+            (1, Field::Bytes(b)) => key = b.to_vec(),
+            (2, Field::Bytes(b)) => value = b.to_vec(),
+            (3, Field::Bytes(b)) => leaf = Some(decode_leaf(b)?),
+            (4, Field::Bytes(b)) => path.push(decode_inner(b)?),
+            _ => (),
+        }
+    }
+
+    Ok(ExistenceProof {
+        key,
+        value,
+        leaf: leaf.context("the existence proof has no leaf")?,
+        path,
+    })
+}
+
+fn decode_non_existence(bytes: &[u8]) -> anyhow::Result<NonExistenceProof> {
+    let mut proof = NonExistenceProof {
+        key: Vec::new(),
+        left: None,
+        right: None,
+    };
+
+    for field in Fields::new(bytes) {
+        match field? {
+            (1, Field::Bytes(b)) => proof.key = b.to_vec(),
+            (2, Field::Bytes(b)) => proof.left = Some(decode_existence(b)?),
+            (3, Field::Bytes(b)) => proof.right = Some(decode_existence(b)?),
+            _ => (),
+        }
+    }
+
+    Ok(proof)
+}
+
+fn decode_leaf(bytes: &[u8]) -> anyhow::Result<LeafOp> {
+    let mut leaf = LeafOp {
+        hash: HashOp::NoHash,
+        prehash_key: HashOp::NoHash,
+        prehash_value: HashOp::NoHash,
+        length: LengthOp::NoPrefix,
+        prefix: Vec::new(),
+    };
+
+    for field in Fields::new(bytes) {
+        match field? {
+            (1, Field::Varint(v)) => leaf.hash = HashOp::from_proto(v)?,
+            (2, Field::Varint(v)) => leaf.prehash_key = HashOp::from_proto(v)?,
+            (3, Field::Varint(v)) => leaf.prehash_value = HashOp::from_proto(v)?,
+            (4, Field::Varint(v)) => leaf.length = LengthOp::from_proto(v)?,
+            (5, Field::Bytes(b)) => leaf.prefix = b.to_vec(),
+            _ => (),
+        }
+    }
+
+    Ok(leaf)
+}
+
+fn decode_inner(bytes: &[u8]) -> anyhow::Result<InnerOp> {
+    let mut inner = InnerOp {
+        hash: HashOp::NoHash,
+        prefix: Vec::new(),
+        suffix: Vec::new(),
+    };
+
+    for field in Fields::new(bytes) {
+        match field? {
+            (1, Field::Varint(v)) => inner.hash = HashOp::from_proto(v)?,
+            (2, Field::Bytes(b)) => inner.prefix = b.to_vec(),
+            (3, Field::Bytes(b)) => inner.suffix = b.to_vec(),
+            _ => (),
+        }
+    }
+
+    Ok(inner)
+}
@@ -1,14 +1,108 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
 
 use lru::LruCache;
-use serde_json::Value;
-use valence_coprocessor::{DataBackend, ExecutionContext, Hash, Hasher, Vm};
+use serde_json::{json, Value};
+use valence_coprocessor::{
+    utils::{self, HttpLimits},
+    DataBackend, ExecutionContext, Hash, Hasher, Opening, StagingBackend, Vm,
+};
 use wasmtime::{Engine, Linker, Module, Store};
 
 use crate::HOST_CONTROLLER;
 
+pub mod breaker;
+pub mod cache;
+pub mod ics23;
+pub mod plugin;
 pub mod valence;
 
+use breaker::{BreakerPolicy, EndpointBreakers, EndpointHealth};
+use cache::{BlockProofCache, CacheStats};
+use plugin::{HostFunction, HostRegistry};
+
+/// Reserved entrypoint argument that bypasses the host caches for a run.
+///
+/// When set to any value other than `null` or `false`, cached results (e.g. block proofs) are
+/// recomputed for the run, including nested domain controller calls. The field is removed from
+/// the arguments, so it never reaches the controller logic.
+pub const FRESH_ARG: &str = "_fresh";
+
+/// Returns `true` if the arguments request a run that bypasses the host caches.
+pub fn is_fresh(args: &Value) -> bool {
+    !matches!(
+        args.get(FRESH_ARG),
+        None | Some(Value::Null) | Some(Value::Bool(false))
+    )
+}
+
+/// Default maximum number of witnesses a controller can return from `get_witnesses`.
+pub const MAX_WITNESSES: usize = 65_536;
+
+/// Error returned when a controller returns more witnesses than the runtime accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyWitnesses {
+    /// Number of witnesses returned by the controller.
+    pub count: usize,
+
+    /// Maximum number of witnesses accepted by the runtime.
+    pub max: usize,
+}
+
+impl fmt::Display for TooManyWitnesses {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the controller returned {} witnesses; at most {} are allowed",
+            self.count, self.max
+        )
+    }
+}
+
+impl std::error::Error for TooManyWitnesses {}
+
+/// Error returned when a controller doesn't implement the called function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportNotFound {
+    /// Name of the missing export.
+    pub export: String,
+}
+
+impl fmt::Display for ExportNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the controller doesn't implement `{}`", self.export)
+    }
+}
+
+impl std::error::Error for ExportNotFound {}
+
+/// The SMT inserts of a run that must not mutate the persisted state, as `get_witnesses`.
+pub struct SmtStaging<D>
+where
+    D: DataBackend,
+{
+    /// The backend the tree nodes are written to.
+    pub data: StagingBackend<D>,
+
+    /// The staged root of each context.
+    pub roots: HashMap<String, Hash>,
+}
+
+impl<D> From<D> for SmtStaging<D>
+where
+    D: DataBackend,
+{
+    fn from(data: D) -> Self {
+        Self {
+            data: StagingBackend::from(data),
+            roots: HashMap::new(),
+        }
+    }
+}
+
 pub struct Runtime<H, D, VM>
 where
     H: Hasher,
@@ -20,6 +114,12 @@ where
     pub ctx: ExecutionContext<H, D>,
     pub log: Vec<String>,
     pub panic: Option<String>,
+    pub http_max_response: usize,
+    pub http_batch_max_len: usize,
+    pub host_functions: Arc<HostRegistry>,
+    pub block_proofs: BlockProofCache,
+    pub breakers: EndpointBreakers,
+    pub smt_staging: Option<SmtStaging<D>>,
     pub vm: VM,
 }
 
@@ -37,9 +137,96 @@ where
             ctx,
             log: Vec::with_capacity(10),
             panic: None,
+            http_max_response: utils::HTTP_MAX_RESPONSE_SIZE,
+            http_batch_max_len: valence::HTTP_BATCH_MAX_LEN,
+            host_functions: Arc::new(HostRegistry::with_builtins(Default::default())),
+            block_proofs: BlockProofCache::new(0),
+            breakers: EndpointBreakers::new(BreakerPolicy::DISABLED),
+            smt_staging: None,
             vm,
         }
     }
+
+    /// Inserts the value into the SMT of the provided context, returning the new root and the
+    /// opening of the value to it.
+    ///
+    /// If the run is staged, the insert chains from the staged root of the context and nothing is
+    /// persisted; otherwise, the root of the context is swapped on the data backend.
+    pub fn smt_insert_and_open(
+        &mut self,
+        context: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> anyhow::Result<(Hash, Opening)> {
+        let staging = match &mut self.smt_staging {
+            Some(s) => s,
+            None => return self.ctx.smt_insert_and_open(context, key, value),
+        };
+
+        let root = match staging.roots.get(context) {
+            Some(r) => *r,
+            None => self.ctx.get_smt_context_root(context)?,
+        };
+
+        let (root, opening) =
+            self.ctx
+                .smt_insert_and_open_from(staging.data.clone(), root, context, key, value)?;
+
+        staging.roots.insert(context.to_string(), root);
+
+        Ok((root, opening))
+    }
+}
+
+impl<H, D, VM> Runtime<H, D, VM>
+where
+    H: Hasher + Send + Sync,
+    D: DataBackend + Send + Sync,
+    VM: Vm<H, D> + Send + Sync,
+{
+    /// Performs the HTTP requests with a bounded pool of workers, returning a `{"response"}` or
+    /// `{"error"}` object per request, in order.
+    ///
+    /// The serialized results are capped to `http_batch_max_len`; a response that doesn't fit is
+    /// replaced by an error, so the rest of the batch is still returned. Fails only if the errors
+    /// alone exceed the cap.
+    pub fn http_batch(&self, requests: &[Value]) -> anyhow::Result<Vec<Value>> {
+        let overflow = json!({ "error": valence::HTTP_BATCH_OVERFLOW });
+        let overflow_len = serde_json::to_vec(&overflow)?.len();
+
+        // every entry reserves the room of an overflow error, along with the array delimiters
+        let mut budget = requests
+            .len()
+            .checked_mul(overflow_len + 1)
+            .and_then(|r| self.http_batch_max_len.checked_sub(r + 2))
+            .ok_or_else(|| anyhow::anyhow!("too many requests for the batch capacity"))?;
+
+        let results = valence::run_bounded(requests, valence::HTTP_BATCH_CONCURRENCY, |r| {
+            valence::perform_http(self, r)
+        });
+
+        results
+            .into_iter()
+            .map(|res| {
+                let entry = match res {
+                    Ok(r) => json!({ "response": r }),
+                    Err(e) => json!({ "error": e.to_string() }),
+                };
+
+                let extra = serde_json::to_vec(&entry)?
+                    .len()
+                    .saturating_sub(overflow_len);
+
+                if extra > budget {
+                    return Ok(overflow.clone());
+                }
+
+                budget -= extra;
+
+                Ok(entry)
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -51,14 +238,22 @@ where
     engine: Engine,
     linker: Linker<Runtime<H, D, Self>>,
     modules: Arc<Mutex<LruCache<Hash, Module>>>,
+    http_limits: HttpLimits,
+    host_functions: Arc<HostRegistry>,
+    block_proofs: BlockProofCache,
+    breakers: EndpointBreakers,
+    max_witnesses: usize,
 }
 
 impl<H, D> ValenceWasm<H, D>
 where
-    H: Hasher + 'static,
-    D: DataBackend + 'static,
+    H: Hasher + Send + Sync + 'static,
+    D: DataBackend + Send + Sync + 'static,
 {
     /// Creates a new instance of the VM.
+    ///
+    /// The batched host calls, `get_state_proofs` and `http_batch`, share the runtime across a
+    /// bounded pool of threads, so the hasher and the data backend must be `Send + Sync`.
     pub fn new(capacity: usize) -> anyhow::Result<Self> {
         let engine = Engine::default();
         let mut linker = Linker::new(&engine);
@@ -66,6 +261,8 @@ where
         linker.func_wrap(HOST_CONTROLLER, "panic", valence::panic)?;
         linker.func_wrap(HOST_CONTROLLER, "args", valence::args)?;
         linker.func_wrap(HOST_CONTROLLER, "ret", valence::ret)?;
+        linker.func_wrap(HOST_CONTROLLER, "abort", valence::abort)?;
+        linker.func_wrap(HOST_CONTROLLER, "now", valence::now)?;
         linker.func_wrap(HOST_CONTROLLER, "get_storage", valence::get_storage)?;
         linker.func_wrap(HOST_CONTROLLER, "set_storage", valence::set_storage)?;
         linker.func_wrap(
@@ -81,22 +278,57 @@ where
         linker.func_wrap(HOST_CONTROLLER, "get_raw_storage", valence::get_raw_storage)?;
         linker.func_wrap(HOST_CONTROLLER, "set_raw_storage", valence::set_raw_storage)?;
         linker.func_wrap(HOST_CONTROLLER, "get_controller", valence::get_controller)?;
+        linker.func_wrap(
+            HOST_CONTROLLER,
+            "get_controller_metadata",
+            valence::get_controller_metadata,
+        )?;
+        linker.func_wrap(
+            HOST_CONTROLLER,
+            "get_witness_encoding",
+            valence::get_witness_encoding,
+        )?;
         linker.func_wrap(HOST_CONTROLLER, "get_historical", valence::get_historical)?;
+        linker.func_wrap(HOST_CONTROLLER, "smt_contains", valence::smt_contains)?;
+        linker.func_wrap(
+            HOST_CONTROLLER,
+            "smt_insert_and_open",
+            valence::smt_insert_and_open,
+        )?;
+        linker.func_wrap(HOST_CONTROLLER, "commit", valence::commit)?;
+        linker.func_wrap(HOST_CONTROLLER, "get_commit", valence::get_commit)?;
         linker.func_wrap(
             HOST_CONTROLLER,
             "get_latest_block",
             valence::get_latest_block,
         )?;
+        linker.func_wrap(HOST_CONTROLLER, "list_domains", valence::list_domains)?;
         linker.func_wrap(HOST_CONTROLLER, "get_block_proof", valence::get_block_proof)?;
+        linker.func_wrap(
+            HOST_CONTROLLER,
+            "get_block_proofs",
+            valence::get_block_proofs,
+        )?;
         linker.func_wrap(
             HOST_CONTROLLER,
             "get_historical_update",
             valence::get_historical_update,
         )?;
         linker.func_wrap(HOST_CONTROLLER, "get_state_proof", valence::get_state_proof)?;
+        linker.func_wrap(
+            HOST_CONTROLLER,
+            "get_state_proofs",
+            valence::get_state_proofs,
+        )?;
         linker.func_wrap(HOST_CONTROLLER, "http", valence::http)?;
+        linker.func_wrap(HOST_CONTROLLER, "http_batch", valence::http_batch)?;
         linker.func_wrap(HOST_CONTROLLER, "alchemy", valence::alchemy)?;
+        linker.func_wrap(HOST_CONTROLLER, "host_call", valence::host_call)?;
         linker.func_wrap(HOST_CONTROLLER, "log", valence::log)?;
+        linker.func_wrap(HOST_CONTROLLER, "hash", valence::hash)?;
+        linker.func_wrap(HOST_CONTROLLER, "merge", valence::merge)?;
+        linker.func_wrap(HOST_CONTROLLER, "keccak256", valence::keccak256)?;
+        linker.func_wrap(HOST_CONTROLLER, "verify_ics23", valence::verify_ics23)?;
 
         let capacity = std::num::NonZeroUsize::new(capacity)
             .ok_or_else(|| anyhow::anyhow!("invalid capacity"))?;
@@ -107,10 +339,137 @@ where
             engine,
             linker,
             modules,
+            http_limits: HttpLimits::default(),
+            host_functions: Arc::new(HostRegistry::with_builtins(HttpLimits::default())),
+            block_proofs: BlockProofCache::default(),
+            breakers: EndpointBreakers::new(BreakerPolicy::DISABLED),
+            max_witnesses: MAX_WITNESSES,
         })
     }
 }
 
+impl<H, D> ValenceWasm<H, D>
+where
+    H: Hasher + 'static,
+    D: DataBackend + 'static,
+{
+    /// A built-in controller that returns its arguments, used to probe the runtime.
+    pub const ECHO_CONTROLLER: &str = r#"
+        (module
+            (import "valence" "args" (func $args (param i32) (result i32)))
+            (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "entrypoint")
+                (drop (call $ret (i32.const 0) (call $args (i32.const 0))))))
+    "#;
+
+    /// Sets the maximum size, in bytes, of a HTTP response body read on behalf of a controller.
+    pub fn with_http_max_response(mut self, limit: usize) -> Self {
+        self.http_limits.max_response = limit;
+        Arc::make_mut(&mut self.host_functions).register_builtins(self.http_limits);
+        self
+    }
+
+    /// Sets the maximum timeout, in milliseconds, of a HTTP request performed on behalf of a
+    /// controller.
+    ///
+    /// Larger timeouts requested by controllers are reduced to it.
+    pub fn with_http_max_timeout(mut self, timeout_ms: u64) -> Self {
+        self.http_limits.max_timeout_ms = timeout_ms;
+        Arc::make_mut(&mut self.host_functions).register_builtins(self.http_limits);
+        self
+    }
+
+    /// Sets the maximum number of witnesses a controller can return from `get_witnesses`.
+    pub fn with_max_witnesses(mut self, max: usize) -> Self {
+        self.max_witnesses = max;
+        self
+    }
+
+    /// Registers a named host function, callable by controllers via `abi::host_call`.
+    ///
+    /// The function must honor the determinism contract described in [`plugin`].
+    pub fn with_host_function<N: Into<String>>(mut self, name: N, f: HostFunction) -> Self {
+        Arc::make_mut(&mut self.host_functions).register(name, f);
+        self
+    }
+
+    /// Sets the capacity of the block proof cache shared across executions.
+    ///
+    /// A capacity of `0` disables the cache.
+    pub fn with_block_proof_cache(mut self, capacity: usize) -> Self {
+        self.block_proofs = BlockProofCache::new(capacity);
+        self
+    }
+
+    /// Sets the thresholds of the circuit breakers of the RPC endpoints called by controllers.
+    ///
+    /// The breakers are disabled by default. A threshold of `0` disables them.
+    pub fn with_endpoint_breaker(mut self, policy: BreakerPolicy) -> Self {
+        self.breakers = self.breakers.with_policy(policy);
+        self
+    }
+
+    /// Sets the endpoint to which the calls to `primary` fail over.
+    pub fn with_endpoint_backup(mut self, primary: &str, backup: &str) -> Self {
+        self.breakers = self.breakers.with_backup(primary, backup);
+        self
+    }
+
+    /// Returns the health of the RPC endpoints called by controllers.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.breakers.health()
+    }
+
+    /// Returns the hit and miss counters of the block proof cache.
+    pub fn block_proof_cache_stats(&self) -> CacheStats {
+        self.block_proofs.stats()
+    }
+
+    /// Returns the registered host functions.
+    pub fn host_functions(&self) -> &HostRegistry {
+        &self.host_functions
+    }
+
+    /// Returns the maximum number of witnesses a controller can return.
+    pub fn max_witnesses(&self) -> usize {
+        self.max_witnesses
+    }
+
+    /// Returns the maximum size, in bytes, of a HTTP response body.
+    pub fn http_max_response(&self) -> usize {
+        self.http_limits.max_response
+    }
+
+    /// Returns the maximum timeout, in milliseconds, of a HTTP request.
+    pub fn http_max_timeout(&self) -> u64 {
+        self.http_limits.max_timeout_ms
+    }
+
+    /// Instantiates and runs the built-in echo controller, asserting the runtime is functional.
+    pub fn probe(&self, ctx: &ExecutionContext<H, D>) -> anyhow::Result<()> {
+        let args = serde_json::json!({"probe": true});
+        let runtime = Runtime::new(ctx.clone(), args.clone(), self.clone());
+
+        let mut store = Store::new(&self.engine, runtime);
+        let module = Module::new(&self.engine, Self::ECHO_CONTROLLER)?;
+
+        self.linker
+            .instantiate(&mut store, &module)?
+            .get_typed_func::<(), ()>(&mut store, "entrypoint")?
+            .call(&mut store, ())?;
+
+        let ret = store.into_data().ret;
+
+        anyhow::ensure!(
+            ret.as_ref() == Some(&args),
+            "unexpected probe return `{ret:?}`"
+        );
+
+        Ok(())
+    }
+}
+
 impl<H, D> Vm<H, D> for ValenceWasm<H, D>
 where
     H: Hasher,
@@ -125,13 +484,33 @@ where
     ) -> anyhow::Result<Value> {
         tracing::trace!("executing controller {controller:x?}, {f}({:?})", args);
 
+        let mut args = args;
+        let mut vm = self.clone();
+
+        if is_fresh(&args) {
+            tracing::debug!("fresh run requested; bypassing host caches...");
+
+            vm.block_proofs = BlockProofCache::new(0);
+        }
+
+        if let Some(a) = args.as_object_mut() {
+            a.remove(FRESH_ARG);
+        }
+
         let runtime = Runtime {
             args,
             ret: None,
             ctx: ctx.clone(),
             log: Vec::with_capacity(10),
             panic: None,
-            vm: self.clone(),
+            http_max_response: self.http_limits.max_response,
+            http_batch_max_len: valence::HTTP_BATCH_MAX_LEN,
+            host_functions: self.host_functions.clone(),
+            block_proofs: vm.block_proofs.clone(),
+            breakers: self.breakers.clone(),
+            smt_staging: (f == ExecutionContext::<H, D>::CONTROLLER_GET_WITNESSES)
+                .then(|| SmtStaging::from(ctx.data().clone())),
+            vm,
         };
 
         let mut store = Store::new(&self.engine, runtime);
@@ -169,6 +548,10 @@ where
 
         tracing::debug!("controller loaded...");
 
+        if instance.get_func(&mut store, f).is_none() {
+            return Err(ExportNotFound { export: f.into() }.into());
+        }
+
         let err = instance
             .get_typed_func::<(), ()>(&mut store, f)?
             .call(&mut store, ())
@@ -184,7 +567,21 @@ where
 
         ctx.extend_log(log)?;
 
-        Ok(ret.unwrap_or_default())
+        let ret = ret.unwrap_or_default();
+
+        if f == ExecutionContext::<H, D>::CONTROLLER_GET_WITNESSES {
+            let count = ret.as_array().map(Vec::len).unwrap_or_default();
+
+            if count > self.max_witnesses {
+                return Err(TooManyWitnesses {
+                    count,
+                    max: self.max_witnesses,
+                }
+                .into());
+            }
+        }
+
+        Ok(ret)
     }
 
     fn updated(&self, controller: &Hash) {
@@ -0,0 +1,129 @@
+//! Registry of named host functions callable by controllers via `abi::host_call`.
+//!
+//! Every host function has the same signature: it receives the JSON arguments provided by the
+//! controller, and returns a JSON value that is written back to the controller memory.
+//!
+//! # Determinism
+//!
+//! Controllers compute the witnesses of a proof, so the output of a host function becomes part of
+//! what is proven. A host function must not be used as a source of non-determinism the circuit
+//! cannot verify: either its output is a pure function of its arguments (e.g. a hash), or it is
+//! data the circuit checks independently (e.g. a state proof opened against a verified root).
+//! Values such as the current time or secrets must be committed to by the circuit inputs, or not
+//! used on the proving path at all.
+
+use std::{collections::HashMap, env, fmt, sync::Arc};
+
+use serde_json::Value;
+use valence_coprocessor::utils::{self, HttpLimits};
+
+use super::valence;
+
+type HostFn = dyn Fn(&Value) -> anyhow::Result<Value> + Send + Sync;
+
+/// A host function callable by name from a controller.
+#[derive(Clone)]
+pub struct HostFunction(Arc<HostFn>);
+
+impl HostFunction {
+    /// Creates a new host function from the provided closure.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Value) -> anyhow::Result<Value> + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Calls the host function with the provided arguments.
+    pub fn call(&self, args: &Value) -> anyhow::Result<Value> {
+        (self.0)(args)
+    }
+}
+
+impl fmt::Debug for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("HostFunction").finish_non_exhaustive()
+    }
+}
+
+/// A set of named host functions.
+#[derive(Debug, Clone, Default)]
+pub struct HostRegistry {
+    functions: HashMap<String, HostFunction>,
+}
+
+impl HostRegistry {
+    /// Name of the built-in HTTP function, also used by `abi::http`.
+    pub const HTTP: &str = "http";
+
+    /// Name of the built-in Alchemy function.
+    pub const ALCHEMY: &str = "alchemy";
+
+    /// Name of an optional function that, if registered, resolves the state proofs requested via
+    /// `abi::get_state_proof` instead of the domain controller.
+    ///
+    /// It receives `{"domain", "args"}` and must return a serialized `StateProof`. Only available
+    /// with the `mocks` feature, for the test harness.
+    #[cfg(feature = "mocks")]
+    pub const STATE_PROOF: &str = "get_state_proof";
+
+    /// Creates a registry with the built-in `http` and `alchemy` functions.
+    ///
+    /// HTTP requests are bounded by the provided limits.
+    pub fn with_builtins(limits: HttpLimits) -> Self {
+        let mut registry = Self::default();
+
+        registry.register_builtins(limits);
+
+        registry
+    }
+
+    /// Registers the built-in `http` and `alchemy` functions, replacing any previous definition.
+    pub fn register_builtins(&mut self, limits: HttpLimits) {
+        let max_response = limits.max_response;
+
+        self.register(
+            Self::HTTP,
+            HostFunction::new(move |args| utils::http_with_limits(args, &limits)),
+        );
+
+        self.register(
+            Self::ALCHEMY,
+            HostFunction::new(move |args| {
+                let key = env::var("ALCHEMY_API_KEY")
+                    .map_err(|e| anyhow::anyhow!("alchemy key not set: {e}"))?;
+
+                let chain = args
+                    .get("chain")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("no provided chain"))?;
+
+                let method = args
+                    .get("method")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("no provided method"))?;
+
+                let params = args.get("params").cloned().unwrap_or_default();
+
+                valence::alchemy_request(&key, chain, method, &params, max_response)
+            }),
+        );
+    }
+
+    /// Registers a host function, replacing any previous function with the same name.
+    pub fn register<N: Into<String>>(&mut self, name: N, f: HostFunction) {
+        self.functions.insert(name.into(), f);
+    }
+
+    /// Returns `true` if a function with the provided name is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Calls the named host function.
+    ///
+    /// Returns `None` if no such function is registered.
+    pub fn call(&self, name: &str, args: &Value) -> Option<anyhow::Result<Value>> {
+        self.functions.get(name).map(|f| f.call(args))
+    }
+}
@@ -1,11 +1,39 @@
-use std::env;
+use std::{
+    env,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
 
 use msgpacker::Packable;
 use serde_json::Value;
-use valence_coprocessor::{utils, DataBackend, FileSystem, Hash, Hasher, Vm, HASH_LEN};
+use sha3::{Digest as _, Keccak256};
+use valence_coprocessor::{
+    utils, Base64, DataBackend, FileSystem, Hash, Hasher, LogLevel, StateProof, Vm, HASH_LEN,
+};
 use wasmtime::{Caller, Extern, Memory};
 
-use super::Runtime;
+use super::{
+    breaker::{EndpointBreakers, EndpointUnavailable},
+    ics23,
+    plugin::HostRegistry,
+    Runtime,
+};
+
+/// Maximum number of state proofs fetched concurrently by [get_state_proofs].
+pub const STATE_PROOFS_CONCURRENCY: usize = 8;
+
+/// Maximum number of HTTP requests performed concurrently by [http_batch].
+pub const HTTP_BATCH_CONCURRENCY: usize = 8;
+
+/// Maximum length of the serialized results of [http_batch]; the capacity of the guest buffer.
+pub const HTTP_BATCH_MAX_LEN: usize = crate::abi::BUF_LEN;
+
+/// Error of a [http_batch] response that doesn't fit in [HTTP_BATCH_MAX_LEN].
+pub const HTTP_BATCH_OVERFLOW: &str = "http response exceeds the batch capacity";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(i32)]
@@ -32,6 +60,19 @@ pub enum ReturnCodes {
     AlchemyResult = -19,
     BlockProof = -20,
     HistoricalUpdate = -21,
+    SmtContains = -22,
+    ResponseTooLarge = -23,
+    HostFunctionNotFound = -24,
+    HostCall = -25,
+    Commit = -26,
+    ControllerMetadata = -27,
+    EndpointUnavailable = -28,
+    Ics23 = -29,
+    SmtInsert = -30,
+    HttpTimeout = -31,
+    Clock = -32,
+    ListDomains = -33,
+    WitnessEncoding = -34,
 }
 
 /// Resolves a panic.
@@ -301,6 +342,52 @@ where
     }
 }
 
+/// Get the deployment metadata of the controller.
+pub fn get_controller_metadata<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let metadata = match caller.data().ctx.get_controller_metadata() {
+        Ok(m) => m,
+        Err(_) => return ReturnCodes::ControllerMetadata as i32,
+    };
+
+    match serialize(&mut caller, &mem, ptr, &metadata) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Get the witness data encoding selected when the controller was deployed.
+pub fn get_witness_encoding<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let encoding = match caller.data().ctx.get_witness_encoding() {
+        Ok(e) => e,
+        Err(_) => return ReturnCodes::WitnessEncoding as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &[encoding.to_byte()]) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
 /// Returns the current historical tree root.
 pub(super) fn get_historical<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32) -> i32
 where
@@ -321,6 +408,172 @@ where
     }
 }
 
+/// Stores a labeled commitment of the data, writing it to `ptr`.
+pub fn commit<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    label_ptr: u32,
+    label_len: u32,
+    data_ptr: u32,
+    data_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let label = match read_string(&mut caller, &mem, label_ptr, label_len) {
+        Ok(l) => l,
+        Err(e) => return e,
+    };
+
+    let data = match read_buffer(&mut caller, &mem, data_ptr, data_len) {
+        Ok(d) => d,
+        Err(e) => return e,
+    };
+
+    let commitment = match caller.data().ctx.commit(&label, &data) {
+        Ok(c) => c,
+        Err(_) => return ReturnCodes::Commit as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &commitment) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Writes the last commitment of the controller under the label to `ptr`.
+///
+/// Returns `0` if there is no such commitment.
+pub fn get_commit<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    label_ptr: u32,
+    label_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let label = match read_string(&mut caller, &mem, label_ptr, label_len) {
+        Ok(l) => l,
+        Err(e) => return e,
+    };
+
+    let commitment = match caller.data().ctx.get_commit(&label) {
+        Ok(Some(c)) => c,
+        Ok(None) => return 0,
+        Err(_) => return ReturnCodes::Commit as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &commitment) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Returns `1` if the key is a member of the SMT of the provided context, `0` otherwise.
+pub fn smt_contains<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    context_ptr: u32,
+    context_len: u32,
+    key_ptr: u32,
+    key_len: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let context = match read_string(&mut caller, &mem, context_ptr, context_len) {
+        Ok(c) => c,
+        Err(e) => return e,
+    };
+
+    let key = match read_buffer(&mut caller, &mem, key_ptr, key_len) {
+        Ok(k) => k,
+        Err(e) => return e,
+    };
+
+    match caller.data().ctx.smt_contains(&context, &key) {
+        Ok(c) => c as i32,
+        Err(_) => ReturnCodes::SmtContains as i32,
+    }
+}
+
+/// Inserts the value into the SMT of the provided context, writing the new root followed by the
+/// packed opening of the value.
+#[allow(clippy::too_many_arguments)]
+pub fn smt_insert_and_open<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    context_ptr: u32,
+    context_len: u32,
+    key_ptr: u32,
+    key_len: u32,
+    value_ptr: u32,
+    value_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let context = match read_string(&mut caller, &mem, context_ptr, context_len) {
+        Ok(c) => c,
+        Err(e) => return e,
+    };
+
+    let key = match read_buffer(&mut caller, &mem, key_ptr, key_len) {
+        Ok(k) => k,
+        Err(e) => return e,
+    };
+
+    let value = match read_buffer(&mut caller, &mem, value_ptr, value_len) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+
+    let (root, opening) = match caller
+        .data_mut()
+        .smt_insert_and_open(&context, &key, &value)
+    {
+        Ok(r) => r,
+        Err(_) => return ReturnCodes::SmtInsert as i32,
+    };
+
+    let mut bytes = root.to_vec();
+
+    bytes.extend(msgpacker::pack_to_vec(&opening));
+
+    match write_buffer(&mut caller, &mem, ptr, &bytes) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
 /// Returns the last included block for the provided domain.
 pub fn get_latest_block<H, D, VM>(
     mut caller: Caller<Runtime<H, D, VM>>,
@@ -354,6 +607,33 @@ where
     }
 }
 
+/// Returns the names of the registered domains.
+pub fn list_domains<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let domains = match caller.data().ctx.list_domains() {
+        Ok(d) => d,
+        Err(_) => return ReturnCodes::ListDomains as i32,
+    };
+
+    let mut bytes = Vec::new();
+
+    msgpacker::pack_array(&mut bytes, &domains);
+
+    match write_buffer(&mut caller, &mem, ptr, &bytes) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
 pub fn get_block_proof<H, D, VM>(
     mut caller: Caller<Runtime<H, D, VM>>,
     domain_ptr: u32,
@@ -381,7 +661,14 @@ where
         Err(e) => return e,
     };
 
-    let proof = match caller.data().ctx.get_block_proof(&domain, block_number) {
+    let data = caller.data();
+    let proof =
+        data.block_proofs
+            .get_or_insert(data.ctx.get_historical(), &domain, block_number, || {
+                data.ctx.get_block_proof(&domain, block_number)
+            });
+
+    let proof = match proof {
         Ok(p) => p,
         Err(_) => return ReturnCodes::BlockProof as i32,
     };
@@ -392,6 +679,94 @@ where
     }
 }
 
+/// Returns the Merkle proofs that open each of the block numbers to the historical root.
+///
+/// The block numbers are read as a sequence of little-endian `u64`.
+pub fn get_block_proofs<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    domain_ptr: u32,
+    domain_len: u32,
+    numbers_ptr: u32,
+    numbers_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let domain = match read_string(&mut caller, &mem, domain_ptr, domain_len) {
+        Ok(d) => d,
+        Err(e) => return e,
+    };
+
+    let numbers = match read_buffer(&mut caller, &mem, numbers_ptr, numbers_len) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+
+    if numbers.len() % 8 != 0 {
+        return ReturnCodes::BlockProof as i32;
+    }
+
+    let data = caller.data();
+    let root = data.ctx.get_historical();
+    let proofs = numbers
+        .chunks_exact(8)
+        .map(|n| {
+            let number = utils::decode_u64_le(n)?;
+
+            data.block_proofs.get_or_insert(root, &domain, number, || {
+                data.ctx.get_block_proof(&domain, number)
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>();
+
+    let proofs = match proofs {
+        Ok(p) => p,
+        Err(_) => return ReturnCodes::BlockProof as i32,
+    };
+
+    let mut bytes = Vec::new();
+
+    msgpacker::pack_array(&mut bytes, &proofs);
+
+    match write_buffer(&mut caller, &mem, ptr, &bytes) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Returns the clock of the execution, pinned to its historical root.
+///
+/// The value is written as little-endian Unix seconds.
+pub fn now<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let now = match caller.data().ctx.now() {
+        Ok(n) => n,
+        Err(_) => return ReturnCodes::Clock as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &utils::encode_u64_le(now)) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
 pub fn get_historical_update<H, D, VM>(
     mut caller: Caller<Runtime<H, D, VM>>,
     root_ptr: u32,
@@ -452,11 +827,7 @@ where
         Err(e) => return e,
     };
 
-    let proof = match caller
-        .data()
-        .ctx
-        .get_state_proof(&caller.data().vm, &domain, args)
-    {
+    let proof = match fetch_state_proof(caller.data(), &domain, args) {
         Ok(p) => p,
         Err(_) => return ReturnCodes::StateProof as i32,
     };
@@ -467,6 +838,83 @@ where
     }
 }
 
+/// Fetches a batch of state proofs concurrently.
+///
+/// The requests are a JSON array of `{"domain", "args"}` objects. The response is a JSON array,
+/// in the order of the requests, of `{"proof"}` or `{"error"}` objects.
+pub fn get_state_proofs<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    requests_ptr: u32,
+    requests_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher + Send + Sync,
+    D: DataBackend + Send + Sync,
+    VM: Vm<H, D> + Send + Sync,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let requests = match read_json(&mut caller, &mem, requests_ptr, requests_len) {
+        Ok(Value::Array(r)) => r,
+        Ok(_) => return ReturnCodes::JsonValue as i32,
+        Err(e) => return e,
+    };
+
+    let data = caller.data();
+    let results: Vec<_> = run_bounded(&requests, STATE_PROOFS_CONCURRENCY, |r| {
+        let domain = r
+            .get("domain")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow::anyhow!("no provided domain"))?;
+        let args = r.get("args").cloned().unwrap_or_default();
+
+        fetch_state_proof(data, domain, args)
+    })
+    .into_iter()
+    .map(|res| match res {
+        Ok(p) => serde_json::json!({ "proof": p }),
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    })
+    .collect();
+
+    let bytes = match serde_json::to_vec(&results) {
+        Ok(b) => b,
+        Err(_) => return ReturnCodes::Serialization as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &bytes) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+fn fetch_state_proof<H, D, VM>(
+    data: &Runtime<H, D, VM>,
+    domain: &str,
+    args: Value,
+) -> anyhow::Result<StateProof>
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    // the seam is compiled only for the test harness, so production proofs always come from the
+    // domain controller
+    #[cfg(feature = "mocks")]
+    if let Some(p) = data.host_functions.call(
+        HostRegistry::STATE_PROOF,
+        &serde_json::json!({"domain": domain, "args": &args}),
+    ) {
+        return p.and_then(|p| Ok(serde_json::from_value(p)?));
+    }
+
+    data.ctx.get_state_proof(&data.vm, domain, args)
+}
+
 /// Perform a HTTP request.
 pub fn http<H, D, VM>(
     mut caller: Caller<Runtime<H, D, VM>>,
@@ -489,8 +937,11 @@ where
         Err(e) => return e,
     };
 
-    let ret = match utils::http(&args) {
+    let ret = match perform_http(caller.data(), &args) {
         Ok(r) => r,
+        Err(e) if e.is::<utils::ResponseTooLarge>() => return ReturnCodes::ResponseTooLarge as i32,
+        Err(e) if e.is::<EndpointUnavailable>() => return ReturnCodes::EndpointUnavailable as i32,
+        Err(e) if e.is::<utils::RequestTimeout>() => return ReturnCodes::HttpTimeout as i32,
         Err(_) => return ReturnCodes::Http as i32,
     };
 
@@ -505,6 +956,115 @@ where
     }
 }
 
+/// Performs a batch of HTTP requests concurrently.
+///
+/// The requests are a JSON array of `abi::http` arguments. The response is a JSON array, in the
+/// order of the requests, of `{"response"}` or `{"error"}` objects. See [Runtime::http_batch].
+pub fn http_batch<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    requests_ptr: u32,
+    requests_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher + Send + Sync,
+    D: DataBackend + Send + Sync,
+    VM: Vm<H, D> + Send + Sync,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let requests = match read_json(&mut caller, &mem, requests_ptr, requests_len) {
+        Ok(Value::Array(r)) => r,
+        Ok(_) => return ReturnCodes::JsonValue as i32,
+        Err(e) => return e,
+    };
+
+    let results = match caller.data().http_batch(&requests) {
+        Ok(r) => r,
+        Err(_) => return ReturnCodes::BufferTooLarge as i32,
+    };
+
+    let bytes = match serde_json::to_vec(&results) {
+        Ok(b) => b,
+        Err(_) => return ReturnCodes::Serialization as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &bytes) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Runs `f` on every item with at most `concurrency` workers, returning the results in the order
+/// of the items.
+pub(super) fn run_bounded<T, R, F>(items: &[T], concurrency: usize, f: F) -> Vec<anyhow::Result<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> anyhow::Result<R> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+
+    thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, items.len().max(1)) {
+            let tx = tx.clone();
+            let (next, f) = (&next, &f);
+
+            s.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let item = match items.get(i) {
+                    Some(item) => item,
+                    None => break,
+                };
+
+                let res = panic::catch_unwind(AssertUnwindSafe(|| f(item)))
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("request panicked")));
+
+                tx.send((i, res)).ok();
+            });
+        }
+    });
+
+    drop(tx);
+
+    let mut results: Vec<_> = (0..items.len()).map(|_| None).collect();
+
+    for (i, res) in rx {
+        results[i] = Some(res);
+    }
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("request not performed"))))
+        .collect()
+}
+
+pub(super) fn perform_http<H, D, VM>(
+    data: &Runtime<H, D, VM>,
+    args: &Value,
+) -> anyhow::Result<Value>
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let url = args.get("url").and_then(Value::as_str).unwrap_or_default();
+
+    data.breakers.call(url, |url| {
+        let mut args = args.clone();
+
+        args["url"] = url.into();
+
+        data.host_functions
+            .call(HostRegistry::HTTP, &args)
+            .unwrap_or_else(|| anyhow::bail!("http function not registered"))
+    })
+}
+
 /// Perform an Alchemy API request.
 #[allow(clippy::too_many_arguments)]
 pub fn alchemy<H, D, VM>(
@@ -550,7 +1110,92 @@ where
         Err(e) => return e,
     };
 
-    let url = format!("https://{chain}.g.alchemy.com/v2/{key}");
+    let data = caller.data();
+    let limit = data.http_max_response;
+    let endpoint = EndpointBreakers::endpoint(&alchemy_url(&chain, ""));
+    let ret = data.breakers.guard(&endpoint, || {
+        alchemy_request(&key, &chain, &method, &params, limit)
+    });
+
+    let ret = match ret {
+        Ok(r) => r,
+        Err(e) if e.is::<utils::ResponseTooLarge>() => return ReturnCodes::ResponseTooLarge as i32,
+        Err(e) if e.is::<EndpointUnavailable>() => return ReturnCodes::EndpointUnavailable as i32,
+        Err(_) => return ReturnCodes::AlchemyResult as i32,
+    };
+
+    let ret = match serde_json::to_vec(&ret) {
+        Ok(r) => r,
+        Err(_) => return ReturnCodes::Http as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &ret) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Calls a host function registered on the runtime by name.
+pub fn host_call<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    name_ptr: u32,
+    name_len: u32,
+    args_ptr: u32,
+    args_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let name = match read_string(&mut caller, &mem, name_ptr, name_len) {
+        Ok(n) => n,
+        Err(e) => return e,
+    };
+
+    let args = match read_json(&mut caller, &mem, args_ptr, args_len) {
+        Ok(a) => a,
+        Err(e) => return e,
+    };
+
+    let ret = match caller.data().host_functions.call(&name, &args) {
+        Some(Ok(r)) => r,
+        Some(Err(e)) if e.is::<utils::ResponseTooLarge>() => {
+            return ReturnCodes::ResponseTooLarge as i32
+        }
+        Some(Err(e)) => {
+            tracing::debug!("host function `{name}` failed: {e}");
+            return ReturnCodes::HostCall as i32;
+        }
+        None => return ReturnCodes::HostFunctionNotFound as i32,
+    };
+
+    let ret = match serde_json::to_vec(&ret) {
+        Ok(r) => r,
+        Err(_) => return ReturnCodes::HostCall as i32,
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &ret) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Performs an Alchemy JSON-RPC request, returning its `result`.
+pub fn alchemy_request(
+    key: &str,
+    chain: &str,
+    method: &str,
+    params: &Value,
+    max_response: usize,
+) -> anyhow::Result<Value> {
+    let url = alchemy_url(chain, key);
     let args = serde_json::json!({
         "url": url,
         "method": "post",
@@ -565,44 +1210,126 @@ where
         }
     });
 
-    let ret = match utils::http(&args) {
-        Ok(r) => r,
-        Err(_) => return ReturnCodes::AlchemyResult as i32,
+    let ret = utils::http_with_limit(&args, max_response)?;
+    let ret = ret
+        .get("body")
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("no alchemy response body"))?;
+
+    let ret: Vec<u8> = serde_json::from_value(ret)?;
+    let ret: Value = serde_json::from_slice(&ret)?;
+
+    ret.get("result")
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("no alchemy result"))
+}
+
+fn alchemy_url(chain: &str, key: &str) -> String {
+    format!("https://{chain}.g.alchemy.com/v2/{key}")
+}
+
+/// Logs a string.
+pub fn log<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32, len: u32) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
     };
 
-    let ret = match ret.get("body").cloned() {
-        Some(r) => r,
-        None => return ReturnCodes::AlchemyResult as i32,
+    let log = match read_string(&mut caller, &mem, ptr, len) {
+        Ok(d) => d,
+        Err(e) => return e,
     };
 
-    let ret: Vec<u8> = match serde_json::from_value(ret) {
-        Ok(r) => r,
-        Err(_) => return ReturnCodes::AlchemyResult as i32,
+    match LogLevel::parse(&log) {
+        (LogLevel::Trace, l) => tracing::trace!("controller log: {l}"),
+        (LogLevel::Debug, l) => tracing::debug!("controller log: {l}"),
+        (LogLevel::Info, l) => tracing::info!("controller log: {l}"),
+        (LogLevel::Warn, l) => tracing::warn!("controller log: {l}"),
+        (LogLevel::Error, l) => tracing::error!("controller log: {l}"),
+    }
+
+    caller.data_mut().log.push(log);
+
+    ReturnCodes::Success as i32
+}
+
+/// Hashes the provided data using the host hasher.
+pub fn hash<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    data_ptr: u32,
+    data_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
     };
 
-    let ret: Value = match serde_json::from_slice(&ret) {
-        Ok(r) => r,
-        Err(_) => return ReturnCodes::AlchemyResult as i32,
+    let data = match read_buffer(&mut caller, &mem, data_ptr, data_len) {
+        Ok(d) => d,
+        Err(e) => return e,
     };
 
-    let ret = match ret.get("result") {
-        Some(r) => r,
-        None => return ReturnCodes::AlchemyResult as i32,
+    let hash = H::hash(&data);
+
+    match write_buffer(&mut caller, &mem, ptr, &hash) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Merges the left `a` and right `b` nodes into their parent using the host hasher.
+pub fn merge<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    a_ptr: u32,
+    b_ptr: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
     };
 
-    let ret = match serde_json::to_vec(&ret) {
-        Ok(r) => r,
-        Err(_) => return ReturnCodes::Http as i32,
+    let a = match read_hash(&mut caller, &mem, a_ptr) {
+        Ok(h) => h,
+        Err(e) => return e,
     };
 
-    match write_buffer(&mut caller, &mem, ptr, &ret) {
+    let b = match read_hash(&mut caller, &mem, b_ptr) {
+        Ok(h) => h,
+        Err(e) => return e,
+    };
+
+    let node = H::merge(&a, &b);
+
+    match write_buffer(&mut caller, &mem, ptr, &node) {
         Ok(len) => len,
         Err(e) => e,
     }
 }
 
-/// Logs a string.
-pub fn log<H, D, VM>(mut caller: Caller<Runtime<H, D, VM>>, ptr: u32, len: u32) -> i32
+/// Computes the Keccak-256 hash of the provided data.
+pub fn keccak256<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    data_ptr: u32,
+    data_len: u32,
+    ptr: u32,
+) -> i32
 where
     H: Hasher,
     D: DataBackend,
@@ -613,16 +1340,108 @@ where
         _ => return ReturnCodes::MemoryExport as i32,
     };
 
-    let log = match read_string(&mut caller, &mem, ptr, len) {
+    let data = match read_buffer(&mut caller, &mem, data_ptr, data_len) {
         Ok(d) => d,
         Err(e) => return e,
     };
 
-    tracing::debug!("controller log: {log}");
+    let hash = Keccak256::digest(&data);
 
-    caller.data_mut().log.push(log);
+    match write_buffer(&mut caller, &mem, ptr, &hash) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
 
-    ReturnCodes::Success as i32
+/// Verifies an ICS23 proof, writing `1` if it is valid, and `0` otherwise.
+///
+/// The arguments are a JSON object with the Base64 `proof`, `root`, `key`, and optional `value`,
+/// and the name of the proof `spec`.
+pub fn verify_ics23<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    args_ptr: u32,
+    args_len: u32,
+    ptr: u32,
+) -> i32
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let mem = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => mem,
+        _ => return ReturnCodes::MemoryExport as i32,
+    };
+
+    let args = match read_json(&mut caller, &mem, args_ptr, args_len) {
+        Ok(a) => a,
+        Err(e) => return e,
+    };
+
+    let verified = match verify_ics23_args(&args) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::debug!("failed to verify ics23 proof: {e}");
+            return ReturnCodes::Ics23 as i32;
+        }
+    };
+
+    match write_buffer(&mut caller, &mem, ptr, &[verified as u8]) {
+        Ok(len) => len,
+        Err(e) => e,
+    }
+}
+
+/// Verifies an ICS23 proof from its JSON arguments.
+pub fn verify_ics23_args(args: &Value) -> anyhow::Result<bool> {
+    let field = |name: &str| -> anyhow::Result<Option<Vec<u8>>> {
+        args.get(name)
+            .and_then(Value::as_str)
+            .map(Base64::decode)
+            .transpose()
+    };
+
+    let proof = field("proof")?.ok_or_else(|| anyhow::anyhow!("missing ics23 proof"))?;
+    let root = field("root")?.ok_or_else(|| anyhow::anyhow!("missing ics23 root"))?;
+    let key = field("key")?.ok_or_else(|| anyhow::anyhow!("missing ics23 key"))?;
+    let value = field("value")?;
+    let spec = args
+        .get("spec")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("missing ics23 spec"))?;
+
+    ics23::verify(&proof, spec, &root, &key, value.as_deref())
+}
+
+/// Aborts the controller execution, setting the return value to the standard error envelope.
+///
+/// The returned error traps the instance so no further guest code is executed.
+pub fn abort<H, D, VM>(
+    mut caller: Caller<Runtime<H, D, VM>>,
+    code: u32,
+    ptr: u32,
+    len: u32,
+) -> anyhow::Result<()>
+where
+    H: Hasher,
+    D: DataBackend,
+    VM: Vm<H, D>,
+{
+    let message = match caller.get_export("memory") {
+        Some(Extern::Memory(mem)) => read_string(&mut caller, &mem, ptr, len).ok(),
+        _ => None,
+    };
+
+    let message = message.unwrap_or_else(|| String::from("undefined abort message"));
+
+    caller.data_mut().ret.replace(serde_json::json!({
+        "error": {
+            "code": code,
+            "message": message,
+        }
+    }));
+
+    anyhow::bail!("controller aborted with code {code}: {message}")
 }
 
 fn read_buffer<H, D, VM>(
@@ -678,9 +1497,7 @@ where
     VM: Vm<H, D>,
 {
     read_buffer(caller, mem, ptr, 8).and_then(|buffer| {
-        <[u8; 8]>::try_from(buffer.as_slice())
-            .map(u64::from_le_bytes)
-            .map_err(|_| ReturnCodes::BufferTooLarge as i32)
+        utils::decode_u64_le(&buffer).map_err(|_| ReturnCodes::BufferTooLarge as i32)
     })
 }
 
@@ -3,8 +3,12 @@
 extern crate alloc;
 
 pub mod abi;
+pub mod portable;
 pub use valence_coprocessor as core;
 
+#[cfg(feature = "mocks")]
+pub mod harness;
+
 #[cfg(feature = "std")]
 pub mod host;
 
@@ -0,0 +1,103 @@
+use alloc::{format, string::ToString as _, vec::Vec};
+
+use miniz_oxide::inflate::{DecompressError, TINFLStatus};
+use valence_coprocessor::utils::HTTP_MAX_RESPONSE_SIZE;
+
+use super::HttpError;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const GZIP_DEFLATE: u8 = 8;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Decodes a body with the provided content coding.
+///
+/// The decoded body is bounded by [`HTTP_MAX_RESPONSE_SIZE`], as the limit of the host only
+/// applies to the encoded bytes. Returns `None` for unsupported codings, so the body is kept as
+/// received.
+pub(crate) fn decode(coding: &str, body: &[u8]) -> Result<Option<Vec<u8>>, HttpError> {
+    match coding {
+        "gzip" | "x-gzip" => gunzip(body).map(Some),
+        "deflate" => inflate(body).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Decompresses a `gzip` member, checking its trailer.
+fn gunzip(body: &[u8]) -> Result<Vec<u8>, HttpError> {
+    let invalid = |e: &str| HttpError::Body(format!("invalid gzip body: {e}"));
+
+    if body.len() < 18 || body[..2] != GZIP_MAGIC || body[2] != GZIP_DEFLATE {
+        return Err(invalid("malformed header"));
+    }
+
+    let flags = body[3];
+    let mut offset = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = body
+            .get(offset..offset + 2)
+            .map(|l| u16::from_le_bytes([l[0], l[1]]) as usize)
+            .ok_or_else(|| invalid("truncated extra field"))?;
+
+        offset += 2 + len;
+    }
+
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = body
+                .get(offset..)
+                .and_then(|b| b.iter().position(|b| *b == 0))
+                .ok_or_else(|| invalid("truncated header field"))?;
+
+            offset += len + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        offset += 2;
+    }
+
+    let data = body
+        .get(offset..body.len() - 8)
+        .ok_or_else(|| invalid("truncated header"))?;
+
+    let decompressed =
+        miniz_oxide::inflate::decompress_to_vec_with_limit(data, HTTP_MAX_RESPONSE_SIZE)
+            .map_err(inflate_err)?;
+
+    let trailer = &body[body.len() - 8..];
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    // the size is stored modulo 2^32
+    if crc32fast::hash(&decompressed) != crc || decompressed.len() as u32 != size {
+        return Err(invalid("trailer mismatch"));
+    }
+
+    Ok(decompressed)
+}
+
+/// Decompresses a `deflate` body.
+///
+/// The coding is specified as a zlib stream, but some servers send a raw deflate stream instead.
+fn inflate(body: &[u8]) -> Result<Vec<u8>, HttpError> {
+    match miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(body, HTTP_MAX_RESPONSE_SIZE) {
+        Ok(b) => Ok(b),
+        Err(e) if e.status == TINFLStatus::HasMoreOutput => Err(inflate_err(e)),
+        Err(_) => miniz_oxide::inflate::decompress_to_vec_with_limit(body, HTTP_MAX_RESPONSE_SIZE)
+            .map_err(inflate_err),
+    }
+}
+
+fn inflate_err(e: DecompressError) -> HttpError {
+    match e.status {
+        TINFLStatus::HasMoreOutput => HttpError::Body(format!(
+            "the decompressed body exceeds the limit of {HTTP_MAX_RESPONSE_SIZE} bytes"
+        )),
+        _ => HttpError::Body(e.to_string()),
+    }
+}
@@ -0,0 +1,591 @@
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString as _},
+    vec::Vec,
+};
+use core::{cell::RefCell, fmt, time::Duration};
+
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::abi;
+
+use super::{multipart, MultipartPart};
+
+/// A HTTP method.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// `GET` method.
+    #[default]
+    Get,
+
+    /// `POST` method.
+    Post,
+
+    /// `PUT` method.
+    Put,
+
+    /// `PATCH` method.
+    Patch,
+
+    /// `DELETE` method.
+    Delete,
+
+    /// `HEAD` method.
+    Head,
+}
+
+impl HttpMethod {
+    /// Returns the method name, as expected by `abi::http`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "get",
+            HttpMethod::Post => "post",
+            HttpMethod::Put => "put",
+            HttpMethod::Patch => "patch",
+            HttpMethod::Delete => "delete",
+            HttpMethod::Head => "head",
+        }
+    }
+}
+
+/// The body of a HTTP request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpBody {
+    /// Raw bytes.
+    Bytes(Vec<u8>),
+
+    /// A JSON value, sent with a `Content-Type: application/json` header.
+    Json(Value),
+}
+
+/// The retry policy of a HTTP request, enforced by the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRetry {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, doubled after each attempt.
+    pub backoff_ms: u64,
+
+    /// Retried status codes, in addition to connection errors.
+    pub statuses: Vec<u16>,
+}
+
+impl HttpRetry {
+    /// Creates a new policy, retrying the
+    /// [`HTTP_RETRY_STATUSES`](valence_coprocessor::utils::HTTP_RETRY_STATUSES).
+    pub fn new(max_attempts: u32, backoff_ms: u64) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff_ms,
+            statuses: valence_coprocessor::utils::HTTP_RETRY_STATUSES.to_vec(),
+        }
+    }
+
+    /// Returns the policy, as expected by `abi::http`.
+    pub fn to_value(&self) -> Value {
+        json!({
+            "max_attempts": self.max_attempts,
+            "backoff_ms": self.backoff_ms,
+            "statuses": self.statuses,
+        })
+    }
+}
+
+/// A HTTP request, executed via [`HttpClient`].
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Request method.
+    pub method: HttpMethod,
+
+    /// Request URL.
+    pub url: String,
+
+    /// Request headers.
+    pub headers: BTreeMap<String, String>,
+
+    /// Query parameters.
+    pub query: BTreeMap<String, String>,
+
+    /// Optional body.
+    pub body: Option<HttpBody>,
+
+    /// Optional bearer token.
+    pub bearer: Option<String>,
+
+    /// Whether the response body is kept as received, without decompression.
+    pub raw_body: bool,
+
+    /// Optional retry policy.
+    pub retry: Option<HttpRetry>,
+
+    /// Optional timeout, enforced by the host.
+    ///
+    /// Defaults to [`HTTP_DEFAULT_TIMEOUT_MS`](valence_coprocessor::utils::HTTP_DEFAULT_TIMEOUT_MS)
+    /// if unset; the host reduces larger timeouts to its configured maximum.
+    pub timeout: Option<Duration>,
+}
+
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // credentials are never logged
+        let headers: BTreeMap<_, _> = self
+            .headers
+            .iter()
+            .map(|(k, v)| match k.eq_ignore_ascii_case("authorization") {
+                true => (k.as_str(), "<redacted>"),
+                false => (k.as_str(), v.as_str()),
+            })
+            .collect();
+
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &headers)
+            .field("query", &self.query)
+            .field("body", &self.body)
+            .field("bearer", &self.bearer.as_ref().map(|_| "<redacted>"))
+            .field("raw_body", &self.raw_body)
+            .field("retry", &self.retry)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl HttpRequest {
+    /// Creates a new request.
+    pub fn new<U: Into<String>>(method: HttpMethod, url: U) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new `GET` request.
+    pub fn get<U: Into<String>>(url: U) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    /// Creates a new `POST` request.
+    pub fn post<U: Into<String>>(url: U) -> Self {
+        Self::new(HttpMethod::Post, url)
+    }
+
+    /// Creates a new `PUT` request.
+    pub fn put<U: Into<String>>(url: U) -> Self {
+        Self::new(HttpMethod::Put, url)
+    }
+
+    /// Creates a new `PATCH` request.
+    pub fn patch<U: Into<String>>(url: U) -> Self {
+        Self::new(HttpMethod::Patch, url)
+    }
+
+    /// Creates a new `DELETE` request.
+    pub fn delete<U: Into<String>>(url: U) -> Self {
+        Self::new(HttpMethod::Delete, url)
+    }
+
+    /// Sets a header, replacing any previous value.
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets a query parameter, replacing any previous value.
+    pub fn query<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.query.insert(key.into(), value.into());
+        self
+    }
+
+    /// Authenticates the request with a bearer token.
+    pub fn bearer<T: Into<String>>(mut self, token: T) -> Self {
+        self.bearer = Some(token.into());
+        self
+    }
+
+    /// Sets the timeout of the request, including the read of the response body.
+    ///
+    /// The host fails the request with [`HttpError::Timeout`] if exceeded.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retries the request on connection errors and on the retried statuses.
+    ///
+    /// The first retry is delayed by `backoff_ms`, doubled after each attempt. A `Retry-After`
+    /// header set in seconds overrides the delay, up to
+    /// [`HTTP_MAX_RETRY_AFTER_MS`](valence_coprocessor::utils::HTTP_MAX_RETRY_AFTER_MS).
+    pub fn retry(mut self, max_attempts: u32, backoff_ms: u64) -> Self {
+        let retry = self.retry.get_or_insert_with(|| HttpRetry::new(1, 0));
+
+        retry.max_attempts = max_attempts.max(1);
+        retry.backoff_ms = backoff_ms;
+
+        self
+    }
+
+    /// Sets the status codes retried by the [`HttpRequest::retry`] policy.
+    pub fn retry_statuses<I: IntoIterator<Item = u16>>(mut self, statuses: I) -> Self {
+        self.retry
+            .get_or_insert_with(|| HttpRetry::new(1, 0))
+            .statuses = statuses.into_iter().collect();
+
+        self
+    }
+
+    /// Keeps the response body as received, instead of decompressing it.
+    pub fn no_auto_decompress(mut self) -> Self {
+        self.raw_body = true;
+        self
+    }
+
+    /// Sets a raw body.
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = Some(HttpBody::Bytes(body.into()));
+        self
+    }
+
+    /// Sets a `multipart/form-data` body, replacing the `Content-Type` header.
+    pub fn multipart(mut self, parts: Vec<MultipartPart>) -> Self {
+        let (boundary, body) = multipart::encode(&parts);
+
+        self.headers
+            .retain(|k, _| !k.eq_ignore_ascii_case("content-type"));
+        self.headers.insert(
+            "Content-Type".into(),
+            alloc::format!("multipart/form-data; boundary={boundary}"),
+        );
+
+        self.body = Some(HttpBody::Bytes(body));
+        self
+    }
+
+    /// Sets a JSON body.
+    pub fn json(mut self, value: Value) -> Self {
+        self.body = Some(HttpBody::Json(value));
+        self
+    }
+
+    /// Returns the arguments of the request, as expected by `abi::http`.
+    pub fn to_args(&self) -> Value {
+        // the body is decoded by the response, not by the host
+        let mut args = json!({
+            "url": self.url,
+            "method": self.method.as_str(),
+            "headers": self.headers,
+            "raw_response": true,
+        });
+
+        if !self.query.is_empty() {
+            args["query"] = json!(self.query);
+        }
+
+        if let Some(b) = &self.bearer {
+            args["bearer"] = b.as_str().into();
+        }
+
+        if let Some(r) = &self.retry {
+            args["retry"] = r.to_value();
+        }
+
+        // sub-millisecond timeouts are rounded up, as zero isn't a valid timeout
+        if let Some(t) = self.timeout {
+            args["timeout_ms"] = (t.as_millis() as u64).max(1).into();
+        }
+
+        match &self.body {
+            Some(HttpBody::Bytes(b)) => args["body"] = json!(b),
+            Some(HttpBody::Json(j)) => args["json"] = j.clone(),
+            None => (),
+        }
+
+        args
+    }
+}
+
+/// A HTTP response.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code.
+    pub status: u16,
+
+    /// Response headers.
+    pub headers: BTreeMap<String, String>,
+
+    /// Response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Parses the response returned by `abi::http`.
+    ///
+    /// The body must be returned as bytes, as requested by [`HttpRequest::to_args`]; any other
+    /// representation is rejected, rather than coerced.
+    pub fn from_value(value: &Value) -> Result<Self, HttpError> {
+        let status = value
+            .get("status")
+            .and_then(Value::as_u64)
+            .and_then(|s| u16::try_from(s).ok())
+            .ok_or_else(|| HttpError::InvalidResponse("missing status".into()))?;
+
+        let headers = value
+            .get("headers")
+            .and_then(Value::as_object)
+            .map(|h| {
+                h.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let body = match value.get("body") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(b)) => b
+                .iter()
+                .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| HttpError::InvalidResponse("the body isn't a byte array".into()))?,
+            Some(_) => {
+                return Err(HttpError::InvalidResponse(
+                    "the body wasn't returned as bytes".into(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Returns the value of a header, ignoring the case of its name.
+    ///
+    /// Servers are inconsistent with the casing of header names; prefer this over the raw
+    /// [`HttpResponse::headers`].
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the `Content-Type` header.
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+
+    /// Returns the `Content-Length` header, if it is a valid length.
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length")?.trim().parse().ok()
+    }
+
+    /// Decompresses the body according to its `Content-Encoding` header.
+    ///
+    /// Supports the `gzip` and `deflate` codings; the body is kept as received for any other
+    /// coding. Once decompressed, the `Content-Encoding` and `Content-Length` headers are removed.
+    #[cfg(feature = "decompress")]
+    pub fn decompress(mut self) -> Result<Self, HttpError> {
+        let codings: Vec<_> = match self.header("content-encoding") {
+            Some(c) => c
+                .split(',')
+                .map(|c| c.trim().to_ascii_lowercase())
+                .filter(|c| !c.is_empty() && c != "identity")
+                .collect(),
+            None => return Ok(self),
+        };
+
+        // codings are listed in the order they were applied
+        let mut body = None;
+
+        for coding in codings.iter().rev() {
+            match super::encoding::decode(coding, body.as_deref().unwrap_or(&self.body))? {
+                Some(b) => body = Some(b),
+                None => return Ok(self),
+            }
+        }
+
+        if let Some(b) = body {
+            self.body = b;
+            self.headers.retain(|k, _| {
+                !k.eq_ignore_ascii_case("content-encoding")
+                    && !k.eq_ignore_ascii_case("content-length")
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Returns `true` if the status is `2xx`.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns `true` if the response has no content.
+    pub fn is_empty_body(&self) -> bool {
+        self.status == 204 || self.body.is_empty()
+    }
+
+    /// Returns the body as text; empty if the response has no content.
+    pub fn text(&self) -> Result<String, HttpError> {
+        String::from_utf8(self.body.clone()).map_err(|e| HttpError::Body(e.to_string()))
+    }
+
+    /// Returns the body as JSON; `null` if the response has no content.
+    pub fn json_value(&self) -> Result<Value, HttpError> {
+        if self.is_empty_body() {
+            return Ok(Value::Null);
+        }
+
+        serde_json::from_slice(&self.body).map_err(|e| HttpError::Body(e.to_string()))
+    }
+
+    /// Deserializes the body as JSON, without an intermediate [`Value`].
+    ///
+    /// A response without content is deserialized from `null`.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        let body = match self.is_empty_body() {
+            true => b"null".as_slice(),
+            false => self.body.as_slice(),
+        };
+
+        serde_json::from_slice(body).map_err(|e| HttpError::Body(e.to_string()))
+    }
+
+    /// Deserializes the body as JSON if the status is `2xx`, or returns it as text otherwise.
+    ///
+    /// Error responses of REST APIs often aren't JSON, or don't match the expected type.
+    pub fn json_or_text<T: DeserializeOwned>(&self) -> Result<JsonOrText<T>, HttpError> {
+        match self.is_success() {
+            true => self.json().map(JsonOrText::Json),
+            false => self.text().map(JsonOrText::Text),
+        }
+    }
+}
+
+/// The body of a response, deserialized by [`HttpResponse::json_or_text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonOrText<T> {
+    /// The deserialized body of a successful response.
+    Json(T),
+
+    /// The text body of an unsuccessful response.
+    Text(String),
+}
+
+/// An error of the portable HTTP client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    /// The host failed to perform the request.
+    Transport(String),
+
+    /// The host returned a malformed response.
+    InvalidResponse(String),
+
+    /// The body couldn't be decoded.
+    Body(String),
+
+    /// The bearer token couldn't be refreshed.
+    TokenRefresh(String),
+
+    /// The request didn't complete within its timeout.
+    Timeout(Duration),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Transport(e) => write!(f, "http request failed: {e}"),
+            HttpError::InvalidResponse(e) => write!(f, "invalid http response: {e}"),
+            HttpError::Body(e) => write!(f, "invalid http body: {e}"),
+            HttpError::TokenRefresh(e) => write!(f, "failed to refresh the bearer token: {e}"),
+            HttpError::Timeout(t) => write!(f, "http request timed out after {t:?}"),
+        }
+    }
+}
+
+impl core::error::Error for HttpError {}
+
+type TokenRefresh = dyn Fn() -> anyhow::Result<String>;
+
+/// Executes [`HttpRequest`]s via `abi::http`.
+#[derive(Default)]
+pub struct HttpClient {
+    refresh: Option<Box<TokenRefresh>>,
+    token: RefCell<Option<String>>,
+}
+
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClient")
+            .field("refresh", &self.refresh.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HttpClient {
+    /// Sets the hook that fetches a new bearer token.
+    ///
+    /// If an authenticated request is rejected with `401 Unauthorized`, the hook is called and
+    /// the request is retried once with the new token. The new token replaces the bearer of the
+    /// subsequent requests executed by the client.
+    pub fn with_token_refresh<F>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> anyhow::Result<String> + 'static,
+    {
+        self.refresh = Some(Box::new(refresh));
+        self
+    }
+
+    /// Executes the request.
+    ///
+    /// With the `decompress` feature, `gzip` and `deflate` response bodies are decompressed unless
+    /// disabled via [`HttpRequest::no_auto_decompress`].
+    pub fn execute(&self, request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+        let mut request = request.clone();
+
+        if request.bearer.is_some() {
+            if let Some(t) = self.token.borrow().as_ref() {
+                request.bearer = Some(t.clone());
+            }
+        }
+
+        let response = Self::send(&request)?;
+
+        let refresh = match &self.refresh {
+            Some(r) if response.status == 401 && request.bearer.is_some() => r,
+            _ => return Ok(response),
+        };
+
+        let token = refresh().map_err(|e| HttpError::TokenRefresh(e.to_string()))?;
+
+        request.bearer = Some(token.clone());
+        self.token.replace(Some(token));
+
+        Self::send(&request)
+    }
+
+    fn send(request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+        let response = abi::http(&request.to_args()).map_err(|e| {
+            match e.downcast_ref::<valence_coprocessor::utils::RequestTimeout>() {
+                Some(t) => HttpError::Timeout(Duration::from_millis(t.timeout_ms)),
+                None => HttpError::Transport(e.to_string()),
+            }
+        })?;
+
+        let response = HttpResponse::from_value(&response)?;
+
+        #[cfg(feature = "decompress")]
+        let response = match request.raw_body {
+            true => response,
+            false => response.decompress()?,
+        };
+
+        Ok(response)
+    }
+}
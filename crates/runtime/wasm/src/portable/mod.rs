@@ -0,0 +1,9 @@
+//! Portable helpers for controllers, running both on the co-processor host and natively.
+
+#[cfg(feature = "decompress")]
+mod encoding;
+mod http;
+mod multipart;
+
+pub use http::*;
+pub use multipart::MultipartPart;
@@ -0,0 +1,92 @@
+use alloc::{format, string::String, vec::Vec};
+
+/// A part of a `multipart/form-data` body.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MultipartPart {
+    /// Name of the form field.
+    pub name: String,
+
+    /// Optional filename, for file uploads.
+    pub filename: Option<String>,
+
+    /// Optional content type; `text/plain` if unset.
+    pub content_type: Option<String>,
+
+    /// Content of the part.
+    pub data: Vec<u8>,
+}
+
+impl MultipartPart {
+    /// Creates a new part.
+    pub fn new<N: Into<String>, D: Into<Vec<u8>>>(name: N, data: D) -> Self {
+        Self {
+            name: name.into(),
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the filename of the part.
+    pub fn with_filename<F: Into<String>>(mut self, filename: F) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Sets the content type of the part.
+    pub fn with_content_type<C: Into<String>>(mut self, content_type: C) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+}
+
+/// Encodes the parts as a `multipart/form-data` body, returning its boundary and bytes.
+///
+/// The boundary is deterministic, as the guest has no source of randomness, and is chosen so it
+/// doesn't occur in the data of any part.
+pub(crate) fn encode(parts: &[MultipartPart]) -> (String, Vec<u8>) {
+    let boundary = (0u64..)
+        .map(|n| format!("valence-boundary-{n:016x}"))
+        .find(|b| {
+            !parts
+                .iter()
+                .any(|p| p.data.windows(b.len()).any(|w| w == b.as_bytes()))
+        })
+        .unwrap_or_default();
+
+    let mut body = Vec::new();
+
+    for part in parts {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"");
+        body.extend_from_slice(escape(&part.name).as_bytes());
+        body.push(b'"');
+
+        if let Some(f) = &part.filename {
+            body.extend_from_slice(b"; filename=\"");
+            body.extend_from_slice(escape(f).as_bytes());
+            body.push(b'"');
+        }
+
+        body.extend_from_slice(b"\r\n");
+
+        if let Some(c) = &part.content_type {
+            body.extend_from_slice(format!("Content-Type: {c}\r\n").as_bytes());
+        }
+
+        body.extend_from_slice(b"\r\n");
+        body.extend_from_slice(&part.data);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    (boundary, body)
+}
+
+/// Escapes a quoted parameter of the content disposition, as browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
@@ -0,0 +1,165 @@
+"""Independent generator of ICS23 fixtures.
+
+Builds a cosmos IAVL tree (AVL balancing, `writeHashBytes` node hashing) and a tendermint simple
+Merkle tree, then converts their paths to ICS23 proofs as `iavl/proof_ics23.go` does. Roots are
+computed from the trees, not from the proofs.
+"""
+import hashlib, json, sys
+
+sha = lambda b: hashlib.sha256(b).digest()
+
+def varint(n):
+    out = b""
+    while n >= 0x80:
+        out += bytes([(n & 0x7F) | 0x80]); n >>= 7
+    return out + bytes([n])
+
+zz = lambda n: varint((n << 1) ^ (n >> 63))
+lp = lambda b: varint(len(b)) + b
+
+# --- IAVL ---
+class Node:
+    def __init__(s, key, value=None, left=None, right=None, version=0):
+        s.key, s.value, s.left, s.right, s.version = key, value, left, right, version
+        s.update()
+    def leaf(s): return s.left is None
+    def update(s):
+        if s.leaf(): s.height, s.size = 0, 1
+        else:
+            s.height = 1 + max(s.left.height, s.right.height)
+            s.size = s.left.size + s.right.size
+    def hash(s):
+        b = zz(s.height) + zz(s.size) + zz(s.version)
+        if s.leaf(): b += lp(s.key) + lp(sha(s.value))
+        else: b += lp(s.left.hash()) + lp(s.right.hash())
+        return sha(b)
+
+def rot_right(n, version):
+    l = n.left; n.left = l.right; n.update(); l.right = n; l.update()
+    l.version = version; return l
+def rot_left(n, version):
+    r = n.right; n.right = r.left; n.update(); r.left = n; r.update()
+    r.version = version; return r
+
+def balance(n, version):
+    b = n.left.height - n.right.height
+    if b > 1:
+        if n.left.left.height < n.left.right.height: n.left = rot_left(n.left, version)
+        return rot_right(n, version)
+    if b < -1:
+        if n.right.right.height < n.right.left.height: n.right = rot_right(n.right, version)
+        return rot_left(n, version)
+    return n
+
+def insert(n, key, value, version):
+    if n is None: return Node(key, value, version=version)
+    if n.leaf():
+        leaf = Node(key, value, version=version)
+        if key < n.key: return Node(n.key, left=leaf, right=n, version=version)
+        if key > n.key: return Node(key, left=n, right=leaf, version=version)
+        return leaf
+    if key < n.key: n.left = insert(n.left, key, value, version)
+    else: n.right = insert(n.right, key, value, version)
+    # nodes on the path of an insertion are saved with the new version
+    n.version = version
+    n.update()
+    return balance(n, version)
+
+def iavl_path(n, key):
+    """Returns the leaf of `key` and its inner ops from the leaf to the root."""
+    ops = []
+    while not n.leaf():
+        pre = zz(n.height) + zz(n.size) + zz(n.version)
+        if key < n.key:
+            ops.append((pre + b"\x20", b"\x20" + n.right.hash())); n = n.left
+        else:
+            ops.append((pre + b"\x20" + n.left.hash() + b"\x20", b"")); n = n.right
+    assert n.key == key
+    return n, ops[::-1]
+
+# --- protobuf ---
+def field(num, wire, payload):
+    tag = varint(num << 3 | wire)
+    return tag + (varint(payload) if wire == 0 else lp(payload))
+
+def leaf_op(prefix):
+    return field(1, 0, 1) + field(3, 0, 1) + field(4, 0, 1) + field(5, 2, prefix)
+
+def inner_op(prefix, suffix):
+    return field(1, 0, 1) + field(2, 2, prefix) + (field(3, 2, suffix) if suffix else b"")
+
+def existence(key, value, leaf_prefix, ops):
+    return (field(1, 2, key) + field(2, 2, value) + field(3, 2, leaf_op(leaf_prefix))
+            + b"".join(field(4, 2, inner_op(p, s)) for p, s in ops))
+
+def iavl_existence(root, key):
+    leaf, ops = iavl_path(root, key)
+    return existence(key, leaf.value, zz(0) + zz(1) + zz(leaf.version), ops)
+
+def nonexistence(key, left, right):
+    return field(2, 2, field(1, 2, key) + (field(2, 2, left) if left else b"")
+                 + (field(3, 2, right) if right else b""))
+
+# --- tendermint simple Merkle ---
+def split(n):
+    k = 1
+    while k * 2 < n: k *= 2
+    return k
+
+def tm_root(leaves):
+    if len(leaves) == 1: return leaves[0]
+    k = split(len(leaves))
+    return sha(b"\x01" + tm_root(leaves[:k]) + tm_root(leaves[k:]))
+
+def tm_path(leaves, i):
+    if len(leaves) == 1: return []
+    k = split(len(leaves))
+    if i < k: return tm_path(leaves[:k], i) + [(b"\x01", tm_root(leaves[k:]))]
+    return tm_path(leaves[k:], i - k) + [(b"\x01" + tm_root(leaves[:k]), b"")]
+
+def tm_leaf(k, v): return sha(b"\x00" + lp(k) + lp(sha(v)))
+
+def iavl_fixture(versions, absent):
+    """Inserts each batch of `versions` with its version, and proves all keys plus `absent`."""
+    root, kvs = None, {}
+    for version, batch in versions:
+        for k, v in batch:
+            root = insert(root, k, v, version); kvs[k] = v
+    keys = sorted(kvs)
+    out = {"spec": "iavl", "root": root.hash().hex(), "exist": [], "nonexist": []}
+    for k in keys:
+        p = iavl_existence(root, k)
+        out["exist"].append({"key": k.decode(), "value": kvs[k].decode(), "proof": field(1, 2, p).hex()})
+    for k in absent:
+        lt = [x for x in keys if x < k]; gt = [x for x in keys if x > k]
+        l = iavl_existence(root, lt[-1]) if lt else None
+        r = iavl_existence(root, gt[0]) if gt else None
+        out["nonexist"].append({"key": k.decode(), "proof": nonexistence(k, l, r).hex()})
+    return out
+
+def tendermint_fixture(kvs):
+    kvs = sorted(kvs)
+    leaves = [tm_leaf(k, v) for k, v in kvs]
+    out = {"spec": "tendermint", "root": tm_root(leaves).hex(), "exist": [], "nonexist": []}
+    for i, (k, v) in enumerate(kvs):
+        p = existence(k, v, b"\x00", tm_path(leaves, i))
+        out["exist"].append({"key": k.decode(), "value": v.decode(), "proof": field(1, 2, p).hex()})
+    return out
+
+if __name__ == "__main__":
+    words = ["alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel",
+             "india", "juliett", "kilo", "lima", "mike", "november", "oscar", "papa"]
+    kv = lambda w: (w[:2].encode(), w.encode())
+    json.dump({
+        "iavl": iavl_fixture(
+            [(5, [(b"e", b"echo"), (b"a", b"alpha"), (b"c", b"charlie")])],
+            [b"b", b"0", b"f"]),
+        "iavl-rebalanced": iavl_fixture(
+            [(3, [kv(w) for w in words[:8]]), (7, [kv(w) for w in reversed(words[8:])]),
+             (9, [(b"de", b"delta-2"), (b"ki", b"kilo-2")])],
+            [b"aa", b"dd", b"hz", b"zz"]),
+        "tendermint": tendermint_fixture([(b"x", b"xray"), (b"y", b"yankee")]),
+        "tendermint-unbalanced": tendermint_fixture(
+            [(w.encode(), w.upper().encode()) for w in words[:5]]),
+    }, sys.stdout, indent=2)
+    print()
@@ -0,0 +1,212 @@
+use serde_json::json;
+use valence_coprocessor::{
+    ArgSchema, Blake3Hasher, ControllerSchema, MemoryBackend, StateProof, Witness,
+};
+use valence_coprocessor_wasm::{
+    harness::ControllerTestHarness,
+    host::{self, ValenceWasm},
+};
+
+const HTTP_CONTROLLER: &str = r#"
+    (module
+        (import "valence" "http" (func $http (param i32 i32 i32) (result i32)))
+        (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "{\"url\":\"mock://price\"}")
+        (func (export "entrypoint")
+            (drop (call $ret (i32.const 1024) (call $http (i32.const 0) (i32.const 22) (i32.const 1024))))))
+"#;
+
+const STATE_PROOFS_CONTROLLER: &str = r#"
+    (module
+        (import "valence" "args" (func $args (param i32) (result i32)))
+        (import "valence" "get_state_proofs" (func $proofs (param i32 i32 i32) (result i32)))
+        (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "entrypoint")
+            (local $len i32)
+            (local.set $len (call $args (i32.const 0)))
+            (drop (call $ret (i32.const 4096) (call $proofs (i32.const 0) (local.get $len) (i32.const 4096))))))
+"#;
+
+const HTTP_BATCH_CONTROLLER: &str = r#"
+    (module
+        (import "valence" "args" (func $args (param i32) (result i32)))
+        (import "valence" "http_batch" (func $batch (param i32 i32 i32) (result i32)))
+        (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "entrypoint")
+            (local $len i32)
+            (local.set $len (call $args (i32.const 0)))
+            (drop (call $ret (i32.const 4096) (call $batch (i32.const 0) (local.get $len) (i32.const 4096))))))
+"#;
+
+const WITNESSES_CONTROLLER: &str = r#"
+    (module
+        (import "valence" "args" (func $args (param i32) (result i32)))
+        (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (func (export "get_witnesses")
+            (drop (call $ret (i32.const 0) (call $args (i32.const 0))))))
+"#;
+
+const DESCRIBE_CONTROLLER: &str = r#"
+    (module
+        (import "valence" "ret" (func $ret (param i32 i32) (result i32)))
+        (memory (export "memory") 1)
+        (data (i32.const 0) "{\"args\":[{\"name\":\"url\",\"type\":\"string\",\"required\":true}]}")
+        (func (export "describe")
+            (drop (call $ret (i32.const 0) (i32.const 57)))))
+"#;
+
+#[test]
+fn harness_runs_controller_with_mocked_http() {
+    let wasm = wat::parse_str(HTTP_CONTROLLER).unwrap();
+    let response = json!({"status": 200, "body": {"price": 42}});
+
+    let harness = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .with_http_response("mock://price", response.clone());
+
+    let output = harness.entrypoint(json!({})).unwrap();
+
+    assert_eq!(output.ret, response);
+}
+
+#[test]
+fn state_proofs_are_fetched_in_order_with_partial_failures() {
+    let wasm = wat::parse_str(STATE_PROOFS_CONTROLLER).unwrap();
+    let proof = |number| StateProof {
+        domain: [number as u8; 32],
+        number,
+        state_root: [0xaa; 32],
+        payload: vec![],
+        proof: vec![number as u8],
+    };
+
+    let harness = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .with_state_proof("ethereum", proof(1))
+        .with_state_proof("neutron", proof(2));
+
+    let output = harness
+        .entrypoint(json!([
+            {"domain": "neutron", "args": {}},
+            {"domain": "solana", "args": {}},
+            {"domain": "ethereum", "args": {}},
+        ]))
+        .unwrap();
+
+    assert_eq!(
+        output.ret,
+        json!([
+            {"proof": proof(2)},
+            {"error": "no mocked state proof for `solana`"},
+            {"proof": proof(1)},
+        ])
+    );
+}
+
+#[test]
+fn http_batch_returns_responses_in_order_with_partial_failures() {
+    let wasm = wat::parse_str(HTTP_BATCH_CONTROLLER).unwrap();
+    let response = |n| json!({"status": 200, "body": {"balance": n}});
+
+    let harness = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .with_http_response("mock://alice", response(1))
+        .with_http_response("mock://bob", response(2));
+
+    let output = harness
+        .entrypoint(json!([
+            {"url": "mock://bob"},
+            {"url": "mock://carol"},
+            {"url": "mock://alice"},
+        ]))
+        .unwrap();
+
+    assert_eq!(
+        output.ret,
+        json!([
+            {"response": response(2)},
+            {"error": "no mocked response for `mock://carol`"},
+            {"response": response(1)},
+        ])
+    );
+}
+
+#[test]
+fn fresh_argument_does_not_reach_the_controller() {
+    let wasm = wat::parse_str(ValenceWasm::<Blake3Hasher, MemoryBackend>::ECHO_CONTROLLER).unwrap();
+    let harness = ControllerTestHarness::new(wasm).unwrap();
+
+    let args = json!({"value": 1, host::FRESH_ARG: true});
+
+    assert!(host::is_fresh(&args));
+    assert!(!host::is_fresh(&json!({host::FRESH_ARG: false})));
+
+    let output = harness.entrypoint(args).unwrap();
+
+    assert_eq!(output.ret, json!({"value": 1}));
+}
+
+#[test]
+fn witnesses_over_the_limit_are_rejected() {
+    let wasm = wat::parse_str(WITNESSES_CONTROLLER).unwrap();
+    let harness = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .with_max_witnesses(2);
+    let witness = serde_json::to_value(Witness::Data(vec![1])).unwrap();
+
+    let output = harness
+        .get_witnesses(json!([witness.clone(), witness.clone()]))
+        .unwrap();
+
+    assert_eq!(output.ret_witnesses().unwrap().len(), 2);
+
+    let err = harness
+        .get_witnesses(json!([witness.clone(), witness.clone(), witness]))
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<host::TooManyWitnesses>(),
+        Some(&host::TooManyWitnesses { count: 3, max: 2 })
+    );
+    assert!(err.to_string().contains("returned 3 witnesses; at most 2"));
+}
+
+#[test]
+fn describe_returns_the_args_schema() {
+    let wasm = wat::parse_str(DESCRIBE_CONTROLLER).unwrap();
+    let output = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .describe()
+        .unwrap();
+    let schema: ControllerSchema = serde_json::from_value(output.ret).unwrap();
+
+    assert_eq!(
+        schema,
+        ControllerSchema {
+            description: None,
+            args: vec![ArgSchema {
+                name: "url".into(),
+                ty: "string".into(),
+                required: true,
+                description: None,
+            }],
+        }
+    );
+
+    let wasm = wat::parse_str(ValenceWasm::<Blake3Hasher, MemoryBackend>::ECHO_CONTROLLER).unwrap();
+    let err = ControllerTestHarness::new(wasm)
+        .unwrap()
+        .describe()
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<host::ExportNotFound>(),
+        Some(&host::ExportNotFound {
+            export: "describe".into()
+        })
+    );
+}
@@ -0,0 +1,478 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_json::json;
+use valence_coprocessor_wasm::portable::{
+    HttpBody, HttpClient, HttpError, HttpRequest, HttpResponse, JsonOrText, MultipartPart,
+};
+
+/// Serves requests, accepting only the `fresh` bearer token.
+fn token_server() -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+
+    thread::spawn(move || {
+        for r in server.incoming_requests() {
+            let authorized = r
+                .headers()
+                .iter()
+                .any(|h| h.field.equiv("Authorization") && h.value == "Bearer fresh");
+
+            let res = match authorized {
+                true => tiny_http::Response::from_string(r#"{"ok":true}"#),
+                false => tiny_http::Response::from_string("").with_status_code(401),
+            };
+
+            r.respond(res).unwrap();
+        }
+    });
+
+    format!("http://127.0.0.1:{port}")
+}
+
+#[test]
+fn bearer_token_is_refreshed_once_on_unauthorized() {
+    let url = token_server();
+    let refreshed = Rc::new(Cell::new(0));
+
+    let client = HttpClient::default().with_token_refresh({
+        let refreshed = refreshed.clone();
+
+        move || {
+            refreshed.set(refreshed.get() + 1);
+            Ok("fresh".into())
+        }
+    });
+
+    let request = HttpRequest::get(&url).bearer("expired");
+
+    for _ in 0..2 {
+        let response = client.execute(&request).unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.json_value().unwrap(), json!({"ok": true}));
+    }
+
+    // the refreshed token is reused by the subsequent requests
+    assert_eq!(refreshed.get(), 1);
+
+    let debug = format!("{request:?}");
+
+    assert!(!debug.contains("expired"));
+    assert!(debug.contains("<redacted>"));
+}
+
+#[test]
+fn token_refresh_is_opt_in() {
+    let url = token_server();
+    let request = HttpRequest::get(&url).bearer("expired");
+
+    let response = HttpClient::default().execute(&request).unwrap();
+
+    assert_eq!(response.status, 401);
+    assert!(response.is_empty_body());
+    assert_eq!(response.json_value().unwrap(), json!(null));
+    assert_eq!(response.text().unwrap(), "");
+
+    let err = HttpClient::default()
+        .with_token_refresh(|| anyhow::bail!("invalid credentials"))
+        .execute(&request)
+        .unwrap_err();
+
+    assert!(matches!(err, HttpError::TokenRefresh(_)));
+}
+
+/// Serves requests after a delay.
+fn slow_server(delay: Duration) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+
+    thread::spawn(move || {
+        for r in server.incoming_requests() {
+            thread::spawn(move || {
+                thread::sleep(delay);
+                r.respond(tiny_http::Response::from_string("slow")).ok();
+            });
+        }
+    });
+
+    format!("http://127.0.0.1:{port}")
+}
+
+#[test]
+fn request_fails_when_exceeding_its_timeout() {
+    let url = slow_server(Duration::from_millis(500));
+    let client = HttpClient::default();
+
+    let request = HttpRequest::get(&url).timeout(Duration::from_millis(50));
+
+    assert_eq!(request.to_args()["timeout_ms"], json!(50));
+    assert_eq!(
+        client.execute(&request).unwrap_err(),
+        HttpError::Timeout(Duration::from_millis(50))
+    );
+
+    let request = HttpRequest::get(&url).timeout(Duration::from_secs(3));
+    let response = client.execute(&request).unwrap();
+
+    assert_eq!(response.text().unwrap(), "slow");
+}
+
+/// Fails the first two requests, then succeeds; returns the url and the served request count.
+fn flaky_server() -> (String, Arc<AtomicUsize>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+    let count = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn({
+        let count = count.clone();
+
+        move || {
+            for r in server.incoming_requests() {
+                let res = match count.fetch_add(1, Ordering::SeqCst) {
+                    0 => tiny_http::Response::from_string("")
+                        .with_status_code(503)
+                        .with_header(tiny_http::Header::from_bytes("Retry-After", "1").unwrap()),
+                    1 => tiny_http::Response::from_string("").with_status_code(502),
+                    _ => tiny_http::Response::from_string(r#"{"ok":true}"#),
+                };
+
+                r.respond(res).unwrap();
+            }
+        }
+    });
+
+    (format!("http://127.0.0.1:{port}"), count)
+}
+
+#[test]
+fn request_is_retried_on_transient_statuses() {
+    let (url, count) = flaky_server();
+    let request = HttpRequest::get(&url).retry(3, 10);
+
+    let start = Instant::now();
+    let response = HttpClient::default().execute(&request).unwrap();
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.json_value().unwrap(), json!({"ok": true}));
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+
+    // the `Retry-After` of the first failure overrides the backoff
+    assert!(start.elapsed() >= Duration::from_secs(1));
+
+    let (url, count) = flaky_server();
+    let request = HttpRequest::get(&url).retry(2, 10);
+    let response = HttpClient::default().execute(&request).unwrap();
+
+    assert_eq!(response.status, 502);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    let (url, count) = flaky_server();
+    let request = HttpRequest::get(&url).retry(3, 10).retry_statuses([503]);
+    let response = HttpClient::default().execute(&request).unwrap();
+
+    assert_eq!(response.status, 502);
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn retries_are_bounded_by_the_request_timeout() {
+    let (url, count) = flaky_server();
+    let request = HttpRequest::get(&url)
+        .retry(u32::MAX, u64::MAX)
+        .timeout(Duration::from_millis(500));
+
+    let start = Instant::now();
+    let response = HttpClient::default().execute(&request).unwrap();
+
+    // the `Retry-After` of the first failure doesn't fit in the timeout
+    assert_eq!(response.status, 503);
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+/// Serves the body with the provided content encoding.
+#[cfg(feature = "decompress")]
+fn encoded_server(encoding: &'static str, body: Vec<u8>) -> String {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+
+    thread::spawn(move || {
+        for r in server.incoming_requests() {
+            let header = tiny_http::Header::from_bytes("Content-Encoding", encoding).unwrap();
+            let res = tiny_http::Response::from_data(body.clone()).with_header(header);
+
+            r.respond(res).unwrap();
+        }
+    });
+
+    format!("http://127.0.0.1:{port}")
+}
+
+#[cfg(feature = "decompress")]
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut body = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+    body.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    body.extend(crc32fast::hash(data).to_le_bytes());
+    body.extend((data.len() as u32).to_le_bytes());
+    body
+}
+
+#[test]
+#[cfg(feature = "decompress")]
+fn encoded_bodies_are_decompressed() {
+    let data = br#"{"foo":"bar"}"#;
+    let servers = [
+        encoded_server("gzip", gzip(data)),
+        encoded_server(
+            "deflate",
+            miniz_oxide::deflate::compress_to_vec_zlib(data, 6),
+        ),
+        encoded_server("deflate", miniz_oxide::deflate::compress_to_vec(data, 6)),
+    ];
+
+    for url in servers {
+        // the host returns encoded bodies as bytes, regardless of the `Accept` header
+        let request = HttpRequest::get(&url).header("Accept", "application/json");
+        let response = HttpClient::default().execute(&request).unwrap();
+
+        assert_eq!(response.json_value().unwrap(), json!({"foo": "bar"}));
+        assert_eq!(response.text().unwrap(), r#"{"foo":"bar"}"#);
+        assert!(response.header("Content-Encoding").is_none());
+
+        let raw = HttpClient::default()
+            .execute(&request.no_auto_decompress())
+            .unwrap();
+
+        assert_ne!(raw.body, data);
+        assert!(raw.header("Content-Encoding").is_some());
+    }
+
+    let mut corrupted = gzip(data);
+    let len = corrupted.len();
+
+    corrupted[len - 5] ^= 0xff;
+
+    let url = encoded_server("gzip", corrupted);
+    let err = HttpClient::default()
+        .execute(&HttpRequest::get(&url))
+        .unwrap_err();
+
+    assert!(matches!(err, HttpError::Body(_)));
+}
+
+#[test]
+#[cfg(feature = "decompress")]
+fn decompressed_bodies_are_bounded() {
+    let bomb = vec![0; valence_coprocessor::utils::HTTP_MAX_RESPONSE_SIZE + 1];
+    let servers = [
+        encoded_server("gzip", gzip(&bomb)),
+        encoded_server(
+            "deflate",
+            miniz_oxide::deflate::compress_to_vec_zlib(&bomb, 6),
+        ),
+        encoded_server("deflate", miniz_oxide::deflate::compress_to_vec(&bomb, 6)),
+    ];
+
+    for url in servers {
+        let err = HttpClient::default()
+            .execute(&HttpRequest::get(&url))
+            .unwrap_err();
+
+        assert!(matches!(err, HttpError::Body(e) if e.contains("exceeds the limit")));
+    }
+}
+
+#[test]
+fn body_deserializes_into_typed_values() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Health {
+        ok: bool,
+    }
+
+    let response = HttpResponse {
+        status: 200,
+        body: br#"{"ok":true}"#.to_vec(),
+        ..Default::default()
+    };
+
+    assert_eq!(response.json::<Health>().unwrap(), Health { ok: true });
+    assert_eq!(
+        response.json_or_text::<Health>().unwrap(),
+        JsonOrText::Json(Health { ok: true })
+    );
+    assert!(matches!(
+        response.json::<Vec<u8>>().unwrap_err(),
+        HttpError::Body(_)
+    ));
+
+    let empty = HttpResponse {
+        status: 204,
+        ..Default::default()
+    };
+
+    assert_eq!(empty.json::<Option<Health>>().unwrap(), None);
+
+    let failed = HttpResponse {
+        status: 500,
+        body: b"internal error".to_vec(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        failed.json_or_text::<Health>().unwrap(),
+        JsonOrText::Text("internal error".into())
+    );
+}
+
+#[test]
+fn headers_are_looked_up_ignoring_case() {
+    let response = HttpResponse::from_value(&json!({
+        "status": 200,
+        "headers": {
+            "content-type": "application/json",
+            "Content-Length": "11",
+            "X-Request-ID": "foo",
+        },
+        "body": br#"{"ok":true}"#.to_vec(),
+    }))
+    .unwrap();
+
+    assert_eq!(response.header("Content-Type"), Some("application/json"));
+    assert_eq!(response.header("x-request-id"), Some("foo"));
+    assert_eq!(response.header("X-REQUEST-ID"), Some("foo"));
+    assert_eq!(response.header("Content-Encoding"), None);
+    assert_eq!(response.content_type(), Some("application/json"));
+    assert_eq!(response.content_length(), Some(11));
+
+    // the raw headers keep the casing of the server
+    assert!(response.headers.contains_key("X-Request-ID"));
+
+    let response = HttpResponse::from_value(&json!({
+        "status": 200,
+        "headers": {"CONTENT-LENGTH": "unknown"},
+    }))
+    .unwrap();
+
+    assert_eq!(response.content_type(), None);
+    assert_eq!(response.content_length(), None);
+}
+
+#[test]
+fn bodies_are_parsed_only_from_bytes() {
+    let parse = |body| {
+        HttpResponse::from_value(&json!({"status": 200, "headers": {}, "body": body}))
+            .map(|r| r.body)
+    };
+
+    assert_eq!(parse(json!(null)).unwrap(), b"");
+    assert_eq!(parse(json!(b"[1,2]".to_vec())).unwrap(), b"[1,2]");
+
+    // values decoded by the host are rejected, rather than coerced into bytes
+    for body in [
+        json!("foo"),
+        json!({"ok": true}),
+        json!([1, 256]),
+        json!([1, -1]),
+    ] {
+        assert!(matches!(parse(body), Err(HttpError::InvalidResponse(_))));
+    }
+}
+
+#[test]
+fn json_bodies_are_returned_as_received() {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let port = server.server_addr().to_ip().unwrap().port();
+
+    thread::spawn(move || {
+        for (r, body) in server
+            .incoming_requests()
+            .zip(["[1,2,3]", r#""foo""#, "{invalid"])
+        {
+            r.respond(tiny_http::Response::from_string(body)).unwrap();
+        }
+    });
+
+    let request =
+        HttpRequest::get(format!("http://127.0.0.1:{port}")).header("Accept", "application/json");
+    let client = HttpClient::default();
+
+    // integer arrays aren't taken for bytes, strings keep their quotes, and invalid JSON isn't
+    // turned into `null`
+    assert_eq!(client.execute(&request).unwrap().body, b"[1,2,3]");
+    assert_eq!(client.execute(&request).unwrap().body, br#""foo""#);
+
+    let response = client.execute(&request).unwrap();
+
+    assert_eq!(response.body, b"{invalid");
+    assert!(matches!(response.json_value(), Err(HttpError::Body(_))));
+}
+
+#[test]
+fn multipart_body_round_trips() {
+    // the data contains the first boundary candidate, which must be skipped
+    let colliding = b"--valence-boundary-0000000000000000\r\n".to_vec();
+    let parts = vec![
+        MultipartPart::new("field", "value"),
+        MultipartPart::new("file", colliding.clone())
+            .with_filename("proof \"0\".bin")
+            .with_content_type("application/octet-stream"),
+        MultipartPart::new("empty", vec![]),
+    ];
+
+    let request = HttpRequest::post("http://localhost")
+        .header("content-type", "text/plain")
+        .multipart(parts);
+
+    let content_type = request.headers["Content-Type"].clone();
+    let body = match request.body {
+        Some(HttpBody::Bytes(b)) => b,
+        b => panic!("unexpected body {b:?}"),
+    };
+
+    assert_eq!(request.headers.len(), 1);
+    assert!(content_type.starts_with("multipart/form-data; boundary="));
+
+    let boundary = multer::parse_boundary(&content_type).unwrap();
+    let stream = futures::stream::once(async move { Ok::<_, std::io::Error>(body) });
+    let mut multipart = multer::Multipart::new(stream, boundary);
+
+    let fields = futures::executor::block_on(async {
+        let mut fields = Vec::new();
+
+        while let Some(f) = multipart.next_field().await.unwrap() {
+            let name = f.name().map(String::from);
+            let filename = f.file_name().map(String::from);
+            let content_type = f.content_type().map(|c| c.to_string());
+            let data = f.bytes().await.unwrap().to_vec();
+
+            fields.push((name, filename, content_type, data));
+        }
+
+        fields
+    });
+
+    assert_eq!(
+        fields,
+        vec![
+            (Some("field".into()), None, None, b"value".to_vec()),
+            (
+                Some("file".into()),
+                Some("proof %220%22.bin".into()),
+                Some("application/octet-stream".into()),
+                colliding,
+            ),
+            (Some("empty".into()), None, None, vec![]),
+        ]
+    );
+}
@@ -0,0 +1,170 @@
+use core::{fmt, str::FromStr};
+
+use alloc::vec::Vec;
+use msgpacker::{MsgPacker, Packable, Unpackable};
+use serde::{Deserialize, Serialize};
+
+use crate::Witness;
+
+/// The encoding of the witness data agreed between a controller and its circuit.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    MsgPacker,
+)]
+pub enum WitnessEncoding {
+    /// The data bytes are forwarded as-is.
+    #[default]
+    Raw,
+
+    /// The data is encoded as msgpack.
+    Msgpack,
+
+    /// The data is encoded as bincode.
+    Bincode,
+}
+
+impl WitnessEncoding {
+    /// All the supported encodings.
+    pub const ALL: [WitnessEncoding; 3] = [
+        WitnessEncoding::Raw,
+        WitnessEncoding::Msgpack,
+        WitnessEncoding::Bincode,
+    ];
+
+    /// Returns the canonical name of the encoding.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            WitnessEncoding::Raw => "raw",
+            WitnessEncoding::Msgpack => "msgpack",
+            WitnessEncoding::Bincode => "bincode",
+        }
+    }
+
+    /// Returns the single-byte tag of the encoding.
+    pub const fn to_byte(&self) -> u8 {
+        match self {
+            WitnessEncoding::Raw => 0,
+            WitnessEncoding::Msgpack => 1,
+            WitnessEncoding::Bincode => 2,
+        }
+    }
+
+    /// Parses the encoding from its single-byte tag.
+    pub fn try_from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(WitnessEncoding::Raw),
+            1 => Ok(WitnessEncoding::Msgpack),
+            2 => Ok(WitnessEncoding::Bincode),
+            b => anyhow::bail!("unknown witness encoding tag `{b}`"),
+        }
+    }
+}
+
+impl fmt::Display for WitnessEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WitnessEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(WitnessEncoding::Raw),
+            "msgpack" => Ok(WitnessEncoding::Msgpack),
+            "bincode" => Ok(WitnessEncoding::Bincode),
+            e => anyhow::bail!("unknown witness encoding `{e}`"),
+        }
+    }
+}
+
+/// A serialization scheme for the witness data, shared by a controller and its circuit.
+pub trait WitnessCodec<T> {
+    /// The encoding implemented by the codec.
+    const ENCODING: WitnessEncoding;
+
+    /// Encodes the value into bytes.
+    fn encode(value: &T) -> anyhow::Result<Vec<u8>>;
+
+    /// Decodes the value from bytes.
+    fn decode(bytes: &[u8]) -> anyhow::Result<T>;
+
+    /// Encodes the value into a data witness.
+    fn to_witness(value: &T) -> anyhow::Result<Witness> {
+        Self::encode(value).map(Witness::Data)
+    }
+
+    /// Decodes the value from a data witness.
+    fn from_witness(witness: &Witness) -> anyhow::Result<T> {
+        let data = witness
+            .as_data()
+            .ok_or_else(|| anyhow::anyhow!("the witness is not a data variant"))?;
+
+        Self::decode(data)
+    }
+}
+
+/// A codec that forwards the data bytes as-is.
+pub struct RawCodec;
+
+impl WitnessCodec<Vec<u8>> for RawCodec {
+    const ENCODING: WitnessEncoding = WitnessEncoding::Raw;
+
+    fn encode(value: &Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        Ok(value.clone())
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// A msgpack codec for the witness data.
+pub struct MsgpackCodec;
+
+impl<T: Packable + Unpackable> WitnessCodec<T> for MsgpackCodec {
+    const ENCODING: WitnessEncoding = WitnessEncoding::Msgpack;
+
+    fn encode(value: &T) -> anyhow::Result<Vec<u8>> {
+        Ok(value.pack_to_vec())
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<T> {
+        T::unpack(bytes)
+            .map(|(_, v)| v)
+            .map_err(|_| anyhow::anyhow!("failed to unpack msgpack witness data"))
+    }
+}
+
+/// A bincode codec for the witness data.
+#[cfg(feature = "bincode")]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<T> WitnessCodec<T> for BincodeCodec
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    const ENCODING: WitnessEncoding = WitnessEncoding::Bincode;
+
+    fn encode(value: &T) -> anyhow::Result<Vec<u8>> {
+        bincode::serialize(value)
+            .map_err(|e| anyhow::anyhow!("failed to serialize bincode witness data: {e}"))
+    }
+
+    fn decode(bytes: &[u8]) -> anyhow::Result<T> {
+        bincode::deserialize(bytes)
+            .map_err(|e| anyhow::anyhow!("failed to deserialize bincode witness data: {e}"))
+    }
+}
@@ -1,6 +1,6 @@
-use core::{ops::Deref, slice};
+use core::{fmt, ops::Deref, slice, str::FromStr};
 
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 use msgpacker::MsgPacker;
 use serde::{Deserialize, Serialize};
 
@@ -53,6 +53,158 @@ impl Hasher for () {
     }
 }
 
+/// The hasher of a domain tree, resolved at runtime.
+///
+/// The hasher of a domain is selected when the domain is registered, and is fixed once chosen:
+/// the openings of the domain blocks already in the historical tree are bound to it.
+///
+/// Domains registered without a hasher, including the ones registered before it could be
+/// selected, resolve to [`HasherKind::Native`]; on the service, this is the SHA-256 hasher of
+/// the zkVM. They need no migration, and keep their roots.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    MsgPacker,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum HasherKind {
+    /// The hasher of the historical tree, defined by the zkVM.
+    #[default]
+    Native,
+
+    /// The blake3 hasher.
+    Blake3,
+
+    /// The Keccak-256 hasher, to mirror EVM-compatible trees.
+    Keccak256,
+
+    /// The SHA-256 hasher.
+    Sha256,
+}
+
+impl HasherKind {
+    /// All the supported hashers.
+    pub const ALL: [HasherKind; 4] = [
+        HasherKind::Native,
+        HasherKind::Blake3,
+        HasherKind::Keccak256,
+        HasherKind::Sha256,
+    ];
+
+    /// Returns the canonical name of the hasher.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            HasherKind::Native => "native",
+            HasherKind::Blake3 => "blake3",
+            HasherKind::Keccak256 => "keccak256",
+            HasherKind::Sha256 => "sha256",
+        }
+    }
+
+    /// Returns the single-byte tag of the hasher.
+    pub const fn to_byte(&self) -> u8 {
+        match self {
+            HasherKind::Native => 0,
+            HasherKind::Blake3 => 1,
+            HasherKind::Keccak256 => 2,
+            HasherKind::Sha256 => 3,
+        }
+    }
+
+    /// Parses the hasher from its single-byte tag.
+    pub fn try_from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(HasherKind::Native),
+            1 => Ok(HasherKind::Blake3),
+            2 => Ok(HasherKind::Keccak256),
+            3 => Ok(HasherKind::Sha256),
+            b => anyhow::bail!("unknown hasher tag `{b}`"),
+        }
+    }
+
+    /// Returns the node that binds a domain tree root to the hasher.
+    ///
+    /// The tag is zero for [`HasherKind::Native`], whose domain roots are unbound.
+    pub const fn to_tag(&self) -> Hash {
+        let mut tag = [0u8; HASH_LEN];
+
+        tag[HASH_LEN - 1] = self.to_byte();
+
+        tag
+    }
+
+    /// Binds the root of a domain tree hashed with this hasher to the hasher, with `H`.
+    ///
+    /// The bound root is the leaf of the domain in the historical tree, so an opening to the
+    /// historical root commits to the hasher of the domain tree. [`HasherKind::Native`] is
+    /// unbound, keeping the roots of the domains registered without a hasher.
+    pub fn bind<H: Hasher>(&self, root: &Hash) -> Hash {
+        match self {
+            HasherKind::Native => *root,
+            _ => H::merge(root, &self.to_tag()),
+        }
+    }
+
+    /// Returns the compound entry that opens a domain tree root to its bound root.
+    ///
+    /// Returns `None` for [`HasherKind::Native`], as its roots are unbound.
+    pub fn to_binding(&self) -> Option<CompoundEntry> {
+        match self {
+            HasherKind::Native => None,
+            _ => Some(CompoundEntry {
+                key: Hash::default(),
+                opening: Opening::new(vec![self.to_tag()]),
+            }),
+        }
+    }
+
+    /// Parses the hasher from the compound entry that binds a domain tree root.
+    pub fn try_from_binding(entry: &CompoundEntry) -> anyhow::Result<Self> {
+        let tag = match entry.opening.path.as_slice() {
+            [tag] if entry.key == Hash::default() => tag,
+            _ => anyhow::bail!("invalid hasher binding entry"),
+        };
+
+        let kind = match tag.split_last() {
+            Some((b, prefix)) if prefix.iter().all(|p| *p == 0) => Self::try_from_byte(*b)?,
+            _ => anyhow::bail!("invalid hasher binding tag"),
+        };
+
+        anyhow::ensure!(kind != HasherKind::Native, "the native hasher is unbound");
+
+        Ok(kind)
+    }
+}
+
+impl fmt::Display for HasherKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HasherKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(HasherKind::Native),
+            "blake3" => Ok(HasherKind::Blake3),
+            "keccak256" => Ok(HasherKind::Keccak256),
+            "sha256" => Ok(HasherKind::Sha256),
+            e => anyhow::bail!("unknown hasher `{e}`"),
+        }
+    }
+}
+
 /// The preimage of a node.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, MsgPacker)]
 pub enum Preimage {
@@ -189,6 +341,113 @@ impl OpeningNonMembership {
         self.opening
             .verify_non_membership::<H>(root, key, Some(value), &self.preimage)
     }
+
+    /// Verifies the absence of the key from a tree whose keys are derived via [`Hasher::key`].
+    ///
+    /// The opening follows the path of the key down to either an empty subtree, or the leaf of
+    /// another key. The key of such a leaf is derived from its pre-image under `context`, so it
+    /// must differ from the provided key while sharing the prefix of the path.
+    pub fn verify_absence<H: Hasher>(&self, context: &str, root: &Hash, key: &Hash) -> bool {
+        let data = match &self.preimage {
+            Preimage::Zero => return self.opening.verify::<H>(root, key, &Hash::default()),
+            Preimage::Data(d) => d,
+            // the key of the leaf can't be checked
+            Preimage::Node(_) => return false,
+        };
+
+        let leaf = H::key(context, data);
+        let depth = self.opening.len();
+
+        if &leaf == key || depth >= HASH_LEN * 8 {
+            return false;
+        }
+
+        let shares_prefix = (0..depth).all(|d| {
+            let (i, j) = (d / 8, d % 8);
+
+            (leaf[i] >> (7 - j)) & 1 == (key[i] >> (7 - j)) & 1
+        });
+
+        shares_prefix && self.opening.verify::<H>(root, key, &H::hash(data))
+    }
+}
+
+/// A proof of many keys to a root, sharing the internal nodes of their paths.
+///
+/// The leaves are ordered by key. Each sibling is a subtree without any of the proven keys, in
+/// the order they are visited by a depth-first traversal, left to right.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct SmtMultiproof {
+    /// Depth of the leaf of each key.
+    pub depths: Vec<u16>,
+
+    /// Root of the subtrees that aren't on the path of any key.
+    pub siblings: Vec<Hash>,
+}
+
+impl SmtMultiproof {
+    /// Computes the root of the proof for the sorted, unique keys and their leaf values.
+    pub fn root<H: Hasher>(&self, leaves: &[(Hash, Hash)]) -> Option<Hash> {
+        if leaves.is_empty() || leaves.len() != self.depths.len() {
+            return None;
+        }
+
+        let mut siblings = self.siblings.iter();
+        let root = Self::subtree::<H>(0, leaves, &self.depths, &mut siblings)?;
+
+        siblings.next().is_none().then_some(root)
+    }
+
+    fn subtree<H: Hasher>(
+        depth: usize,
+        leaves: &[(Hash, Hash)],
+        depths: &[u16],
+        siblings: &mut slice::Iter<'_, Hash>,
+    ) -> Option<Hash> {
+        if let [(_, value)] = leaves {
+            if usize::from(depths[0]) == depth {
+                return Some(*value);
+            }
+        }
+
+        if depth >= HASH_LEN * 8 || depths.iter().any(|d| usize::from(*d) <= depth) {
+            return None;
+        }
+
+        let (i, j) = (depth / 8, depth % 8);
+        let split = leaves.partition_point(|(k, _)| (k[i] >> (7 - j)) & 1 == 0);
+
+        let mut child = |leaves: &[(Hash, Hash)], depths: &[u16]| match leaves.is_empty() {
+            true => siblings.next().copied(),
+            false => Self::subtree::<H>(depth + 1, leaves, depths, siblings),
+        };
+
+        let left = child(&leaves[..split], &depths[..split])?;
+        let right = child(&leaves[split..], &depths[split..])?;
+
+        Some(H::merge(&left, &right))
+    }
+
+    /// Verifies the proof of the keys with their data to a known root.
+    ///
+    /// Each key must be derived from its data under `context`.
+    pub fn verify<H: Hasher>(&self, context: &str, root: &Hash, kvs: &[(Hash, &[u8])]) -> bool {
+        if kvs.iter().any(|(k, d)| k != &H::key(context, d)) {
+            return false;
+        }
+
+        let mut leaves: Vec<_> = kvs.iter().map(|(k, d)| (*k, H::hash(d))).collect();
+
+        leaves.sort_unstable_by_key(|l| l.0);
+
+        if leaves.windows(2).any(|w| w[0].0 == w[1].0) {
+            return false;
+        }
+
+        self.root::<H>(&leaves).as_ref() == Some(root)
+    }
 }
 
 /// A non-membership proof of a domain block in the historical tree.
@@ -201,6 +460,9 @@ pub struct HistoricalNonMembership {
 
     /// Historical opening to the domain root.
     pub historical: OpeningNonMembership,
+
+    /// Hasher of the domain tree, bound to the domain root.
+    pub hasher: HasherKind,
 }
 
 impl HistoricalNonMembership {
@@ -211,6 +473,17 @@ impl HistoricalNonMembership {
         domain_id: &Hash,
         number: u64,
         state_root: &Hash,
+    ) -> bool {
+        self.verify_with::<H, H>(root, domain_id, number, state_root)
+    }
+
+    /// Verifies the non-membership proof of the block, with the domain tree hashed by `K`.
+    pub fn verify_with<H: Hasher, K: Hasher>(
+        &self,
+        root: &Hash,
+        domain_id: &Hash,
+        number: u64,
+        state_root: &Hash,
     ) -> bool {
         if !self.historical.opening.verify_non_membership::<H>(
             root,
@@ -232,10 +505,14 @@ impl HistoricalNonMembership {
         };
 
         let key = HistoricalUpdate::block_number_to_key(number);
+        let node = proof.preimage.to_node::<K>();
 
-        proof
-            .opening
-            .verify_non_membership::<H>(&domain, &key, Some(state_root), &proof.preimage)
+        if &node == state_root {
+            return false;
+        }
+
+        // the historical leaf is the domain root bound to its hasher
+        self.hasher.bind::<H>(&proof.opening.root::<K>(&key, &node)) == domain
     }
 }
 
@@ -264,6 +541,71 @@ impl KeyedOpening {
 
         self.opening.verify::<H>(root, &key, &self.node)
     }
+    /// Converts the opening into the `zk-merkle-proofs` representation, resolving to `root`.
+    pub fn into_zk_merkle_opening(self, root: &Hash) -> ZkMerkleOpening {
+        let mut siblings: Vec<_> = self.opening.path.into_iter().map(|s| s.to_vec()).collect();
+
+        siblings.reverse();
+
+        ZkMerkleOpening {
+            root: root.to_vec(),
+            key: self.key.map(|k| k.to_vec()).unwrap_or_default(),
+            value: self.node.to_vec(),
+            siblings,
+        }
+    }
+
+    /// Creates an opening from its `zk-merkle-proofs` representation, returning it with its root.
+    pub fn from_zk_merkle_opening(opening: &ZkMerkleOpening) -> anyhow::Result<(Hash, Self)> {
+        let root = to_hash(&opening.root, "root")?;
+        let key = match opening.key.is_empty() {
+            true => None,
+            false => Some(to_hash(&opening.key, "key")?),
+        };
+        let node = to_hash(&opening.value, "value")?;
+        let path = opening
+            .siblings
+            .iter()
+            .rev()
+            .map(|s| to_hash(s, "sibling"))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok((
+            root,
+            Self {
+                key,
+                node,
+                opening: Opening::new(path),
+            },
+        ))
+    }
+}
+
+/// A Merkle opening in the byte-oriented layout consumed by the `zk-merkle-proofs` library.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct ZkMerkleOpening {
+    /// Root the opening resolves to.
+    pub root: Vec<u8>,
+
+    /// Key of the leaf; empty if the leaf has no key.
+    pub key: Vec<u8>,
+
+    /// Leaf node value.
+    pub value: Vec<u8>,
+
+    /// Sibling nodes, from the root to the leaf.
+    pub siblings: Vec<Vec<u8>>,
+}
+
+fn to_hash(bytes: &[u8], field: &str) -> anyhow::Result<Hash> {
+    Hash::try_from(bytes).map_err(|_| {
+        anyhow::anyhow!(
+            "invalid `{field}` length; expected {HASH_LEN} bytes, got {}",
+            bytes.len()
+        )
+    })
 }
 
 /// A compound Merkle opening keyed opening.
@@ -290,9 +632,18 @@ pub struct CompoundOpening {
 impl CompoundOpening {
     /// Computes the root for the compound opening.
     pub fn root<H: Hasher>(&self, value: &Hash) -> Hash {
-        let mut node = *value;
+        self.root_with::<H, H>(value)
+    }
 
-        for CompoundEntry { key, opening } in &self.trees {
+    /// Computes the root for the compound opening, with the deepest tree hashed by `K`.
+    pub fn root_with<H: Hasher, K: Hasher>(&self, value: &Hash) -> Hash {
+        let mut trees = self.trees.iter();
+        let mut node = match trees.next() {
+            Some(CompoundEntry { key, opening }) => opening.root::<K>(key, value),
+            None => return *value,
+        };
+
+        for CompoundEntry { key, opening } in trees {
             node = opening.root::<H>(key, &node);
         }
 
@@ -303,6 +654,19 @@ impl CompoundOpening {
     pub fn verify<H: Hasher>(&self, root: &Hash, value: &Hash) -> bool {
         *root == self.root::<H>(value)
     }
+
+    /// Returns the hasher of the domain tree bound to a historical block opening.
+    ///
+    /// A block opening has the domain tree entry first and the historical tree entry last; the
+    /// domains hashed with a hasher other than [`HasherKind::Native`] have their binding entry in
+    /// between.
+    pub fn domain_hasher(&self) -> anyhow::Result<HasherKind> {
+        match self.trees.as_slice() {
+            [_, _] => Ok(HasherKind::Native),
+            [_, binding, _] => HasherKind::try_from_binding(binding),
+            _ => anyhow::bail!("the opening is not a historical block opening"),
+        }
+    }
 }
 
 /// A historical tree transition proof.
@@ -321,33 +685,41 @@ pub struct HistoricalTransitionProof {
 impl HistoricalTransitionProof {
     /// Verifies the correctness of the transition.
     pub fn verify<H: Hasher>(self) -> anyhow::Result<HistoricalUpdate> {
+        self.verify_with::<H, H>()
+    }
+
+    /// Verifies the correctness of the transition, with the domain tree hashed by `K`.
+    pub fn verify_with<H: Hasher, K: Hasher>(self) -> anyhow::Result<HistoricalUpdate> {
         let Self {
             previous,
             update,
             mut proof,
         } = self;
 
-        anyhow::ensure!(previous.verify::<H>(
+        anyhow::ensure!(
+            proof.domain_hasher()? == previous.hasher,
+            "the updated state is not bound to the hasher of the domain"
+        );
+
+        anyhow::ensure!(previous.verify_with::<H, K>(
             &update.previous,
             &update.block.domain,
             update.block.number,
             &update.block.root,
         ));
 
-        anyhow::ensure!(
-            proof.trees.len() == 2,
-            "the historical tree contains two compound levels"
-        );
+        // the shape of the opening is checked by the hasher binding
+        let historical = proof.trees.len() - 1;
 
         proof.trees[0].key = HistoricalUpdate::block_number_to_key(update.block.number);
-        proof.trees[1].key = update.block.domain;
+        proof.trees[historical].key = update.block.domain;
 
         anyhow::ensure!(
-            proof.verify::<H>(&update.root, &update.block.root),
+            proof.root_with::<H, K>(&update.block.root) == update.root,
             "the updated state is not consistent"
         );
 
-        let mut current = proof.trees[1].opening.path.clone();
+        let mut current = proof.trees[historical].opening.path.clone();
         let mut previous = previous.historical.opening.path;
 
         current.reverse();
@@ -359,11 +731,33 @@ impl HistoricalTransitionProof {
 
         Ok(update)
     }
+
+    /// Verifies the correctness of the transition, with the domain tree hashed by the hasher
+    /// bound to the opening; [`HasherKind::Native`] resolves to `H`.
+    pub fn verify_bound<H: Hasher>(self) -> anyhow::Result<HistoricalUpdate> {
+        match self.proof.domain_hasher()? {
+            HasherKind::Native => self.verify_with::<H, H>(),
+            #[cfg(feature = "blake3")]
+            HasherKind::Blake3 => self.verify_with::<H, Blake3Hasher>(),
+            #[cfg(feature = "keccak")]
+            HasherKind::Keccak256 => self.verify_with::<H, Keccak256Hasher>(),
+            #[cfg(feature = "sha256")]
+            HasherKind::Sha256 => self.verify_with::<H, Sha256Hasher>(),
+            #[allow(unreachable_patterns)]
+            k => anyhow::bail!("the hasher `{k}` is not enabled"),
+        }
+    }
 }
 
 #[cfg(feature = "blake3")]
 pub use blake3::*;
 
+#[cfg(feature = "keccak")]
+pub use keccak::*;
+
+#[cfg(feature = "sha256")]
+pub use sha256::*;
+
 use crate::HistoricalUpdate;
 
 #[cfg(feature = "blake3")]
@@ -421,3 +815,217 @@ mod blake3 {
         }
     }
 }
+
+#[cfg(feature = "keccak")]
+mod keccak {
+    use sha3::{Digest as _, Keccak256};
+
+    use super::*;
+
+    /// A Keccak-256 hasher implementation for the Valence protocol, to mirror EVM-compatible
+    /// trees.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct Keccak256Hasher;
+
+    impl Keccak256Hasher {
+        /// Prefix for data hash.
+        pub const DATA_PREFIX: &[u8] = &[0x00];
+
+        /// Prefix for node hash.
+        pub const MERGE_PREFIX: &[u8] = &[0x01];
+    }
+
+    impl Hasher for Keccak256Hasher {
+        fn key(context: &str, data: &[u8]) -> Hash {
+            // the context is hashed first, as blake3 does for its derived keys, so the boundary
+            // between the context and the data is fixed
+            Keccak256::new()
+                .chain_update(Keccak256::digest(context))
+                .chain_update(data)
+                .finalize()
+                .into()
+        }
+
+        fn hash(data: &[u8]) -> Hash {
+            Keccak256::new()
+                .chain_update(Self::DATA_PREFIX)
+                .chain_update(data)
+                .finalize()
+                .into()
+        }
+
+        fn hash_raw(data: &[u8]) -> Hash {
+            Keccak256::digest(data).into()
+        }
+
+        fn merge(a: &Hash, b: &Hash) -> Hash {
+            Keccak256::new()
+                .chain_update(Self::MERGE_PREFIX)
+                .chain_update(a)
+                .chain_update(b)
+                .finalize()
+                .into()
+        }
+
+        fn digest<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> Hash {
+            let mut h = Keccak256::new();
+
+            h.update(Self::DATA_PREFIX);
+
+            data.into_iter().for_each(|d| {
+                h.update(d);
+            });
+
+            h.finalize().into()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn keccak_keys_bind_the_context_boundary() {
+            assert_ne!(
+                Keccak256Hasher::key("ab", b"c"),
+                Keccak256Hasher::key("a", b"bc")
+            );
+            assert_ne!(
+                Keccak256Hasher::key("ctx", b""),
+                Keccak256Hasher::key("", b"ctx")
+            );
+            assert_eq!(
+                Keccak256Hasher::key("ctx", b"data"),
+                Keccak256Hasher::hash_raw(&[&Keccak256::digest("ctx")[..], b"data"].concat())
+            );
+        }
+
+        #[test]
+        fn keccak_hasher_is_domain_separated() {
+            let a = Keccak256Hasher::hash(b"left");
+            let b = Keccak256Hasher::hash(b"right");
+
+            // the raw hash is the plain Keccak-256 used by the EVM
+            assert_eq!(
+                Keccak256Hasher::hash_raw(&[]),
+                [
+                    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc,
+                    0xc7, 0x03, 0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa,
+                    0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70
+                ]
+            );
+
+            assert_ne!(
+                Keccak256Hasher::merge(&a, &b),
+                Keccak256Hasher::merge(&b, &a)
+            );
+            assert_ne!(
+                Keccak256Hasher::merge(&a, &b),
+                Keccak256Hasher::hash(&[a, b].concat())
+            );
+            assert_eq!(
+                Keccak256Hasher::digest([&b"left"[..], b"right"]),
+                Keccak256Hasher::hash(b"leftright")
+            );
+            assert_ne!(
+                Keccak256Hasher::hash(b"left"),
+                Keccak256Hasher::hash_raw(b"left")
+            );
+        }
+    }
+}
+
+#[cfg(feature = "sha256")]
+mod sha256 {
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+
+    /// A SHA-256 hasher implementation for the Valence protocol.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct Sha256Hasher;
+
+    impl Sha256Hasher {
+        /// Prefix for data hash.
+        pub const DATA_PREFIX: &[u8] = &[0x00];
+
+        /// Prefix for node hash.
+        pub const MERGE_PREFIX: &[u8] = &[0x01];
+    }
+
+    impl Hasher for Sha256Hasher {
+        fn key(context: &str, data: &[u8]) -> Hash {
+            // the context is hashed first, as for the keccak hasher
+            Sha256::new()
+                .chain_update(Sha256::digest(context))
+                .chain_update(data)
+                .finalize()
+                .into()
+        }
+
+        fn hash(data: &[u8]) -> Hash {
+            Sha256::new()
+                .chain_update(Self::DATA_PREFIX)
+                .chain_update(data)
+                .finalize()
+                .into()
+        }
+
+        fn hash_raw(data: &[u8]) -> Hash {
+            Sha256::digest(data).into()
+        }
+
+        fn merge(a: &Hash, b: &Hash) -> Hash {
+            Sha256::new()
+                .chain_update(Self::MERGE_PREFIX)
+                .chain_update(a)
+                .chain_update(b)
+                .finalize()
+                .into()
+        }
+
+        fn digest<'a>(data: impl IntoIterator<Item = &'a [u8]>) -> Hash {
+            let mut h = Sha256::new();
+
+            h.update(Self::DATA_PREFIX);
+
+            data.into_iter().for_each(|d| {
+                h.update(d);
+            });
+
+            h.finalize().into()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn sha256_hasher_is_domain_separated() {
+            let a = Sha256Hasher::hash(b"left");
+            let b = Sha256Hasher::hash(b"right");
+
+            // the raw hash is the plain SHA-256
+            assert_eq!(
+                Sha256Hasher::hash_raw(&[]),
+                [
+                    0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99,
+                    0x6f, 0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95,
+                    0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55
+                ]
+            );
+
+            assert_ne!(Sha256Hasher::key("ab", b"c"), Sha256Hasher::key("a", b"bc"));
+            assert_ne!(Sha256Hasher::merge(&a, &b), Sha256Hasher::merge(&b, &a));
+            assert_ne!(
+                Sha256Hasher::merge(&a, &b),
+                Sha256Hasher::hash(&[a, b].concat())
+            );
+            assert_eq!(
+                Sha256Hasher::digest([&b"left"[..], b"right"]),
+                Sha256Hasher::hash(b"leftright")
+            );
+        }
+    }
+}
@@ -4,7 +4,7 @@ use alloc::{string::String, vec, vec::Vec};
 use msgpacker::{MsgPacker, Packable as _, Unpackable as _};
 use serde::{Deserialize, Serialize};
 
-use crate::{Base64, Blake3Hasher, Hash};
+use crate::{Base64, Blake3Hasher, Hash, HasherKind, WitnessCodec, WitnessEncoding};
 
 /// A generic data backend to support multiple contexts.
 pub trait DataBackend: Clone {
@@ -24,6 +24,64 @@ pub trait DataBackend: Clone {
     /// Returns the previous data, if existed.
     fn set(&self, prefix: &[u8], key: &[u8], data: &[u8]) -> anyhow::Result<Option<Vec<u8>>>;
 
+    /// Replaces the underlying data of many keys of the prefix.
+    ///
+    /// Loops over [`DataBackend::set`] by default; backends that can write the entries in a single
+    /// round trip should override it.
+    fn set_many(&self, prefix: &[u8], entries: &[(Vec<u8>, Vec<u8>)]) -> anyhow::Result<()> {
+        for (key, data) in entries {
+            self.set(prefix, key, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the underlying data of many keys of the prefix.
+    ///
+    /// Loops over [`DataBackend::remove`] by default; backends that can remove the keys in a
+    /// single round trip should override it.
+    fn remove_many(&self, prefix: &[u8], keys: &[Vec<u8>]) -> anyhow::Result<()> {
+        for key in keys {
+            self.remove(prefix, key)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns every key of the prefix, along with its data.
+    ///
+    /// The entries are loaded into memory at once, so scanning a prefix with a huge number of keys
+    /// is expensive; see [`DataBackend::scan_prefix_limited`] to bound it.
+    fn scan_prefix(&self, prefix: &[u8]) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.scan_prefix_limited(prefix, usize::MAX)
+    }
+
+    /// Returns at most `max` keys of the prefix, along with their data.
+    ///
+    /// Fails by default, as not every backend can enumerate its keys.
+    fn scan_prefix_limited(
+        &self,
+        _prefix: &[u8],
+        _max: usize,
+    ) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        anyhow::bail!("the data backend doesn't support prefix scans")
+    }
+
+    /// Replaces the data of the key only if its current data is `expected`, where `None` stands
+    /// for an absent key.
+    ///
+    /// Returns `true` if the data was replaced. Fails by default, as not every backend can swap
+    /// atomically.
+    fn compare_and_swap(
+        &self,
+        _prefix: &[u8],
+        _key: &[u8],
+        _expected: Option<&[u8]>,
+        _data: &[u8],
+    ) -> anyhow::Result<bool> {
+        anyhow::bail!("the data backend doesn't support atomic swaps")
+    }
+
     /// Returns the underlying bulk data from the backend.
     fn get_bulk(&self, prefix: &[u8], key: &[u8]) -> anyhow::Result<Option<Vec<u8>>>;
 
@@ -68,6 +126,9 @@ pub struct DomainData {
     pub controller: Vec<u8>,
     /// Circuit associated with the domain block add function.
     pub circuit: Vec<u8>,
+    /// Hasher of the domain tree; fixed once the domain is registered.
+    #[serde(default)]
+    pub hasher: HasherKind,
 }
 
 impl DomainData {
@@ -80,6 +141,7 @@ impl DomainData {
             name,
             controller: vec![],
             circuit: vec![],
+            hasher: HasherKind::default(),
         }
     }
 
@@ -95,6 +157,12 @@ impl DomainData {
         self
     }
 
+    /// Selects the hasher of the domain tree.
+    pub fn with_hasher(mut self, hasher: HasherKind) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
     /// Generates an unique identifier for the domain.
     ///
     /// The controller definition can be hot swapped so it is not part of the identifier
@@ -121,6 +189,8 @@ pub struct ControllerData {
     pub circuit: Vec<u8>,
     /// Deployed nonce value.
     pub nonce: u64,
+    /// Encoding of the witness data agreed between the controller and the circuit.
+    pub codec: WitnessEncoding,
 }
 
 impl ControllerData {
@@ -154,11 +224,82 @@ impl ControllerData {
         self
     }
 
+    /// Set the witness data encoding.
+    pub fn with_codec(mut self, codec: WitnessEncoding) -> Self {
+        self.codec = codec;
+        self
+    }
+
     /// Computes the controller identifier from its parts.
     #[cfg(feature = "blake3")]
     pub fn identifier_from_parts(circuit: &[u8], nonce: u64) -> Hash {
         <Blake3Hasher as crate::Hasher>::digest([Self::ID_PREFIX, circuit, &nonce.to_le_bytes()])
     }
+
+    /// Computes the deployment metadata of the controller.
+    #[cfg(feature = "blake3")]
+    pub fn metadata(&self) -> ControllerMetadata {
+        ControllerMetadata {
+            id: self.identifier(),
+            nonce: self.nonce,
+            circuit: <Blake3Hasher as crate::Hasher>::hash(&self.circuit),
+        }
+    }
+}
+
+/// Deployment metadata of a controller, assigned by the registry.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    MsgPacker,
+)]
+pub struct ControllerMetadata {
+    /// Controller identifier.
+    pub id: Hash,
+    /// Deployed nonce value.
+    pub nonce: u64,
+    /// Hash of the deployed circuit.
+    pub circuit: Hash,
+}
+
+/// The arguments schema of a controller entrypoint, returned by its `describe` export.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct ControllerSchema {
+    /// Optional description of the controller.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Fields of the entrypoint arguments object.
+    #[serde(default)]
+    pub args: Vec<ArgSchema>,
+}
+
+/// A field of the arguments of a controller entrypoint.
+#[derive(
+    Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker,
+)]
+pub struct ArgSchema {
+    /// Name of the field.
+    pub name: String,
+    /// JSON type of the field (e.g. `string`, `number`, `object`).
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// Whether the field is required.
+    #[serde(default)]
+    pub required: bool,
+    /// Optional description of the field.
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 /// A domain-specific state proof.
@@ -180,6 +321,58 @@ pub struct StateProof {
     pub proof: Vec<u8>,
 }
 
+impl StateProof {
+    /// Starts a state proof of the named domain, at the provided state root.
+    ///
+    /// The proof is completed via [`StateProofBuilder::with_proof`], which validates the fields.
+    #[cfg(feature = "blake3")]
+    pub fn build(domain: &str, state_root: Hash, payload: Vec<u8>) -> StateProofBuilder {
+        StateProofBuilder {
+            domain: domain.into(),
+            number: 0,
+            state_root,
+            payload,
+        }
+    }
+}
+
+/// A builder of a [`StateProof`], created via [`StateProof::build`].
+#[cfg(feature = "blake3")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateProofBuilder {
+    domain: String,
+    number: u64,
+    state_root: Hash,
+    payload: Vec<u8>,
+}
+
+#[cfg(feature = "blake3")]
+impl StateProofBuilder {
+    /// Sets the block number of the proven state.
+    pub fn with_number(mut self, number: u64) -> Self {
+        self.number = number;
+        self
+    }
+
+    /// Completes the state proof with the serialized, domain-specific proof.
+    ///
+    /// Fails if the domain name is empty.
+    pub fn with_proof(self, proof: Vec<u8>) -> anyhow::Result<StateProof> {
+        anyhow::ensure!(
+            !self.domain.is_empty(),
+            "the domain of a state proof can't be empty"
+        );
+
+        Ok(StateProof {
+            domain: DomainData::identifier_from_parts(&self.domain),
+            number: self.number,
+            state_root: self.state_root,
+            payload: self.payload,
+            proof,
+        })
+    }
+}
+
 /// A circuit witness data obtained via Valence API.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, MsgPacker)]
 pub enum Witness {
@@ -231,6 +424,9 @@ pub struct Proof {
 }
 
 impl Proof {
+    /// Prefix for the public inputs commitment hash.
+    pub const COMMITMENT_PREFIX: &[u8] = b"proof-inputs";
+
     /// Encodes the arguments and returns a new proven circuit instance.
     pub fn new<P, I>(proof: P, inputs: I) -> Self
     where
@@ -266,6 +462,23 @@ impl Proof {
 
         Ok((proof, inputs))
     }
+
+    /// Computes the commitment to the public inputs of the proof.
+    ///
+    /// Verifiers can compare it with the commitment of the inputs they expect before trusting the
+    /// proof, so a proof can't be reused with mismatched claimed inputs.
+    #[cfg(feature = "blake3")]
+    pub fn input_commitment(&self) -> anyhow::Result<Hash> {
+        let inputs = Base64::decode(&self.inputs)?;
+
+        Ok(Self::input_commitment_from_parts(&inputs))
+    }
+
+    /// Computes the commitment to the provided public inputs.
+    #[cfg(feature = "blake3")]
+    pub fn input_commitment_from_parts(inputs: &[u8]) -> Hash {
+        <Blake3Hasher as crate::Hasher>::digest([Self::COMMITMENT_PREFIX, inputs])
+    }
 }
 
 /// A domain validated block
@@ -318,7 +531,20 @@ pub struct HistoricalUpdate {
 }
 
 impl HistoricalUpdate {
+    /// Unix timestamp, in milliseconds, at which the update was recorded.
+    ///
+    /// The UUID is a v7, whose 48 most significant bits are the timestamp.
+    pub fn timestamp_ms(&self) -> u64 {
+        let mut ms = [0u8; 8];
+
+        ms[2..].copy_from_slice(&self.uuid[..6]);
+
+        u64::from_be_bytes(ms)
+    }
+
     /// Compute the historical Merkle key from the block number.
+    ///
+    /// The block number is encoded as big-endian so keys preserve the numeric order.
     pub fn block_number_to_key(number: u64) -> Hash {
         let key = number.to_be_bytes();
 
@@ -351,6 +577,34 @@ pub struct ValidatedWitnesses {
 
     /// Witness data for the circuit.
     pub witnesses: Vec<Witness>,
+
+    /// Encoding of the witness data selected when the controller was deployed.
+    pub encoding: WitnessEncoding,
+}
+
+impl ValidatedWitnesses {
+    /// Decodes the data witness at `index` with the provided codec.
+    ///
+    /// Fails if the codec doesn't implement the encoding selected when the controller was
+    /// deployed.
+    pub fn decode<C, T>(&self, index: usize) -> anyhow::Result<T>
+    where
+        C: WitnessCodec<T>,
+    {
+        anyhow::ensure!(
+            C::ENCODING == self.encoding,
+            "the witnesses are encoded as `{}`, not `{}`",
+            self.encoding,
+            C::ENCODING
+        );
+
+        let witness = self
+            .witnesses
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("no witness at index `{index}`"))?;
+
+        C::from_witness(witness)
+    }
 }
 
 #[test]
@@ -370,3 +624,20 @@ fn proof_base64_encode_works() {
     assert_eq!(p, proof_bytes);
     assert_eq!(i, inputs);
 }
+
+#[test]
+#[cfg(feature = "blake3")]
+fn proof_input_commitment_binds_the_inputs() {
+    let proof = Proof::new(b"foo", b"bar");
+    let commitment = proof.input_commitment().unwrap();
+
+    assert_eq!(commitment, Proof::input_commitment_from_parts(b"bar"));
+    assert_eq!(
+        commitment,
+        Proof::new(b"baz", b"bar").input_commitment().unwrap()
+    );
+    assert_ne!(
+        commitment,
+        Proof::new(b"foo", b"baz").input_commitment().unwrap()
+    );
+}
@@ -4,10 +4,16 @@
 
 extern crate alloc;
 
+mod codec;
 mod crypto;
 mod data;
+#[cfg(feature = "poseidon")]
+mod poseidon;
 mod utils;
 
+pub use codec::*;
 pub use crypto::*;
 pub use data::*;
+#[cfg(feature = "poseidon")]
+pub use poseidon::*;
 pub use utils::*;